@@ -0,0 +1,51 @@
+//! Criterion benches of the layers `submod bench` times together: parsing
+//! srt, shifting the cues, and writing them as srt and vtt. They run on
+//! 10 000 synthesized subtitles, or as many as `SUBMOD_BENCH_CUES` says,
+//! like the few of the smoke run in CI.
+
+#[macro_use]
+extern crate criterion;
+extern crate submod;
+
+use std::env;
+
+use criterion::{Criterion, Throughput};
+use submod::Subtitles;
+
+/// Generates an srt file with subtitles of two lines each, like the ones
+/// of `submod bench`.
+fn synthesize(cues: usize) -> String {
+    let time = |seconds: usize, millis: usize| format!("{:02}:{:02}:{:02},{:03}",
+        seconds / 3600, seconds / 60 % 60, seconds % 60, millis);
+    let mut srt = String::with_capacity(cues * 80);
+    for i in 0..cues {
+        srt.push_str(&format!("{}\n{} --> {}\n\
+            Subtitle number {}, with <i>some</i> text\nand a second line\n\n",
+            i + 1, time(i * 3 + 1, 0), time(i * 3 + 3, 500), i + 1));
+    }
+    srt
+}
+
+fn layers(c: &mut Criterion) {
+    let cues = env::var("SUBMOD_BENCH_CUES").ok()
+        .map_or(10_000, |cues| cues.parse().expect("A number of subtitles"));
+    let srt = synthesize(cues);
+    let subtitles = Subtitles::parse_srt(&srt).unwrap();
+
+    let mut group = c.benchmark_group("layers");
+    group.throughput(Throughput::Elements(cues as u64));
+    group.bench_function("parse", |b| b.iter(||
+        Subtitles::parse_srt(&srt).unwrap()));
+    group.bench_function("shift", |b| b.iter(|| {
+        let mut shifted = subtitles.clone();
+        shifted.shift(1.5, None).unwrap()
+    }));
+    group.bench_function("write_srt", |b| b.iter(||
+        subtitles.to_srt_string().unwrap()));
+    group.bench_function("write_vtt", |b| b.iter(||
+        subtitles.to_vtt_string()));
+    group.finish();
+}
+
+criterion_group!(benches, layers);
+criterion_main!(benches);
@@ -0,0 +1,239 @@
+use failure::Error;
+
+use subtitles::Error as SubmodError;
+
+
+/// The positions of the fields of the event lines, like `Dialogue:`,
+/// as listed by the `Format:` line of the [Events] section. Legacy SSA v4
+/// scripts start with a `Marked` field where ASS v4+ has a `Layer`, and
+/// scripts may list the fields in any order, so they're never hard-coded.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EventFormat {
+    /// Number of fields; the last one is the text, which may hold commas.
+    pub fields: usize,
+    pub start: usize,
+    pub end: usize,
+    /// Whether the format lists a `Marked` field.
+    pub marked: bool,
+}
+
+impl Default for EventFormat {
+    /// The format of scripts without a `Format:` line, which is the same
+    /// for SSA v4 and ASS v4+ as far as the timing is concerned.
+    fn default() -> EventFormat {
+        EventFormat { fields: 10, start: 1, end: 2, marked: false }
+    }
+}
+
+impl EventFormat {
+    /// Parses the fields of a `Format:` line of the [Events] section.
+    pub fn parse(line: &str) -> Result<EventFormat, Error> {
+        let names: Vec<String> = line.trim_end()["Format:".len()..]
+            .split(',')
+            .map(|name| name.trim().to_lowercase())
+            .collect();
+        let position = |field: &str| names.iter().position(|name| name == field)
+            .ok_or_else(|| SubmodError::Parse { line: None,
+                message: format!("The [Events] format `{}` has no `{}` \
+                    field.", line.trim(), field) });
+        if names.last().map(String::as_str) != Some("text") {
+            return Err(SubmodError::Parse { line: None,
+                message: format!("The [Events] format `{}` doesn't end \
+                    with the `Text` field.", line.trim()) }.into());
+        }
+        Ok(EventFormat {
+            fields: names.len(),
+            start: position("start")?,
+            end: position("end")?,
+            marked: names.iter().any(|name| name == "marked"),
+        })
+    }
+}
+
+/// Shifts the `Start` and `End` of every event of a script, leaving all
+/// other lines and fields untouched, in the order the script has them.
+/// `shift` gets the start and end in seconds and returns the new ones,
+/// which are written with centisecond precision, or `None` to remove the
+/// event.
+pub fn shift<F>(script: &str, mut shift: F) -> Result<String, Error>
+    where F: FnMut(f64, f64) -> Option<(f64, f64)>
+{
+    let mut lines = Vec::new();
+    let mut in_events = false;
+    let mut format = EventFormat::default();
+
+    // Splitting on \n keeps the \r of \r\n line endings in the last field:
+    for (i, line) in script.split('\n').enumerate() {
+        if line.starts_with('[') {
+            in_events = line.trim_end().eq_ignore_ascii_case("[events]");
+        } else if in_events && line.starts_with("Format:") {
+            format = EventFormat::parse(line)?;
+        } else if in_events && is_event(line) {
+            let event = shift_event(line, format, &mut shift).map_err(|error|
+                SubmodError::Parse { line: Some(i + 1),
+                    message: error.to_string() })?;
+            lines.extend(event);
+            continue;
+        }
+        lines.push(line.to_string());
+    }
+    Ok(lines.join("\n"))
+}
+
+/// Whether the line is an event with a timing, i.e. a `Dialogue:`,
+/// or a `Comment:` that editors show on the timeline as well.
+fn is_event(line: &str) -> bool {
+    line.starts_with("Dialogue:") || line.starts_with("Comment:")
+}
+
+fn shift_event<F>(line: &str, mut format: EventFormat, shift: &mut F)
+    -> Result<Option<String>, Error>
+    where F: FnMut(f64, f64) -> Option<(f64, f64)>
+{
+    let colon = line.find(':').unwrap();
+    let (kind, values) = (&line[..colon + 1], &line[colon + 1..]);
+    // Some SSA scripts mark their events without listing the field:
+    if !format.marked && values.trim_start().starts_with("Marked=") {
+        format.fields += 1;
+        format.start += 1;
+        format.end += 1;
+    }
+    let mut fields: Vec<String> = values.splitn(format.fields, ',')
+        .map(str::to_string)
+        .collect();
+    if fields.len() < format.fields {
+        return Err(format_err!("expected {} fields, but found {}",
+            format.fields, fields.len()));
+    }
+    let (start, end) = match shift(get_time(&fields[format.start])?,
+        get_time(&fields[format.end])?) {
+        Some(times) => times,
+        None => return Ok(None),
+    };
+    // Keep the space some scripts have after the colon:
+    for &(index, time) in &[(format.start, start), (format.end, end)] {
+        let padding = fields[index].len() - fields[index].trim_start().len();
+        fields[index] = format!("{}{}", &fields[index][..padding],
+            format_time(time));
+    }
+    Ok(Some(format!("{}{}", kind, fields.join(","))))
+}
+
+/// Reads an event time like `0:01:59.99` into seconds.
+fn get_time(field: &str) -> Result<f64, Error> {
+    let time = field.trim();
+    let parts: Vec<&str> = time.split(':').collect();
+    if parts.len() != 3 {
+        return Err(format_err!("`{}` is not a time like 0:00:00.00", time));
+    }
+    let mut seconds = 0.0;
+    for part in parts {
+        let value: f64 = part.parse().map_err(|_| format_err!(
+            "`{}` is not a time like 0:00:00.00", time))?;
+        seconds = seconds * 60.0 + value;
+    }
+    Ok(seconds)
+}
+
+/// Formats seconds as an event time like `0:01:59.99`, rounded to the
+/// centisecond; times before the start are moved to it.
+fn format_time(seconds: f64) -> String {
+    let centis = (seconds.max(0.0) * 100.0).round() as u64;
+    format!("{}:{:02}:{:02}.{:02}", centis / 360000, centis / 6000 % 60,
+        centis / 100 % 60, centis % 100)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shift_by(script: &str, seconds: f64) -> String {
+        shift(script, |start, end| Some((start + seconds, end + seconds)))
+            .unwrap()
+    }
+
+    #[test]
+    fn shifts_classic_ssa_without_touching_other_fields() {
+        let script = include_str!("../tests/fixtures/classic.ssa");
+        let shifted = shift_by(script, 0.51);
+        assert_eq!(shifted, script
+            .replace("Marked=0,0:00:01.50,0:00:03.25,",
+                "Marked=0,0:00:02.01,0:00:03.76,")
+            .replace("Marked=1,0:01:59.99,0:02:01.00,",
+                "Marked=1,0:02:00.50,0:02:01.51,"));
+    }
+
+    #[test]
+    fn shifts_modern_ass_without_touching_other_fields() {
+        let script = include_str!("../tests/fixtures/modern.ass");
+        let shifted = shift_by(script, -0.75);
+        assert_eq!(shifted, script
+            .replace("0,0:00:00.00,0:00:01.00,", "0,0:00:00.00,0:00:00.25,")
+            .replace("0,0:00:01.50,0:00:03.25,", "0,0:00:00.75,0:00:02.50,")
+            .replace("1,0:01:59.99,0:02:01.00,", "1,0:01:59.24,0:02:00.25,"));
+    }
+
+    #[test]
+    fn maps_fields_by_the_format_line() {
+        let script = "[Events]\n\
+            Format: Start, End, Marked, Style, Name, Text\n\
+            Dialogue: 0:00:01.00, 0:00:02.00,Marked=0,Default,,a, b\n";
+        assert_eq!(shift_by(script, 1.0), "[Events]\n\
+            Format: Start, End, Marked, Style, Name, Text\n\
+            Dialogue: 0:00:02.00, 0:00:03.00,Marked=0,Default,,a, b\n");
+    }
+
+    #[test]
+    fn handles_marked_fields_missing_from_the_format() {
+        let script = "[Events]\n\
+            Format: Start, End, Style, Name, MarginL, MarginR, MarginV, \
+            Effect, Text\n\
+            Dialogue: Marked=0,0:00:01.00,0:00:02.00,Default,,0,0,0,,Hi\n";
+        assert!(shift_by(script, 1.0)
+            .ends_with("Marked=0,0:00:02.00,0:00:03.00,Default,,0,0,0,,Hi\n"));
+    }
+
+    #[test]
+    fn leaves_lines_outside_the_events_alone() {
+        let script = "[Script Info]\nDialogue: not an event\n\n[Events]\n";
+        assert_eq!(shift_by(script, 1.0), script);
+    }
+
+    #[test]
+    fn refuses_malformed_events() {
+        assert!(EventFormat::parse("Format: Layer, Start, Text").is_err());
+        assert!(EventFormat::parse("Format: Start, End, Text, Style").is_err());
+        let script = "[Events]\nDialogue: 0,0:00:01.00,soon,Default,,0,0,0,,a\n";
+        let error = shift(script, |start, end| Some((start, end)))
+            .unwrap_err();
+        assert!(error.to_string().starts_with("Line 2:"));
+        assert!(shift("[Events]\nDialogue: 0,0:00:01.00\n",
+            |start, end| Some((start, end))).is_err());
+    }
+
+    #[test]
+    fn removes_the_events_it_is_told_to() {
+        let script = "[Events]\r\n\
+            Dialogue: 0,0:00:01.00,0:00:02.00,Default,,0,0,0,,Gone\r\n\
+            Dialogue: 0,0:00:05.00,0:00:06.00,Default,,0,0,0,,Kept\r\n";
+        assert_eq!(shift(script, |start, end| if end < 3.0 { None } else {
+            Some((start - 3.0, end - 3.0)) }).unwrap(), "[Events]\r\n\
+            Dialogue: 0,0:00:02.00,0:00:03.00,Default,,0,0,0,,Kept\r\n");
+    }
+
+    #[test]
+    fn formats_centiseconds() {
+        assert_eq!(format_time(119.994), "0:01:59.99");
+        assert_eq!(format_time(119.996), "0:02:00.00");
+        assert_eq!(format_time(-3.0), "0:00:00.00");
+        assert_eq!(format_time(36000.5), "10:00:00.50");
+        // Carries into seconds, minutes and hours:
+        assert_eq!(format_time(0.995), "0:00:01.00");
+        assert_eq!(format_time(59.995), "0:01:00.00");
+        assert_eq!(format_time(get_time("0:59:59.99").unwrap() + 0.01),
+            "1:00:00.00");
+        assert_eq!(format_time(get_time("1:00:00.00").unwrap() - 0.01),
+            "0:59:59.99");
+    }
+}
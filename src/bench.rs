@@ -0,0 +1,150 @@
+use std::fs;
+use std::time::{Duration, Instant};
+
+use clap::{App, Arg};
+use failure::Error;
+
+use cli;
+use color;
+use cue;
+use helpers;
+use status;
+use submod::{self, Options};
+
+
+/// Time spent in each layer of the transform, summed over all runs.
+#[derive(Debug, Default)]
+pub struct Timings {
+    pub parse: Duration,
+    pub modify: Duration,
+    pub write: Duration,
+}
+
+/// Runs the hidden `submod bench` command, which measures how fast
+/// synthesized subtitles are parsed, shifted and written, and returns the
+/// status to exit with.
+pub fn main(args: &[String]) -> i32 {
+    let matches = App::new("submod bench")
+        .about("Measure the performance of submod on synthesized subtitles")
+        .arg(Arg::with_name("cues")
+            .help("Number of subtitles to synthesize")
+            .long("cues")
+            .value_name("count")
+            .takes_value(true)
+            .default_value("100000")
+            .validator(helpers::is_count))
+        .arg(Arg::with_name("runs")
+            .help("Number of times to run the transform")
+            .long("runs")
+            .value_name("count")
+            .takes_value(true)
+            .default_value("5")
+            .validator(helpers::is_count))
+        .arg(Arg::with_name("convert")
+            .help("Convert the srt subtitles to vtt as well")
+            .long("convert"))
+        .get_matches_from_safe(args);
+    let matches = match matches {
+        Ok(matches) => matches,
+        Err(error) => return cli::report(error),
+    };
+
+    // Validated by helpers::is_count, and both have a default value:
+    let cues = matches.value_of("cues").unwrap().parse().unwrap();
+    let runs = matches.value_of("runs").unwrap().parse().unwrap();
+    let convert = matches.is_present("convert");
+
+    let timings = match measure(cues, runs, convert) {
+        Ok(timings) => timings,
+        Err(error) => return status::report(error),
+    };
+    let total = timings.parse + timings.modify + timings.write;
+    let per_second = |duration: Duration|
+        (cues * runs) as f64 / duration.as_secs_f64().max(1e-9);
+
+    outln!("{}", color::title("Benchmark."));
+    outln!(" {} subtitles, {} run{}{}.", cues, runs,
+        if runs == 1 { "" } else { "s" },
+        if convert { ", converted from srt to vtt" } else { "" });
+    for &(layer, duration) in &[("Parsing", timings.parse),
+        ("Shifting", timings.modify), ("Writing", timings.write),
+        ("Total", total)] {
+        outln!(" {:<9} {:>10.0} subtitles/second  ({:.3} s)",
+            layer, per_second(duration), duration.as_secs_f64());
+    }
+    match peak_rss() {
+        Some(kilobytes) => outln!(" Peak memory: {:.1} MB",
+            kilobytes as f64 / 1024.0),
+        None => outln!(" Peak memory: unknown on this platform"),
+    }
+    0
+}
+
+/// Shifts `cues` synthesized subtitles `runs` times,
+/// and times each layer of the transform separately.
+pub fn measure(cues: usize, runs: usize, convert: bool)
+    -> Result<Timings, Error>
+{
+    let input = synthesize(cues);
+    let options = Options { seconds: 1.5, ..Options::default() };
+    let mut timings = Timings::default();
+
+    for _ in 0..runs {
+        let start = Instant::now();
+        let (blocks, switch_opt) = cue::parse(input.as_bytes())?;
+        timings.parse += start.elapsed();
+
+        let start = Instant::now();
+        let (blocks, summary) = submod::modify(blocks, switch_opt, true,
+            !convert, &options)?;
+        timings.modify += start.elapsed();
+
+        let start = Instant::now();
+        let mut output = Vec::with_capacity(input.len());
+        cue::write(&blocks, &mut output, !convert)?;
+        timings.write += start.elapsed();
+
+        if summary.cues as usize != cues {
+            return Err(format_err!("Expected {} subtitles, but found {}",
+                cues, summary.cues));
+        }
+    }
+    Ok(timings)
+}
+
+/// Generates an srt file with subtitles of two lines each.
+fn synthesize(cues: usize) -> String {
+    let mut srt = String::with_capacity(cues * 80);
+    for i in 0..cues {
+        let start = i as f64 * 3.0 + 1.0;
+        srt.push_str(&format!("{}\n{} --> {}\n\
+            Subtitle number {}, with <i>some</i> text\nand a second line\n\n",
+            i + 1, cue::format_time(start, true),
+            cue::format_time(start + 2.5, true), i + 1));
+    }
+    srt
+}
+
+/// Returns the peak resident memory of the process in kilobytes,
+/// where the operating system reports it.
+fn peak_rss() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    status.lines()
+        .find(|line| line.starts_with("VmHWM:"))?
+        .split_whitespace()
+        .nth(1)?
+        .parse()
+        .ok()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn smoke() {
+        measure(50, 2, false).unwrap();
+        measure(50, 1, true).unwrap();
+    }
+}
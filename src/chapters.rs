@@ -0,0 +1,319 @@
+use std::fs;
+use std::path::Path;
+
+use failure::Error;
+use regex::Regex;
+
+use color;
+use cue::{self, Block};
+use text;
+use subtitles::Error as SubmodError;
+
+
+/// A named chapter of the video, from `start` to `end` in seconds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chapter {
+    pub title: String,
+    pub start: f64,
+    pub end: f64,
+}
+
+/// Reads the chapters of a video from either a WebVTT chapters file,
+/// or an ffmpeg metadata file as written by `ffmpeg -f ffmetadata`.
+pub fn read(path: &Path) -> Result<Vec<Chapter>, Error> {
+    let contents = fs::read_to_string(path).map_err(|error|
+        SubmodError::Io { path: path.to_owned(), message: format!(
+            "Could not read the chapters file `{}`: {}", path.display(),
+            error) })?;
+    let chapters = if contents.starts_with(";FFMETADATA") {
+        parse_ffmetadata(&contents)?
+    } else {
+        parse_vtt(&contents)?
+    };
+    if chapters.is_empty() {
+        return Err(format_err!("No chapters found in `{}`\n\n\
+            Use a WebVTT chapters file, or an ffmpeg metadata file \
+            with [CHAPTER] sections.", path.display()));
+    }
+    Ok(chapters)
+}
+
+/// Every cue of a WebVTT chapters file is a chapter, titled by its text.
+fn parse_vtt(contents: &str) -> Result<Vec<Chapter>, Error> {
+    let (blocks, _) = cue::parse(contents.as_bytes())?;
+    Ok(blocks.into_iter()
+        .filter_map(|block| match block {
+            Block::Cue(cue) => Some(Chapter {
+                title: cue.text.join(" "),
+                start: cue.start,
+                end: cue.end,
+            }),
+            _ => None,
+        })
+        .collect())
+}
+
+/// Reads the [CHAPTER] sections of an ffmpeg metadata file, whose
+/// START and END are given in units of their TIMEBASE.
+fn parse_ffmetadata(contents: &str) -> Result<Vec<Chapter>, Error> {
+    let mut chapters = Vec::new();
+    let mut in_chapter = false;
+    let (mut start, mut end): (Option<f64>, Option<f64>) = (None, None);
+    let (mut timebase, mut title) = (0.001, String::new());
+
+    // An extra section header at the end flushes the last chapter:
+    for line in contents.lines().chain(Some("[END]")).map(str::trim) {
+        if line.starts_with('[') {
+            if in_chapter {
+                match (start, end) {
+                    (Some(start), Some(end)) => chapters.push(Chapter {
+                        title: title.split_off(0),
+                        start: start * timebase,
+                        end: end * timebase,
+                    }),
+                    _ => return Err(format_err!("Chapter `{}` is missing \
+                        its START or END time.", title)),
+                }
+            }
+            in_chapter = line == "[CHAPTER]";
+            timebase = 0.001;
+            start = None;
+            end = None;
+            continue;
+        }
+        if !in_chapter {
+            continue;
+        }
+        let (key, value) = match line.find('=') {
+            Some(i) => (&line[..i], &line[i + 1..]),
+            None => continue,
+        };
+        match key {
+            "TIMEBASE" => {
+                let mut parts = value.split('/').map(|n| n.parse::<f64>());
+                timebase = match (parts.next(), parts.next()) {
+                    (Some(Ok(num)), Some(Ok(den))) if den != 0.0 => num / den,
+                    _ => return Err(format_err!("Invalid chapter TIMEBASE \
+                        `{}`", value)),
+                };
+            },
+            "START" => start = value.parse().ok(),
+            "END" => end = value.parse().ok(),
+            "title" => title = unescape_ffmetadata(value),
+            _ => {},
+        }
+    }
+    Ok(chapters)
+}
+
+/// Undoes the backslashes ffmpeg escapes `=`, `;`, `#`, `\` and newlines
+/// with in metadata values.
+fn unescape_ffmetadata(value: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unescaped.extend(chars.next()),
+            _ => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+
+fn escape_ffmetadata(value: &str) -> String {
+    let mut escaped = String::new();
+    for c in value.chars() {
+        if "=;#\\\n".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// The kind of chapters file `submod chapters` writes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    /// A WebVTT chapters track, with a cue per chapter.
+    Vtt,
+    /// An ffmpeg metadata file, as read by `ffmpeg -i chapters.txt
+    /// -map_metadata 1`.
+    Ffmetadata,
+}
+
+/// Makes a chapter of every cue whose visible text matches `pattern`,
+/// titled by its first capture group, or else by its text. Every chapter
+/// lasts until the next one starts, and the last one until the end of
+/// the last cue, or of the video if it's later.
+pub fn extract(blocks: &[Block], pattern: &Regex, video_duration_opt: Option<f64>)
+    -> Result<Vec<Chapter>, Error>
+{
+    let cues: Vec<&cue::Cue> = blocks.iter()
+        .filter_map(|block| match *block {
+            Block::Cue(ref cue) => Some(cue),
+            _ => None,
+        })
+        .collect();
+    let end = cues.iter().map(|cue| cue.end)
+        .chain(video_duration_opt)
+        .fold(0.0, f64::max);
+
+    let mut chapters: Vec<Chapter> = Vec::new();
+    for cue in cues {
+        let text = cue.text.iter()
+            .map(|line| text::visible_text(line))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let title = match pattern.captures(&text) {
+            Some(captures) => captures.get(1)
+                .map_or(text.as_str(), |group| group.as_str())
+                .trim()
+                .to_string(),
+            None => continue,
+        };
+        if let Some(previous) = chapters.last_mut() {
+            previous.end = cue.start;
+        }
+        chapters.push(Chapter { title, start: cue.start, end });
+    }
+    if chapters.is_empty() {
+        return Err(format_err!("No subtitle matches `{}`, so there are \
+            no chapters to write.", pattern));
+    }
+    Ok(chapters)
+}
+
+/// Writes chapters as a chapters file of the given format.
+pub fn write(chapters: &[Chapter], format: Format) -> String {
+    let mut contents = String::new();
+    match format {
+        Format::Vtt => {
+            contents.push_str("WEBVTT\n");
+            for (i, chapter) in chapters.iter().enumerate() {
+                contents.push_str(&format!("\n{}\n{} --> {}\n{}\n", i + 1,
+                    cue::format_time(chapter.start, false),
+                    cue::format_time(chapter.end, false), chapter.title));
+            }
+        },
+        Format::Ffmetadata => {
+            contents.push_str(";FFMETADATA1\n");
+            for chapter in chapters {
+                contents.push_str(&format!("\n[CHAPTER]\nTIMEBASE=1/1000\n\
+                    START={}\nEND={}\ntitle={}\n",
+                    cue::to_millis(chapter.start).max(0),
+                    cue::to_millis(chapter.end).max(0),
+                    escape_ffmetadata(&chapter.title)));
+            }
+        },
+    }
+    contents
+}
+
+/// Finds the chapter whose title matches `name`: an exact match ignoring
+/// case, or else the only title containing it.
+pub fn find<'a>(chapters: &'a [Chapter], name: &str)
+    -> Result<&'a Chapter, Error>
+{
+    let lowercase = name.to_lowercase();
+    if let Some(chapter) = chapters.iter()
+        .find(|chapter| chapter.title.to_lowercase() == lowercase) {
+        return Ok(chapter);
+    }
+    let matches: Vec<&Chapter> = chapters.iter()
+        .filter(|chapter| chapter.title.to_lowercase().contains(&lowercase))
+        .collect();
+    match matches.len() {
+        1 => Ok(matches[0]),
+        0 => Err(format_err!("No chapter matches `{}`\n\n\
+            Available chapters are:\n{}", name, list(chapters))),
+        _ => Err(format_err!("Several chapters match `{}`:\n{}",
+            name, list(&matches.into_iter().cloned().collect::<Vec<_>>()))),
+    }
+}
+
+fn list(chapters: &[Chapter]) -> String {
+    chapters.iter()
+        .map(|chapter| format!("    {} ({})", color::hint(&chapter.title),
+            cue::format_time(chapter.start, false)))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FFMETADATA: &str = ";FFMETADATA1\ntitle=Pilot\n\n\
+        [CHAPTER]\nTIMEBASE=1/1000\nSTART=0\nEND=95000\ntitle=Episode Recap\n\n\
+        [CHAPTER]\nTIMEBASE=1/90000\nSTART=8550000\nEND=11250000\n\
+        title=Opening\n\n\
+        [STREAM]\ntitle=Audio\n";
+
+    #[test]
+    fn parses_ffmetadata_chapters() {
+        assert_eq!(parse_ffmetadata(FFMETADATA).unwrap(), [
+            Chapter { title: String::from("Episode Recap"),
+                start: 0.0, end: 95.0 },
+            Chapter { title: String::from("Opening"),
+                start: 95.0, end: 125.0 },
+        ]);
+    }
+
+    #[test]
+    fn parses_vtt_chapters() {
+        let vtt = "WEBVTT\n\n1\n00:00:00.000 --> 00:01:35.000\nEpisode Recap\n\n\
+            2\n00:01:35.000 --> 00:02:05.000\nOpening\n";
+        assert_eq!(parse_vtt(vtt).unwrap(), parse_ffmetadata(FFMETADATA)
+            .unwrap());
+    }
+
+    #[test]
+    fn extracts_chapters_from_matching_cues() {
+        let (blocks, _) = cue::parse("1\n00:00:01,000 --> 00:00:03,000\n\
+            [Scene: Kitchen]\n\n\
+            2\n00:00:04,000 --> 00:00:06,000\nHello.\n\n\
+            3\n00:01:00,000 --> 00:01:02,500\n<i>[Scene: Garden; dusk]</i>\n\n\
+            4\n00:01:03,000 --> 00:01:05,000\nBye.\n".as_bytes()).unwrap();
+        let pattern = Regex::new(r"^\[Scene: (.*)\]").unwrap();
+        assert_eq!(extract(&blocks, &pattern, None).unwrap(), [
+            Chapter { title: String::from("Kitchen"), start: 1.0, end: 60.0 },
+            Chapter { title: String::from("Garden; dusk"),
+                start: 60.0, end: 65.0 },
+        ]);
+        assert_eq!(extract(&blocks, &pattern, Some(90.0)).unwrap()[1].end,
+            90.0);
+        let whole = Regex::new(r"^\[Scene:").unwrap();
+        assert_eq!(extract(&blocks, &whole, None).unwrap()[0].title,
+            "[Scene: Kitchen]");
+        assert!(extract(&blocks, &Regex::new("Credits").unwrap(), None)
+            .is_err());
+    }
+
+    #[test]
+    fn writes_chapters_that_read_back_the_same() {
+        let chapters = vec![
+            Chapter { title: String::from("Kitchen"), start: 1.0, end: 60.0 },
+            Chapter { title: String::from("Garden; a=b #2 \\o/"),
+                start: 60.0, end: 3725.25 },
+        ];
+        let ffmetadata = write(&chapters, Format::Ffmetadata);
+        assert!(ffmetadata.contains("[CHAPTER]\nTIMEBASE=1/1000\n\
+            START=60000\nEND=3725250\ntitle=Garden\\; a\\=b \\#2 \\\\o/\n"));
+        assert_eq!(parse_ffmetadata(&ffmetadata).unwrap(), chapters);
+        let vtt = write(&chapters, Format::Vtt);
+        assert!(vtt.starts_with("WEBVTT\n\n1\n00:00:01.000 --> 00:01:00.000\n\
+            Kitchen\n"));
+        assert_eq!(parse_vtt(&vtt).unwrap(), chapters);
+    }
+
+    #[test]
+    fn finds_chapters_by_case_insensitive_substring() {
+        let chapters = parse_ffmetadata(FFMETADATA).unwrap();
+        assert_eq!(find(&chapters, "recap").unwrap().start, 0.0);
+        assert_eq!(find(&chapters, "OPENING").unwrap().start, 95.0);
+        let error = find(&chapters, "Credits").unwrap_err().to_string();
+        assert!(error.contains("Episode Recap") && error.contains("Opening"));
+        assert!(find(&chapters, "e").is_err());
+    }
+}
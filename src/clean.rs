@@ -0,0 +1,271 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use clap::{App, Arg};
+use failure::Error;
+use regex::Regex;
+
+use cli;
+use color;
+use name;
+use status;
+
+
+/// A file submod generated, as recognized by the tag in its name.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Artifact {
+    /// An output file, like `movie__[+2.50_Sec+].srt`.
+    Shifted(PathBuf),
+    /// An input renamed by `--overname`, like `movie__[Original].srt`.
+    Original(PathBuf),
+    /// A copy of an input saved by `--keep-backups`,
+    /// like `movie.srt.bak-20240501T120301`. Never restored.
+    Backup(PathBuf),
+}
+
+/// What `clean` did, or would do without `delete` and `restore`.
+#[derive(Debug, Default, PartialEq)]
+pub struct Cleaned {
+    pub deleted: Vec<PathBuf>,
+    /// The original files, and the files they were renamed to.
+    pub restored: Vec<(PathBuf, PathBuf)>,
+    /// The files that were left alone.
+    pub kept: Vec<PathBuf>,
+}
+
+/// Runs the `submod clean` command, which lists the files submod generated
+/// in a directory, and deletes them or restores the originals on request,
+/// and returns the status to exit with.
+pub fn main(args: &[String]) -> i32 {
+    let matches = App::new("submod clean")
+        .about("List, delete or restore the files submod generated \
+            in a directory.\nOnly files with a submod tag like \
+            __[+2.50_Sec+] or __[Original], and the backups of \
+            --keep-backups like movie.srt.bak-20240501T120301, \
+            are ever touched.")
+        .arg(Arg::with_name("dir")
+            .help("Directory to clean; subdirectories are left alone")
+            .required(true)
+            .index(1))
+        .arg(Arg::with_name("yes")
+            .help("Delete the files, instead of only listing them")
+            .long("yes"))
+        .arg(Arg::with_name("restore")
+            .help("Rename the __[Original] files back over their processed\n\
+                counterparts, like `movie__[Original].srt` to `movie.srt`")
+            .long("restore"))
+        .get_matches_from_safe(args);
+    let matches = match matches {
+        Ok(matches) => matches,
+        Err(error) => return cli::report(error),
+    };
+
+    let dir = Path::new(matches.value_of("dir").unwrap());
+    let delete = matches.is_present("yes");
+    let restore = matches.is_present("restore");
+    let cleaned = match find(dir)
+        .and_then(|artifacts| clean(artifacts, delete, restore)) {
+        Ok(cleaned) => cleaned,
+        Err(error) => return status::report(error),
+    };
+
+    if cleaned.deleted.is_empty() && cleaned.restored.is_empty()
+        && cleaned.kept.is_empty() {
+        outln!(" No files generated by submod were found in `{}`.",
+            dir.display());
+        return 0;
+    }
+    if !cleaned.deleted.is_empty() || !cleaned.restored.is_empty() {
+        outln!("{}", color::title("Success."));
+    }
+    for (original, restored) in &cleaned.restored {
+        outln!(" Restored: {} -> {}", original.display(),
+            restored.display());
+    }
+    for path in &cleaned.deleted {
+        outln!(" Deleted:  {}", path.display());
+    }
+    if !cleaned.kept.is_empty() {
+        outln!(" Found {} file{} generated by submod:", cleaned.kept.len(),
+            if cleaned.kept.len() == 1 { "" } else { "s" });
+        for path in &cleaned.kept {
+            outln!("    {}", path.display());
+        }
+        outln!(" Nothing was deleted. Use {} to delete {}.",
+            color::hint("--yes"),
+            if cleaned.kept.len() == 1 { "it" } else { "them" });
+        if !restore && cleaned.kept.iter()
+            .any(|path| is_artifact(path) == Some(true)) {
+            outln!(" Use {} to rename the __[Original] files back over \
+                their processed counterparts.", color::hint("--restore"));
+        }
+    }
+    0
+}
+
+/// Finds the files submod generated in `dir`, not looking in its
+/// subdirectories. Only regular files with a submod tag ending their stem,
+/// and the extension of a subtitle file, are recognized, along with the
+/// backups `--keep-backups` made of subtitle files.
+pub fn find(dir: &Path) -> Result<Vec<Artifact>, Error> {
+    if !dir.is_dir() {
+        return Err(format_err!("`{}` is not a directory.", dir.display()));
+    }
+    let mut artifacts: Vec<Artifact> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().map(|t| t.is_file()).unwrap_or(false))
+        .filter_map(|entry| {
+            let path = entry.path();
+            if is_backup(&path) {
+                return Some(Artifact::Backup(path));
+            }
+            is_artifact(&path).map(|original| if original {
+                Artifact::Original(path)
+            } else {
+                Artifact::Shifted(path)
+            })
+        })
+        .collect();
+    artifacts.sort();
+    Ok(artifacts)
+}
+
+/// Deletes the `artifacts` if `delete`, and renames the originals back over
+/// their processed counterparts first if `restore`. Without either,
+/// nothing is changed, and all artifacts are reported as kept.
+pub fn clean(artifacts: Vec<Artifact>, delete: bool, restore: bool)
+    -> Result<Cleaned, Error>
+{
+    let mut cleaned = Cleaned::default();
+    for artifact in artifacts {
+        match artifact {
+            Artifact::Original(path) if restore => {
+                let counterpart = counterpart(&path);
+                fs::rename(&path, &counterpart)?;
+                cleaned.restored.push((path, counterpart));
+            },
+            Artifact::Original(path) | Artifact::Shifted(path)
+                | Artifact::Backup(path) if delete => {
+                fs::remove_file(&path)?;
+                cleaned.deleted.push(path);
+            },
+            Artifact::Original(path) | Artifact::Shifted(path)
+                | Artifact::Backup(path) => cleaned.kept.push(path),
+        }
+    }
+    Ok(cleaned)
+}
+
+/// Whether the file name has the tag of an original (`Some(true)`),
+/// of an output (`Some(false)`), or no submod tag at all (`None`).
+fn is_artifact(path: &Path) -> Option<bool> {
+    let name = path.file_name()?.to_str()?;
+    let tags = Regex::new(&format!(r"^.*?((__\[Original\])|{})\.(srt|vtt|ass|ssa|sub|ttml|dfxp|xml)$",
+        name::SHIFT_TAGS)).unwrap();
+    tags.captures(name).map(|captures| captures.get(2).is_some())
+}
+
+/// Whether the file name is that of a backup `--keep-backups` made of
+/// a subtitle file, like `movie.srt.bak`, `movie.srt.bak-20240501T120301`
+/// or `movie.srt.bak-20240501T120301-2`.
+fn is_backup(path: &Path) -> bool {
+    let backup = Regex::new(
+        r"^.+\.(srt|vtt|ass|ssa|sub|ttml|dfxp|xml)\.bak(-\d{8}T\d{6})?(-\d+)?$")
+        .unwrap();
+    path.file_name().and_then(|name| name.to_str())
+        .is_some_and(|name| backup.is_match(name))
+}
+
+/// The processed file an original was renamed from by `--overname`,
+/// like `movie.srt` for `movie__[Original].srt`.
+fn counterpart(original: &Path) -> PathBuf {
+    let name = original.file_name().unwrap().to_str().unwrap()
+        .replacen("__[Original].", ".", 1);
+    original.with_file_name(name)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use testing;
+
+    /// Creates an empty directory for a test, with the given files in it.
+    fn temp_dir(test: &str, files: &[&str]) -> PathBuf {
+        let dir = testing::temp_dir(test);
+        fs::create_dir(dir.join("sub")).unwrap();
+        for file in files {
+            fs::write(dir.join(file), file).unwrap();
+        }
+        dir
+    }
+
+    fn names(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(dir).unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        names
+    }
+
+    const FILES: &[&str] = &["movie.srt", "movie__[+2.50_Sec+].srt",
+        "movie__[Original].srt", "movie.en__[-1.00_Sec-].vtt",
+        "old__[+1.00_Sec+]__[+0.50_Sec+].srt", "notes__[Original].txt",
+        "Show [+1.5] __[x].srt", "movie__[+2.50_Sec+].srt.bak",
+        "movie.srt.bak-20240501T120301", "movie.srt.bak-20240501T120301-2",
+        "movie.srt.backup", "notes.txt.bak",
+        "__[+2.50_Sec+] is a title.srt", "sub/movie__[+1.00_Sec+].srt"];
+
+    #[test]
+    fn lists_without_changing_anything() {
+        let dir = temp_dir("list", FILES);
+        let cleaned = clean(find(&dir).unwrap(), false, false).unwrap();
+        assert_eq!(cleaned.kept, [
+            dir.join("movie.en__[-1.00_Sec-].vtt"),
+            dir.join("movie__[+2.50_Sec+].srt"),
+            dir.join("old__[+1.00_Sec+]__[+0.50_Sec+].srt"),
+            dir.join("movie__[Original].srt"),
+            dir.join("movie.srt.bak-20240501T120301"),
+            dir.join("movie.srt.bak-20240501T120301-2"),
+            dir.join("movie__[+2.50_Sec+].srt.bak"),
+        ]);
+        assert_eq!(names(&dir).len(), FILES.len());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn only_deletes_tagged_files() {
+        let dir = temp_dir("delete", FILES);
+        clean(find(&dir).unwrap(), true, false).unwrap();
+        assert_eq!(names(&dir), ["Show [+1.5] __[x].srt",
+            "__[+2.50_Sec+] is a title.srt", "movie.srt",
+            "movie.srt.backup", "notes.txt.bak", "notes__[Original].txt",
+            "sub"]);
+        assert_eq!(names(&dir.join("sub")), ["movie__[+1.00_Sec+].srt"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn restores_originals_over_their_counterparts() {
+        let dir = temp_dir("restore", FILES);
+        let cleaned = clean(find(&dir).unwrap(), false, true).unwrap();
+        assert_eq!(cleaned.restored, [(dir.join("movie__[Original].srt"),
+            dir.join("movie.srt"))]);
+        assert_eq!(fs::read_to_string(dir.join("movie.srt")).unwrap(),
+            "movie__[Original].srt");
+        assert_eq!(names(&dir).len(), FILES.len() - 1);
+        assert_eq!(cleaned.kept.len(), 6);
+
+        // Restoring and deleting leaves only the untagged files:
+        let dir = temp_dir("restore-delete", FILES);
+        clean(find(&dir).unwrap(), true, true).unwrap();
+        assert_eq!(names(&dir), ["Show [+1.5] __[x].srt",
+            "__[+2.50_Sec+] is a title.srt", "movie.srt",
+            "movie.srt.backup", "notes.txt.bak", "notes__[Original].txt",
+            "sub"]);
+        assert_eq!(fs::read_to_string(dir.join("movie.srt")).unwrap(),
+            "movie__[Original].srt");
+        fs::remove_dir_all(&dir).unwrap();
+        fs::remove_dir_all(temp_dir("restore", &[])).unwrap();
+    }
+}
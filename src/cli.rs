@@ -0,0 +1,927 @@
+use clap::{self, App, AppSettings, Arg};
+
+use helpers;
+use status;
+
+
+/// The options with a short form, which presets must know to let them
+/// override their own options, like `-s` overrides `--start`.
+pub const SHORT_OPTIONS: &[(char, &str)] = &[('s', "--start"),
+    ('S', "--stop"), ('o', "--overwrite"), ('O', "--overname"),
+    ('q', "--quiet")];
+
+/// The arguments of submod.
+pub fn build_cli() -> App<'static, 'static> {
+    let app = App::new(env!("CARGO_PKG_NAME"))
+        .version(env!("CARGO_PKG_VERSION"))
+        .version_short("v")
+        // AllowNegativeNumbers allows passing negative seconds, while
+        // flags still end the list of files:
+        .setting(AppSettings::AllowNegativeNumbers)
+        .about("Modify the time encoding of .srt, .vtt, .ass, .ssa, .sub \
+                or TTML subtitle files.\n\
+                By default, submod generates a new output file, \
+                without overwriting the input.")
+        // Clap can't end a list of files with a positional argument that
+        // may be left out, so <seconds> is split off them by
+        // `split_positionals`, and only described here:
+        .usage("submod [FLAGS] [OPTIONS] <file>... <seconds>")
+        .after_help(status::HELP)
+        .arg(Arg::with_name("file")
+            .help("File names or paths of the subtitle files to modify,\n\
+                one by one, like S01E*.srt, followed by <seconds>:\n\
+                the seconds by which to add or subtract the time encoding,\n\
+                like -83.5, or a time like -01:23.5 or -00:01:23.500;\n\
+                optional with --speed, --rate, --factor, --step or\n\
+                --range")
+            .required_unless("manifest")
+            .multiple(true)
+            .index(1))
+        .arg(Arg::with_name("start")
+            .help("Specify at what time the modification should start;\n\
+                subtitles ending exactly then are still modified")
+            .short("s") // By default, start is at the beginning of the file
+            .long("start")
+            .value_name("hh:mm:ss")
+            .takes_value(true)
+            .validator(helpers::is_timing_or_percentage))
+        .arg(Arg::with_name("stop")
+            .help("Specify at what time the modification should stop;\n\
+                subtitles starting exactly then are not modified\n\
+                Use ':' to separate hours, minutes and seconds, like so:\n\
+                hh:mm:ss to specify hours, minutes and seconds\n   \
+                mm:ss to only specify minutes and seconds\n      \
+                ss to only specify seconds\n\
+                Seconds may have milliseconds, like 00:14:07.438 or\n\
+                00:14:07,438 as copied from an srt file\n\
+                Or give a percentage of the file duration, like 90%")
+            .short("S") // By default, stop is at the end of the file
+            .long("stop")
+            .value_name("hh:mm:ss")
+            .takes_value(true)
+            .validator(helpers::is_timing_or_percentage))
+        .arg(Arg::with_name("from_cue")
+            .help("Start the modification at this subtitle, counting from 1\n\
+                in the order of the file, instead of at a time")
+            .long("from-cue")
+            .value_name("N")
+            .takes_value(true)
+            .validator(helpers::is_count)
+            .conflicts_with_all(&["start", "stop", "start_chapter",
+                "stop_chapter"]))
+        .arg(Arg::with_name("to_cue")
+            .help("Stop the modification after this subtitle, which is\n\
+                modified too, instead of at a time")
+            .long("to-cue")
+            .value_name("M")
+            .takes_value(true)
+            .validator(helpers::is_count)
+            .conflicts_with_all(&["start", "stop", "start_chapter",
+                "stop_chapter"]))
+        .arg(Arg::with_name("chapters")
+            .help("Chapters of the video, as a WebVTT chapters file or an\n\
+                ffmpeg metadata file, for --start-chapter and --stop-chapter")
+            .long("chapters")
+            .value_name("file")
+            .takes_value(true))
+        .arg(Arg::with_name("start_chapter")
+            .help("Start the modification where this chapter starts;\n\
+                names match case-insensitively, or by a part of them")
+            .long("start-chapter")
+            .value_name("name")
+            .takes_value(true)
+            .requires("chapters")
+            .conflicts_with("start"))
+        .arg(Arg::with_name("stop_chapter")
+            .help("Stop the modification where this chapter ends")
+            .long("stop-chapter")
+            .value_name("name")
+            .takes_value(true)
+            .requires("chapters")
+            .conflicts_with("stop"))
+        .arg(Arg::with_name("shift_starts")
+            .help("Shift the start of every subtitle by this many seconds\n\
+                on top of <seconds>, like -0.1 for earlier in-cues")
+            .long("shift-starts")
+            .value_name("seconds")
+            .takes_value(true)
+            .validator(helpers::is_float))
+        .arg(Arg::with_name("shift_ends")
+            .help("Shift the end of every subtitle by this many seconds\n\
+                on top of <seconds>; ends are kept from overlapping the\n\
+                next subtitle, and from coming before their start")
+            .long("shift-ends")
+            .value_name("seconds")
+            .takes_value(true)
+            .validator(helpers::is_float))
+        .arg(Arg::with_name("trim_leading_silence")
+            .help("Shift all subtitles so the first one starts this many\n\
+                seconds after 00:00:00, instead of by <seconds>; without\n\
+                a value, like `--trim-leading-silence`, it starts at 1\n\
+                second. Delays of more than 10 seconds take --force")
+            .long("trim-leading-silence")
+            .value_name("seconds")
+            .takes_value(true)
+            .min_values(0)
+            .require_equals(true)
+            .validator(helpers::is_non_negative))
+        .arg(Arg::with_name("speed")
+            .help("Playback speed of the video in percent of the speed the\n\
+                subtitles were made for, e.g. 104.271 for a PAL speedup;\n\
+                `pal` and `ntsc` are aliases for 104.271 and 95.904")
+            .long("speed")
+            .value_name("percent")
+            .takes_value(true)
+            .conflicts_with_all(&["rate", "factor"])
+            .validator(helpers::is_speed))
+        .arg(Arg::with_name("rate")
+            .help("Frame rate the subtitles were made for, and that of the\n\
+                video, like 23.976:25; timestamps are multiplied by their\n\
+                ratio before adding <seconds>")
+            .long("rate")
+            .value_name("from:to")
+            .takes_value(true)
+            .conflicts_with("factor")
+            .validator(helpers::is_rate))
+        .arg(Arg::with_name("factor")
+            .help("Multiply all timestamps by this factor before adding\n\
+                <seconds>, like 0.95904 for 23.976 to 25 fps")
+            .long("factor")
+            .value_name("factor")
+            .takes_value(true)
+            .validator(helpers::is_positive))
+        .arg(Arg::with_name("step")
+            .help("Add seconds to all subtitles starting from this time,\n\
+                on top of <seconds> and any earlier steps; may be repeated\n\
+                E.g. `--step 12:30=-85` shifts everything from 12:30\n\
+                85 seconds earlier, and leaves what precedes it untouched")
+            .long("step")
+            .value_name("hh:mm:ss=seconds")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .validator(helpers::is_step))
+        .arg(Arg::with_name("range")
+            .help("Add seconds to the subtitles starting from the start of\n\
+                this range up to its end, on top of <seconds> and any\n\
+                steps; leave out the start or end for an open range, like\n\
+                `-5:00=-2` or `1:30:00-=1.5`; may be repeated")
+            .long("range")
+            .value_name("start-end=seconds")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .allow_hyphen_values(true)
+            .validator(helpers::is_range))
+        .arg(Arg::with_name("shift")
+            .help("Add seconds to the subtitles starting within each of\n\
+                these disjoint ranges, separated by commas, on top of\n\
+                <seconds>, which may be left out; like\n\
+                `12:00-25:00=+30,25:00-=+60` for a rip with ad breaks")
+            .long("shift")
+            .value_name("ranges")
+            .takes_value(true)
+            .allow_hyphen_values(true)
+            .validator(helpers::is_ranges))
+        .arg(Arg::with_name("max_shift")
+            .help("Flag subtitles that --step or --speed shift by more than\n\
+                this many seconds on top of <seconds>; 0 disables the guard")
+            .long("max-shift-per-cue")
+            .value_name("seconds")
+            .takes_value(true)
+            .default_value("600")
+            .validator(helpers::is_non_negative))
+        .arg(Arg::with_name("max_line")
+            .help("Flag subtitles with a line longer than this many\n\
+                characters, not counting tags")
+            .long("max-line")
+            .value_name("chars")
+            .takes_value(true)
+            .validator(helpers::is_count))
+        .arg(Arg::with_name("max_lines")
+            .help("Flag subtitles with more lines than this")
+            .long("max-lines")
+            .value_name("count")
+            .takes_value(true)
+            .validator(helpers::is_count))
+        .arg(Arg::with_name("wide_chars")
+            .help("Count wide characters like CJK ideographs, kana and\n\
+                emoji as 2 in the lines of --max-line and\n\
+                --merge-max-line, for the width they take up on screen")
+            .long("wide-chars"))
+        .arg(Arg::with_name("warnings_as_errors")
+            .help("Exit with status 4 if there were any warnings, like\n\
+                deleted subtitles, after writing the output as usual")
+            .long("warnings-as-errors"))
+        .arg(Arg::with_name("strict")
+            .help("Fail instead of warning when a safety check is tripped,\n\
+                like --max-shift-per-cue, or when the input switches to\n\
+                another format halfway")
+            .long("strict"))
+        .arg(Arg::with_name("on_negative")
+            .help("What to do with subtitles shifted to end before\n\
+                00:00:00: `delete` them, `clamp` them to start at it and\n\
+                last a millisecond, or fail with an `error` as soon as\n\
+                any subtitle would start before it [default: delete]")
+            .long("on-negative")
+            .value_name("policy")
+            .takes_value(true)
+            .possible_values(&["delete", "clamp", "error"]))
+        .arg(Arg::with_name("overflow")
+            .help("What to do with subtitles ending after 99:59:59,999,\n\
+                the latest time srt timestamps can represent: `clamp`\n\
+                them to it, `drop` them, or fail with an `error`\n\
+                [default: clamp, or error with --strict]")
+            .long("overflow")
+            .value_name("policy")
+            .takes_value(true)
+            .possible_values(&["clamp", "drop", "error"]))
+        .arg(Arg::with_name("min_start")
+            .help("Earliest time in seconds a subtitle may start, as some\n\
+                players glitch on subtitles shown at 00:00:00.000; earlier\n\
+                subtitles start at it, or are dropped if they end before it")
+            .long("min-start")
+            .value_name("seconds")
+            .takes_value(true)
+            .default_value("0")
+            .validator(helpers::is_non_negative))
+        .arg(Arg::with_name("boundary_conflict")
+            .help("What to do with subtitles that overlap or are out of\n\
+                order because only some were shifted: `reorder` sorts\n\
+                them again, `trim` cuts the earlier one short, `warn`\n\
+                only reports them, and `error` fails\n\
+                [default: warn, or error with --strict]")
+            .long("boundary-conflict")
+            .value_name("policy")
+            .takes_value(true)
+            .possible_values(&["reorder", "trim", "warn", "error"]))
+        .arg(Arg::with_name("merge_continuations")
+            .help("Join subtitles that continue the sentence of the one\n\
+                before them, like auto-captions often split them, if the\n\
+                same speaker is speaking and the result fits in two lines")
+            .long("merge-continuations"))
+        .arg(Arg::with_name("merge_max_gap")
+            .help("Longest gap in seconds between subtitles to join")
+            .long("merge-max-gap")
+            .value_name("seconds")
+            .takes_value(true)
+            .default_value("0.1")
+            .validator(helpers::is_non_negative))
+        .arg(Arg::with_name("merge_max_line")
+            .help("Longest line in characters a joined subtitle may have")
+            .long("merge-max-line")
+            .value_name("chars")
+            .takes_value(true)
+            .default_value("42")
+            .validator(helpers::is_count))
+        .arg(Arg::with_name("merge_max_cps")
+            .help("Most characters per second a joined subtitle may have")
+            .long("merge-max-cps")
+            .value_name("chars")
+            .takes_value(true)
+            .default_value("20")
+            .validator(helpers::is_positive))
+        .arg(Arg::with_name("fix_overlaps")
+            .help("Shorten subtitles that overlap the next one, like\n\
+                after shifting, to end --min-gap before it starts")
+            .long("fix-overlaps"))
+        .arg(Arg::with_name("min_gap")
+            .help("Gap in seconds --fix-overlaps leaves between subtitles")
+            .long("min-gap")
+            .value_name("seconds")
+            .takes_value(true)
+            .default_value("0.001")
+            .validator(helpers::is_non_negative))
+        .arg(Arg::with_name("merge_collapsed")
+            .help("Merge the subtitles --fix-overlaps can't shorten, as\n\
+                they'd be left without any duration, into the next one")
+            .long("merge-collapsed")
+            .requires("fix_overlaps"))
+        .arg(Arg::with_name("min_duration")
+            .help("Lengthen subtitles lasting less than this many seconds,\n\
+                like for slower readers, as far as the next one allows")
+            .long("min-duration")
+            .value_name("seconds")
+            .takes_value(true)
+            .validator(helpers::is_positive))
+        .arg(Arg::with_name("max_duration")
+            .help("Split subtitles lasting longer than this many seconds,\n\
+                preferably at the end of a sentence or clause")
+            .long("max-duration")
+            .value_name("seconds")
+            .takes_value(true)
+            .validator(helpers::is_positive))
+        .arg(Arg::with_name("truncate_long")
+            .help("Cut subtitles lasting longer than --max-duration short\n\
+                instead of splitting them")
+            .long("truncate-long")
+            .requires("max_duration")
+            .conflicts_with("split_weights"))
+        .arg(Arg::with_name("drop_low_confidence")
+            .help("Drop the subtitles of a speech recognizer that have a\n\
+                lower confidence than this in the vtt NOTE before them,\n\
+                as `confidence: 0.8` or JSON, along with that NOTE")
+            .long("drop-low-confidence")
+            .value_name("threshold")
+            .takes_value(true)
+            .validator(helpers::is_float))
+        .arg(Arg::with_name("prepend_cue")
+            .help("Insert a subtitle with this text before the first one,\n\
+                like a credit, if there's room for it; `\\n` breaks the line")
+            .long("prepend-cue")
+            .value_name("text")
+            .takes_value(true))
+        .arg(Arg::with_name("append_cue")
+            .help("Insert a subtitle with this text after the last one,\n\
+                like a credit; `\\n` breaks the line")
+            .long("append-cue")
+            .value_name("text")
+            .takes_value(true))
+        .arg(Arg::with_name("credit_duration")
+            .help("How long the subtitles of --prepend-cue and --append-cue\n\
+                last, in seconds")
+            .long("credit-duration")
+            .value_name("seconds")
+            .takes_value(true)
+            .default_value("3")
+            .validator(helpers::is_positive))
+        .arg(Arg::with_name("credit_gap")
+            .help("Gap in seconds between the subtitles of --prepend-cue\n\
+                and --append-cue and the first or last subtitle")
+            .long("credit-gap")
+            .value_name("seconds")
+            .takes_value(true)
+            .default_value("0.5")
+            .validator(helpers::is_non_negative))
+        .arg(Arg::with_name("split_weights")
+            .help("What the time of a split subtitle is divided in\n\
+                proportion to: `chars` or `words`, which gives long\n\
+                words like German compounds less time [default: chars]")
+            .long("split-weights")
+            .value_name("weights")
+            .takes_value(true)
+            .requires("max_duration")
+            .possible_values(&["chars", "words"]))
+        .arg(Arg::with_name("video_duration")
+            .help("Duration of the video, to warn when the subtitles end\n\
+                more than --duration-margin before or after it, like\n\
+                subtitles made for a different cut")
+            .long("video-duration")
+            .value_name("hh:mm:ss")
+            .takes_value(true)
+            .validator(helpers::is_timing))
+        .arg(Arg::with_name("duration_margin")
+            .help("Seconds the subtitles may end before or after the video")
+            .long("duration-margin")
+            .value_name("seconds")
+            .takes_value(true)
+            .default_value("120")
+            .validator(helpers::is_non_negative))
+        .arg(Arg::with_name("companion")
+            .help("Give the subtitles of this file, like a vtt track of\n\
+                metadata, the same timing as those of the input they\n\
+                pair with: the one with the same identifier, or else the\n\
+                next one without a pair. It's written next to itself, tagged\n\
+                like the output, and both must have the same subtitles")
+            .long("companion")
+            .value_name("file")
+            .takes_value(true)
+            .conflicts_with_all(&["stdout", "check", "dry_run", "manifest"]))
+        .arg(Arg::with_name("verify_against")
+            .help("Measure how far the output is still off from the subtitle\n\
+                with the same stem in this directory, like a correctly\n\
+                timed translation, and warn when it's more than\n\
+                --verify-tolerance")
+            .long("verify-against")
+            .value_name("dir")
+            .takes_value(true))
+        .arg(Arg::with_name("verify_tolerance")
+            .help("Largest offset in seconds --verify-against accepts")
+            .long("verify-tolerance")
+            .value_name("seconds")
+            .takes_value(true)
+            .default_value("0.5")
+            .validator(helpers::is_non_negative))
+        .arg(Arg::with_name("lenient")
+            .help("Repair inputs where possible, like files that switch\n\
+                to another format halfway because several files were\n\
+                concatenated: all their subtitles are merged and sorted")
+            .long("lenient")
+            .conflicts_with("strict"))
+        .arg(Arg::with_name("only_italic")
+            .help("Only modify subtitles that are entirely in italics,\n\
+                like forced narrative subtitles often are")
+            .long("only-italic")
+            .conflicts_with("only_non_italic"))
+        .arg(Arg::with_name("only_non_italic")
+            .help("Only modify subtitles that are not entirely in italics")
+            .long("only-non-italic"))
+        .arg(Arg::with_name("pin_first")
+            .help("Keep the timing of the first subtitle, like a credit\n\
+                authored to show at the very start; shifted subtitles\n\
+                overlapping it are trimmed")
+            .long("pin-first-cue"))
+        .arg(Arg::with_name("pin_last")
+            .help("Keep the timing of the last subtitle")
+            .long("pin-last-cue"))
+        .arg(Arg::with_name("include_cues")
+            .help("Only modify these cue numbers; either a comma-separated\n\
+                list of numbers and ranges like `1-44,121-`, or `@file`\n\
+                to read one range per line from a file")
+            .long("include-cues")
+            .value_name("spec|@file")
+            .takes_value(true)
+            .validator(helpers::is_cue_spec))
+        .arg(Arg::with_name("exclude_cues")
+            .help("Never modify these cue numbers (same syntax as --include-cues)\n\
+                All selectors are combined: a cue is only modified when it\n\
+                falls within --start/--stop, is included, is not excluded,\n\
+                and matches --only-italic or --only-non-italic")
+            .long("exclude-cues")
+            .value_name("spec|@file")
+            .takes_value(true)
+            .validator(helpers::is_cue_spec))
+        .arg(Arg::with_name("manifest")
+            .help("Run the jobs of this file, or of stdin for `-`, one per\n\
+                line as `input<TAB>seconds<TAB>output`, where the output\n\
+                is optional; blank lines and # comments are skipped, and\n\
+                the other options apply to every job")
+            .long("manifest")
+            .value_name("file")
+            .takes_value(true)
+            .conflicts_with_all(&["file", "output", "check", "stdout"]))
+        .arg(Arg::with_name("output")
+            .help("Specify file name or path to store the output file\n")
+            .long("out")
+            .value_name("filename")
+            .takes_value(true)
+            // The filename extension of `--output` takes precedence
+            // over --srt and --vtt, so we don't allow combining them:
+            .conflicts_with_all(&["overwrite", "overname", "srt", "vtt"])
+            // (Ideally, we should be able to notify the user
+            // with an added error message!)
+            .validator(helpers::is_subtitle))
+        .arg(Arg::with_name("name_template")
+            .help("Name the output file after this template, relative to\n\
+                the directory of the input, with the placeholders {stem},\n\
+                {tag} (like __[+2.500_Sec+]) and {seconds} (like +2.500).\n\
+                The extension is added, and unsafe characters replaced")
+            .long("name-template")
+            .value_name("template")
+            .takes_value(true)
+            .conflicts_with_all(&["output", "overwrite", "overname"])
+            .validator(helpers::is_name_template))
+        .arg(Arg::with_name("allow_outside_output_dir")
+            .help("Write outputs named by --name-template, or by the lines\n\
+                of --manifest, even outside the directory of their input,\n\
+                or the current directory for --manifest, like through ..\n\
+                or a symlink")
+            .long("allow-outside-output-dir"))
+        .arg(Arg::with_name("tag_mode")
+            .help("How to tag the output file name: `cumulative` adds this\n\
+                shift to the tag of the input, `delta` only shows this shift,\n\
+                and `none` removes the tag [default: cumulative]")
+            .long("tag-mode")
+            .value_name("mode")
+            .takes_value(true)
+            .possible_values(&["cumulative", "delta", "none"])
+            .conflicts_with("output"))
+        .arg(Arg::with_name("precision")
+            .help("Number of decimals of the seconds in the tag of the\n\
+                output file name, from 0 to 3; with 3, tags keep the\n\
+                millisecond, so they add up exactly however often an\n\
+                output is shifted again")
+            .long("precision")
+            .value_name("decimals")
+            .takes_value(true)
+            .default_value("3")
+            .validator(helpers::is_precision))
+        .arg(Arg::with_name("stdout")
+            .help("Write the output to stdout instead of a file")
+            .long("stdout")
+            .conflicts_with_all(&["output", "overwrite", "overname",
+                "check", "explain"]))
+        .arg(Arg::with_name("format")
+            .help("Format of the input, for stdin, pipes and other inputs\n\
+                without a file extension, like <(curl ...); by default,\n\
+                it's told by their content, and those without any timing\n\
+                lines are srt, those with a [Script Info] header ass,\n\
+                and those with an XML declaration or <tt> element ttml.\n\
+                The dialect of .sub files is detected by their content,\n\
+                and given by microdvd or subviewer when it can't be")
+            .long("format")
+            .value_name("format")
+            .takes_value(true)
+            .possible_values(&["srt", "vtt", "ass", "ssa", "ttml",
+                "microdvd", "subviewer"]))
+        .arg(Arg::with_name("fps")
+            .help("Frame rate of the video of MicroDVD .sub files, like\n\
+                23.976, to convert their frames to and from seconds;\n\
+                by default, the one their {1}{1}<rate> line gives")
+            .long("fps")
+            .value_name("rate")
+            .takes_value(true)
+            .validator(helpers::is_positive))
+        .arg(Arg::with_name("max_input_size")
+            .help("Largest number of megabytes to read from a pipe,\n\
+                or to download from a URL")
+            .long("max-input-size")
+            .value_name("MB")
+            .takes_value(true)
+            .default_value("64")
+            .validator(helpers::is_count))
+        .arg(Arg::with_name("timeout")
+            .help("Seconds to wait at most for downloading a URL")
+            .long("timeout")
+            .value_name("seconds")
+            .takes_value(true)
+            .default_value("30")
+            .hidden(!cfg!(feature = "http"))
+            .validator(helpers::is_count))
+        .arg(Arg::with_name("selection_out")
+            .help("Write the numbers and original timing lines of the\n\
+                subtitles chosen by the selectors to this file")
+            .long("selection-out")
+            .value_name("path")
+            .takes_value(true))
+        .arg(Arg::with_name("map_out")
+            .help("Write the original and new timing of every subtitle\n\
+                to this file, for editors to re-map their bookmarks")
+            .long("map-out")
+            .value_name("path")
+            .takes_value(true))
+        .arg(Arg::with_name("stats_per_range")
+            .help("Report how many subtitles were shifted, clamped and\n\
+                deleted in each range of --start, --stop and --step")
+            .long("stats-per-range"))
+        .arg(Arg::with_name("report")
+            .help("Format of the files written by --selection-out and\n\
+                --map-out (text is CSV for --map-out)")
+            .long("report")
+            .value_name("format")
+            .takes_value(true)
+            .possible_values(&["text", "json"])
+            .default_value("text"))
+        .arg(Arg::with_name("input_encoding")
+            .help("Character encoding of the input, when detecting it goes\n\
+                wrong, by any of the labels browsers know, like latin1,\n\
+                iso-8859-15, cp1251 or shift_jis. By default, inputs with\n\
+                a UTF-16 byte order mark are UTF-16, valid UTF-8 is UTF-8,\n\
+                and others windows-1252")
+            .long("encoding")
+            .value_name("label")
+            .takes_value(true)
+            .hidden(!cfg!(feature = "encodings"))
+            .validator(helpers::is_encoding))
+        .arg(Arg::with_name("lang")
+            .help("Only modify inputs in this language, like `en`: the one\n\
+                of the suffix of their name, like movie.en.srt, or with\n\
+                --detect-lang, the one detected in their text. Others are\n\
+                skipped, which is meant for batches of several languages")
+            .long("lang")
+            .value_name("code")
+            .takes_value(true)
+            .validator(helpers::is_language))
+        .arg(Arg::with_name("keep_bom")
+            .help("Start the output with a UTF-8 byte order mark when\n\
+                the input starts with one, which is left out otherwise")
+            .long("keep-bom")
+            .conflicts_with("output_encoding"))
+        .arg(Arg::with_name("output_encoding")
+            .help("Character encoding of the output file, by any of the\n\
+                labels of --encoding but UTF-16 ones [default: utf-8]")
+            .long("output-encoding")
+            .value_name("label")
+            .takes_value(true)
+            .hidden(!cfg!(feature = "encodings"))
+            .validator(helpers::is_output_encoding))
+        .arg(Arg::with_name("fallback")
+            .help("How to write characters the output encoding can't:\n\
+                `translit` replaces quotes, dashes etc. by look-alikes,\n\
+                `question` replaces every character by a '?'\n\
+                [default: translit]")
+            .long("encoding-fallback")
+            .value_name("mode")
+            .takes_value(true)
+            .possible_values(&["translit", "question"])
+            .hidden(!cfg!(feature = "encodings"))
+            .requires("output_encoding"))
+        .arg(Arg::with_name("overwrite")
+            .help("Overwrite input file, destroying the original")
+            .short("o")
+            .long("overwrite")
+            // The extension of the input can't change in place:
+            .conflicts_with_all(&["overname", "srt", "vtt"])
+            .display_order(1))
+        .arg(Arg::with_name("keep_backups")
+            .help("When overwriting, first save the input as a timestamped\n\
+                backup like `movie.srt.bak-20240501T120301` (in UTC),\n\
+                and keep only the newest <count> backups of the file")
+            .long("keep-backups")
+            .value_name("count")
+            .takes_value(true)
+            .validator(helpers::is_count)
+            .display_order(2))
+        .arg(Arg::with_name("verify_source_unchanged")
+            .help("Hash the input when it's read, and again right before\n\
+                anything is replaced, and fail without touching it if\n\
+                another program modified it in between, removing the\n\
+                files written from it")
+            .long("verify-source-unchanged"))
+        .arg(Arg::with_name("stamp_date")
+            .help("Date of the backups of --keep-backups: `now`, `none`\n\
+                for none at all, like `movie.srt.bak`, or a fixed UTC\n\
+                date like `fixed:2024-05-01T12:03:01Z` [default: now]")
+            .long("stamp-date")
+            .value_name("when")
+            .takes_value(true)
+            .validator(helpers::is_stamp_date)
+            .requires("keep_backups")
+            .display_order(2))
+        .arg(Arg::with_name("deterministic")
+            .help("Write the same files on every run of the same command:\n\
+                backups get --stamp-date none unless it's fixed, and\n\
+                --json and the JSON --selection-out leave out timings")
+            .long("deterministic")
+            .display_order(7))
+        .arg(Arg::with_name("overname")
+            .help("Overwrite input file, renaming the original\n\
+                    (Only necessary on first call; \
+                    consecutive `overnames` on same input\n\
+                    will NOT rename the input since this would \
+                    overwrite the 'original' input)")
+            .short("O")
+            .long("overname")
+            .conflicts_with_all(&["srt", "vtt"])
+            .display_order(2))
+        .arg(Arg::with_name("fix_rtl")
+            .help("Add right-to-left marks to Arabic and Hebrew subtitles,\n\
+                so players show their punctuation on the correct side")
+            .long("fix-rtl")
+            .display_order(5))
+        .arg(Arg::with_name("preset")
+            .help("Apply a named set of options: `netflix`, `ebu`, or a\n\
+                custom preset from the config file; options that are\n\
+                given explicitly override those of the preset")
+            .long("preset")
+            .value_name("name")
+            .takes_value(true))
+        .arg(Arg::with_name("normalize")
+            .help("Normalize whitespace in the subtitle text")
+            .long("normalize")
+            .display_order(5))
+        .arg(Arg::with_name("strip_tags")
+            .help("Remove all tags from the subtitle text, and the vtt\n\
+                styles of classes that are no longer used")
+            .long("strip-tags")
+            .display_order(5))
+        .arg(Arg::with_name("rename_class")
+            .help("Rename a vtt class in the subtitle text and in the\n\
+                STYLE block; may be repeated")
+            .long("rename-class")
+            .value_name("old=new")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .validator(helpers::is_class_rename))
+        .arg(Arg::with_name("rename_region")
+            .help("Rename a vtt region in its REGION block and in the\n\
+                settings of the subtitles shown in it; may be repeated")
+            .long("rename-region")
+            .value_name("old=new")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .validator(helpers::is_region_rename))
+        .arg(Arg::with_name("replace")
+            .help("Replace matches of the regex in the subtitle text,\n\
+                using $1 etc. for capture groups and \\= for an = in the\n\
+                regex. Rules are applied in order, and subtitles left\n\
+                without text are deleted")
+            .long("replace")
+            .value_name("regex=replacement")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .validator(helpers::is_replacement))
+        .arg(Arg::with_name("replace_file")
+            .help("Read --replace rules from a file, one per line,\n\
+                applied before those given with --replace")
+            .long("replace-file")
+            .value_name("path")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1))
+        .arg(Arg::with_name("quiet")
+            .help("Only print errors, not the summary of a successful run")
+            .short("q")
+            .long("quiet")
+            .conflicts_with("explain"))
+        .arg(Arg::with_name("color")
+            .help("When to color the output: `auto` colors it for a\n\
+                terminal, unless the NO_COLOR environment variable is set")
+            .long("color")
+            .value_name("when")
+            .takes_value(true)
+            .possible_values(&["auto", "always", "never"])
+            .default_value("auto"))
+        .arg(Arg::with_name("no_color")
+            .help("Don't color the output, like --color never")
+            .long("no-color"))
+        .arg(Arg::with_name("json")
+            .help("Print the result as a JSON object on stdout instead of\n\
+                the summary, and errors as one on stderr, one line each")
+            .long("json")
+            .conflicts_with_all(&["stdout", "check", "dry_run", "explain",
+                "match"]))
+        .arg(Arg::with_name("force")
+            .help("Write the output even when it's identical to the input;\n\
+                by default, nothing is written then, and the input is\n\
+                reported as unchanged")
+            .long("force")
+            .display_order(7))
+        .arg(Arg::with_name("allow_noop")
+            .help("Write a copy of the input even when nothing would change,\n\
+                like with 0 seconds and no other operation")
+            .long("allow-noop")
+            .display_order(7))
+        .arg(Arg::with_name("explain")
+            .help("Print how the timestamps will be modified")
+            .long("explain")
+            .display_order(6))
+        .arg(Arg::with_name("dry_run")
+            .help("Don't write anything, but show the old and new timing\n\
+                of the first and last subtitles, and the output file\n\
+                that would be written")
+            .long("dry-run")
+            .conflicts_with_all(&["check", "stdout", "quiet"])
+            .display_order(7))
+        .arg(Arg::with_name("check")
+            .help("Don't write anything, but exit with status 1 if the\n\
+                output would differ from the input file, like when it\n\
+                isn't in the normalized form submod writes")
+            .long("check")
+            .display_order(7))
+        .arg(Arg::with_name("line_ending")
+            .help("Line endings of the output: `keep` writes those of the\n\
+                input, i.e. the ending most of its lines have, or each\n\
+                line's own for .ass, .ssa, .sub and TTML files that are\n\
+                shifted")
+            .long("line-ending")
+            .value_name("ending")
+            .takes_value(true)
+            .possible_values(&["keep", "lf", "crlf"])
+            .default_value("keep"))
+        .arg(Arg::with_name("gen_ids")
+            .help("Give vtt cues without an identifier a generated one:\n\
+                `index` numbers them, `timestamp` uses their start time,\n\
+                and `none` leaves them without one, like srt indices\n\
+                are when converting srt to vtt")
+            .long("gen-ids")
+            .value_name("mode")
+            .takes_value(true)
+            .possible_values(&["none", "index", "timestamp"]))
+        .arg(Arg::with_name("keep_numbering")
+            .help("Keep the indices of srt subtitles as they are, instead\n\
+                of numbering the remaining subtitles from 1, or as cue\n\
+                identifiers when converting srt to vtt")
+            .long("keep-numbering"))
+        .arg(Arg::with_name("srt")
+            .help("Convert to srt format")
+            .long("srt")
+            .display_order(3)
+            .conflicts_with("vtt"))
+        .arg(Arg::with_name("vtt")
+            .help("Convert to vtt format")
+            .long("vtt")
+            .display_order(4));
+    // Flags of features left out of this build are hidden, but still
+    // accepted to explain why they don't work:
+    app.arg(Arg::with_name("notify")
+            .help("Show a desktop notification when done")
+            .long("notify")
+            .hidden(!cfg!(feature = "notify"))
+            .display_order(8))
+        .arg(Arg::with_name("detect_lang")
+            .help("Detect and report the language of the subtitle text")
+            .long("detect-lang")
+            .hidden(!cfg!(feature = "langdetect"))
+            .display_order(8))
+}
+
+/// The usage line of the help of `app`, like
+/// `submod [FLAGS] [OPTIONS] <file>... <seconds>`.
+pub fn usage(app: &App) -> String {
+    let mut help = Vec::new();
+    app.write_help(&mut help).expect("Writing to a Vec can't fail");
+    String::from_utf8_lossy(&help)
+        .split("USAGE:")
+        .nth(1)
+        .and_then(|rest| rest.split("\n\n").next())
+        .map_or(String::new(), |usage| usage.trim().to_string())
+}
+
+/// Reports an error of clap, and returns the status to exit with: 1 for
+/// invalid arguments, reported on stderr, or 0 for the help and version
+/// it stands for with `--help` and `--version`, printed on stdout.
+pub fn report(error: clap::Error) -> i32 {
+    if error.use_stderr() {
+        errln!("{}", error.message);
+        return status::USAGE;
+    }
+    outln!("{}", error.message);
+    0
+}
+
+/// Adds the arguments of `submod chapters` to those of submod, which
+/// shift the cues before their chapters are extracted.
+pub fn chapters_app<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+    app.bin_name("submod chapters")
+        .about("Write the subtitles matching a regex as the chapters of the \
+                video, to stdout.\n\
+                Every chapter lasts until the next one, and the last one \
+                until the end of the subtitles or --video-duration. The \
+                subtitles are modified as usual first, like shifted.")
+        .usage("submod chapters [FLAGS] [OPTIONS] <file>... [seconds] \
+                --match <regex> --to <format>")
+        .arg(Arg::with_name("match")
+            .help("Make a chapter of every subtitle whose text matches\n\
+                this regex, like '^\\[Scene: (.*)\\]', titled by its first\n\
+                capture group, or else by the whole text")
+            .long("match")
+            .value_name("regex")
+            .takes_value(true)
+            .required(true)
+            .validator(helpers::is_regex)
+            .conflicts_with_all(&["output", "overwrite", "overname",
+                "stdout", "check", "manifest", "srt", "vtt", "keep_backups"])
+            .display_order(0))
+        .arg(Arg::with_name("to")
+            .help("Kind of chapters file to write: a WebVTT chapters\n\
+                track, or an ffmpeg metadata file")
+            .long("to")
+            .value_name("format")
+            .takes_value(true)
+            .required(true)
+            .possible_values(&["vtt-chapters", "ffmetadata"])
+            .display_order(0))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The arguments listed in the help of `app`, like `<file>...`.
+    fn positionals(app: &App) -> Vec<String> {
+        let mut help = Vec::new();
+        app.write_help(&mut help).unwrap();
+        String::from_utf8(help).unwrap()
+            .split("ARGS:\n").nth(1).unwrap()
+            .lines()
+            .take_while(|line| !line.is_empty())
+            .filter_map(|line| line.split_whitespace().next())
+            .filter(|word| word.starts_with('<'))
+            .map(String::from)
+            .collect()
+    }
+
+    #[test]
+    fn usage_mentions_every_positional() {
+        // <seconds> is split off the files, and only described with them:
+        for &(ref app, seconds) in &[(build_cli(), "<file>... <seconds>"),
+            (chapters_app(build_cli()), "<file>... [seconds]")] {
+            let usage = usage(app);
+            assert!(usage.starts_with("submod "), "{}", usage);
+            assert!(usage.contains(seconds), "{} isn't in `{}`", seconds,
+                usage);
+            let positionals = positionals(app);
+            assert!(!positionals.is_empty());
+            for positional in &positionals {
+                assert!(usage.contains(positional.as_str()), "{} isn't in \
+                    `{}`", positional, usage);
+            }
+        }
+    }
+
+    #[test]
+    fn lists_every_short_option() {
+        let mut help = Vec::new();
+        build_cli().write_help(&mut help).unwrap();
+        let mut shorts: Vec<(char, String)> = String::from_utf8(help).unwrap()
+            .lines()
+            .map(str::trim_start)
+            .filter(|line| line.starts_with('-')
+                && line[2..].starts_with(", --"))
+            .map(|line| (line[1..].chars().next().unwrap(),
+                line[4..].split_whitespace().next().unwrap().to_string()))
+            .filter(|(_, long)| long != "--help" && long != "--version")
+            .collect();
+        let mut listed: Vec<(char, String)> = SHORT_OPTIONS.iter()
+            .map(|&(short, long)| (short, long.to_string()))
+            .collect();
+        shorts.sort();
+        listed.sort();
+        assert_eq!(shorts, listed);
+    }
+}
@@ -0,0 +1,207 @@
+use std::env;
+use std::fmt::Display;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use atty::{self, Stream};
+
+
+/// Like `println!`, leaving out the colors when stdout shouldn't have any.
+macro_rules! outln {
+    () => (println!());
+    ($($arg:tt)*) => (println!("{}",
+        $crate::color::paint(format!($($arg)*), ::atty::Stream::Stdout)));
+}
+
+/// Like `print!`, leaving out the colors when stdout shouldn't have any.
+macro_rules! out {
+    ($($arg:tt)*) => (print!("{}",
+        $crate::color::paint(format!($($arg)*), ::atty::Stream::Stdout)));
+}
+
+/// Like `eprintln!`, leaving out the colors when stderr shouldn't have any.
+macro_rules! errln {
+    () => (eprintln!());
+    ($($arg:tt)*) => (eprintln!("{}",
+        $crate::color::paint(format!($($arg)*), ::atty::Stream::Stderr)));
+}
+
+/// When to color the output, as given by `--color`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Choice {
+    /// Only when writing to a terminal, and `NO_COLOR` isn't set.
+    Auto,
+    Always,
+    Never,
+}
+
+static CHOICE: AtomicUsize = AtomicUsize::new(0);
+
+/// The choice of `--color` or `--no-color` in the arguments, the last one
+/// winning, which is read before they're parsed.
+pub fn choice(args: &[String]) -> Choice {
+    let mut choice = Choice::Auto;
+    for (i, arg) in args.iter().enumerate() {
+        let value = match arg.as_str() {
+            "--no-color" => "never",
+            "--color" => args.get(i + 1).map_or("", String::as_str),
+            arg => arg.strip_prefix("--color=").unwrap_or(""),
+        };
+        choice = match value {
+            "auto" => Choice::Auto,
+            "always" => Choice::Always,
+            "never" => Choice::Never,
+            _ => continue,
+        };
+    }
+    choice
+}
+
+/// Sets when to color the output from now on; it's `Auto` until then.
+pub fn set(choice: Choice) {
+    CHOICE.store(choice as usize, Ordering::Relaxed);
+}
+
+/// When to color the output, as set last.
+fn current() -> Choice {
+    match CHOICE.load(Ordering::Relaxed) {
+        1 => Choice::Always,
+        2 => Choice::Never,
+        _ => Choice::Auto,
+    }
+}
+
+/// Whether what's written to `stream` should be colored, by `choice`.
+fn enabled_by(choice: Choice, stream: Stream) -> bool {
+    match choice {
+        Choice::Always => true,
+        Choice::Never => false,
+        // Any value but an empty one turns colors off, see no-color.org:
+        Choice::Auto => env::var_os("NO_COLOR")
+            .is_none_or(|value| value.is_empty()) && atty::is(stream),
+    }
+}
+
+/// Leaves the colors out of text for `stream`, if it shouldn't have any.
+pub fn paint(text: String, stream: Stream) -> String {
+    paint_by(current(), text, stream)
+}
+
+fn paint_by(choice: Choice, text: String, stream: Stream) -> String {
+    if enabled_by(choice, stream) { text } else { strip(&text) }
+}
+
+/// Ends the style of the text before it.
+const RESET: &str = "\u{001b}[0m";
+
+fn style<T: Display>(code: &str, text: T) -> String {
+    format!("\u{001b}[{}m{}{}", code, text, RESET)
+}
+
+/// Something to type, like an option or a value, in green.
+pub fn hint<T: Display>(text: T) -> String {
+    style("32", text)
+}
+
+/// An argument of the command line that's wrong, in yellow, like clap
+/// shows them.
+pub fn arg<T: Display>(text: T) -> String {
+    style("33", text)
+}
+
+/// The title of a report, like `Success.`, in bold green.
+pub fn title<T: Display>(text: T) -> String {
+    style("32;1", text)
+}
+
+/// The title of a report that needs looking into, like `Would change.`,
+/// in bold yellow.
+pub fn alert<T: Display>(text: T) -> String {
+    style("33;1", text)
+}
+
+/// The label of an error, like `Error:`, in orange.
+pub fn error<T: Display>(text: T) -> String {
+    style("38;5;208", text)
+}
+
+/// Text in bold, like a heading.
+pub fn bold<T: Display>(text: T) -> String {
+    style("1", text)
+}
+
+/// A line that's removed, in red.
+pub fn removed<T: Display>(text: T) -> String {
+    style("31", text)
+}
+
+/// A line that's added, in green.
+pub fn added<T: Display>(text: T) -> String {
+    style("32", text)
+}
+
+/// The ` ! ` in front of a warning, on red.
+pub fn warning_sign() -> String {
+    style("41;1", " ! ")
+}
+
+/// A file that's written, in bold on grey.
+pub fn file<T: Display>(text: T) -> String {
+    format!("\u{001b}[1m \u{001b}[48;5;238m {} {}", text, RESET)
+}
+
+/// Removes the escape sequences that color text, like `\u{001b}[32;1m`.
+pub fn strip(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\u{001b}' && chars.peek() == Some(&'[') {
+            // The parameters are digits and semicolons, up to the `m`:
+            let length = chars.clone().skip(1)
+                .take_while(|&c| c.is_ascii_digit() || c == ';')
+                .count();
+            if chars.clone().nth(length + 1) == Some('m') {
+                chars.nth(length + 1);
+                continue;
+            }
+        }
+        stripped.push(c);
+    }
+    stripped
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_color_escapes_only() {
+        assert_eq!(strip("\u{001b}[38;5;208mError:\u{001b}[0m Use \
+            \u{001b}[32m--out\u{001b}[0m."), "Error: Use --out.");
+        assert_eq!(strip("\u{001b}[2J[0m 100%"), "\u{001b}[2J[0m 100%");
+        assert_eq!(strip("Ends in \u{001b}["), "Ends in \u{001b}[");
+    }
+
+    #[test]
+    fn reads_the_choice_from_the_arguments() {
+        let args = |args: &[&str]| args.iter().map(|arg| arg.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(choice(&args(&["submod", "a.srt", "1"])), Choice::Auto);
+        assert_eq!(choice(&args(&["submod", "--color", "always", "1"])),
+            Choice::Always);
+        assert_eq!(choice(&args(&["submod", "--color=always", "--no-color"])),
+            Choice::Never);
+    }
+
+    #[test]
+    fn explicit_choices_override_the_terminal() {
+        assert_eq!(paint_by(Choice::Never, bold("Hi"), Stream::Stdout), "Hi");
+        assert!(enabled_by(Choice::Always, Stream::Stderr));
+    }
+
+    #[test]
+    fn styles_text() {
+        assert_eq!(hint("--out"), "\u{001b}[32m--out\u{001b}[0m");
+        assert_eq!(strip(&file("movie.srt")), "  movie.srt ");
+    }
+}
@@ -0,0 +1,1031 @@
+use std::env;
+
+use clap;
+
+use bench;
+use chapters;
+use cli;
+use clean;
+use color;
+use cue;
+use encoding;
+use helpers;
+#[cfg(feature = "http")]
+use http;
+use info;
+use lint;
+use manifest;
+use merge;
+use name;
+#[cfg(feature = "notify")]
+use notify;
+use preset;
+use selection;
+use split;
+use status;
+use submod;
+use subviewer;
+use verify;
+use wizard;
+
+/// The largest delay `--trim-leading-silence` applies without `--force`.
+const MAX_LEADING_DELAY: f64 = 10.0;
+
+/// Runs the `submod` command on the arguments of the process, and returns
+/// the status to exit with, like 0 when it succeeds or 2 when an input
+/// isn't subtitles of its format. It never exits by itself.
+pub fn main() -> i32 {
+    let args: Vec<String> = env::args().collect();
+    // The hidden `bench` command has arguments of its own:
+    if args.get(1).map(String::as_str) == Some("bench") {
+        return bench::main(&args[1..]);
+    }
+    // So do the `clean`, `lint` and `info` commands:
+    if args.get(1).map(String::as_str) == Some("clean") {
+        return clean::main(&args[1..]);
+    }
+    if args.get(1).map(String::as_str) == Some("lint") {
+        return lint::main(&args[1..]);
+    }
+    if args.get(1).map(String::as_str) == Some("info") {
+        return info::main(&args[1..]);
+    }
+    // The wizard asks for the arguments, and then runs them as usual:
+    if args.get(1).map(String::as_str) == Some("wizard") {
+        return match wizard::ask(&args[0]) {
+            Ok(Some(wizard_args)) => {
+                let status = run_args(wizard_args.clone());
+                if status == 0 {
+                    wizard::print_command(&wizard_args);
+                }
+                status
+            },
+            Ok(None) => 0,
+            Err(error) => status::report(error),
+        };
+    }
+    run_args(args)
+}
+
+fn run_args(mut args: Vec<String>) -> i32 {
+    // `submod chapters` takes the usual arguments, and two of its own:
+    let chapters_mode = args.get(1).map(String::as_str) == Some("chapters");
+    if chapters_mode {
+        args.remove(1);
+    }
+    let args = match preset::expand(args) {
+        Ok(args) => args,
+        Err(error) => return status::report(error),
+    };
+    let args = helpers::negative_times_to_seconds(args);
+
+    let app = if chapters_mode {
+        cli::chapters_app(cli::build_cli())
+    } else {
+        cli::build_cli()
+    };
+    // Read before the rest, so errors in the other arguments follow it:
+    color::set(color::choice(&args));
+    let matches = match app.get_matches_from_safe(&args) {
+        Ok(matches) => matches,
+        Err(error) => return cli::report(error),
+    };
+
+    let notify = cfg!(feature = "notify") && matches.is_present("notify");
+    let json = matches.is_present("json");
+
+    for &(arg, feature, enabled) in &[
+        ("notify", "notify", cfg!(feature = "notify")),
+        ("detect_lang", "langdetect", cfg!(feature = "langdetect")),
+        ("output_encoding", "encodings", cfg!(feature = "encodings")),
+        ("input_encoding", "encodings", cfg!(feature = "encodings")),
+    ] {
+        if !enabled && matches.is_present(arg) {
+            return fail(format_err!("--{} needs the `{}` feature, which \
+                this build of submod leaves out.\n\n\
+                Reinstall it with {} to use it.",
+                arg.replace('_', "-"), feature, color::hint(format!(
+                    "cargo install submod --features {}", feature))),
+                notify, json);
+        }
+    }
+
+    if let Some(manifest) = matches.value_of("manifest") {
+        return run_manifest(manifest, &matches, &args, notify);
+    }
+
+    let (inputs, seconds_opt) = match split_positionals(&matches) {
+        Ok(positionals) => positionals,
+        Err(error) => return fail(error, notify, json),
+    };
+    // SECONDS may be left out when scaling the timestamps. Parsing it
+    // can't panic, because it was validated by helpers::is_offset:
+    let seconds: f64 = seconds_opt.map_or(0.0, submod::get_secs);
+
+    if inputs.len() > 1 {
+        return run_batch(&inputs, seconds, &matches, &args, notify);
+    }
+    match shift(&matches, &args, inputs[0], seconds,
+        matches.value_of("output")) {
+        Ok((warnings, changed)) => {
+            if notify {
+                #[cfg(feature = "notify")]
+                notify::send(1, 0);
+            }
+            if changed {
+                return status::USAGE;
+            }
+            deny_warnings(warnings, &matches)
+        },
+        Err(error) => fail(error, notify, json),
+    }
+}
+
+/// Splits <seconds> off the files, as clap takes all positional arguments
+/// for files: it's the last one, or else the first one, like in
+/// `submod 2.5 S01E*.srt`. No subtitle file name is a number or a time.
+fn split_positionals<'a>(matches: &'a clap::ArgMatches)
+    -> Result<(Vec<&'a str>, Option<&'a str>), failure::Error>
+{
+    let mut values: Vec<&str> = matches.values_of("file").into_iter()
+        .flatten()
+        .collect();
+    let is_offset = |value: &&str| helpers::is_offset(value.to_string())
+        .is_ok();
+    let seconds_opt = if values.last().is_some_and(is_offset) {
+        values.pop()
+    } else if values.first().is_some_and(is_offset) {
+        Some(values.remove(0))
+    } else {
+        None
+    };
+    // Chapters can be extracted without shifting them, the shift to trim
+    // leading silence is found in the file, and --shift, --step and --range
+    // have their own:
+    let scaled = ["speed", "rate", "factor", "match", "trim_leading_silence",
+        "shift", "step", "range"]
+        .iter()
+        .any(|name| matches.is_present(name));
+    if seconds_opt.is_some() && matches.is_present("trim_leading_silence") {
+        return Err(format_err!("--trim-leading-silence finds the seconds to \
+            shift by itself, so leave out {}.", color::arg("<seconds>")));
+    }
+    if seconds_opt.is_none() && !scaled {
+        // A last value that isn't a file was most likely meant as seconds:
+        if let Some(&last) = values.last().filter(|last|
+            values.len() > 1 || helpers::is_input(last.to_string()).is_err()) {
+            if let Err(error) = helpers::is_offset(last.to_string()) {
+                return Err(format_err!("Invalid value for \
+                    '{}': {}", color::arg("<seconds>"), error));
+            }
+        }
+        return Err(format_err!("Missing {}\n\n\
+            Give the seconds to shift by after the files, shift parts of \
+            them with --step or --range, or scale the timestamps with \
+            --speed, --rate or --factor.",
+            color::arg("<seconds>")));
+    }
+    if values.is_empty() {
+        return Err(format_err!("Missing {}\n\n\
+            Give the subtitle files to modify before the seconds.",
+            color::arg("<file>")));
+    }
+    for value in &values {
+        helpers::is_input(value.to_string()).map_err(|error| format_err!(
+            "Invalid value for '{}': {}", color::arg("<file>"), error))?;
+    }
+    Ok((values, seconds_opt))
+}
+
+/// Modifies several files by the same seconds and options, like separate
+/// runs, and reports those that failed at the end.
+fn run_batch(inputs: &[&str], seconds: f64, matches: &clap::ArgMatches,
+    args: &[String], notify: bool) -> i32
+{
+    let json = matches.is_present("json");
+    if let Some(name) = ["output", "stdout", "match", "companion"]
+        .iter()
+        .find(|name| matches.is_present(name)) {
+        return fail(format_err!("--{} only applies to a single input, \
+            but {} were given.", if *name == "output" { "out" } else { name },
+            inputs.len()), notify, json);
+    }
+    let (mut succeeded, mut warnings, mut changed) = (0, 0, 0);
+    let mut failures = Vec::new();
+    for &input in inputs {
+        match shift(matches, args, input, seconds, None) {
+            Ok((count, would_change)) => {
+                succeeded += 1;
+                warnings += count;
+                changed += would_change as usize;
+            },
+            Err(error) => failures.push((format!("`{}`", input), error)),
+        }
+    }
+    finish_batch("file", succeeded, failures, warnings, changed, matches,
+        notify)
+}
+
+/// Runs every job of a `--manifest` with the options of the command line,
+/// like separate runs, and reports those that failed by their line.
+fn run_manifest(manifest: &str, matches: &clap::ArgMatches, args: &[String],
+    notify: bool) -> i32
+{
+    let json = matches.is_present("json");
+    let text = match manifest::read(manifest) {
+        Ok(text) => text,
+        Err(error) => return fail(error, notify, json),
+    };
+    let (mut succeeded, mut warnings, mut changed) = (0, 0, 0);
+    let mut failures = Vec::new();
+    for (line, job) in manifest::parse(&text) {
+        let result = job.and_then(|job| {
+            if job.output_opt.is_some() && ["overwrite", "overname", "srt",
+                "vtt"].iter().any(|name| matches.is_present(name)) {
+                return Err(format_err!("An output can't be combined with \
+                    --overwrite, --overname, --srt or --vtt."));
+            }
+            shift(matches, args, &job.input, job.seconds,
+                job.output_opt.as_deref())
+        });
+        match result {
+            Ok((count, would_change)) => {
+                succeeded += 1;
+                warnings += count;
+                changed += would_change as usize;
+            },
+            Err(error) => failures.push((format!("Line {}", line), error)),
+        }
+    }
+    finish_batch("manifest job", succeeded, failures, warnings, changed,
+        matches, notify)
+}
+
+/// Reports how a batch went, and returns the status of its first failure,
+/// or else 1 if `--check` found any output that would differ, or else that
+/// of `deny_warnings`.
+fn finish_batch(unit: &str, succeeded: usize,
+    failures: Vec<(String, failure::Error)>, warnings: usize, changed: usize,
+    matches: &clap::ArgMatches, notify: bool) -> i32
+{
+    helpers::report_batch(unit, succeeded, changed, &failures,
+        matches.is_present("quiet"), matches.is_present("json"));
+    if notify {
+        // Outputs that would change fail --check:
+        #[cfg(feature = "notify")]
+        notify::send(succeeded - changed, failures.len() + changed);
+    }
+    // The first failure decides the status, like a single file would:
+    if let Some((_, error)) = failures.first() {
+        return status::of(error);
+    }
+    if changed > 0 {
+        return status::USAGE;
+    }
+    deny_warnings(warnings, matches)
+}
+
+/// Returns status 4 if there were any warnings and `--warnings-as-errors`
+/// is given, once all the work is done, or else 0.
+fn deny_warnings(warnings: usize, matches: &clap::ArgMatches) -> i32 {
+    if warnings > 0 && matches.is_present("warnings_as_errors") {
+        if matches.is_present("json") {
+            helpers::report_error_json(&format_err!("{} warning{} treated \
+                as errors by --warnings-as-errors.", warnings,
+                if warnings == 1 { " was" } else { "s were" }), None);
+            return status::WARNINGS;
+        }
+        helpers::report_denied_warnings(warnings);
+        return status::WARNINGS;
+    }
+    0
+}
+
+/// Modifies one input by `seconds`, with the options of the command line,
+/// and returns the number of warnings, and whether `--check` found that
+/// the output would differ.
+fn shift(matches: &clap::ArgMatches, args: &[String], input: &str,
+    seconds: f64, output_opt: Option<&str>)
+    -> Result<(usize, bool), failure::Error>
+{
+    // A download is named after the file in its URL, for its format:
+    let (downloaded_opt, name);
+    let input = if helpers::is_url(input) {
+        check_stream(input, "a URL", matches)?;
+        let (file_name, bytes) = download(input, matches)?;
+        downloaded_opt = Some(bytes);
+        name = file_name;
+        name.as_str()
+    } else {
+        if helpers::is_stream(std::path::Path::new(input)) {
+            check_stream(input, "a pipe or another stream", matches)?;
+        } else {
+            helpers::check_input(std::path::Path::new(input))?;
+        }
+        downloaded_opt = None;
+        input
+    };
+    // Inputs in other languages than --lang are skipped:
+    if let Some(lang) = matches.value_of("lang") {
+        let input_path = std::path::Path::new(input);
+        let language_opt = if matches.is_present("detect_lang") {
+            info::describe(input_path)?.language_opt
+        } else {
+            input_path.file_stem().and_then(|stem| stem.to_str())
+                .and_then(name::language_suffix)
+        };
+        if language_opt != Some(lang) {
+            if !matches.is_present("quiet") && !matches.is_present("json") {
+                helpers::report_other_language(input_path, language_opt, lang);
+            }
+            return Ok((0, false));
+        }
+    }
+    // Taken before anything is read, so no change goes unnoticed:
+    let fingerprint_opt = if matches.is_present("verify_source_unchanged") {
+        Some(helpers::fingerprint(std::path::Path::new(input))?)
+    } else {
+        None
+    };
+
+    // Percentages are of the span of the file, which takes a pre-scan:
+    let (start_string_opt, stop_string_opt) =
+        (matches.value_of("start"), matches.value_of("stop"));
+    let mut span = (0.0, 0.0);
+    if start_string_opt.into_iter().chain(stop_string_opt)
+        .any(|time_string| time_string.ends_with('%')) {
+        span = submod::span(std::path::Path::new(input))?;
+    }
+    let (mut start_opt, mut stop_opt, mut partial) = (None, None, false);
+    if let Some(time_string) = start_string_opt {
+        start_opt = Some(submod::get_time(time_string, span));
+        partial = true; // Indicate partial modification
+    }
+    if let Some(time_string) = stop_string_opt {
+        stop_opt = Some(submod::get_time(time_string, span));
+        partial = true;
+    }
+
+    // Resolve `--start-chapter` and `--stop-chapter` to their times:
+    if let Some(path) = matches.value_of("chapters") {
+        let chapters = chapters::read(std::path::Path::new(path))?;
+        if let Some(name) = matches.value_of("start_chapter") {
+            start_opt = Some(chapters::find(&chapters, name)?.start);
+            partial = true;
+        }
+        if let Some(name) = matches.value_of("stop_chapter") {
+            stop_opt = Some(chapters::find(&chapters, name)?.end);
+            partial = true;
+        }
+    }
+
+    // Convert the `--step` breakpoints to (time, seconds) pairs,
+    // and every `--range` and range of `--shift` to a step at its start
+    // and end:
+    let mut steps: Vec<(f64, f64)> = matches.values_of("step")
+        .map_or(Vec::new(), |values| values.map(submod::get_step).collect());
+    if let Some(ranges) = matches.values_of("range") {
+        // Validated by helpers::is_range:
+        steps.extend(ranges.flat_map(|range|
+            submod::range_steps(submod::get_range(range).unwrap())));
+    }
+    if let Some(spec) = matches.value_of("shift") {
+        // Validated by helpers::is_ranges:
+        steps.extend(submod::get_ranges(spec).unwrap().into_iter()
+            .flat_map(submod::range_steps));
+    }
+    steps.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+    if !steps.is_empty() {
+        partial = true;
+    }
+
+    // Validated by helpers::is_count:
+    let cue_number = |name| matches.value_of(name).map(|n| n.parse().unwrap());
+    let selection = selection::CueSelection::new(
+        matches.value_of("include_cues"), matches.value_of("exclude_cues"))?
+        .with_range(cue_number("from_cue"), cue_number("to_cue"))?;
+    if selection.is_partial() {
+        partial = true;
+    }
+
+    let mut italic_opt = None;
+    if matches.is_present("only_italic") {
+        italic_opt = Some(true);
+    } else if matches.is_present("only_non_italic") {
+        italic_opt = Some(false);
+    }
+    if italic_opt.is_some() {
+        partial = true;
+    }
+
+    let mut replacements = Vec::new();
+    for path in matches.values_of("replace_file").into_iter().flatten() {
+        replacements.extend(helpers::read_replacements(path)?);
+    }
+    // Validated by helpers::is_replacement:
+    replacements.extend(matches.values_of("replace").into_iter().flatten()
+        .map(|rule| helpers::parse_replacement(rule).unwrap()));
+
+    let (pin_first, pin_last) =
+        (matches.is_present("pin_first"), matches.is_present("pin_last"));
+    if pin_first || pin_last {
+        partial = true;
+    }
+
+    let (mut overwrite, mut rename) = (false, false);
+    if matches.is_present("overname") {
+        overwrite = true;
+        rename = true;
+    }
+    if matches.is_present("overwrite") {
+        overwrite = true;
+    }
+
+    // Validated by helpers::is_count:
+    let keep_backups_opt = matches.value_of("keep_backups")
+        .map(|count| count.parse::<usize>().unwrap());
+    if keep_backups_opt.is_some() && !overwrite {
+        return Err(format_err!("--keep-backups only applies when \
+            overwriting the input\n\n\
+            Combine it with {} or {}.", color::hint("--overwrite"),
+            color::hint("--overname")));
+    }
+
+    // Validated by helpers::is_positive:
+    let min_duration_opt: Option<f64> = matches.value_of("min_duration")
+        .map(|seconds| seconds.parse().unwrap());
+    let max_duration_opt: Option<f64> = matches.value_of("max_duration")
+        .map(|seconds| seconds.parse().unwrap());
+    if let (Some(min), Some(max)) = (min_duration_opt, max_duration_opt) {
+        if min > max {
+            return Err(format_err!("--min-duration {} can't be longer \
+                than --max-duration {}.", min, max));
+        }
+    }
+
+    // Validated by helpers::is_stamp_date:
+    let stamp_date = matches.value_of("stamp_date")
+        .map(|when| helpers::parse_stamp_date(when).unwrap());
+    if stamp_date == Some(helpers::StampDate::Now)
+        && matches.is_present("deterministic") {
+        return Err(format_err!("--stamp-date now dates every backup \
+            differently, so it can't be --deterministic\n\n\
+            Use {} instead.", color::hint("--stamp-date fixed:<date>")));
+    }
+    let stamp_date = stamp_date.unwrap_or(
+        if matches.is_present("deterministic") { helpers::StampDate::None }
+        else { helpers::StampDate::Now });
+
+    let mut convert_opt = None;
+    if matches.is_present("vtt") {
+        convert_opt = Some("vtt");
+    }
+    else if matches.is_present("srt") {
+        convert_opt = Some("srt");
+    }
+
+    // Validated by helpers::is_speed, is_rate and is_positive:
+    let factor = if let Some(speed) = matches.value_of("speed") {
+        submod::get_speed_factor(speed)
+    } else if let Some(rate) = matches.value_of("rate") {
+        submod::get_rate_factor(rate)
+    } else {
+        matches.value_of("factor").map_or(1.0, |factor| factor.parse().unwrap())
+    };
+
+    // --trim-leading-silence gives the shift by where the first subtitle
+    // should start rather than by <seconds>:
+    let (seconds, first_start_opt) =
+        if matches.is_present("trim_leading_silence") {
+            let first_start = first_start(input, matches)?;
+            (trim_leading_silence(first_start, factor, matches)?,
+                Some(first_start))
+        } else {
+            (seconds, None)
+        };
+
+    let tagging = helpers::Tagging {
+        mode: match matches.value_of("tag_mode") {
+            Some("delta") => helpers::TagMode::Delta,
+            Some("none") => helpers::TagMode::None,
+            _ => helpers::TagMode::Cumulative,
+        },
+        // Validated by helpers::is_precision, and has a default value:
+        precision: matches.value_of("precision").unwrap().parse().unwrap(),
+        factor,
+    };
+
+    let options = submod::Options {
+        seconds,
+        // Validated by helpers::is_float:
+        shift_starts: matches.value_of("shift_starts")
+            .map_or(0.0, |seconds| seconds.parse().unwrap()),
+        shift_ends: matches.value_of("shift_ends")
+            .map_or(0.0, |seconds| seconds.parse().unwrap()),
+        factor,
+        start_opt,
+        stop_opt,
+        italic_opt,
+        selection,
+        steps,
+        // Validated by helpers::is_non_negative, and has a default value:
+        pin_first,
+        pin_last,
+        max_shift: matches.value_of("max_shift").unwrap().parse().unwrap(),
+        // Validated by helpers::is_count:
+        max_line_opt: matches.value_of("max_line")
+            .map(|chars| chars.parse().unwrap()),
+        max_lines_opt: matches.value_of("max_lines")
+            .map(|count| count.parse().unwrap()),
+        wide_chars: matches.is_present("wide_chars"),
+        strict: matches.is_present("strict"),
+        on_negative: match matches.value_of("on_negative") {
+            Some("clamp") => submod::OnNegative::Clamp,
+            Some("error") => submod::OnNegative::Error,
+            _ => submod::OnNegative::Delete,
+        },
+        overflow: match matches.value_of("overflow") {
+            Some("drop") => submod::Overflow::Drop,
+            Some("error") => submod::Overflow::Error,
+            Some(_) => submod::Overflow::Clamp,
+            None if matches.is_present("strict") => submod::Overflow::Error,
+            None => submod::Overflow::Clamp,
+        },
+        // Validated by helpers::is_non_negative, and has a default value:
+        min_start: matches.value_of("min_start").unwrap().parse().unwrap(),
+        boundary_conflict: match matches.value_of("boundary_conflict") {
+            Some("reorder") => submod::BoundaryConflict::Reorder,
+            Some("trim") => submod::BoundaryConflict::Trim,
+            Some("error") => submod::BoundaryConflict::Error,
+            Some(_) => submod::BoundaryConflict::Warn,
+            None if matches.is_present("strict") =>
+                submod::BoundaryConflict::Error,
+            None => submod::BoundaryConflict::Warn,
+        },
+        // Validated, and all have a default value:
+        merge_opt: if matches.is_present("merge_continuations") {
+            Some(merge::MergeLimits {
+                max_gap: matches.value_of("merge_max_gap").unwrap()
+                    .parse().unwrap(),
+                max_line: matches.value_of("merge_max_line").unwrap()
+                    .parse().unwrap(),
+                max_cps: matches.value_of("merge_max_cps").unwrap()
+                    .parse().unwrap(),
+                wide_chars: matches.is_present("wide_chars"),
+            })
+        } else {
+            None
+        },
+        // Validated by helpers::is_non_negative, with a default value:
+        fix_overlaps_opt: if matches.is_present("fix_overlaps") {
+            Some(matches.value_of("min_gap").unwrap().parse().unwrap())
+        } else {
+            None
+        },
+        merge_collapsed: matches.is_present("merge_collapsed"),
+        // Validated by helpers::is_positive:
+        min_duration_opt,
+        max_duration: max_duration_opt,
+        truncate_long: matches.is_present("truncate_long"),
+        // Validated by helpers::is_float:
+        min_confidence_opt: matches.value_of("drop_low_confidence")
+            .map(|threshold| threshold.parse().unwrap()),
+        prepend_cue_opt: matches.value_of("prepend_cue").map(String::from),
+        append_cue_opt: matches.value_of("append_cue").map(String::from),
+        // Validated by helpers::is_positive and helpers::is_non_negative:
+        credit_duration: matches.value_of("credit_duration").unwrap()
+            .parse().unwrap(),
+        credit_gap: matches.value_of("credit_gap").unwrap().parse().unwrap(),
+        split_weights: match matches.value_of("split_weights") {
+            Some("words") => split::SplitWeights::Words,
+            _ => split::SplitWeights::Chars,
+        },
+        lenient: matches.is_present("lenient"),
+        detect_lang: cfg!(feature = "langdetect")
+            && matches.is_present("detect_lang"),
+        // Scripts and .sub files are only previewed by their summary:
+        map_timing: matches.is_present("map_out")
+            || matches.is_present("companion")
+            || (matches.is_present("dry_run")
+                && !submod::is_ass(std::path::Path::new(input))
+                && !submod::is_ttml(std::path::Path::new(input))
+                && !input.ends_with(".sub")
+                && matches.value_of("format").is_none_or(|format|
+                    format == "srt" || format == "vtt")),
+        stats_per_range: matches.is_present("stats_per_range"),
+        input_srt_opt: match matches.value_of("format") {
+            Some("srt") => Some(true),
+            Some("vtt") => Some(false),
+            _ => None,
+        },
+        input_ass: matches.value_of("format")
+            .is_some_and(|format| format == "ass" || format == "ssa"),
+        input_ttml: matches.value_of("format") == Some("ttml"),
+        output_ttml: output_opt.is_some_and(|output|
+            submod::is_ttml(std::path::Path::new(output))),
+        sub_dialect_opt: match matches.value_of("format") {
+            Some("microdvd") => Some(subviewer::Dialect::MicroDvd),
+            Some("subviewer") => Some(subviewer::Dialect::SubViewer),
+            _ => None,
+        },
+        // Validated by helpers::is_positive:
+        fps_opt: matches.value_of("fps").map(|fps| fps.parse().unwrap()),
+        // Validated by helpers::is_count, and has a default value:
+        first_start_opt,
+        max_input_size: matches.value_of("max_input_size").unwrap()
+            .parse::<u64>().unwrap() * 1024 * 1024,
+        downloaded_opt,
+        line_ending: match matches.value_of("line_ending") {
+            Some("lf") => submod::LineEnding::Lf,
+            Some("crlf") => submod::LineEnding::Crlf,
+            _ => submod::LineEnding::Keep,
+        },
+        gen_ids: match matches.value_of("gen_ids") {
+            Some("none") => Some(submod::IdMode::None),
+            Some("index") => Some(submod::IdMode::Index),
+            Some("timestamp") => Some(submod::IdMode::Timestamp),
+            _ => None,
+        },
+        fix_rtl: matches.is_present("fix_rtl"),
+        normalize: matches.is_present("normalize"),
+        strip_tags: matches.is_present("strip_tags"),
+        replacements,
+        // Validated by helpers::is_region_rename:
+        region_renames: matches.values_of("rename_region")
+            .map_or(Vec::new(), |values| values.map(split_rename).collect()),
+        // Validated by helpers::is_class_rename:
+        class_renames: matches.values_of("rename_class")
+            .map_or(Vec::new(), |values| values.map(split_rename).collect()),
+        // Validated by helpers::is_encoding:
+        input_encoding_opt: matches.value_of("input_encoding")
+            .map(|label| encoding::for_label(label).unwrap()),
+        keep_bom: matches.is_present("keep_bom"),
+        keep_numbering: matches.is_present("keep_numbering"),
+        // Validated by helpers::is_output_encoding:
+        output_encoding: matches.value_of("output_encoding")
+            .map_or(encoding::UTF_8,
+                |label| encoding::for_label(label).unwrap()),
+        fallback: match matches.value_of("fallback") {
+            Some("question") => encoding::Fallback::Question,
+            _ => encoding::Fallback::Transliterate,
+        },
+        // The copy --allow-noop asks for is identical to the input:
+        force: matches.is_present("force") || matches.is_present("allow_noop"),
+        // Validated by helpers::is_timing:
+        video_duration_opt: matches.value_of("video_duration")
+            .map(submod::get_secs),
+        // Validated by helpers::is_non_negative, and has a default value:
+        duration_margin: matches.value_of("duration_margin").unwrap()
+            .parse().unwrap(),
+        reference_opt: matches.value_of("verify_against").and_then(|dir|
+            verify::find_reference(std::path::Path::new(dir),
+                std::path::Path::new(input))),
+        // Validated by helpers::is_non_negative, and has a default value:
+        tolerance: matches.value_of("verify_tolerance").unwrap()
+            .parse().unwrap(),
+    };
+
+    // --check and --stdout write no file, and reports are an operation too:
+    let reports = ["check", "stdout", "selection_out", "match"].iter()
+        .any(|name| matches.is_present(name));
+    if options.is_noop() && convert_opt.is_none() && !reports
+        && !matches.is_present("allow_noop") {
+        return Err(format_err!("Shifting by 0 seconds without any other \
+            operation wouldn't change anything.\n\n\
+            Check the <seconds> argument, or use {} to write a tagged \
+            copy of the input anyway.", color::hint("--allow-noop")));
+    }
+
+    // Scripts are only shifted, so --out has to keep them scripts as well:
+    let input_ass = submod::is_ass(std::path::Path::new(input));
+    if input_ass && (convert_opt.is_some() || output_opt.is_some_and(|output|
+        !submod::is_ass(std::path::Path::new(output)))) {
+        return Err(format_err!("Converting .ass and .ssa scripts to \
+            another format isn't supported; they can only be shifted."));
+    }
+    if !input_ass && output_opt.is_some_and(|output|
+        submod::is_ass(std::path::Path::new(output))) {
+        return Err(format_err!("Converting to .ass and .ssa scripts \
+            isn't supported; use {} with a .srt or .vtt file.",
+            color::hint("--out")));
+    }
+    if !input.ends_with(".sub") && output_opt.is_some_and(|output|
+        output.ends_with(".sub")) {
+        return Err(format_err!("Converting to .sub files isn't supported; \
+            use {} with a .srt or .vtt file.", color::hint("--out")));
+    }
+    // srt and vtt files are written as TTML by `process`, and TTML files
+    // only shifted:
+    if (input.ends_with(".sub") || matches.value_of("format").is_some_and(
+        |format| format == "microdvd" || format == "subviewer"))
+        && options.output_ttml {
+        return Err(format_err!("Converting .sub files to TTML isn't \
+            supported; use {} with a .srt or .vtt file.",
+            color::hint("--out")));
+    }
+
+    if matches.is_present("explain") {
+        helpers::explain(&options, args, matches.value_of("preset"));
+    }
+
+    if let Some(pattern) = matches.value_of("match") {
+        let (blocks, summary) = submod::modified_blocks(
+            std::path::Path::new(input), &options)?;
+        // Validated by helpers::is_regex:
+        let chapters = chapters::extract(&blocks,
+            &regex::Regex::new(pattern).unwrap(), options.video_duration_opt)?;
+        let format = match matches.value_of("to") {
+            Some("ffmetadata") => chapters::Format::Ffmetadata,
+            _ => chapters::Format::Vtt,
+        };
+        print!("{}", chapters::write(&chapters, format));
+        write_reports(matches, &summary, seconds)?;
+        return Ok((summary.warnings(), false));
+    }
+
+    if matches.is_present("stdout") {
+        let to_srt_opt = convert_opt.map(|extension| extension == "srt");
+        let input_path = std::path::Path::new(input);
+        let summary = submod::print(input_path, to_srt_opt, &options)?;
+        write_reports(matches, &summary, seconds)?;
+        return Ok((summary.warnings(), false));
+    }
+
+    let (mut input_path, mut output_path, mut rename_opt) =
+        helpers::get_paths(input, seconds, partial,
+            rename, output_opt, convert_opt,
+            matches.value_of("name_template"), tagging)?;
+    // Without a tag, the output can be named exactly like the input:
+    if output_path == input_path && (!overwrite || rename) {
+        return Err(format_err!("The output would be named `{}`, \
+            like the input, which would be lost.\n\n\
+            Use {} to overwrite it, or {}.", input_path.display(),
+            color::hint("--overwrite"), color::hint("--out <filename>")));
+    }
+
+    // The companion is paired up front, so a mismatch writes neither:
+    let companion_opt = match matches.value_of("companion") {
+        Some(companion) => {
+            let (_, companion_output, _) = helpers::get_paths(companion,
+                seconds, partial, false, None, None,
+                matches.value_of("name_template"), tagging)?;
+            Some((submod::pair_companion(&input_path,
+                std::path::Path::new(companion), &options)?, companion_output))
+        },
+        None => None,
+    };
+
+    // `--out` writes where it's told; the other outputs stay next to their
+    // input, and those of a manifest within the current directory:
+    if !matches.is_present("allow_outside_output_dir") {
+        let dir_of = |path: &std::path::Path| path.parent()
+            .unwrap_or_else(|| std::path::Path::new("")).to_owned();
+        if output_opt.is_none() {
+            helpers::check_within(&output_path, &dir_of(&input_path))?;
+        } else if matches.is_present("manifest") {
+            helpers::check_within(&output_path, std::path::Path::new(""))?;
+        }
+        if let Some(companion) = matches.value_of("companion") {
+            if let Some((_, ref companion_output)) = companion_opt {
+                helpers::check_within(companion_output,
+                    &dir_of(std::path::Path::new(companion)))?;
+            }
+        }
+    }
+
+    if matches.is_present("check") {
+        let (summary, changes_opt) =
+            submod::check(&input_path, &output_path, &options)?;
+        write_reports(matches, &summary, seconds)?;
+        let changed = changes_opt.is_some();
+        helpers::report_check(&input_path, changes_opt);
+        return Ok((summary.warnings(), changed));
+    }
+
+    if matches.is_present("dry_run") {
+        let summary = submod::dry_run(&input_path, &output_path, &options)?;
+        // The reports describe the run, whether it was a dry run or not:
+        write_reports(matches, &summary, seconds)?;
+        helpers::report_dry_run(&summary, &output_path);
+        return Ok((summary.warnings(), false));
+    }
+
+    // Transform the file and return a summary of the modified subtitles:
+    let summary = submod::transform(&input_path, &output_path, &options)?;
+    write_reports(matches, &summary, seconds)?;
+
+    // Nothing was written, so there's nothing to back up or rename either:
+    if summary.unchanged {
+        if matches.is_present("json") {
+            helpers::report_json(&summary, &input_path, &output_path,
+                seconds, false, matches.is_present("deterministic"));
+        } else if !matches.is_present("quiet") {
+            helpers::report_unchanged(&input_path);
+        }
+        return Ok((summary.warnings(), false));
+    }
+
+    let companion_summary_opt = match companion_opt {
+        Some((companion, companion_output)) => Some((submod::write_companion(
+            companion, &summary.timing_map, &companion_output, &options)?,
+            companion_output)),
+        None => None,
+    };
+
+    // The input is verified right before backups prune older ones, and
+    // before it's replaced, or else once the output is written:
+    let mut written = vec![output_path.clone()];
+    written.extend(companion_summary_opt.iter()
+        .map(|(_, companion_output)| companion_output.clone()));
+    let verify = |written: &[std::path::PathBuf]| match fingerprint_opt {
+        Some(fingerprint) => helpers::verify_unchanged(&input_path,
+            fingerprint, &written.iter().map(|path| path.as_path())
+                .collect::<Vec<_>>()),
+        None => Ok(()),
+    };
+    if keep_backups_opt.is_some() || !overwrite {
+        verify(&written)?;
+    }
+
+    let mut backups_opt = None;
+    if let Some(keep) = keep_backups_opt {
+        let backups = helpers::keep_backup(&input_path, keep, stamp_date)?;
+        written.push(backups.created.clone());
+        backups_opt = Some(backups);
+    }
+
+    if overwrite {
+        verify(&written)?;
+        helpers::do_overwrites(&mut input_path, &mut output_path,
+            &mut overwrite, &mut rename_opt)?;
+    }
+
+    if matches.is_present("json") {
+        helpers::report_json(&summary, &input_path, &output_path,
+            seconds, overwrite, matches.is_present("deterministic"));
+    } else if !matches.is_present("quiet") {
+        helpers::report_success(&summary, partial, &output_path,
+            overwrite, rename_opt, backups_opt);
+        if let Some((companion, companion_output)) = companion_summary_opt {
+            helpers::report_companion(&companion, &companion_output);
+        }
+    }
+    Ok((summary.warnings(), false))
+}
+
+
+/// Finds when the first subtitle of an srt or vtt file starts, for
+/// `--trim-leading-silence`.
+fn first_start(input: &str, matches: &clap::ArgMatches)
+    -> Result<f64, failure::Error>
+{
+    let input_path = std::path::Path::new(input);
+    if submod::is_ass(input_path) || submod::is_ttml(input_path)
+        || input.ends_with(".sub")
+        || matches!(matches.value_of("format"),
+            Some("microdvd") | Some("subviewer") | Some("ttml")) {
+        return Err(format_err!("--trim-leading-silence only applies to \
+            .srt and .vtt subtitles."));
+    }
+    Ok(submod::span(input_path)?.0)
+}
+
+/// The shift that makes the first subtitle start `--trim-leading-silence`
+/// seconds after 00:00:00, or 1 second by default. Subtitles are rarely
+/// meant to be delayed by much, so a larger delay takes `--force`, in case
+/// the first subtitle is something like an ad that should go.
+fn trim_leading_silence(first_start: f64, factor: f64,
+    matches: &clap::ArgMatches) -> Result<f64, failure::Error>
+{
+    // Validated by helpers::is_non_negative:
+    let target: f64 = matches.value_of("trim_leading_silence")
+        .map_or(1.0, |seconds| seconds.parse().unwrap());
+    // Rounded like the times it's added to:
+    let shift = cue::to_millis(target - first_start * factor) as f64 / 1000.0;
+    if shift > MAX_LEADING_DELAY && !matches.is_present("force") {
+        return Err(format_err!("The first subtitle starts at {}, so \
+            starting it at {} would delay all subtitles by {:.3} seconds, \
+            more than the {} seconds --trim-leading-silence allows.\n\n\
+            Check the first subtitle, or use {} to delay them anyway.",
+            submod::format_secs(first_start),
+            submod::format_secs(target), shift, MAX_LEADING_DELAY,
+            color::hint("--force")));
+    }
+    Ok(shift)
+}
+
+/// Splits a validated `old=new` rename into its parts.
+fn split_rename(rename: &str) -> (String, String) {
+    let i = rename.find('=').unwrap();
+    (rename[..i].to_string(), rename[i + 1..].to_string())
+}
+
+/// Pipes, other streams and URLs can only be read once, and have no
+/// sibling files, so the options that need those are refused up front.
+/// `what` says which of those the input is.
+fn check_stream(input: &str, what: &str, matches: &clap::ArgMatches)
+    -> Result<(), failure::Error>
+{
+    if matches.is_present("overwrite") || matches.is_present("overname") {
+        return Err(format_err!("Can't overwrite `{}`, which is {} \
+            rather than a file.\n\n\
+            Use {} or {} instead.", input, what,
+            color::hint("--out <filename>"), color::hint("--stdout")));
+    }
+    if !["output", "stdout", "match"].iter()
+        .any(|name| matches.is_present(name)) {
+        return Err(format_err!("Can't name the output after `{}`, which is \
+            {} rather than a file.\n\n\
+            Use {} or {}.", input, what, color::hint("--out <filename>"),
+            color::hint("--stdout")));
+    }
+    if matches.is_present("check") {
+        return Err(format_err!("--check compares the output with the input, \
+            but `{}` is {} that can only be read once.", input, what));
+    }
+    if matches.values_of("start").into_iter().chain(matches.values_of("stop"))
+        .flatten().any(|time_string| time_string.ends_with('%')) {
+        return Err(format_err!("Percentages for --start and --stop need \
+            reading the input twice, but `{}` is {} that can only be \
+            read once.\n\n\
+            Use times like {} instead.", input, what,
+            color::hint("hh:mm:ss")));
+    }
+    if matches.is_present("lang") && matches.is_present("detect_lang") {
+        return Err(format_err!("--lang with --detect-lang needs reading the \
+            input twice, but `{}` is {} that can only be read once.",
+            input, what));
+    }
+    if let Some(name) = ["trim_leading_silence", "companion",
+        "verify_source_unchanged"].iter()
+        .find(|name| matches.is_present(name)) {
+        return Err(format_err!("--{} needs reading the input twice, but \
+            `{}` is {} that can only be read once.", name.replace('_', "-"),
+            input, what));
+    }
+    Ok(())
+}
+
+/// Downloads the input from `url` into memory, and returns it with a file
+/// name for it: the one in the URL, with the extension of its format.
+#[cfg(feature = "http")]
+fn download(url: &str, matches: &clap::ArgMatches)
+    -> Result<(String, Vec<u8>), failure::Error>
+{
+    // Validated by helpers::is_count, and have default values:
+    let max_size = matches.value_of("max_input_size").unwrap()
+        .parse::<u64>().unwrap() * 1024 * 1024;
+    let timeout = matches.value_of("timeout").unwrap().parse().unwrap();
+    let download = http::download(url, max_size, timeout)?;
+    let extension = http::extension(url, &download);
+    let file_name = http::file_name(url);
+    let name = if file_name.to_lowercase().ends_with(&format!(".{}", extension)) {
+        file_name.to_string()
+    } else if file_name.is_empty() {
+        format!("download.{}", extension)
+    } else {
+        format!("{}.{}", file_name, extension)
+    };
+    Ok((name, download.bytes))
+}
+
+#[cfg(not(feature = "http"))]
+fn download(_: &str, _: &clap::ArgMatches)
+    -> Result<(String, Vec<u8>), failure::Error>
+{
+    Err(format_err!("Reading subtitles from URLs needs the `http` feature, \
+        which this build of submod leaves out.\n\n\
+        Reinstall it with {} to use it.",
+        color::hint("cargo install submod --features http")))
+}
+
+/// Writes the `--selection-out` and `--map-out` files, if requested.
+fn write_reports(matches: &clap::ArgMatches, summary: &submod::Summary,
+    seconds: f64) -> Result<(), failure::Error>
+{
+    let json = matches.value_of("report") == Some("json");
+    if let Some(path) = matches.value_of("selection_out") {
+        helpers::write_selection(std::path::Path::new(path), summary,
+            seconds, json, matches.is_present("deterministic"))?;
+    }
+    if let Some(path) = matches.value_of("map_out") {
+        helpers::write_timing_map(std::path::Path::new(path), summary, json)?;
+    }
+    Ok(())
+}
+
+/// Reports the error, as JSON with `--json`, and returns the status of its
+/// kind, like 2 when the input isn't subtitles of its format.
+fn fail(error: failure::Error, notify: bool, json: bool) -> i32 {
+    let status = status::of(&error);
+    if json {
+        helpers::report_error_json(&error, None);
+    } else {
+        helpers::report_error(error);
+    }
+    if notify {
+        #[cfg(feature = "notify")]
+        notify::send(0, 1);
+    }
+    status
+}
@@ -0,0 +1,488 @@
+use std::io::prelude::*;
+
+use regex::Regex;
+use failure::Error;
+
+
+/// Matches a timing line, capturing the start and end timestamps,
+/// and anything following them, like vtt cue settings.
+pub const TIMING: &str =
+    r"^(\d{2,}:\d{2}:\d{2}[,.]\d{3}) --> (\d{2,}:\d{2}:\d{2}[,.]\d{3})(\s.*)?$";
+
+/// The latest time an srt timestamp can represent, 99:59:59,999;
+/// vtt timestamps can have any number of hours.
+pub const SRT_MAX_TIME: f64 = 99.0 * 3600.0 + 59.0 * 60.0 + 59.999;
+
+/// A single subtitle.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cue {
+    /// Position of the cue in the input file, starting at 1.
+    pub number: usize,
+    /// Line of the timing line in the input file, starting at 1,
+    /// or 0 for cues that weren't read from one.
+    pub line: usize,
+    /// The lines preceding the timing line,
+    /// i.e. the srt index or the vtt cue identifier.
+    pub id: Vec<String>,
+    pub start: f64,
+    pub end: f64,
+    /// Anything following the timestamps on the timing line.
+    pub settings: String,
+    pub text: Vec<String>,
+}
+
+/// The parts a subtitle file consists of, in order.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Block {
+    Cue(Cue),
+    /// Any other block of lines, like the WEBVTT header or a NOTE,
+    /// which is kept as is.
+    Other(Vec<String>),
+    /// An empty line, separating the blocks.
+    Empty,
+}
+
+/// Where a file switches to another subtitle format or numbering,
+/// like an srt file that was appended to a vtt file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormatSwitch {
+    /// Line number of the first line in the new format, starting at 1.
+    pub line: usize,
+    pub description: &'static str,
+}
+
+impl Cue {
+    pub fn timing_line(&self, srt: bool) -> String {
+        format!("{} --> {}{}", format_time(self.start, srt),
+            format_time(self.end, srt), self.settings)
+    }
+}
+
+/// Splits the input into blocks separated by empty lines.
+/// Also returns where the file first switches to another format, if it does.
+pub fn parse<R: BufRead>(reader: R)
+    -> Result<(Vec<Block>, Option<FormatSwitch>), Error>
+{
+    let timing = Regex::new(TIMING)?;
+    let mut blocks = Vec::new();
+    let mut number = 0;
+    // Lines of the current block, i.e. everything up to the next empty line,
+    // and the line number of the first:
+    let mut lines: Vec<String> = Vec::new();
+    let mut first_line = 1;
+    let mut detector = SwitchDetector::default();
+
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let in_cue = lines.iter().any(|l| timing.is_match(l));
+        // Within a subtitle, a timing line only starts the next one when
+        // it follows an srt index, as the empty line between them may be
+        // missing. Otherwise it's text, like in a subtitle about subtitles,
+        // and a vtt comment never contains subtitles:
+        let starts_cue = timing.is_match(&line) && (!in_cue
+            || lines.last().is_some_and(|l| is_index(l)))
+            && !lines.first().is_some_and(|l| is_note(l));
+        detector.check(i + 1, &line, &lines, &timing, starts_cue);
+
+        if line.is_empty() {
+            if !lines.is_empty() {
+                blocks.push(to_block(lines.split_off(0), &timing, &mut number,
+                    first_line));
+            }
+            blocks.push(Block::Empty);
+            continue;
+        }
+
+        if starts_cue && in_cue {
+            // Keep the srt index with the subtitle it belongs to:
+            let index = lines.pop();
+            blocks.push(to_block(lines.split_off(0), &timing, &mut number,
+                first_line));
+            first_line = if index.is_some() { i } else { i + 1 };
+            lines.extend(index);
+        }
+        if lines.is_empty() {
+            first_line = i + 1;
+        }
+        lines.push(line);
+    }
+    if !lines.is_empty() {
+        blocks.push(to_block(lines, &timing, &mut number, first_line));
+    }
+
+    Ok((blocks, detector.switch))
+}
+
+/// Whether the line starts a vtt comment, which lasts until the next
+/// empty line.
+pub fn is_note(line: &str) -> bool {
+    line == "NOTE" || line.starts_with("NOTE ") || line.starts_with("NOTE\t")
+}
+
+/// Whether the line is an srt index, i.e. only digits.
+fn is_index(line: &str) -> bool {
+    !line.is_empty() && line.chars().all(|c| c.is_ascii_digit())
+}
+
+/// Watches the lines of a file for signs of a second file appended to it.
+#[derive(Default)]
+struct SwitchDetector {
+    switch: Option<FormatSwitch>,
+    vtt_header: bool,
+    /// Whether the first timestamp used a comma, as in srt files.
+    comma_opt: Option<bool>,
+    last_index: u64,
+}
+
+impl SwitchDetector {
+    /// Checks a line, given the preceding lines of its block,
+    /// and whether it's the timing line that starts a subtitle.
+    fn check(&mut self, line_number: usize, line: &str, block: &[String],
+        timing: &Regex, starts_cue: bool)
+    {
+        if self.switch.is_some() {
+            return;
+        }
+        let description = if line.starts_with("WEBVTT") {
+            if line_number == 1 {
+                self.vtt_header = true;
+                return;
+            }
+            "a second WEBVTT header"
+        } else if starts_cue {
+            // The separator of the milliseconds of the start time:
+            let start = line.split(' ').next().unwrap();
+            let comma = start.as_bytes()[start.len() - 4] == b',';
+            let first = *self.comma_opt.get_or_insert(comma);
+            let index = block.last().and_then(|l| l.parse::<u64>().ok());
+            if comma != first && comma && self.vtt_header {
+                "srt timestamps after a WEBVTT header"
+            } else if comma != first && comma {
+                "srt timestamps after vtt timestamps"
+            } else if comma != first {
+                "vtt timestamps after srt timestamps"
+            } else if comma && index == Some(1) && self.last_index > 0 {
+                "srt numbering that starts over"
+            } else {
+                if let Some(index) = index {
+                    self.last_index = index;
+                }
+                return;
+            }
+        } else {
+            return;
+        };
+        // The new format starts with the identifier of the cue, if any:
+        let id_lines = if block.iter().any(|l| timing.is_match(l)) {
+            block.last().map_or(0, |l| l.parse::<u64>().is_ok() as usize)
+        } else {
+            block.len()
+        };
+        self.switch = Some(FormatSwitch {
+            line: line_number - id_lines,
+            description,
+        });
+    }
+}
+
+/// Merges the cues of files that were concatenated into a single list,
+/// sorted by start time and renumbered. Repeated WEBVTT headers are dropped,
+/// and any other blocks, like notes, stay in front of the cue they preceded.
+pub fn merge_segments(blocks: Vec<Block>) -> Vec<Block> {
+    let mut blocks = sort(blocks, true);
+    let cues = blocks.iter_mut().filter_map(|block| match *block {
+        Block::Cue(ref mut cue) => Some(cue),
+        _ => None,
+    });
+    for (i, cue) in cues.enumerate() {
+        cue.number = i + 1;
+    }
+    blocks
+}
+
+/// Sorts the cues by start time, and renumbers srt indices. Any other
+/// blocks, like notes, stay in front of the cue they preceded.
+/// Cue numbers are kept, so they still identify the cues of the input.
+pub fn sort_cues(blocks: Vec<Block>) -> Vec<Block> {
+    sort(blocks, false)
+}
+
+fn sort(blocks: Vec<Block>, drop_headers: bool) -> Vec<Block> {
+    let first_cue = blocks.iter().position(|b| matches!(b, Block::Cue(_)))
+        .unwrap_or(blocks.len());
+    let last_cue = blocks.iter().rposition(|b| matches!(b, Block::Cue(_)))
+        .map_or(blocks.len(), |i| i + 1);
+    let mut blocks = blocks;
+    let trailing = blocks.split_off(last_cue);
+    let body = blocks.split_off(first_cue);
+
+    let mut chunks: Vec<(Vec<Block>, Cue)> = Vec::new();
+    let mut preceding = Vec::new();
+    for block in body {
+        match block {
+            Block::Cue(cue) => chunks.push((preceding.split_off(0), cue)),
+            Block::Other(ref lines)
+                if drop_headers && lines[0].starts_with("WEBVTT") => {},
+            Block::Other(lines) => preceding.push(Block::Other(lines)),
+            Block::Empty => {},
+        }
+    }
+    // A stable sort keeps cues with the same start time in order:
+    chunks.sort_by(|a, b| a.1.start.partial_cmp(&b.1.start).unwrap());
+
+    for (i, (preceding, mut cue)) in chunks.into_iter().enumerate() {
+        if i > 0 {
+            blocks.push(Block::Empty);
+        }
+        for block in preceding {
+            blocks.push(block);
+            blocks.push(Block::Empty);
+        }
+        // Renumber srt indices, but keep vtt identifiers:
+        if cue.id.len() == 1 && cue.id[0].parse::<u64>().is_ok() {
+            cue.id[0] = (i + 1).to_string();
+        }
+        blocks.push(Block::Cue(cue));
+    }
+    blocks.extend(trailing);
+    blocks
+}
+
+fn to_block(mut lines: Vec<String>, timing: &Regex, number: &mut usize,
+    first_line: usize) -> Block
+{
+    let i = match lines.iter().position(|l| timing.is_match(l)) {
+        Some(i) if !is_note(&lines[0]) => i,
+        _ => return Block::Other(lines),
+    };
+    *number += 1;
+    let text = lines.split_off(i + 1);
+    let timing_line = lines.pop().unwrap();
+    let captures = timing.captures(&timing_line).unwrap();
+
+    Block::Cue(Cue {
+        number: *number,
+        line: first_line + i,
+        id: lines,
+        start: get_secs(&captures[1]),
+        end: get_secs(&captures[2]),
+        settings: captures.get(3).map_or("", |m| m.as_str()).to_string(),
+        text,
+    })
+}
+
+/// Writes the blocks, adding \n to each line. Srt files are written as
+/// they are, but vtt files always get exactly one empty line between
+/// blocks and none before the first or after the last, however many the
+/// input had, and the WEBVTT header loses any trailing whitespace, so that
+/// writing them again changes nothing.
+pub fn write<W: Write>(blocks: &[Block], out: &mut W, srt: bool)
+    -> Result<(), Error>
+{
+    if srt {
+        for block in blocks {
+            write_block(block, out, srt)?;
+        }
+        return Ok(());
+    }
+    let blocks = blocks.iter().filter(|block| **block != Block::Empty);
+    for (i, block) in blocks.enumerate() {
+        if i > 0 {
+            writeln!(out)?;
+        }
+        match *block {
+            Block::Other(ref lines) if i == 0
+                && lines[0].starts_with("WEBVTT") => {
+                writeln!(out, "{}", lines[0].trim_end())?;
+                for line in &lines[1..] {
+                    writeln!(out, "{}", line)?;
+                }
+            },
+            ref block => write_block(block, out, srt)?,
+        }
+    }
+    Ok(())
+}
+
+fn write_block<W: Write>(block: &Block, out: &mut W, srt: bool)
+    -> Result<(), Error>
+{
+    match *block {
+        Block::Cue(ref cue) => {
+            for line in &cue.id {
+                writeln!(out, "{}", line)?;
+            }
+            writeln!(out, "{}", cue.timing_line(srt))?;
+            for line in &cue.text {
+                writeln!(out, "{}", line)?;
+            }
+        },
+        Block::Other(ref lines) => {
+            for line in lines {
+                writeln!(out, "{}", line)?;
+            }
+        },
+        Block::Empty => writeln!(out)?,
+    }
+    Ok(())
+}
+
+/// Processes a &str of the form 'hh:mm:ss.sss', or 'hh:mm:ss,sss' as in
+/// srt files, into the total number of seconds as f64.
+/// A leading sign applies to the whole time, so '-01:23.5' is -83.5.
+pub fn get_secs(time_string: &str) -> f64 {
+    let time_string = time_string.replace(',', ".");
+    let (sign, time_string) = match time_string.chars().next() {
+        Some('-') => (-1.0, &time_string[1..]),
+        Some('+') => (1.0, &time_string[1..]),
+        _ => (1.0, &time_string[..]),
+    };
+    sign * time_string.rsplit(':')
+        // can't panic since time_string is validated by regex:
+        .map(|t| t.parse::<f64>().unwrap())
+        .zip(&[1.0, 60.0, 3600.0])
+        .map(|(a, b)| a * b)
+        .sum::<f64>()
+}
+
+/// Checks whether a cue from `start` to `end` falls inside the window of
+/// `--start`/`--stop`: the start is inclusive, so cues ending exactly at it
+/// are inside, and the stop is exclusive, so cues starting exactly at it are
+/// not. Times are compared in whole milliseconds, so that `00:01:00` and
+/// `00:00:60.000`, or an srt and vtt timestamp of the same time, always agree.
+pub fn in_window(start: f64, end: f64, start_opt: Option<f64>,
+    stop_opt: Option<f64>) -> bool
+{
+    if let Some(start_transform) = start_opt {
+        if to_millis(end) < to_millis(start_transform) {
+            return false;
+        }
+    }
+    if let Some(stop_transform) = stop_opt {
+        if to_millis(start) >= to_millis(stop_transform) {
+            return false;
+        }
+    }
+    true
+}
+
+/// Rounds seconds to whole milliseconds, the precision of the timestamps,
+/// to compare times without the noise of floating point arithmetic.
+pub fn to_millis(seconds: f64) -> i64 {
+    (seconds * 1000.0).round() as i64
+}
+
+/// Formats non-negative seconds as 'hh:mm:ss.sss',
+/// or as 'hh:mm:ss,sss' for srt files. The seconds are rounded to whole
+/// milliseconds first, so that a time like 59.9996 carries over into
+/// the minutes instead of being written as '00:00:60.000'.
+pub fn format_time(seconds: f64, srt: bool) -> String {
+    let millis = to_millis(seconds).max(0) as u64;
+    format!("{:02}:{:02}:{:02}{}{:03}", millis / 3_600_000,
+        millis / 60_000 % 60, millis / 1000 % 60, if srt { ',' } else { '.' },
+        millis % 1000)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_one_empty_line_between_vtt_blocks() {
+        let vtt = "\nWEBVTT  \n\n\n\nNOTE hi\n\n\n\
+            00:00:01.000 --> 00:00:02.000\nA\n\n\n\n\n\
+            00:00:03.000 --> 00:00:04.000\nB\n\n\n";
+        let write = |input: &[u8]| {
+            let (blocks, _) = parse(input).unwrap();
+            let mut output = Vec::new();
+            write(&blocks, &mut output, false).unwrap();
+            output
+        };
+        let once = write(vtt.as_bytes());
+        assert_eq!(String::from_utf8(once.clone()).unwrap(), "WEBVTT\n\n\
+            NOTE hi\n\n00:00:01.000 --> 00:00:02.000\nA\n\n\
+            00:00:03.000 --> 00:00:04.000\nB\n");
+        assert_eq!(write(&once), once);
+    }
+
+    #[test]
+    fn rounds_times_to_the_millisecond() {
+        assert_eq!(format_time(59.9996, false), "00:01:00.000");
+        assert_eq!(format_time(3599.9995, true), "01:00:00,000");
+        assert_eq!(format_time(0.1 + 0.2, true), "00:00:00,300");
+        assert_eq!(format_time(-0.0004, false), "00:00:00.000");
+        assert_eq!(format_time(360_000.0, false), "100:00:00.000");
+    }
+
+    #[test]
+    fn carries_shifts_across_every_boundary() {
+        // (timestamp, shift, shifted timestamp), in srt notation:
+        let cases = [
+            ("00:00:00,999", "0.001", "00:00:01,000"),
+            ("00:00:00,998", "0.002", "00:00:01,000"),
+            ("00:00:01,000", "-0.001", "00:00:00,999"),
+            ("00:00:09,999", "0.001", "00:00:10,000"),
+            ("00:00:59,999", "0.001", "00:01:00,000"),
+            ("00:00:59,999", "0.002", "00:01:00,001"),
+            ("00:01:00,000", "-0.001", "00:00:59,999"),
+            ("00:01:00,001", "-0.002", "00:00:59,999"),
+            ("00:09:59,999", "0.001", "00:10:00,000"),
+            ("00:59:59,999", "0.001", "01:00:00,000"),
+            ("00:59:59,999", "0.002", "01:00:00,001"),
+            ("01:00:00,000", "-0.001", "00:59:59,999"),
+            ("01:00:00,001", "-0.002", "00:59:59,999"),
+            ("00:59:59,000", "1", "01:00:00,000"),
+            ("00:59:00,000", "60", "01:00:00,000"),
+            ("00:59:59,999", "00:00:00.001", "01:00:00,000"),
+            ("00:59:30,500", "29.5", "01:00:00,000"),
+            ("01:59:59,999", "0.001", "02:00:00,000"),
+            ("09:59:59,999", "0.001", "10:00:00,000"),
+            ("23:59:59,999", "0.001", "24:00:00,000"),
+            ("01:00:00,000", "-3600", "00:00:00,000"),
+            ("02:00:00,000", "-00:59:59.999", "01:00:00,001"),
+            ("00:00:00,100", "0.2", "00:00:00,300"),
+            ("00:00:00,300", "-0.1", "00:00:00,200"),
+            ("00:00:00,700", "0.6", "00:00:01,300"),
+            ("00:16:40,123", "3600.877", "01:16:41,000"),
+        ];
+        for &(time, shift, expected) in &cases {
+            let shifted = get_secs(time) + get_secs(shift);
+            assert_eq!(format_time(shifted, true), expected,
+                "{} shifted by {}", time, shift);
+            assert_eq!(format_time(shifted, false), expected.replace(',', "."),
+                "{} shifted by {}", time, shift);
+        }
+    }
+
+    #[test]
+    fn applies_the_sign_to_the_whole_time() {
+        assert_eq!(get_secs("-83.5"), -83.5);
+        assert_eq!(get_secs("1:23.5"), 83.5);
+        assert_eq!(get_secs("-00:01:23.500"), -83.5);
+        assert_eq!(get_secs("+01:00:00"), 3600.0);
+    }
+
+    #[test]
+    fn keeps_timing_lines_in_notes_out_of_the_cues() {
+        let vtt = "WEBVTT\n\nNOTE\nThe cue below used to be\n\
+            00:00:01.000 --> 00:00:02.000\n\n\
+            00:00:03.000 --> 00:00:04.000\nHello\n";
+        let (blocks, switch_opt) = parse(vtt.as_bytes()).unwrap();
+        assert_eq!(blocks[2], Block::Other(vec![String::from("NOTE"),
+            String::from("The cue below used to be"),
+            String::from("00:00:01.000 --> 00:00:02.000")]));
+        match blocks[4] {
+            Block::Cue(ref cue) => assert_eq!((cue.number, cue.start), (1, 3.0)),
+            ref block => panic!("{:?}", block),
+        }
+        assert_eq!(switch_opt, None);
+    }
+
+    #[test]
+    fn reads_milliseconds_after_a_dot_or_a_comma() {
+        assert_eq!(to_millis(get_secs("00:14:07.438")), 847_438);
+        assert_eq!(to_millis(get_secs("00:14:07,438")), 847_438);
+        assert_eq!(get_secs("7.5"), 7.5);
+    }
+}
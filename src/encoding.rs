@@ -0,0 +1,336 @@
+use std::borrow::Cow;
+use std::str;
+
+#[cfg(feature = "encodings")]
+use encoding_rs::{self, Encoder, EncoderResult};
+#[cfg(feature = "encodings")]
+pub use encoding_rs::{Encoding, UTF_8};
+
+
+/// The only encoding of builds without the `encodings` feature, which
+/// `--encoding` and `--output-encoding` need for any other.
+#[cfg(not(feature = "encodings"))]
+#[derive(Debug, PartialEq, Eq)]
+pub struct Encoding;
+
+#[cfg(not(feature = "encodings"))]
+pub static UTF_8: &Encoding = &Encoding;
+
+#[cfg(not(feature = "encodings"))]
+impl Encoding {
+    pub fn name(&self) -> &'static str {
+        "UTF-8"
+    }
+
+    pub fn output_encoding(&'static self) -> &'static Encoding {
+        self
+    }
+}
+
+
+/// What to write for characters the output encoding cannot represent.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Fallback {
+    /// Replace them with a question mark.
+    Question,
+    /// Replace common punctuation with a look-alike, and anything else
+    /// with a question mark.
+    Transliterate,
+}
+
+/// How the input was decoded.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Decoded {
+    #[default]
+    Utf8,
+    /// The input started with a UTF-16 byte order mark.
+    Utf16,
+    /// The input was read in the encoding given by `--encoding`.
+    Given(&'static Encoding),
+    /// The input wasn't valid UTF-8.
+    #[cfg(feature = "encodings")]
+    Windows1252,
+    /// The input started with a UTF-8 byte order mark, but the rest
+    /// wasn't valid UTF-8, like when a BOM was added to a legacy file.
+    #[cfg(feature = "encodings")]
+    BomWindows1252,
+    /// The input wasn't valid UTF-8, and its invalid bytes were replaced,
+    /// as this build leaves out the `encodings` feature.
+    #[cfg(not(feature = "encodings"))]
+    Lossy,
+}
+
+/// The UTF-8 encoded byte order mark.
+#[cfg(feature = "encodings")]
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// The UTF-16 byte order marks, little-endian first.
+const UTF16_LE_BOM: &[u8] = b"\xFF\xFE";
+const UTF16_BE_BOM: &[u8] = b"\xFE\xFF";
+
+/// Decodes the input as UTF-16 when it starts with a UTF-16 byte order
+/// mark, or else as UTF-8, or as Windows-1252 when it isn't valid UTF-8,
+/// even if it starts with a UTF-8 byte order mark.
+/// A byte order mark is kept as a U+FEFF, for `split_bom` to find.
+pub fn decode(input: &[u8]) -> (Cow<'_, str>, Decoded) {
+    if input.starts_with(UTF16_LE_BOM) || input.starts_with(UTF16_BE_BOM) {
+        return (Cow::Owned(decode_utf16(input)), Decoded::Utf16);
+    }
+    if let Ok(text) = str::from_utf8(input) {
+        return (Cow::Borrowed(text), Decoded::Utf8);
+    }
+    decode_legacy(input)
+}
+
+/// Looks up an encoding by any of its WHATWG labels, like `latin1`,
+/// `cp1251` or `shift_jis`, case-insensitively.
+#[cfg(feature = "encodings")]
+pub fn for_label(label: &str) -> Option<&'static Encoding> {
+    Encoding::for_label_no_replacement(label.as_bytes())
+}
+
+#[cfg(not(feature = "encodings"))]
+pub fn for_label(_label: &str) -> Option<&'static Encoding> {
+    None
+}
+
+/// Decodes `input` in the given encoding, replacing the bytes that aren't
+/// valid in it. A UTF-8 byte order mark is kept as a U+FEFF, like `decode`
+/// does, and so is a UTF-16 one in UTF-16.
+#[cfg(feature = "encodings")]
+pub fn decode_as<'a>(input: &'a [u8], encoding: &'static Encoding)
+    -> Cow<'a, str>
+{
+    match input.strip_prefix(UTF8_BOM) {
+        Some(rest) => Cow::Owned(["\u{FEFF}",
+            &encoding.decode_without_bom_handling(rest).0].concat()),
+        None => encoding.decode_without_bom_handling(input).0,
+    }
+}
+
+/// Encodes `text` in the given encoding, and returns the bytes along with
+/// the number of characters that had to be replaced. UTF-16 is written
+/// as UTF-8, as encoders do.
+#[cfg(feature = "encodings")]
+pub fn encode(text: &str, encoding: &'static Encoding, fallback: Fallback)
+    -> (Vec<u8>, usize)
+{
+    if encoding.output_encoding() == UTF_8 {
+        return (text.as_bytes().to_vec(), 0);
+    }
+    let mut encoder = encoding.new_encoder();
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut replaced = 0;
+    let mut rest = text;
+    while let Some((c, after)) = encode_mappable(&mut encoder, rest,
+        &mut bytes, false) {
+        replaced += 1;
+        let substitute = match fallback {
+            Fallback::Transliterate => transliterate(c),
+            Fallback::Question => None,
+        };
+        // Transliterations may themselves be unmappable (like '€'):
+        let mut substitute = substitute.unwrap_or("?");
+        while let Some((_, after)) = encode_mappable(&mut encoder,
+            substitute, &mut bytes, false) {
+            encode_mappable(&mut encoder, "?", &mut bytes, false);
+            substitute = after;
+        }
+        rest = after;
+    }
+    // Stateful encodings like ISO-2022-JP end by switching back to ASCII:
+    encode_mappable(&mut encoder, "", &mut bytes, true);
+    (bytes, replaced)
+}
+
+#[cfg(not(feature = "encodings"))]
+pub fn decode_as<'a>(input: &'a [u8], _encoding: &'static Encoding)
+    -> Cow<'a, str>
+{
+    String::from_utf8_lossy(input)
+}
+
+#[cfg(not(feature = "encodings"))]
+pub fn encode(text: &str, _encoding: &'static Encoding, _fallback: Fallback)
+    -> (Vec<u8>, usize)
+{
+    (text.as_bytes().to_vec(), 0)
+}
+
+/// Encodes `text` up to the first character the encoder can't, and
+/// returns that character along with the text after it, if there is one.
+#[cfg(feature = "encodings")]
+fn encode_mappable<'a>(encoder: &mut Encoder, text: &'a str,
+    bytes: &mut Vec<u8>, last: bool) -> Option<(char, &'a str)>
+{
+    let mut rest = text;
+    loop {
+        bytes.reserve(encoder
+            .max_buffer_length_from_utf8_without_replacement(rest.len())
+            .unwrap_or(rest.len() * 4));
+        let (result, read) = encoder
+            .encode_from_utf8_to_vec_without_replacement(rest, bytes, last);
+        rest = &rest[read..];
+        match result {
+            EncoderResult::InputEmpty => return None,
+            EncoderResult::OutputFull => {},
+            EncoderResult::Unmappable(c) => return Some((c, rest)),
+        }
+    }
+}
+
+/// Decodes UTF-16 in the byte order of its byte order mark, including
+/// the mark itself. Unpaired surrogates and a trailing odd byte are
+/// replaced, as they have no character to decode to.
+fn decode_utf16(input: &[u8]) -> String {
+    let little_endian = input.starts_with(UTF16_LE_BOM);
+    let units: Vec<u16> = input.chunks(2)
+        .map(|pair| match *pair {
+            [first, second] if little_endian =>
+                u16::from_le_bytes([first, second]),
+            [first, second] => u16::from_be_bytes([first, second]),
+            _ => 0xFFFD,
+        })
+        .collect();
+    String::from_utf16_lossy(&units)
+}
+
+/// Splits the U+FEFF that `decode` keeps of a byte order mark off
+/// the start of the text, and tells whether there was one.
+pub fn split_bom(text: &str) -> (bool, &str) {
+    match text.strip_prefix('\u{FEFF}') {
+        Some(rest) => (true, rest),
+        None => (false, text),
+    }
+}
+
+#[cfg(feature = "encodings")]
+fn decode_legacy(input: &[u8]) -> (Cow<'_, str>, Decoded) {
+    let decoded = if input.starts_with(UTF8_BOM) {
+        Decoded::BomWindows1252
+    } else {
+        Decoded::Windows1252
+    };
+    (decode_as(input, encoding_rs::WINDOWS_1252), decoded)
+}
+
+#[cfg(not(feature = "encodings"))]
+fn decode_legacy(input: &[u8]) -> (Cow<'_, str>, Decoded) {
+    (String::from_utf8_lossy(input), Decoded::Lossy)
+}
+
+/// Plain ASCII look-alikes for common typographic characters.
+#[cfg(feature = "encodings")]
+fn transliterate(c: char) -> Option<&'static str> {
+    let substitute = match c {
+        '‘' | '’' | '‚' | '‛' | '′' => "'",
+        '“' | '”' | '„' | '‟' | '″' => "\"",
+        '‐' | '‑' | '‒' | '–' | '—' | '―' | '−' => "-",
+        '…' => "...",
+        '‹' => "<",
+        '›' => ">",
+        '•' | '·' => "*",
+        '€' => "EUR",
+        '™' => "(TM)",
+        '\u{2000}'..='\u{200A}' | '\u{202F}' | '\u{205F}' => " ",
+        '\u{200B}'..='\u{200F}' | '\u{FEFF}' => "",
+        _ => return None,
+    };
+    Some(substitute)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_utf_8_with_or_without_bom() {
+        assert_eq!(decode("Café".as_bytes()), (Cow::Borrowed("Café"),
+            Decoded::Utf8));
+        assert_eq!(decode("\u{FEFF}Café".as_bytes()),
+            (Cow::Borrowed("\u{FEFF}Café"), Decoded::Utf8));
+    }
+
+    #[cfg(feature = "encodings")]
+    #[test]
+    fn decodes_windows_1252_despite_a_utf_8_bom() {
+        let (text, decoded) = decode(b"\xEF\xBB\xBF\x93Caf\xe9\x94 \x81");
+        assert_eq!(text, "\u{FEFF}“Café” \u{81}");
+        assert_eq!(decoded, Decoded::BomWindows1252);
+        let (text, decoded) = decode(b"cr\xe8me br\xfbl\xe9e");
+        assert_eq!(text, "crème brûlée");
+        assert_eq!(decoded, Decoded::Windows1252);
+    }
+
+    #[test]
+    fn decodes_utf_16_in_either_byte_order() {
+        let little = b"\xFF\xFEC\x00a\x00f\x00\xE9\x00\n\x00";
+        assert_eq!(decode(little), (Cow::Owned(String::from("\u{FEFF}Café\n")),
+            Decoded::Utf16));
+        let big = b"\xFE\xFF\x00C\x00a\x00f\x00\xE9\xD8\x3D\xDE\x00";
+        assert_eq!(decode(big).0, "\u{FEFF}Café😀");
+        assert_eq!(decode(b"\xFF\xFE\x00\xD8a").0, "\u{FEFF}\u{FFFD}\u{FFFD}");
+    }
+
+    #[test]
+    fn splits_off_the_byte_order_mark() {
+        assert_eq!(split_bom("\u{FEFF}1\n"), (true, "1\n"));
+        assert_eq!(split_bom("1\n\u{FEFF}"), (false, "1\n\u{FEFF}"));
+    }
+
+    #[cfg(feature = "encodings")]
+    #[test]
+    fn decodes_the_given_encoding() {
+        let decode = |input, label| decode_as(input, for_label(label).unwrap());
+        assert_eq!(decode(b"\xEF\xBB\xBF\xa4uvre \xbd", "iso-8859-15"),
+            "\u{FEFF}€uvre œ");
+        // As in browsers, latin1 is read as windows-1252:
+        assert_eq!(decode(b"\x93caf\xe9\x94", "ISO-8859-1"), "“café”");
+        assert_eq!(decode(b"\x8d\xa1\x82\xcd", "shift_jis"), "今は");
+        assert_eq!(decode(b"\xcf\xf0\xe8\xe2\xe5\xf2", "cp1251"), "Привет");
+        assert_eq!(decode(b"caf\xe9", "utf8"), "caf\u{FFFD}");
+        assert_eq!(for_label("iso-2022-kr"), None);
+        assert_eq!(for_label("klingon"), None);
+    }
+
+    #[cfg(feature = "encodings")]
+    #[test]
+    fn encodes_windows_1252_punctuation() {
+        let (bytes, replaced) = encode("“Café” – 5€",
+            encoding_rs::WINDOWS_1252, Fallback::Question);
+        assert_eq!(bytes, b"\x93Caf\xe9\x94 \x96 5\x80");
+        assert_eq!(replaced, 0);
+    }
+
+    #[cfg(feature = "encodings")]
+    #[test]
+    fn transliterates_unmappable_characters() {
+        let (bytes, replaced) = encode("“Oui”… ✓", encoding_rs::ISO_8859_2,
+            Fallback::Transliterate);
+        assert_eq!(bytes, b"\"Oui\"... ?");
+        assert_eq!(replaced, 4);
+        let (bytes, replaced) = encode("今は€", encoding_rs::SHIFT_JIS,
+            Fallback::Transliterate);
+        assert_eq!(bytes, b"\x8d\xa1\x82\xcdEUR");
+        assert_eq!(replaced, 1);
+    }
+
+    #[cfg(feature = "encodings")]
+    #[test]
+    fn latin_9_replaces_some_latin_1_characters() {
+        let (bytes, replaced) = encode("œuvre ½ €", encoding_rs::ISO_8859_15,
+            Fallback::Question);
+        assert_eq!(bytes, b"\xbduvre ? \xa4");
+        assert_eq!(replaced, 1);
+    }
+
+    #[cfg(feature = "encodings")]
+    #[test]
+    fn ends_stateful_encodings_in_ascii() {
+        let (bytes, replaced) = encode("今は✓", encoding_rs::ISO_2022_JP,
+            Fallback::Question);
+        assert_eq!(bytes, b"\x1b$B:#$O\x1b(B?");
+        assert_eq!(replaced, 1);
+    }
+}
@@ -1,13 +1,30 @@
 use std::path::{Path, PathBuf};
 use std::ffi::OsStr;
 use std::fs;
+use std::io::{self, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use regex::Regex;
+use regex::{self, Regex};
 use failure::Error;
 
+use cli;
+use color;
+use cue;
+use encoding;
+use layout;
+use name::{split_tag, format_increment, format_factor, build_output_name};
+use selection;
+use template;
+use split::SplitWeights;
+use submod::{self, Summary, Changes, Options};
+use subtitles::{self, Error as SubmodError};
 
+
+#[allow(clippy::too_many_arguments, clippy::needless_return,
+    clippy::partialeq_to_none)]
 pub fn get_paths(input: &str, seconds: f64, partial: bool, rename: bool,
-        output_opt: Option<&str>, convert_opt: Option<&str>)
+        output_opt: Option<&str>, convert_opt: Option<&str>,
+        template_opt: Option<&str>, tagging: Tagging)
     -> Result<(PathBuf, PathBuf, Option<PathBuf>), Error>
 {
     // Create full path for inputfile:
@@ -23,72 +40,241 @@ pub fn get_paths(input: &str, seconds: f64, partial: bool, rename: bool,
     // => parent will be empty if the path consists of the filename alone
     let parent = input_path.parent()
         .ok_or(format_err!("Invalid value for \
-            '\u{001b}[33m<INPUT>\u{001b}[0m': incorrect path"))?;
+            '{}': incorrect path", color::arg("<INPUT>")))?;
 
     // Create output file name and full path:
-    let output_name = smart_name(&input_path, seconds, partial, convert_opt)?;
-    let output_path = parent.join(output_name); // creates owned PathBuf
+    let output_path = match template_opt {
+        Some(template) => {
+            let (stem, tag, seconds, extension) = smart_parts(input_path,
+                seconds, partial, convert_opt, tagging);
+            parent.join(template::expand(template, &stem, &tag, &seconds,
+                &extension)?)
+        },
+        None => parent.join(smart_name(input_path, seconds, partial,
+            convert_opt, tagging)),
+    };
 
     // Create an optional rename in case user specified `--overname` flag:
     let mut rename_opt = None;
     if rename {
-        rename_opt = smart_rename(&input_path);
+        rename_opt = backup_path(input_path);
         if rename_opt == None {
             return Err(format_err!("Invalid value for \
-                '\u{001b}[33m<INPUT>\u{001b}[0m': invalid file name"));
+                '{}': invalid file name", color::arg("<INPUT>")));
         }
     }
 
     return Ok( (input_path.to_owned(), output_path, rename_opt) );
 }
 
+/// Fails if writing `path` would put a file outside `dir`, once `..`
+/// and symlinks are resolved. The file itself is replaced by renaming,
+/// so only the directories it's written to are resolved.
+pub fn check_within(path: &Path, dir: &Path) -> Result<(), Error> {
+    let current = |path: &Path| if path == Path::new("") {
+        Path::new(".").to_owned() } else { path.to_owned() };
+    let dir = current(dir);
+    let root = fs::canonicalize(&dir).map_err(|error| SubmodError::Io {
+        path: dir.clone(), message: format!("Could not resolve `{}`: {}",
+            dir.display(), error) })?;
+    let parent = current(path.parent().unwrap_or_else(|| Path::new("")));
+    // Resolve the directories that exist, which may be symlinks, and
+    // follow the `..` of those that don't on what's left:
+    let mut existing = parent;
+    let mut missing = Vec::new();
+    while !existing.exists() && existing != Path::new(".") {
+        missing.push(existing.file_name().map(OsStr::to_owned));
+        existing = current(existing.parent().unwrap_or_else(||
+            Path::new("")));
+    }
+    let mut resolved = fs::canonicalize(&existing).map_err(|error|
+        SubmodError::Io { path: existing.clone(), message: format!(
+            "Could not resolve `{}`: {}", existing.display(), error) })?;
+    for name in missing.into_iter().rev() {
+        match name {
+            Some(name) => resolved.push(name),
+            None => { resolved.pop(); },
+        }
+    }
+    if resolved.starts_with(&root) {
+        return Ok(());
+    }
+    Err(format_err!("The output `{}` would be written in `{}`, outside \
+        `{}`.\n\nCheck --name-template and the outputs of --manifest, or use \
+        {} to write it \
+        there anyway.", path.display(), resolved.display(), root.display(),
+        color::hint("--allow-outside-output-dir")))
+}
+
+/// Directories with more entries than this aren't scanned for suggestions.
+const MAX_SCAN: usize = 2000;
+
+/// Fails when the input doesn't exist, suggesting the subtitle files
+/// of its directory that it's most likely a typo of.
+pub fn check_input(input_path: &Path) -> Result<(), Error> {
+    if input_path.exists() {
+        return Ok(());
+    }
+    let suggestions = suggest_paths(input_path);
+    if suggestions.is_empty() {
+        return Err(format_err!("`{}` does not exist.",
+            input_path.display()));
+    }
+    Err(format_err!("`{}` does not exist.\n\nDid you mean {}?",
+        input_path.display(), suggestions.iter()
+            .map(|path| color::hint(path.display()))
+            .collect::<Vec<_>>()
+            .join(" or ")))
+}
+
+/// Up to 3 subtitle files next to a missing path, the closest first.
+fn suggest_paths(input_path: &Path) -> Vec<PathBuf> {
+    let name = match input_path.file_name().and_then(OsStr::to_str) {
+        Some(name) => name,
+        None => return Vec::new(),
+    };
+    let parent = input_path.parent().unwrap_or_else(|| Path::new(""));
+    let entries = match fs::read_dir(if parent == Path::new("") {
+        Path::new(".") } else { parent }) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+    let names: Vec<String> = entries.take(MAX_SCAN + 1)
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .collect();
+    if names.len() > MAX_SCAN {
+        return Vec::new();
+    }
+    suggest_names(name, &names).into_iter()
+        .map(|candidate| parent.join(candidate))
+        .collect()
+}
+
+/// The subtitle file names within a few typos of `name`, or with the same
+/// episode number, sorted by how many edits away they are.
+fn suggest_names<'a>(name: &str, names: &'a [String]) -> Vec<&'a str> {
+    let lowercase = name.to_lowercase();
+    let max_distance = (name.chars().count() / 4).max(2);
+    let episode_opt = episode(name);
+    let mut candidates: Vec<(usize, &str)> = names.iter()
+        .filter(|candidate| is_subtitle(candidate.to_string()).is_ok())
+        .map(|candidate| (edit_distance(&lowercase,
+            &candidate.to_lowercase()), candidate.as_str()))
+        .filter(|&(distance, candidate)| distance <= max_distance
+            || (episode_opt.is_some() && episode(candidate) == episode_opt))
+        .collect();
+    candidates.sort();
+    candidates.into_iter().take(3).map(|(_, candidate)| candidate).collect()
+}
+
+/// The episode number in a file name like `show.S01E07.srt` or `ep07.srt`.
+fn episode(name: &str) -> Option<u32> {
+    let regex = Regex::new(r"(?i)(?:^|[^a-z])(?:e|ep|episode|\d+x)[ ._-]?(\d+)")
+        .unwrap();
+    regex.captures(name)?[1].parse().ok()
+}
+
+/// The Levenshtein distance: the number of characters to insert, delete
+/// or substitute to turn one string into the other.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &b_char) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(a_char != b_char);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// How `--tag-mode` tags the default output file name.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TagMode {
+    /// The total shift, merging the tag of the input with this run.
+    Cumulative,
+    /// Only the shift of this run, replacing the tag of the input.
+    Delta,
+    /// No tag at all; the tag of the input is removed.
+    None,
+}
+
+/// How the default output file name is tagged: the `--tag-mode`,
+/// the number of decimals of the seconds, set by `--precision`, and the
+/// factor this run scales the timestamps by, like with `--rate`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tagging {
+    pub mode: TagMode,
+    pub precision: usize,
+    pub factor: f64,
+}
+
+impl Default for Tagging {
+    fn default() -> Tagging {
+        Tagging { mode: TagMode::Cumulative, precision: 3, factor: 1.0 }
+    }
+}
+
 /// This functions smartly formats the default output file name,
 /// such that output files that are reused as input still receive a sane name,
 /// without any redundant extra suffixes from repeated calls.
-fn smart_name(input_path: &Path, mut seconds: f64, partial: bool,
-    convert_opt: Option<&str>) -> Result<String, Error>
+fn smart_name(input_path: &Path, seconds: f64, partial: bool,
+    convert_opt: Option<&str>, tagging: Tagging) -> String
 {
-    let mut stem = input_path.file_stem().unwrap().to_str().unwrap();
-    let mut extension = input_path.extension().unwrap().to_str().unwrap();
-    if let Some(to_ext) = convert_opt {
-        extension = to_ext;
-    }
-    if let Some(i) = stem.find("__[Original]") {
-        stem = &stem[..i];
-    }
-    // '-' indicates that only part of the file was modified:
-    let partial = if partial { "-" } else { "+" };
-    // Regex to check if the inputfile was generated by submod:
-    let tag = Regex::new(r"__\[[+-]\d+\.\d+_Sec[+-]\]")?;
-    let was_processed: bool = tag.is_match(stem);
-
-    if was_processed {
-        // Extract the increment number from the filename,
-        // and add it to seconds:
-        seconds +=
-            // regex for finding number in filename:
-            Regex::new(r"[+-]\d+\.\d+")?
-            // unwrap capture groups corresponding to the leftmost match:
-            .captures(stem).unwrap()
-            // take out entire match and unwrap from Option:
-            .get(0).unwrap()
-            // convert to str and then to float:
-            .as_str().parse::<f64>()?;
-
-        let tag_start = tag.find(stem).unwrap().start();
-        stem = &stem[..tag_start];
-    }
-
-    let output = if seconds >= 0.0 {
-        format!("{}__[+{:.2}_Sec{}].{}", stem, seconds, partial, extension)
-    } else {
-        format!("{}__[{:.2}_Sec{}].{}", stem, seconds, partial, extension)
+    let (stem, tag, _, extension) =
+        smart_parts(input_path, seconds, partial, convert_opt, tagging);
+    build_output_name(&stem, &tag, &extension)
+}
+
+/// Splits the default output file name into its stem, its tag
+/// like `__[+1.50_Sec+]`, the seconds of that tag like `+1.50`,
+/// and its extension. The tag is empty with `TagMode::None`.
+fn smart_parts(input_path: &Path, seconds: f64, partial: bool,
+    convert_opt: Option<&str>, tagging: Tagging)
+    -> (String, String, String, String)
+{
+    let stem = input_path.file_stem().unwrap().to_str().unwrap();
+    let extension = convert_opt.unwrap_or_else(||
+        input_path.extension().unwrap().to_str().unwrap());
+
+    let (stem, tagged_opt) = split_tag(stem);
+    // Scaling also scales the seconds the input was already shifted by:
+    let tagged_opt = tagged_opt.map(|(factor, seconds)|
+        (factor * tagging.factor, seconds * tagging.factor));
+    let seconds = format_increment(
+        merge_increment(seconds, tagged_opt.map(|(_, seconds)| seconds),
+            tagging.mode), tagging.precision);
+    let factor = match (tagging.mode, tagged_opt) {
+        (TagMode::Cumulative, Some((factor, _))) => factor,
+        _ => tagging.factor,
+    };
+    let tag = match tagging.mode {
+        TagMode::None => String::new(),
+        // '-' indicates that only part of the file was modified:
+        _ => format!("{}__[{}_Sec{}]", format_factor(factor), seconds,
+            if partial { "-" } else { "+" }),
     };
 
-    return Ok(output);
+    (stem.to_string(), tag, seconds, extension.to_string())
 }
 
-fn smart_rename(input_path: &Path) -> Option<PathBuf> {
+/// Adds the seconds of the tag of the input to those of this run,
+/// unless only this run counts.
+fn merge_increment(seconds: f64, tagged_opt: Option<f64>, mode: TagMode)
+    -> f64
+{
+    match mode {
+        TagMode::Cumulative => seconds + tagged_opt.unwrap_or(0.0),
+        _ => seconds,
+    }
+}
+
+/// The name `--overname` renames the input to, like `movie__[Original].srt`;
+/// an input that already is an original keeps its name.
+fn backup_path(input_path: &Path) -> Option<PathBuf> {
     if input_path.to_str()?.contains("__[Original].") {
         Some(input_path.to_owned())
     } else {
@@ -103,6 +289,7 @@ fn smart_rename(input_path: &Path) -> Option<PathBuf> {
     }
 }
 
+#[allow(clippy::needless_return)]
 pub fn do_overwrites(input_path: &mut PathBuf, output_path: &mut PathBuf,
         overwrite: &mut bool, rename_opt: &mut Option<PathBuf>)
     -> Result<(), Error>
@@ -120,11 +307,8 @@ pub fn do_overwrites(input_path: &mut PathBuf, output_path: &mut PathBuf,
     if input_path.to_str().unwrap().contains("__[Original].") {
         *overwrite = false;
     } else {
-        if input_path.extension() != output_path.extension() {
-            // Modify input file extension if it doesn't match output file:
-            *input_path = input_path.with_extension(
-                output_path.extension().unwrap());
-        }
+        // Renaming is atomic, so the input is either the original
+        // or the complete output, never a partly written one:
         fs::rename(&output_path, &input_path)?;
         // Rename output_path so it is reported correctly to user:
         *output_path = input_path.to_owned();
@@ -133,77 +317,1910 @@ pub fn do_overwrites(input_path: &mut PathBuf, output_path: &mut PathBuf,
     return Ok(());
 }
 
-pub fn is_srt_or_vtt(input: String) -> Result<(), String> {
-    if input.ends_with(".srt") || input.ends_with(".vtt") {
+/// The backup `--keep-backups` made before overwriting the input,
+/// and the older backups it deleted.
+pub struct Backups {
+    pub created: PathBuf,
+    pub pruned: Vec<PathBuf>,
+}
+
+/// Writes a file by writing a temporary file next to it, and renaming it
+/// to `path` once complete and on disk. An error or a crash halfway through
+/// never leaves a truncated file at `path`.
+pub fn write_atomically(path: &Path, contents: &[u8]) -> Result<(), Error> {
+    let name = path.file_name().and_then(OsStr::to_str).unwrap_or("output");
+    let temporary = path.with_file_name(format!(".{}.submod-{}.tmp", name,
+        std::process::id()));
+    let result = write_synced(&temporary, contents)
+        .and_then(|_| fs::rename(&temporary, path));
+    if let Err(error) = result {
+        let _ = fs::remove_file(&temporary);
+        return Err(SubmodError::Io { path: path.to_owned(),
+            message: format!("Could not write `{}`: {}", path.display(),
+                error) }.into());
+    }
+    sync_parent(path);
+    Ok(())
+}
+
+/// Writes a new file, and waits for its contents to be on disk, so a crash
+/// right after renaming it can't leave it empty.
+fn write_synced(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut file = fs::File::create(path)?;
+    file.write_all(contents)?;
+    file.sync_all()
+}
+
+/// Waits for the directory of a renamed file to be on disk, so the rename
+/// survives a crash too. Filesystems that can't sync directories already
+/// have the file in place, so this is only ever tried.
+#[cfg(unix)]
+fn sync_parent(path: &Path) {
+    let parent = path.parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+    if let Ok(directory) = fs::File::open(parent) {
+        let _ = directory.sync_all();
+    }
+}
+
+/// Only unix can open directories to sync them.
+#[cfg(not(unix))]
+fn sync_parent(_path: &Path) {}
+
+/// The contents of the input as it was read, which
+/// `--verify-source-unchanged` compares it with before replacing anything.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fingerprint {
+    length: u64,
+    hash: u64,
+}
+
+/// Hashes the file at `path`, which is only opened for reading.
+pub fn fingerprint(path: &Path) -> Result<Fingerprint, Error> {
+    use std::hash::{Hash, Hasher};
+
+    let contents = fs::read(path)
+        .map_err(|error| SubmodError::Io { path: path.to_owned(),
+            message: format!("Could not read `{}`: {}", path.display(),
+                error) })?;
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    contents.hash(&mut hasher);
+    Ok(Fingerprint { length: contents.len() as u64, hash: hasher.finish() })
+}
+
+/// Fails if the input no longer has its `fingerprint`, as another program
+/// modified it since it was read, after removing the files written from
+/// it in this run, so the input is left exactly as that program left it.
+pub fn verify_unchanged(path: &Path, fingerprint: Fingerprint,
+    written: &[&Path]) -> Result<(), Error>
+{
+    let changed = match self::fingerprint(path) {
+        Ok(now) => now != fingerprint,
+        Err(_) => true,
+    };
+    if !changed {
         return Ok(());
     }
-    Err(String::from("incorrect file extension\n\n\
-        Only \u{001b}[32m.srt\u{001b}[0m or \u{001b}[32m.vtt\u{001b}[0m \
-        files are allowed."))
+    for written in written {
+        let _ = fs::remove_file(written);
+    }
+    Err(format_err!("`{}` was modified by another program while submod \
+        was working on it, so it was left as it is, and nothing written \
+        from it was kept.\n\n\
+        Run submod again once the other program is done with it.",
+        path.display()))
+}
+
+/// Where the dates submod writes, like that of a backup, come from.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StampDate {
+    /// The time of the run.
+    Now,
+    /// No date at all.
+    None,
+    /// A given time, so that reruns name their files alike.
+    Fixed(SystemTime),
+}
+
+pub fn is_stamp_date(when: String) -> Result<(), String> {
+    parse_stamp_date(&when).map(|_| ())
+}
+
+/// Parses a `--stamp-date` like `now`, `none`, or `fixed:2024-05-01T12:03:01Z`,
+/// where the time of day and the `Z` are optional. Times are in UTC.
+pub fn parse_stamp_date(when: &str) -> Result<StampDate, String> {
+    match when {
+        "now" => return Ok(StampDate::Now),
+        "none" => return Ok(StampDate::None),
+        _ => {},
+    }
+    let usage = "should be `now`, `none`, or `fixed:<date>` with a date \
+        like 2024-05-01 or 2024-05-01T12:03:01Z (in UTC)";
+    let iso = Regex::new(concat!(r"^fixed:(\d{4})-(\d{2})-(\d{2})",
+        r"(?:T(\d{2}):(\d{2}):(\d{2}))?Z?$")).unwrap();
+    let captures = iso.captures(when).ok_or_else(|| usage.to_string())?;
+    let part = |i: usize| captures.get(i)
+        .map_or(0, |m| m.as_str().parse::<u64>().unwrap());
+    let (year, month, day) = (part(1), part(2), part(3));
+    if year < 1970 {
+        return Err(String::from("the date can't be before 1970"));
+    }
+    // Days since 1970-01-01, the inverse of backup_stamp:
+    let year_of_march = if month <= 2 { year - 1 } else { year };
+    let (era, year_of_era) = (year_of_march / 400, year_of_march % 400);
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day.max(1) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100
+        + day_of_year;
+    let days = era * 146097 + day_of_era - 719468;
+    let secs = days * 86400 + part(4) * 3600 + part(5) * 60 + part(6);
+    let time = UNIX_EPOCH + std::time::Duration::from_secs(secs);
+    // Dates like 2023-02-29 or times like 24:00:00 don't come back the same:
+    let digits: String = when.chars().filter(char::is_ascii_digit).collect();
+    if !backup_stamp(time).replace('T', "").starts_with(&digits) {
+        return Err(format!("`{}` is not a valid date", &when[6..]));
+    }
+    Ok(StampDate::Fixed(time))
+}
+
+/// Copies the input file to a timestamped backup next to it,
+/// like `movie.srt.bak-20240501T120301`, or `movie.srt.bak` without
+/// a `stamp`, and deletes all but the newest `keep` backups of that file,
+/// counting the new one as the newest.
+/// Only files named exactly like that are ever deleted.
+pub fn keep_backup(input_path: &Path, keep: usize, stamp: StampDate)
+    -> Result<Backups, Error>
+{
+    let name = input_path.file_name().unwrap().to_str().unwrap();
+    let dir = match input_path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+
+    let pattern = Regex::new(&format!(
+        r"^{}\.bak(-\d{{8}}T\d{{6}})?(?:-(\d+))?$", regex::escape(name)))?;
+    let mut backups: Vec<((String, u64), PathBuf)> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().map(|t| t.is_file()).unwrap_or(false))
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter_map(|file_name| {
+            let key = {
+                let captures = pattern.captures(&file_name)?;
+                (captures.get(1).map_or("", |m| m.as_str()).to_string(),
+                    captures.get(2).map_or(1, |m| m.as_str().parse()
+                        .unwrap_or(0)))
+            };
+            Some((key, input_path.with_file_name(&file_name)))
+        })
+        .collect();
+    // Timestamps sort chronologically, after undated backups, and suffixes
+    // after their timestamp:
+    backups.sort();
+
+    let stamp = match stamp {
+        StampDate::Now => format!("-{}", backup_stamp(SystemTime::now())),
+        StampDate::Fixed(time) => format!("-{}", backup_stamp(time)),
+        StampDate::None => String::new(),
+    };
+    // Never overwrite a backup with the same stamp, but number the new one
+    // after it:
+    let last = backups.iter().rev().find(|backup| (backup.0).0 == stamp);
+    let created = match last {
+        Some(&((_, suffix), _)) => input_path.with_file_name(
+            format!("{}.bak{}-{}", name, stamp, suffix + 1)),
+        None => input_path.with_file_name(format!("{}.bak{}", name, stamp)),
+    };
+    fs::copy(input_path, &created)?;
+
+    // The new backup is always kept:
+    let excess = (backups.len() + 1).saturating_sub(keep);
+    let mut pruned = Vec::new();
+    for (_, path) in backups.into_iter().take(excess) {
+        fs::remove_file(&path)?;
+        pruned.push(path);
+    }
+
+    Ok(Backups { created, pruned })
+}
+
+/// Formats a time as a UTC timestamp like `20240501T120301`.
+fn backup_stamp(time: SystemTime) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let (days, secs) = (secs / 86400, secs % 86400);
+    // Convert days since 1970-01-01 to a date in the Gregorian calendar:
+    let days = days as i64 + 719468;
+    let era = days / 146097;
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524
+        - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era
+        - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}{:02}{:02}T{:02}{:02}{:02}", year, month, day,
+        secs / 3600, secs % 3600 / 60, secs % 60)
+}
+
+/// Validates the input file, which may also be `-` for stdin, a pipe
+/// or another stream, or a URL to download it from.
+pub fn is_input(input: String) -> Result<(), String> {
+    if is_stream(Path::new(&input)) || is_url(&input) {
+        return Ok(());
+    }
+    is_subtitle(input)
+}
+
+/// Whether the path is `-` for stdin, a pipe, like from process
+/// substitution, or another stream that can only be read once,
+/// rather than a file.
+pub fn is_stream(path: &Path) -> bool {
+    path == Path::new("-") || fs::metadata(path).is_ok_and(|metadata|
+        !metadata.is_file() && !metadata.is_dir())
+}
+
+/// Whether the input is an http or https URL rather than a path.
+pub fn is_url(input: &str) -> bool {
+    let lowercase = input.to_lowercase();
+    lowercase.starts_with("http://") || lowercase.starts_with("https://")
+}
+
+pub fn is_subtitle(input: String) -> Result<(), String> {
+    if [".srt", ".vtt", ".ass", ".ssa", ".sub", ".ttml", ".dfxp", ".xml"]
+        .iter().any(|extension| input.ends_with(extension)) {
+        return Ok(());
+    }
+    Err(format!("incorrect file extension\n\n\
+        Only {}, {}, {}, {}, {}, {}, {} or {} files are allowed.",
+        color::hint(".srt"), color::hint(".vtt"),
+        color::hint(".ass"), color::hint(".ssa"), color::hint(".sub"),
+        color::hint(".ttml"), color::hint(".dfxp"), color::hint(".xml")))
+}
+
+pub fn is_name_template(template: String) -> Result<(), String> {
+    // Templates are relative to the directory of the input, so a leading
+    // separator or drive would be silently dropped:
+    let drive = Regex::new(r"^[A-Za-z]:").unwrap();
+    if template.starts_with('/') || template.starts_with('\\')
+        || drive.is_match(&template) {
+        return Err(format!("`{}` is an absolute path\n\n\
+            The template is relative to the directory of the input; use \
+            {} to write elsewhere.", template,
+            color::hint("--out <filename>")));
+    }
+    let placeholders = Regex::new(r"\{[^{}]*\}").unwrap();
+    for placeholder in placeholders.find_iter(&template) {
+        if !template::PLACEHOLDERS.contains(&placeholder.as_str()) {
+            return Err(format!("unknown placeholder `{}`\n\n\
+                Use {}", placeholder.as_str(), template::PLACEHOLDERS.iter()
+                    .map(color::hint)
+                    .collect::<Vec<_>>().join(", ")));
+        }
+    }
+    Ok(())
+}
+
+pub fn is_regex(pattern: String) -> Result<(), String> {
+    Regex::new(&pattern).map(|_| ())
+        .map_err(|error| format!("invalid regex: {}", error))
+}
+
+pub fn is_replacement(rule: String) -> Result<(), String> {
+    parse_replacement(&rule).map(|_| ())
+}
+
+/// Splits a `--replace` rule like `Jon=John` into its regex and
+/// replacement, at the first `=` that isn't escaped as `\=`.
+pub fn parse_replacement(rule: &str) -> Result<(Regex, String), String> {
+    let mut escaped = false;
+    let split = rule.char_indices().find(|&(_, c)| {
+        let found = c == '=' && !escaped;
+        escaped = c == '\\' && !escaped;
+        found
+    });
+    let i = match split {
+        Some((i, _)) => i,
+        None => return Err(format!("should be <regex>=<replacement>\n\n\
+            Use \\= for an = in the regex, and $1 in the replacement \
+            to insert the first capture group, like so:\n    \
+            {}", color::hint("--replace 'Mrs?\\. (Smyth)=Ms. $1'"))),
+    };
+    let pattern = rule[..i].replace("\\=", "=");
+    if pattern.is_empty() {
+        return Err(String::from("the regex should not be empty"));
+    }
+    match Regex::new(&pattern) {
+        Ok(regex) => Ok((regex, rule[i + 1..].to_string())),
+        Err(error) => Err(format!("invalid regex `{}`: {}", pattern, error)),
+    }
+}
+
+/// Reads `--replace` rules from a file, one per line,
+/// skipping blank lines and comments.
+pub fn read_replacements(path: &str) -> Result<Vec<(Regex, String)>, Error> {
+    let contents = fs::read_to_string(path).map_err(|error| format_err!(
+        "Cannot read replacements `{}`: {}", path, error))?;
+    let mut rules = Vec::new();
+    for (i, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() || line.trim_start().starts_with('#') {
+            continue;
+        }
+        rules.push(parse_replacement(line).map_err(|error|
+            format_err!("{}, line {}: {}", path, i + 1, error))?);
+    }
+    Ok(rules)
 }
 
 pub fn is_float(seconds: String) -> Result<(), String> {
     // Ideally, we should be able to return the f64 in Ok variant,
-    // but this most likely requires more advanced `dyn` or `impl` returns
-    if let Ok(_) = seconds.parse::<f64>() {
+    // but this most likely requires more advanced `dyn` or `impl` returns.
+    // get_secs rejects `nan` and `inf`, but also takes times, which
+    // aren't numbers:
+    if let (Ok(_), Ok(_)) = (seconds.parse::<f64>(),
+        subtitles::get_secs(&seconds)) {
         Ok(())
     } else {
         Err("should be a number".to_string())
     }
 }
 
+/// Validates <seconds>, which is either a number like `-83.5`,
+/// or a signed time like `-01:23.5` or `-00:01:23.500`.
+/// Whatever is wrong, the error lists both forms.
+pub fn is_offset(seconds: String) -> Result<(), String> {
+    if subtitles::get_secs(&seconds).is_ok() {
+        return Ok(());
+    }
+    Err(format!("should be a number of seconds, or a time\n\n\
+        Use a number like {}, or ':' to separate hours, minutes and \
+        seconds,\nlike {} or {}; the sign applies to the whole time",
+        color::hint("-83.5"), color::hint("-01:23.5"),
+        color::hint("-00:01:23.500")))
+}
+
+/// Turns negative times like `-01:23.5` into seconds, like `-83.5`,
+/// as clap would take them for flags, unlike negative numbers.
+pub fn negative_times_to_seconds(args: Vec<String>) -> Vec<String> {
+    args.into_iter()
+        .map(|arg| if arg.starts_with('-') && arg.contains(':')
+            && is_offset(arg.clone()).is_ok() {
+            cue::get_secs(&arg).to_string()
+        } else {
+            arg
+        })
+        .collect()
+}
+
+pub fn is_non_negative(seconds: String) -> Result<(), String> {
+    match seconds.parse::<f64>() {
+        Ok(seconds) if seconds >= 0.0 => Ok(()),
+        _ => Err("should be a non-negative number".to_string()),
+    }
+}
+
+pub fn is_positive(seconds: String) -> Result<(), String> {
+    match seconds.parse::<f64>() {
+        Ok(seconds) if seconds > 0.0 => Ok(()),
+        _ => Err("should be a positive number".to_string()),
+    }
+}
+
+pub fn is_count(count: String) -> Result<(), String> {
+    match count.parse::<usize>() {
+        Ok(count) if count > 0 => Ok(()),
+        _ => Err("should be a positive whole number".to_string()),
+    }
+}
+
+pub fn is_precision(decimals: String) -> Result<(), String> {
+    match decimals.parse::<usize>() {
+        Ok(decimals) if decimals <= 3 => Ok(()),
+        _ => Err("should be a whole number from 0 to 3".to_string()),
+    }
+}
+
+/// Validates `--start` and `--stop`, which also accept a percentage
+/// of the file duration, like `90%`.
+pub fn is_timing_or_percentage(time_string: String) -> Result<(), String> {
+    if !time_string.ends_with('%') {
+        return is_timing(time_string);
+    }
+    match time_string.trim_end_matches('%').parse::<f64>() {
+        Ok(percent) if (0.0..=100.0).contains(&percent) => Ok(()),
+        Ok(_) => Err(String::from("percentages should be between \
+            0% and 100%")),
+        Err(_) => Err(format!("incorrect percentage formatting\n\n\
+            Use a number followed by '%', like \
+            {}", color::hint("90%"))),
+    }
+}
+
 pub fn is_timing(time_string: String) -> Result<(), String> {
+    // Seconds may have a fraction after a '.', or after a ',' as in srt:
     let result: Result<Vec<_>, _> = time_string.rsplit(":")
-        .map(|t| t.parse::<f64>())
+        .map(|t| t.replace(',', ".").parse::<f64>())
         // use collect() on iterator of Result<T, E>s,
         // to see if any of them failed:
         .collect();
 
     match result {
         Ok(_) => Ok(()),
-        Err(_) => Err(String::from("incorrect time formatting\n\n\
+        Err(_) => Err(format!("incorrect time formatting\n\n\
             Use ':' to separate hours, minutes and seconds, like so:\n    \
-            \u{001b}[32mhh:mm:ss\u{001b}[0m to specify \
-            hours, minutes and seconds\n       \
-            \u{001b}[32mmm:ss\u{001b}[0m to only specify \
-            minutes and seconds\n          \
-            \u{001b}[32mss\u{001b}[0m to only specify seconds"))
+            {} to specify hours, minutes and seconds\n       \
+            {} to only specify minutes and seconds\n          \
+            {} to only specify seconds\n\
+            Seconds may have milliseconds, like {} or {}",
+            color::hint("hh:mm:ss"), color::hint("mm:ss"),
+            color::hint("ss"), color::hint("00:14:07.438"),
+            color::hint("00:14:07,438")))
+    }
+}
+
+pub fn is_encoding(label: String) -> Result<(), String> {
+    // Builds without the `encodings` feature tell why it can't be used:
+    if !cfg!(feature = "encodings") || encoding::for_label(&label).is_some() {
+        return Ok(());
+    }
+    Err(format!("unknown encoding\n\n\
+        Give any of the labels browsers know, like {} or {}.",
+        color::hint("iso-8859-15"), color::hint("shift_jis")))
+}
+
+pub fn is_output_encoding(label: String) -> Result<(), String> {
+    match encoding::for_label(&label) {
+        Some(encoding) if encoding.output_encoding() != encoding =>
+            Err(format!("{} can be read, but not written\n\n\
+                Write {} instead.", encoding.name(), color::hint("utf-8"))),
+        Some(_) => Ok(()),
+        None => is_encoding(label),
+    }
+}
+
+pub fn is_language(code: String) -> Result<(), String> {
+    if (2..=3).contains(&code.len())
+        && code.chars().all(|c| c.is_ascii_lowercase()) {
+        return Ok(());
+    }
+    Err(format!("must be a language code of two or three lowercase \
+        letters, like {}", color::hint("en")))
+}
+
+pub fn is_speed(speed: String) -> Result<(), String> {
+    if speed == "pal" || speed == "ntsc" {
+        return Ok(());
+    }
+    match speed.trim_end_matches('%').parse::<f64>() {
+        Ok(percent) if percent > 0.0 => Ok(()),
+        _ => Err(format!("should be a positive percentage, \
+            or {} or {}", color::hint("pal"), color::hint("ntsc"))),
+    }
+}
+
+pub fn is_rate(rate: String) -> Result<(), String> {
+    let positive = |fps: &str| fps.parse::<f64>().is_ok_and(|fps|
+        fps > 0.0 && fps.is_finite());
+    match rate.find(':') {
+        Some(i) if positive(&rate[..i]) && positive(&rate[i + 1..]) => Ok(()),
+        _ => Err(format!("should be two positive frame rates \
+            separated by ':', like {}", color::hint("23.976:25"))),
+    }
+}
+
+pub fn is_step(step: String) -> Result<(), String> {
+    let (time_string, seconds) = match step.find('=') {
+        Some(i) => (&step[..i], &step[i + 1..]),
+        None => return Err(format!("missing '=' between time and seconds\n\n\
+            Use {}, like so:\n    \
+            {}", color::hint("hh:mm:ss=seconds"), color::hint("12:30=-85"))),
+    };
+    is_timing(time_string.to_string())?;
+    is_float(seconds.to_string())
+}
+
+pub fn is_range(spec: String) -> Result<(), String> {
+    submod::get_range(&spec).map(|_| ())
+}
+
+pub fn is_ranges(spec: String) -> Result<(), String> {
+    submod::get_ranges(&spec).map(|_| ())
+}
+
+pub fn is_cue_spec(spec: String) -> Result<(), String> {
+    // Cue list files are only read after argument parsing:
+    if spec.starts_with('@') {
+        return Ok(());
+    }
+    match selection::parse_ranges(&spec) {
+        Ok(_) => Ok(()),
+        Err(error) => Err(format!("{}\n\n\
+            Use commas to separate cue numbers and ranges, like so:\n    \
+            {}", error, color::hint("3,45-120,200-"))),
+    }
+}
+
+pub fn is_class_rename(rename: String) -> Result<(), String> {
+    let class = Regex::new(r"^[^\s.<>=]+=[^\s.<>=]+$").unwrap();
+    if class.is_match(&rename) {
+        Ok(())
+    } else {
+        Err(format!("expected two class names separated by '='\n\n\
+            Use {}, like so:\n    \
+            {}", color::hint("old=new"), color::hint("yellow=highlight")))
+    }
+}
+
+pub fn is_region_rename(rename: String) -> Result<(), String> {
+    let region = Regex::new(r"^[^\s=]+=[^\s=]+$").unwrap();
+    if region.is_match(&rename) && !rename.contains("-->") {
+        Ok(())
+    } else {
+        Err(format!("expected two region identifiers separated by '='\n\n\
+            Use {}, like so:\n    \
+            {}", color::hint("old=new"), color::hint("fred=top")))
     }
 }
 
 pub fn report_error(error: Error) {
-    eprintln!("\u{001b}[38;5;208mError:\u{001b}[0m {}\n", error);
-    println!("USAGE:\n    \
-                submod [FLAGS] [OPTIONS] <filename> <seconds>\n        \
-                    <filename>   (Path to) .srt or .vtt subtitle file \
-                    to modify\n        \
-                    <seconds>    seconds to add or subtract \
-                    from time encoding\n\n\
-                    For more information try \
-                    \u{001b}[32msubmod --help\u{001b}[0m");
-}
-
-pub fn report_success(deleted_subs: i32, output_path: &Path,
-    overwrite: bool, rename_opt: Option<PathBuf>)
+    errln!("{} {}\n", color::error("Error:"), error);
+    if let Some(failure) = error.downcast_ref::<submod::PartialFailure>() {
+        errln!("{}\n", format_progress(&failure.progress));
+    }
+    errln!("USAGE:\n    {}\n\n\
+        For more information try {}", cli::usage(&cli::build_cli()),
+        color::hint("submod --help"));
+}
+
+/// Prints an error as a JSON object on stderr for `--json`, with the
+/// input or manifest line of a batch job it's about, if any.
+pub fn report_error_json(error: &Error, job_opt: Option<&str>) {
+    let progress = error.downcast_ref::<submod::PartialFailure>()
+        .map_or(String::new(), |failure| format!(", \"progress\": \"{}\"",
+            json_escape(&format_progress(&failure.progress))));
+    let job = job_opt.map_or(String::new(), |job|
+        format!("\"job\": \"{}\", ", json_escape(job)));
+    errln!("{{{}\"error\": \"{}\"{}}}", job,
+        json_escape(&color::strip(&error.to_string())), progress);
+}
+
+/// Prints the outcome of a run as a JSON object on stdout for `--json`,
+/// in place of `report_success` or `report_unchanged`.
+pub fn report_json(summary: &Summary, input_path: &Path, output_path: &Path,
+    seconds: f64, overwritten: bool, deterministic: bool)
+{
+    let mut fields = vec![
+        ("input", format!("\"{}\"",
+            json_escape(&input_path.display().to_string()))),
+        ("output", format!("\"{}\"",
+            json_escape(&output_path.display().to_string()))),
+        ("overwritten", overwritten.to_string()),
+    ];
+    fields.extend(summary_json(summary, seconds, deterministic, false));
+    let fields: Vec<String> = fields.iter()
+        .map(|&(key, ref value)| format!("\"{}\": {}", key, value))
+        .collect();
+    outln!("{{{}}}", fields.join(", "));
+}
+
+/// The fields of the JSON objects that both `--json` and `--selection-out`
+/// report a run with, in this order: the `offset` in seconds, the number
+/// of `cues`, and of those `modified` and `deleted`, the number of
+/// `warnings`, whether the output was `unchanged`, `elapsed_seconds` and
+/// `cues_per_second`, which are `null` when `deterministic`, the
+/// `duration_mismatch_seconds` of `--video-duration`, and the `ranges`
+/// of `--stats-per-range`. `pretty` puts every range on a line of its own.
+fn summary_json(summary: &Summary, seconds: f64, deterministic: bool,
+    pretty: bool) -> Vec<(&'static str, String)>
+{
+    let mismatch = summary.duration_mismatch_opt.map_or(
+        String::from("null"), |mismatch| format!("{:.3}",
+            mismatch.mismatch()));
+    let (elapsed, speed) = if deterministic {
+        (String::from("null"), String::from("null"))
+    } else {
+        (format!("{:.6}", summary.elapsed.as_secs_f64()),
+            format!("{:.0}", cues_per_second(summary)))
+    };
+    vec![
+        ("offset", format!("{:.3}", seconds)),
+        ("cues", summary.cues.to_string()),
+        ("modified", summary.selected.to_string()),
+        ("deleted", summary.deleted.to_string()),
+        ("warnings", summary.warnings().to_string()),
+        ("unchanged", summary.unchanged.to_string()),
+        ("elapsed_seconds", elapsed),
+        ("cues_per_second", speed),
+        ("duration_mismatch_seconds", mismatch),
+        ("ranges", format!("[{}]", format_ranges_json(summary, pretty))),
+    ]
+}
+
+/// Describes how far a transform got before it failed, like
+/// `Stopped at line 6320 (byte 123456), after 1581 of 2000 subtitles...`.
+pub fn format_progress(progress: &submod::Progress) -> String {
+    let position = match (progress.line_opt, progress.offset_opt) {
+        (Some(line), Some(offset)) =>
+            format!("Stopped at line {} (byte {}), after", line, offset),
+        (Some(line), None) => format!("Stopped at line {}, after", line),
+        _ => String::from("Stopped after"),
+    };
+    let last = progress.last_start_opt.map_or(String::new(), |start|
+        format!("; the last one processed starts at {}",
+            submod::format_secs(start)));
+    format!("{} processing {} of {} subtitle{}{}.", position, progress.cues,
+        progress.total, if progress.total == 1 { "" } else { "s" }, last)
+}
+
+/// Prints how many of a batch of files or `--manifest` jobs succeeded,
+/// and why the others failed, by their file name or line in the manifest,
+/// or how many of them `--check` found would change.
+/// The `unit` is what the batch consists of, like `file`.
+pub fn report_batch(unit: &str, succeeded: usize, changed: usize,
+    failures: &[(String, Error)], quiet: bool, json: bool)
+{
+    if json {
+        for (label, error) in failures {
+            report_error_json(error, Some(label.trim_matches('`')));
+        }
+        return;
+    }
+    let total = succeeded + failures.len();
+    let units = format!("{}{}", unit, if total == 1 { "" } else { "s" });
+    if !failures.is_empty() {
+        errln!("{} {} of {} {} failed:", color::error("Error:"), failures.len(),
+            total, units);
+        for (label, error) in failures {
+            let mut message = error.to_string();
+            if let Some(failure) =
+                error.downcast_ref::<submod::PartialFailure>() {
+                message = format!("{}\n\n{}", message,
+                    format_progress(&failure.progress));
+            }
+            let message: Vec<String> = message.lines()
+                .map(|text| if text.is_empty() { String::new() } else {
+                    format!("    {}", text) })
+                .collect();
+            errln!(" {}:\n{}", label, message.join("\n"));
+        }
+    }
+    // Like failures, outputs --check finds would change are always told:
+    if changed > 0 {
+        outln!("{}\n {} of {} {} would change.", color::alert("Would change."),
+            changed, total, units);
+    } else if failures.is_empty() && !quiet {
+        outln!("{}\n {} {} succeeded.", color::title("Success."),
+            if total == 1 { String::from("The") } else {
+                format!("All {}", total) }, units);
+    }
+}
+
+/// Prints the formula that will be applied to every selected timestamp.
+pub fn explain(options: &Options, args: &[String], preset_opt: Option<&str>) {
+    outln!("{}", color::bold("Explanation:"));
+    let width = layout::width();
+    let say = |sentence: &str| outln!("{}",
+        layout::wrap(sentence, width, " "));
+    if let Some(preset) = preset_opt {
+        outln!(" Preset `{}` expands to:\n    submod {}",
+            preset, args[1..].join(" "));
+    }
+    if options.factor != 1.0 {
+        say(&format!("Timestamps are multiplied by {:.6}, i.e. divided by \
+            the playback speed of {:.3}%.",
+            options.factor, 100.0 / options.factor));
+    }
+    say(&format!("Timestamps are shifted by {:+.3} seconds.", options.seconds));
+    if options.shift_starts != 0.0 || options.shift_ends != 0.0 {
+        say(&format!("Starts are shifted by another {:+.3} seconds, and ends \
+            by another {:+.3} seconds.", options.shift_starts,
+            options.shift_ends));
+    }
+    for &(time, seconds) in &options.steps {
+        say(&format!("Timestamps of subtitles starting from {} are shifted \
+            by another {:+.3} seconds.", submod::format_secs(time), seconds));
+    }
+    if let Some(start) = options.start_opt {
+        say(&format!("Subtitles ending before {} are left untouched.",
+            submod::format_secs(start)));
+    }
+    if let Some(stop) = options.stop_opt {
+        say(&format!("Subtitles starting at or after {} are left untouched.",
+            submod::format_secs(stop)));
+    }
+    if let Some(range) = options.selection.range() {
+        say(&match range.last {
+            Some(last) => format!("Only subtitles {} to {} in the order of \
+                the file are modified.", range.first, last),
+            None => format!("Subtitles before subtitle {} in the order of \
+                the file are left untouched.", range.first),
+        });
+    }
+    if options.selection.has_lists() {
+        say("Only the subtitles selected by --include-cues and \
+            --exclude-cues are modified.");
+    }
+    match options.italic_opt {
+        Some(true) => say("Only subtitles entirely in italics \
+            are modified."),
+        Some(false) => say("Only subtitles not entirely in italics \
+            are modified."),
+        None => {},
+    }
+    if options.pin_first {
+        say("The first subtitle keeps its timing.");
+    }
+    if options.pin_last {
+        say("The last subtitle keeps its timing.");
+    }
+    if options.pin_first || options.pin_last {
+        say("Shifted subtitles overlapping a pinned one are trimmed.");
+    }
+    match options.boundary_conflict {
+        submod::BoundaryConflict::Reorder => say("Subtitles out of \
+            order because only some were shifted are sorted again."),
+        submod::BoundaryConflict::Trim => say("Subtitles overlapping \
+            because only some were shifted are trimmed to not overlap."),
+        _ => {},
+    }
+    match options.on_negative {
+        submod::OnNegative::Clamp => say("Subtitles shifted to end before \
+            00:00:00 start at it instead, and last a millisecond."),
+        submod::OnNegative::Error => say("Nothing is written if any \
+            subtitle would start before 00:00:00."),
+        submod::OnNegative::Delete => {},
+    }
+    if let Some(limits) = options.merge_opt {
+        say(&format!("Subtitles continuing the sentence of the one before them \
+            within {} seconds are joined with it, if the same speaker is \
+            speaking and the result has at most two lines of {} characters{} \
+            and {} characters per second.", limits.max_gap, limits.max_line,
+            if limits.wide_chars { ", wide ones counting as 2," } else { "" },
+            limits.max_cps));
+    }
+    if let Some(min_gap) = options.fix_overlaps_opt {
+        say(&format!("Subtitles ending less than {} seconds before the next \
+            one starts are shortened to end {} seconds before it{}.", min_gap,
+            min_gap, if options.merge_collapsed { ", or merged into it if \
+            that leaves them no time" } else { "" }));
+    }
+    if let Some(min_duration) = options.min_duration_opt {
+        say(&format!("Subtitles lasting less than {} seconds are lengthened, \
+            as far as the next one allows.", min_duration));
+    }
+    if let Some(max_line) = options.max_line_opt {
+        say(&format!("Subtitles with a line longer than {} characters{} are \
+            flagged.", max_line, if options.wide_chars {
+                ", wide ones counting as 2," } else { "" }));
+    }
+    if let Some(max_lines) = options.max_lines_opt {
+        say(&format!("Subtitles with more than {} line{} are flagged.",
+            max_lines, if max_lines == 1 { "" } else { "s" }));
+    }
+    if options.prepend_cue_opt.is_some() {
+        say(&format!("A subtitle lasting {} seconds is inserted {} seconds \
+            before the first one, if there's room for it.",
+            options.credit_duration, options.credit_gap));
+    }
+    if options.append_cue_opt.is_some() {
+        say(&format!("A subtitle lasting {} seconds is inserted {} seconds \
+            after the last one.", options.credit_duration,
+            options.credit_gap));
+    }
+    if let Some(threshold) = options.min_confidence_opt {
+        say(&format!("Subtitles with a confidence below {} in the NOTE \
+            before them are dropped, along with that NOTE.", threshold));
+    }
+    if let Some(max_duration) = options.max_duration {
+        if options.truncate_long {
+            say(&format!("Subtitles lasting longer than {} seconds are cut \
+                short.", max_duration));
+        } else {
+            say(&format!("Subtitles lasting longer than {} seconds are split, \
+                their time divided in proportion to their {}.", max_duration,
+                match options.split_weights {
+                    SplitWeights::Chars => "characters",
+                    SplitWeights::Words => "words",
+                }));
+        }
+    }
+    if options.min_start > 0.0 {
+        say(&format!("Subtitles starting before {} start at it instead, or are \
+            dropped if they end before it.",
+            submod::format_secs(options.min_start)));
+    }
+    for (regex, replacement) in &options.replacements {
+        say(&format!("Matches of `{}` in the subtitle text are replaced \
+            by `{}`.", regex, replacement));
+    }
+    if !options.replacements.is_empty() {
+        say("Subtitles left without text are deleted.");
+    }
+    if options.normalize {
+        say("Whitespace in the subtitle text is normalized.");
+    }
+    for (old, new) in &options.class_renames {
+        say(&format!("The `{}` class is renamed to `{}`.", old, new));
+    }
+    for (old, new) in &options.region_renames {
+        say(&format!("The `{}` region is renamed to `{}`.", old, new));
+    }
+    if options.strip_tags {
+        say("Tags are removed from the subtitle text, along with \
+            the styles of classes that are no longer used.");
+    }
+}
+
+/// Writes the cues chosen by the selectors, one per line with their
+/// original timing line, or as a JSON object. Its timings vary from run
+/// to run, so they're `null` if `deterministic`.
+pub fn write_selection(path: &Path, summary: &Summary, seconds: f64,
+    json: bool, deterministic: bool) -> Result<(), Error>
 {
-    println!("\u{001b}[32;1mSuccess.\u{001b}[0m");
+    let mut contents = String::new();
+    if json {
+        let cues: Vec<String> = summary.selection.iter()
+            .map(|&(number, ref timing)| format!(
+                "    {{\"cue\": {}, \"timing\": \"{}\"}}",
+                number, json_escape(timing)))
+            .collect();
+        contents.push_str("{\n");
+        for (key, value) in summary_json(summary, seconds, deterministic,
+            true) {
+            contents.push_str(&format!("  \"{}\": {},\n", key, value));
+        }
+        contents.push_str(&format!("  \"selection\": [\n{}\n  ]\n}}\n",
+            cues.join(",\n")));
+    } else {
+        for &(number, ref timing) in &summary.selection {
+            contents.push_str(&format!("{}\t{}\n", number, timing));
+        }
+    }
+    fs::write(path, contents).map_err(|error| SubmodError::Io {
+        path: path.to_owned(), message: format!("Could not write the \
+            selection to `{}`: {}", path.display(), error) }.into())
+}
+
+/// Formats the `--stats-per-range` counts as the items of a JSON array,
+/// with the boundaries and offset of every range in seconds, each on a
+/// line of its own if `pretty`.
+fn format_ranges_json(summary: &Summary, pretty: bool) -> String {
+    let ranges: Vec<String> = summary.ranges.iter()
+        .map(|range| format!("{}{{\"start\": {:.3}, \"end\": {}, \
+            \"seconds\": {:.3}, \"shifted\": {}, \"clamped\": {}, \
+            \"deleted\": {}}}", if pretty { "\n    " } else { "" },
+            range.start, range.end_opt.map_or(
+                String::from("null"), |end| format!("{:.3}", end)),
+            range.seconds, range.shifted, range.clamped, range.deleted))
+        .collect();
+    if ranges.is_empty() {
+        String::new()
+    } else if pretty {
+        format!("{}\n  ", ranges.join(","))
+    } else {
+        ranges.join(", ")
+    }
+}
+
+/// Writes the original and new timing of every cue, in seconds with
+/// millisecond precision, as CSV or as a JSON object. The columns are, in
+/// this order: `cue`, `old_start`, `old_end`, `new_start`, `new_end` and
+/// `status`, which is `changed`, `unchanged` or `deleted`.
+/// Deleted cues have no new start and end.
+pub fn write_timing_map(path: &Path, summary: &Summary, json: bool)
+    -> Result<(), Error>
+{
+    fs::write(path, format_timing_map(summary, json)).map_err(|error|
+        SubmodError::Io { path: path.to_owned(), message: format!(
+            "Could not write the timing map to `{}`: {}", path.display(),
+            error) }.into())
+}
 
+fn format_timing_map(summary: &Summary, json: bool) -> String {
+    let seconds = |time_opt: Option<f64>, none: &str| time_opt
+        .map_or(none.to_string(), |time| format!("{:.3}", time));
+    let mut rows = Vec::with_capacity(summary.timing_map.len());
+    for change in &summary.timing_map {
+        let status = match change.new_opt {
+            None => "deleted",
+            Some(new) if format!("{:.3} {:.3}", new.0, new.1)
+                == format!("{:.3} {:.3}", change.old.0, change.old.1) =>
+                "unchanged",
+            Some(_) => "changed",
+        };
+        let none = if json { "null" } else { "" };
+        rows.push((change.cue, seconds(Some(change.old.0), none),
+            seconds(Some(change.old.1), none),
+            seconds(change.new_opt.map(|new| new.0), none),
+            seconds(change.new_opt.map(|new| new.1), none), status));
+    }
+
+    let mut contents = String::new();
+    if json {
+        let cues: Vec<String> = rows.iter()
+            .map(|row| format!("    {{\"cue\": {}, \"old_start\": {}, \
+                \"old_end\": {}, \"new_start\": {}, \"new_end\": {}, \
+                \"status\": \"{}\"}}", row.0, row.1, row.2, row.3, row.4, row.5))
+            .collect();
+        contents.push_str(&format!("{{\n  \"cues\": [\n{}\n  ]\n}}\n",
+            cues.join(",\n")));
+    } else {
+        contents.push_str("cue,old_start,old_end,new_start,new_end,status\n");
+        for row in &rows {
+            contents.push_str(&format!("{},{},{},{},{},{}\n",
+                row.0, row.1, row.2, row.3, row.4, row.5));
+        }
+    }
+    contents
+}
+
+/// Escapes a string for use inside a JSON string literal.
+fn json_escape(string: &str) -> String {
+    let mut escaped = String::with_capacity(string.len());
+    for c in string.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 =>
+                escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Lists cues with their excessive corrections, like `#12 (+734.000 s)`.
+pub fn format_outliers(outliers: &[(usize, f64)]) -> String {
+    outliers.iter()
+        .map(|&(number, correction)| format!("#{} ({:+.3} s)",
+            number, correction))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Lists pairs of cues, like `#12 and #13, #40 and #41`.
+pub fn format_conflicts(conflicts: &[(usize, usize)]) -> String {
+    conflicts.iter()
+        .map(|&(earlier, later)| format!("#{} and #{}", earlier, later))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Number of subtitles `--dry-run` shows at each end of the file.
+const PREVIEWED: usize = 3;
+
+/// Reports what `--dry-run` would have done: the old and new timing of
+/// the first and last subtitles, and the file it would have written.
+pub fn report_dry_run(summary: &Summary, output_path: &Path) {
+    outln!("{}", color::title("Dry run."));
+    let width = layout::width();
+    let map = &summary.timing_map;
+    let previewed: Vec<&submod::TimingChange> = if map.len() > 2 * PREVIEWED {
+        map[..PREVIEWED].iter().chain(&map[map.len() - PREVIEWED..]).collect()
+    } else {
+        map.iter().collect()
+    };
+    for (i, change) in previewed.iter().enumerate() {
+        if i == PREVIEWED && map.len() > 2 * PREVIEWED {
+            outln!("    ...");
+        }
+        let new = change.new_opt.map_or(String::from("deleted"), |new|
+            format!("{} --> {}", cue::format_time(new.0, false),
+                cue::format_time(new.1, false)));
+        outln!("{}", layout::row(&[format!("{:>5}  {} --> {}", change.cue,
+            cue::format_time(change.old.0, false),
+            cue::format_time(change.old.1, false)),
+            format!("=>  {}", new)], width, "    "));
+    }
+    if summary.deleted > 0 {
+        outln!("    {}   \
+            {} subtitle{} would be deleted for being shifted before \
+            the start.", color::warning_sign(), summary.deleted,
+            if summary.deleted == 1 { "" } else { "s" });
+    }
+    if summary.clamped > 0 {
+        outln!(" {} subtitle{} would start at 00:00:00 rather than \
+            before it.",
+            summary.clamped, if summary.clamped == 1 { "" } else { "s" });
+    }
+    if let Some((first_start, seconds)) = summary.leading_silence_opt {
+        outln!(" The first subtitle starts at {}, so all would be \
+            shifted by {:+.3} s.", submod::format_secs(first_start), seconds);
+    }
+    report_fps_mismatch(summary);
+    if let Some(sniffed) = summary.sniffed_opt {
+        outln!(" The input was read as {}, told by its content with {:.0}% \
+            confidence.", sniffed.format, sniffed.confidence * 100.0);
+    }
+    if summary.unchanged {
+        outln!(" The output would be identical to the input.");
+    }
+    outln!(" Nothing was written; the output would be {}",
+        color::file(output_path.display()));
+}
+
+/// Warns that `--fps` overrode the frame rate a MicroDVD file gives.
+fn report_fps_mismatch(summary: &Summary) {
+    if let Some((given, header)) = summary.fps_mismatch_opt {
+        outln!("    {}   \
+            The frames were read at the {} fps of --fps, but the file says \
+            its frame rate is {} fps.", color::warning_sign(), given, header);
+    }
+}
+
+/// Reports that the run failed for its warnings alone, with
+/// `--warnings-as-errors`.
+pub fn report_denied_warnings(warnings: usize) {
+    errln!("{} {} warning{} treated as \
+        {} by --warnings-as-errors; the output was still written.",
+        color::error("Error:"), warnings,
+        if warnings == 1 { " was" } else { "s were" },
+        if warnings == 1 { "an error" } else { "errors" });
+}
+
+pub fn report_check(input_path: &Path, changes_opt: Option<Changes>) {
+    let changes = match changes_opt {
+        Some(changes) => changes,
+        None => {
+            outln!("{}", color::title("Unchanged."));
+            outln!(" `{}` is already in the expected form.",
+                input_path.display());
+            return;
+        }
+    };
+    outln!("{}", color::alert("Would change."));
+    outln!(" `{}`: {} line{} would differ, starting at line {}:",
+        input_path.display(), changes.lines,
+        if changes.lines == 1 { "" } else { "s" }, changes.first);
+    // Long lines are cut off, to keep the preview to a line each:
+    let width = layout::width() - "    - ".len();
+    if let Some(line) = changes.old_line {
+        outln!("    {}", color::removed(format!("- {}",
+            layout::truncate(&line.replace('\r', "\\r"), width))));
+    }
+    if let Some(line) = changes.new_line {
+        outln!("    {}", color::added(format!("+ {}",
+            layout::truncate(&line.replace('\r', "\\r"), width))));
+    }
+}
+
+/// Reports what was written for `--companion`, after the output it
+/// pairs with.
+pub fn report_companion(companion: &submod::CompanionSummary,
+    output_path: &Path)
+{
+    outln!(" Companion: {} subtitle{} retimed along with the output{}.",
+        companion.cues, if companion.cues == 1 { "" } else { "s" },
+        if companion.deleted == 0 { String::new() } else {
+            format!(", and {} deleted along with theirs", companion.deleted)
+        });
+    outln!(" Output: {}", color::file(output_path.display()));
+}
+
+pub fn report_unchanged(input_path: &Path) {
+    outln!("{}", color::title("Unchanged."));
+    outln!(" `{}` is already in the requested form, so nothing was written.",
+        input_path.display());
+    outln!(" Use --force to write the output anyway.");
+}
+
+/// Reports an input that `--lang` leaves alone, with its language
+/// if it has one.
+pub fn report_other_language(input_path: &Path, language_opt: Option<&str>,
+    lang: &str)
+{
+    outln!("{}", color::title("Skipped."));
+    match language_opt {
+        Some(language) if language != "unknown" =>
+            outln!(" `{}` is in `{}`, not `{}`, so it was left alone.",
+                input_path.display(), language, lang),
+        _ => outln!(" `{}` isn't known to be in `{}`, so it was left alone.",
+            input_path.display(), lang),
+    }
+}
+
+pub fn report_success(summary: &Summary, partial: bool, output_path: &Path,
+    overwrite: bool, rename_opt: Option<PathBuf>, backups_opt: Option<Backups>)
+{
+    let deleted_subs = summary.deleted;
+    outln!("{}", color::title("Success."));
+
+    if let Some(italic) = summary.italic_opt {
+        outln!(" Italic subtitles: {}, non-italic subtitles: {}.",
+            italic, summary.cues - italic);
+    }
+    match summary.decoded {
+        encoding::Decoded::Utf8 | encoding::Decoded::Given(_) => {},
+        encoding::Decoded::Utf16 => outln!(" The input is UTF-16, \
+            so it was converted."),
+        #[cfg(feature = "encodings")]
+        encoding::Decoded::Windows1252 => outln!(" The input isn't UTF-8, \
+            so it was read as Windows-1252."),
+        #[cfg(feature = "encodings")]
+        encoding::Decoded::BomWindows1252 => outln!(
+            "    {}   \
+            The input starts with a UTF-8 byte order mark, \
+            but isn't UTF-8,\n        \
+            so it was read as Windows-1252 instead.", color::warning_sign()),
+        #[cfg(not(feature = "encodings"))]
+        encoding::Decoded::Lossy => outln!(
+            "    {}   \
+            The input isn't UTF-8, so its invalid characters were replaced;\n        \
+            reinstall submod with the `encodings` feature to read it \
+            as Windows-1252.", color::warning_sign()),
+    }
+    report_fps_mismatch(summary);
+    if summary.partly_italic > 0 {
+        outln!("    {}   \
+            {} partly italic subtitle{} counted as non-italic.",
+            color::warning_sign(), summary.partly_italic,
+            if summary.partly_italic == 1 { " was" } else { "s were" });
+    }
+    if let Some(language) = summary.language {
+        outln!(" Detected language: {}.", language);
+    }
+    if let Some(ref switch) = summary.format_switch {
+        outln!("    {}   \
+            The input switches to {} at line {}.", color::warning_sign(),
+            switch.description, switch.line);
+        if summary.merged {
+            outln!("        All subtitles were merged into one sorted list.");
+        } else {
+            outln!("        Use --lenient to merge all subtitles \
+                into one sorted list.");
+        }
+    }
+    if partial {
+        outln!(" {} of {} subtitles were selected for modification.",
+            summary.selected, summary.cues);
+    }
+
+    if !summary.ranges.is_empty() {
+        outln!(" Per range:");
+        let width = layout::width();
+        for range in &summary.ranges {
+            outln!("{}", layout::row(&[format!("{} to {} ({:+.3} s):",
+                cue::format_time(range.start, false),
+                range.end_opt.map_or(String::from("the end"),
+                    |end| cue::format_time(end, false)), range.seconds),
+                format!("{} shifted, {} clamped, {} deleted", range.shifted,
+                    range.clamped, range.deleted)], width, "    "));
+        }
+    }
+
+    // Name the file, as several may be reported one after the other:
+    let file_name = output_path.file_name()
+        .map_or(output_path.as_os_str(), |name| name).to_string_lossy();
     if deleted_subs > 0 {
         if deleted_subs == 1 {
-            println!("    \u{001b}[41;1m ! \u{001b}[0m   \
-                One subtitle was deleted at the beginning of the file.");
+            outln!("    {}   \
+                One subtitle was deleted at the beginning of `{}`.",
+                color::warning_sign(), file_name);
+        } else {
+            outln!("    {}   \
+                {} subtitles were deleted at the beginning of `{}`.",
+                color::warning_sign(), deleted_subs, file_name);
+        }
+    }
+    if summary.clamped > 0 {
+        outln!(" {} subtitle{} clamped to start at 00:00:00 rather than \
+            before it.", summary.clamped,
+            if summary.clamped == 1 { " was" } else { "s were" });
+    }
+    if let Some((first_start, seconds)) = summary.leading_silence_opt {
+        outln!(" The first subtitle started at {}, so all were shifted \
+            by {:+.3} s.", submod::format_secs(first_start), seconds);
+    }
+    if let Some((starts, ends)) = summary.edge_shifts_opt {
+        outln!(" Starts were shifted by another {:+.3} s, and ends by \
+            another {:+.3} s.", starts, ends);
+    }
+    if summary.capped > 0 {
+        outln!(" {} subtitle{} shortened to not overlap the next one.",
+            summary.capped,
+            if summary.capped == 1 { " was" } else { "s were" });
+    }
+    if summary.trimmed == 1 {
+        outln!(" One subtitle was trimmed to not overlap a pinned one.");
+    } else if summary.trimmed > 1 {
+        outln!(" {} subtitles were trimmed to not overlap a pinned one.",
+            summary.trimmed);
+    }
+    if summary.covered > 0 {
+        outln!("    {}   \
+            {} subtitle{} deleted for being covered by a pinned one.",
+            color::warning_sign(), summary.covered,
+            if summary.covered == 1 { " was" } else { "s were" });
+    }
+    for (regex, count) in &summary.replacements {
+        if *count == 0 {
+            outln!("    {}   \
+                `{}` didn't match any subtitle.", color::warning_sign(), regex);
         } else {
-            println!("    \u{001b}[41;1m ! \u{001b}[0m   \
-                {} subtitles were deleted at the beginning of the file.",
-                deleted_subs);
+            outln!(" `{}` was replaced in {} subtitle{}.", regex, count,
+                if *count == 1 { "" } else { "s" });
+        }
+    }
+    if summary.emptied > 0 {
+        outln!(" {} subtitle{} deleted for being left without text.",
+            summary.emptied,
+            if summary.emptied == 1 { " was" } else { "s were" });
+    }
+    if summary.normalized == 1 {
+        outln!(" Whitespace was normalized in one subtitle.");
+    } else if summary.normalized > 1 {
+        outln!(" Whitespace was normalized in {} subtitles.",
+            summary.normalized);
+    }
+    if summary.rtl_fixed == 1 {
+        outln!(" One subtitle was marked as right-to-left.");
+    } else if summary.rtl_fixed > 1 {
+        outln!(" {} subtitles were marked as right-to-left.",
+            summary.rtl_fixed);
+    }
+    if !summary.outliers.is_empty() {
+        outln!("    {}   \
+            {} subtitle{} shifted further than --max-shift-per-cue allows:",
+            color::warning_sign(), summary.outliers.len(),
+            if summary.outliers.len() == 1 { " was" } else { "s were" });
+        outln!("        {}", format_outliers(&summary.outliers));
+    }
+    if !summary.boundary_conflicts.is_empty() {
+        let count = summary.boundary_conflicts.len();
+        match summary.boundary_policy {
+            submod::BoundaryConflict::Reorder => outln!(" {} pair{} of \
+                subtitles out of order after the partial shift {} re-sorted.",
+                count, if count == 1 { "" } else { "s" },
+                if count == 1 { "was" } else { "were" }),
+            submod::BoundaryConflict::Trim => outln!(" Subtitles \
+                overlapping after the partial shift were trimmed: \
+                {} trimmed and {} deleted.", summary.boundary_trimmed,
+                summary.boundary_dropped),
+            _ => {
+                outln!("    {}   \
+                    {} pair{} of subtitles overlap or are out of order \
+                    after the partial shift:", color::warning_sign(), count,
+                    if count == 1 { "" } else { "s" });
+                outln!("        {}",
+                    format_conflicts(&summary.boundary_conflicts));
+                outln!("        Use --boundary-conflict reorder or trim \
+                    to fix them.");
+            },
         }
     }
+    if summary.continuations > 0 {
+        outln!(" {} subtitle{} joined with the one before, continuing \
+            its sentence.", summary.continuations,
+            if summary.continuations == 1 { " was" } else { "s were" });
+    }
+    if summary.overlaps_fixed > 0 {
+        outln!(" {} subtitle{} shortened to end before the next one.",
+            summary.overlaps_fixed,
+            if summary.overlaps_fixed == 1 { " was" } else { "s were" });
+    }
+    if summary.collapsed_merged > 0 {
+        outln!(" {} subtitle{} merged into the next one, overlapping it \
+            too much to be shortened.", summary.collapsed_merged,
+            if summary.collapsed_merged == 1 { " was" } else { "s were" });
+    }
+    if !summary.collapsed.is_empty() {
+        let cues: Vec<String> = summary.collapsed.iter()
+            .map(|cue| cue.to_string())
+            .collect();
+        outln!("    {}   \
+            Subtitle{} {} overlap{} the next one too much to be shortened; \
+            use --merge-collapsed to merge them into it.",
+            color::warning_sign(), if cues.len() == 1 { "" } else { "s" },
+            cues.join(", "), if cues.len() == 1 { "s" } else { "" });
+    }
+    if !summary.overlong.is_empty() {
+        let cues: Vec<String> = summary.overlong.iter()
+            .map(|cue| cue.to_string())
+            .collect();
+        outln!("    {}   \
+            Subtitle{} {} {} longer lines or more lines than --max-line and \
+            --max-lines allow.", color::warning_sign(),
+            if cues.len() == 1 { "" } else { "s" }, cues.join(", "),
+            if cues.len() == 1 { "has" } else { "have" });
+    }
+    if summary.extended > 0 {
+        outln!(" {} subtitle{} lengthened to last --min-duration.",
+            summary.extended,
+            if summary.extended == 1 { " was" } else { "s were" });
+    }
+    if summary.extended_partly > 0 {
+        outln!(" {} subtitle{} too close to the next one to last \
+            --min-duration, and only lengthened as far as it allows.",
+            summary.extended_partly,
+            if summary.extended_partly == 1 { " is" } else { "s are" });
+    }
+    if summary.truncated > 0 {
+        outln!(" {} subtitle{} cut short for lasting longer than \
+            --max-duration.", summary.truncated,
+            if summary.truncated == 1 { " was" } else { "s were" });
+    }
+    if summary.low_confidence > 0 {
+        outln!(" {} subtitle{} dropped for a confidence below \
+            --drop-low-confidence.", summary.low_confidence,
+            if summary.low_confidence == 1 { " was" } else { "s were" });
+    }
+    if summary.unreadable_confidence > 0 {
+        outln!("    {}   \
+            {} NOTE{} before a subtitle had no confidence that could be \
+            read, so {} kept.", color::warning_sign(),
+            summary.unreadable_confidence,
+            if summary.unreadable_confidence == 1 { "" } else { "s" },
+            if summary.unreadable_confidence == 1 { "it was" }
+                else { "they were" });
+    }
+    if summary.credits > 0 {
+        outln!(" {} subtitle{} inserted by --prepend-cue or --append-cue.",
+            summary.credits,
+            if summary.credits == 1 { " was" } else { "s were" });
+    }
+    if summary.prepend_skipped {
+        outln!("    {}   \
+            The subtitle of --prepend-cue wasn't inserted, as the first one \
+            starts too early to make room for it.", color::warning_sign());
+    }
+    if summary.split > 0 {
+        outln!(" {} subtitle{} split into {} for lasting longer than \
+            --max-duration.", summary.split,
+            if summary.split == 1 { " was" } else { "s were" },
+            summary.pieces);
+    }
+    if let Some(mismatch) = summary.duration_mismatch_opt {
+        if mismatch.exceeds() {
+            outln!("    {}   \
+                The subtitles end at {}, but the video at {}: they may be \
+                for a different cut,\n        so a constant shift may not fix \
+                them. Try --speed or --step, and check with --verify-against.",
+                color::warning_sign(), format_span(mismatch.subtitles),
+                format_span(mismatch.video));
+        }
+    }
+    if let Some(ref residual) = summary.residual_opt {
+        match residual.offset_opt {
+            Some(offset) if residual.exceeds() => outln!(
+                "    {}   \
+                The subtitles are still {:+.3} s off from `{}`, \
+                more than --verify-tolerance allows.", color::warning_sign(),
+                offset, residual.reference.display()),
+            Some(offset) => outln!(" The subtitles are {:+.3} s off from \
+                `{}`, measured over {} subtitle{}.", offset,
+                residual.reference.display(), residual.pairs,
+                if residual.pairs == 1 { "" } else { "s" }),
+            None => outln!("    {}   \
+                No subtitles could be paired with those of `{}`.",
+                color::warning_sign(), residual.reference.display()),
+        }
+    }
+    if summary.min_start_moved > 0 || summary.min_start_dropped > 0 {
+        outln!(" Subtitles starting before --min-start: {} moved to it, \
+            and {} dropped for ending before it.", summary.min_start_moved,
+            summary.min_start_dropped);
+    }
+    if summary.overflowed > 0 {
+        outln!("    {}   \
+            {} subtitle{} ended past 99:59:59,999, the latest time \
+            srt timestamps can represent.", color::warning_sign(),
+            summary.overflowed, if summary.overflowed == 1 { "" } else { "s" });
+    }
+    if summary.regions_dropped > 0 {
+        outln!(" {} vtt region{} dropped, as srt has no regions.",
+            summary.regions_dropped,
+            if summary.regions_dropped == 1 { " was" } else { "s were" });
+    }
+    if summary.settings_joined > 0 {
+        outln!("    {}   \
+            {} subtitle{} continued their settings after the timing line; \
+            they were joined onto it.", color::warning_sign(),
+            summary.settings_joined,
+            if summary.settings_joined == 1 { "" } else { "s" });
+    }
+    if summary.duplicate_settings > 0 {
+        outln!("    {}   \
+            {} subtitle{} gave the same setting more than once; the last \
+            value was kept.", color::warning_sign(), summary.duplicate_settings,
+            if summary.duplicate_settings == 1 { "" } else { "s" });
+    }
+    for region in &summary.dangling_regions {
+        outln!("    {}   \
+            Subtitles refer to the region `{}`, which isn't defined.",
+            color::warning_sign(), region);
+    }
+    if summary.styles_pruned > 0 {
+        outln!(" {} unused style{} removed.", summary.styles_pruned,
+            if summary.styles_pruned == 1 { " was" } else { "s were" });
+    }
+    for selector in &summary.styles_untouched {
+        outln!("    {}   \
+            The style selector `{}` was left untouched.", color::warning_sign(),
+            selector);
+    }
+    if summary.replaced > 0 {
+        outln!("    {}   \
+            {} character{} could not be encoded and {} replaced.",
+            color::warning_sign(), summary.replaced,
+            if summary.replaced == 1 { "" } else { "s" },
+            if summary.replaced == 1 { "was" } else { "were" });
+    }
     if let Some(rename) = rename_opt {
-        println!(" The input file was renamed to `{}`.", rename.display());
+        outln!(" The input file was renamed to `{}`.", rename.display());
     } else if overwrite {
-        println!(" The input file was overwritten.");
+        outln!(" The input file was overwritten.");
+    }
+    if let Some(backups) = backups_opt {
+        outln!(" A backup was saved as `{}`.", backups.created.display());
+        for path in backups.pruned {
+            outln!(" The old backup `{}` was deleted.", path.display());
+        }
+    }
+
+    let warnings = summary.warnings();
+    if warnings > 0 {
+        outln!(" {} warning{}.", warnings,
+            if warnings == 1 { "" } else { "s" });
+    }
+    outln!(" Processed {} subtitle{} in {:.3} s ({:.0} subtitles/second).",
+        summary.cues, if summary.cues == 1 { "" } else { "s" },
+        summary.elapsed.as_secs_f64(), cues_per_second(summary));
+    outln!(" Output: {}", color::file(output_path.display()));
+}
+
+/// Formats seconds as a duration like `1:38:12`.
+fn format_span(seconds: f64) -> String {
+    let seconds = seconds.round() as u64;
+    format!("{}:{:02}:{:02}", seconds / 3600, seconds % 3600 / 60, seconds % 60)
+}
+
+fn cues_per_second(summary: &Summary) -> f64 {
+    summary.cues as f64 / summary.elapsed.as_secs_f64().max(1e-9)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use testing;
+
+    fn name(input: &str, seconds: f64, partial: bool) -> String {
+        smart_name(Path::new(input), seconds, partial, None,
+            Tagging::default())
+    }
+
+    fn name_with(input: &str, seconds: f64, mode: TagMode) -> String {
+        smart_name(Path::new(input), seconds, false, None,
+            Tagging { mode, ..Tagging::default() })
+    }
+
+    #[test]
+    fn tags_untagged_names() {
+        assert_eq!(name("movie.srt", 1.0, false), "movie__[+1.000_Sec+].srt");
+        assert_eq!(name("movie.en.vtt", -2.5, true),
+            "movie.en__[-2.500_Sec-].vtt");
+    }
+
+    #[test]
+    fn merges_with_existing_tag() {
+        assert_eq!(name("movie__[+1.00_Sec+].srt", 0.5, false),
+            "movie__[+1.500_Sec+].srt");
+        assert_eq!(name("movie__[-1.00_Sec-].srt", 0.25, true),
+            "movie__[-0.750_Sec-].srt");
+    }
+
+    #[test]
+    fn collapses_stacked_tags() {
+        assert_eq!(name("movie__[+1.00_Sec+]__[+2.00_Sec+].srt", 0.5, false),
+            "movie__[+3.500_Sec+].srt");
+        assert_eq!(name("movie__[+1.00_Sec+]__[-1.50_Sec-].srt", 0.0, false),
+            "movie__[-0.500_Sec+].srt");
+    }
+
+    #[test]
+    fn ignores_numbers_and_brackets_in_title() {
+        assert_eq!(name("2001 [+1.5] +3.25.srt", -1.0, false),
+            "2001 [+1.5] +3.25__[-1.000_Sec+].srt");
+        assert_eq!(name("Show [S01E02] [x264]__[-0.50_Sec-].srt", 0.5, false),
+            "Show [S01E02] [x264]__[+0.000_Sec+].srt");
+        assert_eq!(name("Up +2.00 Down__[+1.00_Sec+].srt", 1.0, false),
+            "Up +2.00 Down__[+2.000_Sec+].srt");
+    }
+
+    #[test]
+    fn ignores_tags_that_do_not_end_the_stem() {
+        assert_eq!(name("movie__[+1.00_Sec+] extra.srt", 1.0, false),
+            "movie__[+1.00_Sec+] extra__[+1.000_Sec+].srt");
+        assert_eq!(name("movie__[+1.00_Sec+]x.srt", 1.0, false),
+            "movie__[+1.00_Sec+]x__[+1.000_Sec+].srt");
+    }
+
+    #[test]
+    fn strips_original_marker() {
+        assert_eq!(name("movie__[+1.00_Sec+]__[Original].srt", 1.0, false),
+            "movie__[+2.000_Sec+].srt");
+    }
+
+    #[test]
+    fn tags_only_this_run_in_delta_mode() {
+        assert_eq!(name_with("movie__[+1.00_Sec+].srt", 0.5, TagMode::Delta),
+            "movie__[+0.500_Sec+].srt");
+        assert_eq!(name_with("movie__[+1.00_Sec+]__[-2.00_Sec-].srt", -0.25,
+            TagMode::Delta), "movie__[-0.250_Sec+].srt");
+        // Re-running on a delta output counts its tag once:
+        assert_eq!(name("movie__[+0.50_Sec+].srt", 0.5, false),
+            "movie__[+1.000_Sec+].srt");
+    }
+
+    #[test]
+    fn strips_tags_in_none_mode() {
+        assert_eq!(name_with("movie__[+1.00_Sec+]__[+2.00_Sec+].srt", 0.5,
+            TagMode::None), "movie.srt");
+        assert_eq!(name_with("movie__[+1.00_Sec+]__[Original].srt", 0.5,
+            TagMode::None), "movie.srt");
+        assert_eq!(name("movie.srt", 0.5, false), "movie__[+0.500_Sec+].srt");
+    }
+
+    #[test]
+    fn merges_increments_by_tag_mode() {
+        assert_eq!(merge_increment(0.5, Some(1.0), TagMode::Cumulative), 1.5);
+        assert_eq!(merge_increment(0.5, None, TagMode::Cumulative), 0.5);
+        assert_eq!(merge_increment(0.5, Some(1.0), TagMode::Delta), 0.5);
+        assert_eq!(merge_increment(0.5, Some(1.0), TagMode::None), 0.5);
+    }
+
+    #[test]
+    fn formats_increments_with_the_precision() {
+        assert_eq!(format_increment(1.5, 2), "+1.50");
+        assert_eq!(format_increment(-0.125, 3), "-0.125");
+        assert_eq!(format_increment(2.4, 0), "+2");
+        assert_eq!(format_increment(0.0, 1), "+0.0");
+        assert_eq!(format_increment(-0.0417, 3), "-0.042");
+        assert_eq!(format_increment(0.0417, 2), "+0.04");
+        assert_eq!(format_increment(-0.0417, 1), "+0.0");
+        assert_eq!(format_increment(-0.0001, 3), "+0.000");
+        assert_eq!(format_increment(-0.0001, 2), "+0.00");
+        // A tag with more precision is read back in full:
+        assert_eq!(name("movie__[+0.125_Sec+].srt", 0.125, false),
+            "movie__[+0.250_Sec+].srt");
+        // The library names its outputs the same way:
+        assert_eq!(::output_path(Path::new("movie__[-1.25_Sec+].srt"),
+            0.5, true), Path::new(&name("movie__[-1.25_Sec+].srt", 0.5, true)));
+    }
+
+    #[test]
+    fn chains_frame_shifts_to_the_millisecond() {
+        // Frames of 23.976 fps video end on these milliseconds, so shifting
+        // by 24 of them, one at a time, shifts by exactly 1.001 seconds:
+        let frame_end = |frame: f64| (frame * 1001.0 / 24.0).round() / 1000.0;
+        let mut input = String::from("movie.srt");
+        for frame in 1..=24 {
+            let frame = frame as f64;
+            input = name(&input, frame_end(frame) - frame_end(frame - 1.0),
+                false);
+        }
+        assert_eq!(input, "movie__[+1.001_Sec+].srt");
+    }
+
+    #[test]
+    fn tags_the_factor_of_scaled_timestamps() {
+        let scaled = |input: &str, seconds, factor, mode| smart_name(
+            Path::new(input), seconds, false, None,
+            Tagging { mode, factor, ..Tagging::default() });
+        let factor = 23.976 / 25.0;
+        assert_eq!(scaled("movie.srt", 0.0, factor, TagMode::Cumulative),
+            "movie__[x0.95904]__[+0.000_Sec+].srt");
+        // The seconds of the input are scaled along with the timestamps:
+        assert_eq!(scaled("movie__[+2.00_Sec+].srt", 1.0, 0.5,
+            TagMode::Cumulative), "movie__[x0.5]__[+2.000_Sec+].srt");
+        assert_eq!(scaled("movie__[x0.95904]__[+0.00_Sec+].srt", 0.5,
+            1.0 / factor, TagMode::Cumulative), "movie__[+0.500_Sec+].srt");
+        assert_eq!(scaled("movie__[x0.5]__[+2.00_Sec+].srt", 1.0, 1.0,
+            TagMode::Delta), "movie__[+1.000_Sec+].srt");
+        assert_eq!(format_factor(25.0 / 23.976), "__[x1.04271]");
+        assert_eq!(format_factor(1.000001), "");
+    }
+
+    #[test]
+    fn computes_backup_paths() {
+        assert_eq!(backup_path(Path::new("dir/movie.srt")),
+            Some(PathBuf::from("dir/movie__[Original].srt")));
+        assert_eq!(backup_path(Path::new("dir/movie__[Original].srt")),
+            Some(PathBuf::from("dir/movie__[Original].srt")));
+        assert_eq!(backup_path(Path::new("movie")), None);
     }
 
-    println!(" Output: \u{001b}[1m \u{001b}[48;5;238m {} \u{001b}[0m",
-        output_path.display());
-}
\ No newline at end of file
+    #[test]
+    fn gets_paths_for_every_combination() {
+        let inputs = [("dir/movie.srt", 0.0),
+            ("dir/movie__[+1.00_Sec+].srt", 1.0),
+            ("dir/movie__[-0.50_Sec-]__[Original].srt", -0.5)];
+        for &(input, tagged) in &inputs {
+            for &convert_opt in &[None, Some("vtt")] {
+                for &partial in &[false, true] {
+                    for &rename in &[false, true] {
+                        let (input_path, output_path, rename_opt) =
+                            get_paths(input, 2.0, partial, rename, None,
+                                convert_opt, None, Tagging::default())
+                            .unwrap();
+                        assert_eq!(input_path, PathBuf::from(input));
+                        assert_eq!(output_path, PathBuf::from(format!(
+                            "dir/movie__[+{:.3}_Sec{}].{}", 2.0 + tagged,
+                            if partial { "-" } else { "+" },
+                            convert_opt.unwrap_or("srt"))));
+                        assert_eq!(rename_opt, if rename {
+                            backup_path(Path::new(input)) } else { None });
+                    }
+                }
+            }
+        }
+        // `--out` is taken as is:
+        let (_, output_path, rename_opt) = get_paths("dir/movie.srt", 2.0,
+            false, true, Some("out.vtt"), Some("srt"), None,
+            Tagging::default()).unwrap();
+        assert_eq!((output_path, rename_opt), (PathBuf::from("out.vtt"), None));
+    }
+
+    #[test]
+    fn formats_timing_map_like_the_golden_files() {
+        let srt = "1\n00:00:00,500 --> 00:00:01,500\nDeleted\n\n\
+            2\n00:00:02,000 --> 00:00:04,250\nShifted\n\n\
+            3\n00:01:00,000 --> 00:01:02,000\nPinned\n";
+        let (blocks, switch_opt) = ::cue::parse(srt.as_bytes()).unwrap();
+        let options = Options { seconds: -1.75, pin_last: true,
+            map_timing: true, ..Options::default() };
+        let (_, summary) = submod::modify(blocks, switch_opt, true, true,
+            &options).unwrap();
+        assert_eq!(format_timing_map(&summary, false),
+            include_str!("../tests/golden/timing_map.csv"));
+        assert_eq!(format_timing_map(&summary, true),
+            include_str!("../tests/golden/timing_map.json"));
+    }
+
+    #[test]
+    fn tags_scripts_like_other_subtitles() {
+        assert_eq!(name("episode.ass", 1.0, false),
+            "episode__[+1.000_Sec+].ass");
+        assert_eq!(name("episode__[+1.00_Sec+].ssa", -0.5, true),
+            "episode__[+0.500_Sec-].ssa");
+        for input in &["a.srt", "a.vtt", "a.ass", "a.ssa", "a.sub", "a.ttml",
+            "a.dfxp", "a.xml"] {
+            assert!(is_subtitle(input.to_string()).is_ok());
+        }
+        assert!(is_subtitle(String::from("a.idx")).is_err());
+    }
+
+    #[test]
+    fn accepts_urls_as_input() {
+        assert!(is_url("https://example.com/movie.srt"));
+        assert!(is_url("HTTP://example.com/get?id=5"));
+        assert!(!is_url("movie.srt") && !is_url("ftp://example.com/a.srt"));
+        assert!(is_input(String::from("https://example.com/get?id=5")).is_ok());
+    }
+
+    #[test]
+    fn validates_times_with_milliseconds() {
+        for time in &["00:14:07", "00:14:07.438", "00:14:07,438", "14:07,4",
+            "7.5"] {
+            assert!(is_timing(time.to_string()).is_ok(), "{}", time);
+        }
+        for time in &["00:14:07;438", "00:14:07.4.3", "7,5,0"] {
+            assert!(is_timing(time.to_string()).is_err(), "{}", time);
+        }
+    }
+
+    #[test]
+    fn validates_percentages_of_the_duration() {
+        assert!(is_timing_or_percentage(String::from("90%")).is_ok());
+        assert!(is_timing_or_percentage(String::from("0%")).is_ok());
+        assert!(is_timing_or_percentage(String::from("12.5%")).is_ok());
+        assert!(is_timing_or_percentage(String::from("01:30")).is_ok());
+        assert!(is_timing_or_percentage(String::from("101%")).is_err());
+        assert!(is_timing_or_percentage(String::from("-5%")).is_err());
+        assert!(is_timing_or_percentage(String::from("half%")).is_err());
+    }
+
+    #[test]
+    fn validates_offsets_as_numbers_or_times() {
+        for offset in &["-83.5", "+0.5", "2", "1:23.5", "-00:01:23.500",
+            "+01:00"] {
+            assert!(is_offset(offset.to_string()).is_ok(), "{}", offset);
+        }
+        for offset in &["", "1:", "1:-23", "--1:23", "1:2:3:4", "a:01",
+            "1:inf", "nan", "inf", "-inf"] {
+            assert!(is_offset(offset.to_string()).is_err(), "{}", offset);
+        }
+        // Numbers and times are both suggested, whichever was meant:
+        for offset in &["abc", "1.5s", "1:2:3:4"] {
+            let error = is_offset(offset.to_string()).unwrap_err();
+            assert!(error.starts_with("should be a number of seconds, \
+                or a time"), "{}", offset);
+            assert!(error.contains("-83.5") && error.contains("-01:23.5"),
+                "{}", offset);
+        }
+    }
+
+    #[test]
+    fn validates_finite_numbers() {
+        for seconds in &["-0.5", "+2", "1e3"] {
+            assert!(is_float(seconds.to_string()).is_ok(), "{}", seconds);
+        }
+        for seconds in &["nan", "inf", "-inf", "01:30", "1,5", ""] {
+            assert!(is_float(seconds.to_string()).is_err(), "{}", seconds);
+        }
+    }
+
+    #[test]
+    fn turns_negative_times_into_seconds() {
+        let args: Vec<String> = ["submod", "a.srt", "-01:23.5", "-1",
+            "--range", "-00:05:00=-2", "--shift-starts", "-0.1"].iter()
+            .map(|arg| arg.to_string()).collect();
+        assert_eq!(negative_times_to_seconds(args), ["submod", "a.srt",
+            "-83.5", "-1", "--range", "-00:05:00=-2", "--shift-starts",
+            "-0.1"]);
+    }
+
+    #[test]
+    fn parses_replacements_at_the_first_unescaped_equals_sign() {
+        let (regex, replacement) = parse_replacement("Jon=John").unwrap();
+        assert_eq!((regex.as_str(), replacement.as_str()), ("Jon", "John"));
+        let (regex, replacement) = parse_replacement(r"a\=b=c=d").unwrap();
+        assert_eq!((regex.as_str(), replacement.as_str()), ("a=b", "c=d"));
+        let (regex, replacement) = parse_replacement(r"\\=x").unwrap();
+        assert_eq!((regex.as_str(), replacement.as_str()), (r"\\", "x"));
+        assert!(parse_replacement("(sighs)=").is_ok());
+        assert!(parse_replacement("no separator").is_err());
+        assert!(parse_replacement("=empty").is_err());
+        assert!(parse_replacement("(unclosed=x").is_err());
+    }
+
+    #[test]
+    fn formats_spans_as_durations() {
+        assert_eq!(format_span(5892.4), "1:38:12");
+        assert_eq!(format_span(59.6), "0:01:00");
+    }
+
+    #[test]
+    fn refuses_absolute_name_templates() {
+        for template in &["/tmp/abs_{stem}", "\\\\server\\{stem}",
+            "C:\\subs\\{stem}", "c:{stem}"] {
+            assert!(is_name_template(template.to_string()).unwrap_err()
+                .contains("is an absolute path"), "{}", template);
+        }
+        assert!(is_name_template(String::from("subs/{stem}{tag}")).is_ok());
+        assert!(is_name_template(String::from("{stem}_{nope}")).is_err());
+    }
+
+    #[test]
+    fn escapes_json_strings() {
+        assert_eq!(json_escape("00:00:01.000 --> 00:00:02.000 \"a\"\\\t\u{1}"),
+            "00:00:01.000 --> 00:00:02.000 \\\"a\\\"\\\\\\t\\u0001");
+    }
+
+    #[test]
+    fn formats_backup_timestamps_in_utc() {
+        let time = UNIX_EPOCH + std::time::Duration::from_secs(1714564981);
+        assert_eq!(backup_stamp(time), "20240501T120301");
+        let time = UNIX_EPOCH + std::time::Duration::from_secs(951782400);
+        assert_eq!(backup_stamp(time), "20000229T000000");
+    }
+
+    #[test]
+    fn writes_files_atomically() {
+        let dir = testing::temp_dir("atomic");
+        fs::create_dir_all(dir.join("taken.srt")).unwrap();
+        let path = dir.join("movie.srt");
+        fs::write(&path, "old").unwrap();
+        write_atomically(&path, b"new").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        // A directory can't be replaced, and nothing is left behind:
+        assert!(write_atomically(&dir.join("taken.srt"), b"new").is_err());
+        let mut names: Vec<String> = fs::read_dir(&dir).unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        assert_eq!(names, ["movie.srt", "taken.srt"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn keeps_outputs_within_their_directory() {
+        let dir = testing::temp_dir("within");
+        fs::create_dir_all(dir.join("in/sub")).unwrap();
+        let input_dir = dir.join("in");
+        for path in &["in/movie.srt", "in/sub/movie.srt", "in/new/movie.srt",
+            "in/new/../movie.srt", "in/sub/../../in/movie.srt"] {
+            assert!(check_within(&dir.join(path), &input_dir).is_ok(),
+                "{}", path);
+        }
+        for path in &["movie.srt", "in/../movie.srt", "in/new/../../x.srt",
+            "in/sub/../../../etc/cron.d/x.srt"] {
+            assert!(check_within(&dir.join(path), &input_dir).is_err(),
+                "{}", path);
+        }
+        assert!(check_within(Path::new("/etc/cron.d/x.srt"), &input_dir)
+            .is_err());
+        #[cfg(unix)]
+        {
+            fs::create_dir_all(dir.join("elsewhere")).unwrap();
+            std::os::unix::fs::symlink(dir.join("elsewhere"),
+                dir.join("in/link")).unwrap();
+            assert!(check_within(&dir.join("in/link/movie.srt"), &input_dir)
+                .is_err());
+            // An input directory that is a symlink itself is resolved too:
+            std::os::unix::fs::symlink(&input_dir, dir.join("alias"))
+                .unwrap();
+            assert!(check_within(&dir.join("in/movie.srt"),
+                &dir.join("alias")).is_ok());
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn removes_what_was_written_from_a_modified_input() {
+        let dir = testing::temp_dir("verify");
+        let (input, output) = (dir.join("movie.srt"), dir.join("out.srt"));
+        fs::write(&input, "old").unwrap();
+        let fingerprint = fingerprint(&input).unwrap();
+        fs::write(&output, "shifted").unwrap();
+        verify_unchanged(&input, fingerprint, &[&output]).unwrap();
+        assert!(output.exists());
+
+        // Another program rewrites the input in the meantime:
+        fs::write(&input, "new").unwrap();
+        let error = verify_unchanged(&input, fingerprint, &[&output])
+            .unwrap_err();
+        assert!(error.to_string().contains("was modified by another"));
+        assert!(!output.exists());
+        assert_eq!(fs::read_to_string(&input).unwrap(), "new");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn parses_stamp_dates() {
+        assert_eq!(parse_stamp_date("now"), Ok(StampDate::Now));
+        assert_eq!(parse_stamp_date("none"), Ok(StampDate::None));
+        let time = UNIX_EPOCH + std::time::Duration::from_secs(1714564981);
+        assert_eq!(parse_stamp_date("fixed:2024-05-01T12:03:01Z"),
+            Ok(StampDate::Fixed(time)));
+        let time = UNIX_EPOCH + std::time::Duration::from_secs(951782400);
+        assert_eq!(parse_stamp_date("fixed:2000-02-29"),
+            Ok(StampDate::Fixed(time)));
+        for when in &["fixed:2023-02-29", "fixed:2024-13-01",
+            "fixed:2024-05-01T24:00:00", "fixed:1969-12-31", "2024-05-01",
+            "fixed:2024-05-01 12:03:01", "later"] {
+            assert!(parse_stamp_date(when).is_err(), "{}", when);
+        }
+    }
+
+    #[test]
+    fn measures_edit_distance() {
+        assert_eq!(edit_distance("ep07.srt", "ep07.srt"), 0);
+        assert_eq!(edit_distance("ep7.srt", "ep07.srt"), 1);
+        assert_eq!(edit_distance("movie.srt", "moive.srt"), 2);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
+
+    #[test]
+    fn suggests_close_subtitle_names() {
+        let names: Vec<String> = ["ep07.en.srt", "ep07.en.mkv", "ep08.en.srt",
+            "Show.S01E07.fr.vtt", "notes.txt", "ep01.en.srt", "ep02.en.srt"]
+            .iter().map(|name| name.to_string()).collect();
+        assert_eq!(suggest_names("ep7.en.srt", &names),
+            ["ep07.en.srt", "ep01.en.srt", "ep02.en.srt"]);
+        // A matching episode number counts even when the names differ a lot:
+        assert_eq!(suggest_names("show.e07.srt", &names),
+            ["ep07.en.srt", "Show.S01E07.fr.vtt"]);
+        assert!(suggest_names("unrelated.srt", &names).is_empty());
+    }
+
+    #[test]
+    fn finds_episode_numbers() {
+        assert_eq!(episode("Show.S01E07.srt"), Some(7));
+        assert_eq!(episode("ep12.en.vtt"), Some(12));
+        assert_eq!(episode("show.2x03.srt"), Some(3));
+        assert_eq!(episode("movie.srt"), None);
+    }
+}
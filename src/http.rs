@@ -0,0 +1,165 @@
+use std::error::Error as StdError;
+use std::fmt;
+use std::io::{self, Read};
+use std::time::Duration;
+
+use failure::{Error, Fail};
+use ureq;
+
+use color;
+
+
+/// A download that failed for reasons of the network or the server,
+/// which submod exits with a status of its own for.
+#[derive(Debug)]
+pub struct NetworkError(String);
+
+impl fmt::Display for NetworkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Fail for NetworkError {}
+
+/// A subtitle file downloaded into memory.
+#[derive(Debug)]
+pub struct Download {
+    pub bytes: Vec<u8>,
+    /// The media type of the Content-Type header, like `text/vtt`.
+    pub content_type_opt: Option<String>,
+}
+
+/// Downloads `url`, following redirects, giving up after `timeout`
+/// seconds and refusing anything larger than `max_size` bytes. Nothing is
+/// written to disk.
+pub fn download(url: &str, max_size: u64, timeout: u64)
+    -> Result<Download, Error>
+{
+    let failed = |reason: String| NetworkError(format!("Could not download \
+        `{}`: {}", url, reason));
+    let agent = ureq::AgentBuilder::new()
+        .timeout(Duration::from_secs(timeout))
+        .build();
+    let response = match agent.get(url).call() {
+        Ok(response) => response,
+        // Like `404 Not Found`:
+        Err(ureq::Error::Status(code, response)) => return Err(failed(
+            format!("{} {}", code, response.status_text())).into()),
+        Err(ureq::Error::Transport(transport)) =>
+            return Err(failed(reason(&transport, timeout)).into()),
+    };
+
+    let too_large = || format_err!("`{}` is larger than {} MB.\n\n\
+        Use {} to download larger \
+        files.", url, max_size / (1024 * 1024),
+        color::hint("--max-input-size"));
+    let length_opt = response.header("Content-Length")
+        .and_then(|length| length.trim().parse::<u64>().ok());
+    if length_opt.is_some_and(|length| length > max_size) {
+        return Err(too_large());
+    }
+    let content_type_opt = response.header("Content-Type")
+        .and_then(|content_type| content_type.split(';').next())
+        .map(|media_type| media_type.trim().to_lowercase())
+        .filter(|media_type| !media_type.is_empty());
+
+    // The size of a response isn't always known up front, so it's
+    // checked while reading as well:
+    let mut bytes = Vec::new();
+    response.into_reader().take(max_size + 1).read_to_end(&mut bytes)
+        .map_err(|error| failed(if is_timeout(&error) {
+            format!("it took longer than {} seconds", timeout)
+        } else {
+            error.to_string()
+        }))?;
+    if bytes.len() as u64 > max_size {
+        return Err(too_large());
+    }
+    Ok(Download { bytes, content_type_opt })
+}
+
+/// Why a request failed, without the URL ureq starts its errors with.
+fn reason(transport: &ureq::Transport, timeout: u64) -> String {
+    let io_error_opt = StdError::source(transport)
+        .and_then(|source| source.downcast_ref::<io::Error>());
+    match io_error_opt {
+        Some(error) if is_timeout(error) =>
+            format!("it took longer than {} seconds", timeout),
+        Some(error) => error.to_string(),
+        None => transport.message()
+            .map_or_else(|| transport.kind().to_string(), String::from),
+    }
+}
+
+/// Whether reading or writing stopped as the timeout ran out.
+fn is_timeout(error: &io::Error) -> bool {
+    error.kind() == io::ErrorKind::TimedOut
+        || error.kind() == io::ErrorKind::WouldBlock
+}
+
+/// The extension of the subtitle format of a download: the one of the
+/// file name in the URL, or else the one its Content-Type stands for, or
+/// else vtt if it starts with a WEBVTT header, and srt otherwise.
+pub fn extension(url: &str, download: &Download) -> &'static str {
+    let name = file_name(url).to_lowercase();
+    let by_name = ["srt", "vtt", "ass", "ssa", "sub", "ttml", "dfxp", "xml"]
+        .iter()
+        .find(|extension| name.ends_with(&format!(".{}", extension)));
+    let by_content_type = || match download.content_type_opt.as_deref() {
+        Some("text/vtt") => Some("vtt"),
+        Some("application/x-subrip") | Some("text/srt")
+            | Some("application/srt") => Some("srt"),
+        Some("text/x-ssa") => Some("ssa"),
+        Some("text/x-ass") => Some("ass"),
+        Some("application/ttml+xml") => Some("ttml"),
+        _ => None,
+    };
+    by_name.cloned().or_else(by_content_type).unwrap_or_else(|| {
+        let contents = String::from_utf8_lossy(&download.bytes);
+        if contents.trim_start_matches('\u{feff}').trim_start()
+            .starts_with("WEBVTT") { "vtt" } else { "srt" }
+    })
+}
+
+/// The last segment of the path of a URL, without its query or fragment.
+pub fn file_name(url: &str) -> &str {
+    let after_scheme = url.find("://").map_or(url, |i| &url[i + 3..]);
+    let path = after_scheme.split(['?', '#']).next().unwrap_or("");
+    match path.find('/') {
+        Some(i) => path[i..].rsplit('/').next().unwrap_or(""),
+        None => "",
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(content_type: Option<&str>, contents: &str) -> Download {
+        Download { bytes: contents.as_bytes().to_vec(),
+            content_type_opt: content_type.map(String::from) }
+    }
+
+    #[test]
+    fn takes_the_file_name_from_the_path() {
+        assert_eq!(file_name("https://example.com/subs/Movie.en.srt?t=1#x"),
+            "Movie.en.srt");
+        assert_eq!(file_name("https://example.com/download/"), "");
+        assert_eq!(file_name("https://example.com?file=a.srt"), "");
+    }
+
+    #[test]
+    fn infers_the_format_from_url_content_type_or_contents() {
+        let vtt = response(Some("text/vtt"), "1\n");
+        assert_eq!(extension("https://example.com/a.SRT?t=1", &vtt), "srt");
+        assert_eq!(extension("https://example.com/get?id=5", &vtt), "vtt");
+        assert_eq!(extension("https://example.com/get",
+            &response(Some("application/x-subrip"), "")), "srt");
+        assert_eq!(extension("https://example.com/get",
+            &response(Some("text/plain"), "\u{feff}WEBVTT\n")), "vtt");
+        assert_eq!(extension("https://example.com/get",
+            &response(None, "1\n00:00:01,000 --> 00:00:02,000\n")), "srt");
+    }
+}
@@ -0,0 +1,129 @@
+use std::fs;
+use std::path::Path;
+
+use clap::{App, Arg};
+use failure::Error;
+
+use cli;
+use color;
+use cue::{self, Block};
+use encoding;
+#[cfg(feature = "langdetect")]
+use submod;
+use status;
+use subtitles::Error as SubmodError;
+
+
+/// What `submod info` reports about an srt or vtt file.
+#[derive(Debug, PartialEq)]
+pub struct Info {
+    pub srt: bool,
+    /// Number of subtitles.
+    pub cues: usize,
+    /// From the start of the first subtitle to the end of the last one,
+    /// if there are any.
+    pub span_opt: Option<(f64, f64)>,
+    /// The language of the text of the first subtitles, as an ISO 639-1
+    /// code or `unknown`, with the `langdetect` feature.
+    pub language_opt: Option<&'static str>,
+}
+
+/// Runs the `submod info` command, which reports the format, subtitles,
+/// span and language of srt and vtt files without writing anything, and
+/// returns the status to exit with.
+pub fn main(args: &[String]) -> i32 {
+    let matches = App::new("submod info")
+        .about("Report the format, number of subtitles, span and detected \
+            language of srt and\nvtt files, without writing anything.")
+        .arg(Arg::with_name("file")
+            .help("Subtitle files to describe")
+            .required(true)
+            .multiple(true)
+            .index(1))
+        .get_matches_from_safe(args);
+    let matches = match matches {
+        Ok(matches) => matches,
+        Err(error) => return cli::report(error),
+    };
+
+    for input in matches.values_of("file").unwrap() {
+        let path = Path::new(input);
+        let info = match describe(path) {
+            Ok(info) => info,
+            Err(error) => return status::report(error),
+        };
+        outln!("{}", color::title("Info."));
+        outln!(" `{}`", path.display());
+        outln!("    Format:    {}", if info.srt { "srt" } else { "vtt" });
+        outln!("    Subtitles: {}", info.cues);
+        if let Some((start, end)) = info.span_opt {
+            outln!("    Span:      {} --> {}",
+                cue::format_time(start, info.srt),
+                cue::format_time(end, info.srt));
+        }
+        if let Some(language) = info.language_opt {
+            outln!("    Language:  {}", language);
+        }
+    }
+    0
+}
+
+/// Reads and describes an srt or vtt file.
+pub fn describe(path: &Path) -> Result<Info, Error> {
+    let srt = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("srt") => true,
+        Some("vtt") => false,
+        _ => return Err(format_err!("`{}` isn't an .srt or .vtt file, \
+            which are the only ones submod describes.", path.display())),
+    };
+    let input = fs::read(path).map_err(|error| SubmodError::Io {
+        path: path.to_owned(), message: format!("Could not read `{}`: {}",
+            path.display(), error) })?;
+    let (text, _) = encoding::decode(&input);
+    info(encoding::split_bom(&text).1, srt)
+}
+
+/// Describes the text of an srt or vtt file.
+pub fn info(text: &str, srt: bool) -> Result<Info, Error> {
+    let (blocks, _) = cue::parse(text.as_bytes())?;
+    let cues: Vec<&cue::Cue> = blocks.iter()
+        .filter_map(|block| match *block {
+            Block::Cue(ref cue) => Some(cue),
+            _ => None,
+        })
+        .collect();
+    let span_opt = cues.iter()
+        .map(|cue| (cue.start, cue.end))
+        .fold(None, |span_opt: Option<(f64, f64)>, (start, end)|
+            Some(span_opt.map_or((start, end),
+                |span| (span.0.min(start), span.1.max(end)))));
+    #[cfg(feature = "langdetect")]
+    let language_opt = Some(submod::detect_language(&blocks));
+    #[cfg(not(feature = "langdetect"))]
+    let language_opt = None;
+    Ok(Info { srt, cues: cues.len(), span_opt, language_opt })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn describes_the_subtitles() {
+        let srt = "1\n00:00:04,000 --> 00:00:06,000\nWhat is this that you \
+            have in the box?\n\n2\n00:00:01,000 --> 00:00:03,500\nI don't \
+            know, and it's not for you to open.\n";
+        let info = info(srt, true).unwrap();
+        assert_eq!((info.srt, info.cues, info.span_opt),
+            (true, 2, Some((1.0, 6.0))));
+        assert_eq!(info.language_opt,
+            if cfg!(feature = "langdetect") { Some("en") } else { None });
+
+        let empty = super::info("WEBVTT\n", false).unwrap();
+        assert_eq!((empty.cues, empty.span_opt), (0, None));
+        if cfg!(feature = "langdetect") {
+            assert_eq!(empty.language_opt, Some("unknown"));
+        }
+    }
+}
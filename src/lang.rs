@@ -0,0 +1,72 @@
+use whatlang::{self, Lang};
+
+
+/// Number of cues sampled, so huge files don't slow detection down.
+pub const SAMPLE_CUES: usize = 200;
+
+/// Detects the language of subtitle text, as an ISO 639-1 code.
+/// Returns `None` when whatlang isn't confident enough to tell.
+pub fn detect(lines: &[String]) -> Option<&'static str> {
+    whatlang::detect(&lines.join("\n"))
+        .filter(|info| info.is_reliable())
+        .map(|info| iso_639_1(info.lang()))
+}
+
+/// The ISO 639-1 code of a language, like most subtitle file names use,
+/// instead of the ISO 639-3 code whatlang names it by.
+fn iso_639_1(lang: Lang) -> &'static str {
+    match lang {
+        Lang::Epo => "eo", Lang::Eng => "en", Lang::Rus => "ru",
+        Lang::Cmn => "zh", Lang::Spa => "es", Lang::Por => "pt",
+        Lang::Ita => "it", Lang::Ben => "bn", Lang::Fra => "fr",
+        Lang::Deu => "de", Lang::Ukr => "uk", Lang::Kat => "ka",
+        Lang::Ara => "ar", Lang::Hin => "hi", Lang::Jpn => "ja",
+        Lang::Heb => "he", Lang::Yid => "yi", Lang::Pol => "pl",
+        Lang::Amh => "am", Lang::Jav => "jv", Lang::Kor => "ko",
+        Lang::Nob => "nb", Lang::Dan => "da", Lang::Swe => "sv",
+        Lang::Fin => "fi", Lang::Tur => "tr", Lang::Nld => "nl",
+        Lang::Hun => "hu", Lang::Ces => "cs", Lang::Ell => "el",
+        Lang::Bul => "bg", Lang::Bel => "be", Lang::Mar => "mr",
+        Lang::Kan => "kn", Lang::Ron => "ro", Lang::Slv => "sl",
+        Lang::Hrv => "hr", Lang::Srp => "sr", Lang::Mkd => "mk",
+        Lang::Lit => "lt", Lang::Lav => "lv", Lang::Est => "et",
+        Lang::Tam => "ta", Lang::Vie => "vi", Lang::Urd => "ur",
+        Lang::Tha => "th", Lang::Guj => "gu", Lang::Uzb => "uz",
+        Lang::Pan => "pa", Lang::Aze => "az", Lang::Ind => "id",
+        Lang::Tel => "te", Lang::Pes => "fa", Lang::Mal => "ml",
+        Lang::Ori => "or", Lang::Mya => "my", Lang::Nep => "ne",
+        Lang::Sin => "si", Lang::Khm => "km", Lang::Tuk => "tk",
+        Lang::Aka => "ak", Lang::Zul => "zu", Lang::Sna => "sn",
+        Lang::Afr => "af", Lang::Lat => "la", Lang::Slk => "sk",
+        Lang::Cat => "ca", Lang::Tgl => "tl", Lang::Hye => "hy",
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(|l| l.to_string()).collect()
+    }
+
+    #[test]
+    fn detects_languages_as_iso_639_1_codes() {
+        assert_eq!(detect(&lines("What are you doing here?\n\
+            I don't know, it's the only place I have.")), Some("en"));
+        assert_eq!(detect(&lines("Qu'est-ce que tu fais là ?\n\
+            Je ne sais pas, c'est la seule chose que j'ai.")), Some("fr"));
+        assert_eq!(detect(&lines("Was machst du hier?\n\
+            Ich weiß es nicht, das ist alles, was ich habe.")), Some("de"));
+        assert_eq!(detect(&lines("こんにちは、世界！")), Some("ja"));
+        assert_eq!(detect(&lines("你好，世界！")), Some("zh"));
+    }
+
+    #[test]
+    fn reports_too_little_evidence_as_unknown() {
+        assert_eq!(detect(&lines("OK.\nJohn!")), None);
+        assert_eq!(detect(&lines("Hola")), None);
+        assert_eq!(detect(&lines("♪ ♪")), None);
+    }
+}
@@ -0,0 +1,128 @@
+use std::env;
+use std::io;
+
+use atty;
+use terminal_size::{self, Width};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+
+/// Width of the reports when stdout isn't a terminal, like when it's
+/// redirected to a file, so they don't depend on the terminal they ran in.
+pub const FIXED_WIDTH: usize = 100;
+
+/// Width of terminals that don't tell theirs.
+const DEFAULT_WIDTH: usize = 80;
+
+/// Narrowest width reports are laid out for, however narrow the terminal.
+const MIN_WIDTH: usize = 40;
+
+/// The width to lay out reports for: that of the terminal, as given by
+/// `COLUMNS` or else by the terminal itself, or `FIXED_WIDTH` when stdout
+/// isn't a terminal.
+pub fn width() -> usize {
+    if !atty::is(atty::Stream::Stdout) {
+        return FIXED_WIDTH;
+    }
+    env::var("COLUMNS").ok()
+        .and_then(|columns| columns.parse().ok())
+        .or_else(terminal_width)
+        .unwrap_or(DEFAULT_WIDTH)
+        .max(MIN_WIDTH)
+}
+
+/// The width of the terminal stdout is, on unix and Windows alike.
+fn terminal_width() -> Option<usize> {
+    terminal_size::terminal_size_of(io::stdout())
+        .map(|(Width(width), _)| width as usize)
+        .filter(|&width| width > 0)
+}
+
+/// Lays out the columns of a table row side by side when they fit in
+/// `width`, or else stacked, each on a line of its own after `indent`.
+pub fn row(columns_: &[String], width: usize, indent: &str) -> String {
+    let line = format!("{}{}", indent, columns_.join("  "));
+    if columns(&line) <= width {
+        return line;
+    }
+    columns_.iter()
+        .map(|column| format!("{}{}", indent, column.trim_start()))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The columns the text takes up on screen.
+pub fn columns(text: &str) -> usize {
+    text.width()
+}
+
+/// Wraps the text at word boundaries into lines of at most `width`
+/// columns, each starting with `indent`. Words longer than a line are
+/// left whole rather than broken up.
+pub fn wrap(text: &str, width: usize, indent: &str) -> String {
+    let mut lines = Vec::new();
+    let mut line = String::from(indent);
+    for word in text.split_whitespace() {
+        let fits = columns(&line) + 1 + columns(word) <= width;
+        if line.len() > indent.len() && !fits {
+            lines.push(line);
+            line = String::from(indent);
+        }
+        if line.len() > indent.len() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    lines.push(line);
+    lines.join("\n")
+}
+
+/// Shortens the text to at most `width` columns, ending it with an
+/// ellipsis when anything was cut off.
+pub fn truncate(text: &str, width: usize) -> String {
+    if columns(text) <= width {
+        return text.to_string();
+    }
+    let mut truncated = String::new();
+    let mut used = 0;
+    for grapheme in text.graphemes(true) {
+        used += grapheme.width();
+        if used + 1 > width {
+            break;
+        }
+        truncated.push_str(grapheme);
+    }
+    truncated.push('…');
+    truncated
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wraps_at_word_boundaries() {
+        assert_eq!(wrap("Timestamps are shifted by +1.500 seconds.", 20, " "),
+            " Timestamps are\n shifted by +1.500\n seconds.");
+        assert_eq!(wrap("Short.", 20, " "), " Short.");
+        assert_eq!(wrap("a `--merge-continuations` b", 10, "  "),
+            "  a\n  `--merge-continuations`\n  b");
+    }
+
+    #[test]
+    fn stacks_rows_that_do_not_fit() {
+        let cells = [String::from("    1"), String::from("00:00:01.000"),
+            String::from("=>  deleted")];
+        assert_eq!(row(&cells, 40, "  "), "      1  00:00:01.000  =>  deleted");
+        assert_eq!(row(&cells, 30, "  "), "  1\n  00:00:01.000\n  =>  deleted");
+    }
+
+    #[test]
+    fn truncates_with_an_ellipsis() {
+        assert_eq!(truncate("Where are we going?", 30), "Where are we going?");
+        assert_eq!(truncate("Where are we going?", 10), "Where are…");
+        assert_eq!(columns(&truncate("こんにちは、世界！", 9)), 9);
+        assert_eq!(truncate("こんにちは、世界！", 9), "こんにち…");
+    }
+}
@@ -0,0 +1,93 @@
+//! Parsing and shifting srt, vtt and TTML subtitles, as the `submod` command
+//! does, for tools that would rather call it than run it.
+//!
+//! ```
+//! extern crate submod;
+//! use submod::Subtitles;
+//!
+//! let mut subtitles = Subtitles::parse_srt("1\n00:00:02,000 --> \
+//!     00:00:03,500\nWhere are we going?\n").unwrap();
+//! subtitles.shift(-1.5, None).unwrap();
+//! assert_eq!(subtitles.to_vtt_string(), "WEBVTT\n\n\
+//!     00:00:00.500 --> 00:00:02.000\nWhere are we going?\n");
+//! ```
+//!
+//! Files can be shifted as a whole, by the same code as the command, to
+//! where it would write them:
+//!
+//! ```no_run
+//! extern crate submod;
+//! use std::path::Path;
+//!
+//! let input = Path::new("movie.srt");
+//! let offset = submod::get_secs("-01:23.5").unwrap();
+//! let output = submod::output_path(input, offset, false);
+//! submod::transform(input, &output, offset, None).unwrap();
+//! ```
+//!
+//! The `submod` binary itself only calls `run`.
+#![deny(missing_docs)]
+
+extern crate regex;
+extern crate clap;
+extern crate atty;
+#[cfg(feature = "encodings")]
+extern crate encoding_rs;
+extern crate terminal_size;
+#[cfg(feature = "langdetect")]
+extern crate whatlang;
+#[cfg(feature = "notify")]
+extern crate notify_rust;
+#[cfg(feature = "http")]
+extern crate ureq;
+extern crate unicode_segmentation;
+extern crate unicode_width;
+#[macro_use]
+extern crate failure;
+
+#[macro_use]
+mod color;
+mod cli;
+mod command;
+mod submod;
+mod helpers;
+mod layout;
+mod selection;
+mod encoding;
+mod preset;
+mod chapters;
+mod bench;
+mod clean;
+mod lint;
+mod info;
+mod wizard;
+mod split;
+mod merge;
+mod style;
+mod template;
+mod verify;
+mod ass;
+mod subviewer;
+mod microdvd;
+mod sniff;
+mod status;
+mod manifest;
+#[cfg(feature = "notify")]
+mod notify;
+#[cfg(feature = "http")]
+mod http;
+#[cfg(feature = "langdetect")]
+mod lang;
+mod subtitles;
+mod cue;
+mod name;
+mod settings;
+mod text;
+mod ttml;
+#[cfg(test)]
+mod testing;
+
+pub use command::main as run;
+pub use settings::{Align, CueSettings, Edge, Line, LineValue, Position,
+    Vertical};
+pub use subtitles::{Cue, Error, Subtitles, get_secs, output_path, transform};
@@ -0,0 +1,257 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use clap::{App, Arg};
+use failure::Error;
+use regex::Regex;
+
+use cli;
+use color;
+use cue;
+use encoding;
+use helpers;
+
+
+/// A problem `submod lint` found with a subtitle.
+#[derive(Debug, PartialEq)]
+pub struct Problem {
+    /// Position of the subtitle in the file, starting at 1.
+    pub cue: usize,
+    /// Line the subtitle starts at, with its index or identifier.
+    pub line: usize,
+    pub kind: Kind,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Kind {
+    EndsBeforeStart,
+    /// Starts before the subtitle before it.
+    OutOfOrder,
+    /// Starts before the end of an earlier subtitle, given by position.
+    Overlaps(usize),
+    /// Follows the text of the subtitle before it without an empty line.
+    MissingSeparator,
+    Empty,
+    /// Lasts less than the minimum duration, given in seconds.
+    TooShort(f64),
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Kind::EndsBeforeStart => write!(f, "ends before it starts"),
+            Kind::OutOfOrder => write!(f, "starts before the subtitle \
+                before it"),
+            Kind::Overlaps(cue) => write!(f, "overlaps subtitle {}", cue),
+            Kind::MissingSeparator => write!(f, "has no empty line \
+                before it"),
+            Kind::Empty => write!(f, "has no text"),
+            Kind::TooShort(duration) => write!(f, "lasts only {:.3} s",
+                duration),
+        }
+    }
+}
+
+/// A subtitle as `lint` sees it: where it is, and what it's made of.
+struct Linted {
+    line: usize,
+    start: f64,
+    end: f64,
+    text: usize,
+    separated: bool,
+}
+
+/// Runs the `submod lint` command, which reports the problems of srt and
+/// vtt files without writing anything, and returns status 1 if any file
+/// has problems.
+pub fn main(args: &[String]) -> i32 {
+    let matches = App::new("submod lint")
+        .about("Report the problems of srt and vtt files, like overlapping \
+            or empty subtitles,\nwithout writing anything. Exits with \
+            status 1 if any were found.")
+        .arg(Arg::with_name("file")
+            .help("Subtitle files to check")
+            .required(true)
+            .multiple(true)
+            .index(1))
+        .arg(Arg::with_name("min_duration")
+            .help("Report subtitles shown for less than this many seconds")
+            .long("min-duration")
+            .value_name("seconds")
+            .takes_value(true)
+            .default_value("0.5")
+            .validator(helpers::is_non_negative))
+        .get_matches_from_safe(args);
+    let matches = match matches {
+        Ok(matches) => matches,
+        Err(error) => return cli::report(error),
+    };
+
+    // Validated by helpers::is_non_negative:
+    let min_duration = matches.value_of("min_duration").unwrap()
+        .parse().unwrap();
+    let mut failed = false;
+    for input in matches.values_of("file").unwrap() {
+        let path = Path::new(input);
+        let problems = match read(path).map(|text| lint(&text, min_duration)) {
+            Ok(problems) => problems,
+            Err(error) => {
+                helpers::report_error(error);
+                return 1;
+            },
+        };
+        if problems.is_empty() {
+            outln!("{}", color::title("Clean."));
+            outln!(" `{}` has no problems.", path.display());
+            continue;
+        }
+        failed = true;
+        outln!("{}", color::alert("Problems found."));
+        outln!(" `{}` has {} problem{}:", path.display(), problems.len(),
+            if problems.len() == 1 { "" } else { "s" });
+        for problem in &problems {
+            outln!("    line {}: subtitle {} {}", problem.line, problem.cue,
+                problem.kind);
+        }
+    }
+    if failed { 1 } else { 0 }
+}
+
+/// Reads and decodes an srt or vtt file.
+fn read(path: &Path) -> Result<String, Error> {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("srt") | Some("vtt") => {},
+        _ => return Err(format_err!("`{}` isn't an .srt or .vtt file, \
+            which are the only ones submod lints.", path.display())),
+    }
+    let input = fs::read(path).map_err(|error| format_err!("Could not \
+        read `{}`: {}", path.display(), error))?;
+    let (text, _) = encoding::decode(&input);
+    Ok(encoding::split_bom(&text).1.to_string())
+}
+
+/// Finds the problems of the subtitles of an srt or vtt file, in the order
+/// of the subtitles. Times are compared to the millisecond.
+pub fn lint(text: &str, min_duration: f64) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    let linted = subtitles(text);
+    // The subtitle that ends last of those before, to find overlaps:
+    let mut last_end_opt: Option<(usize, i64)> = None;
+    for (i, cue) in linted.iter().enumerate() {
+        let problem = |kind| Problem { cue: i + 1, line: cue.line, kind };
+        let (start, end) = (cue::to_millis(cue.start), cue::to_millis(cue.end));
+        if !cue.separated {
+            problems.push(problem(Kind::MissingSeparator));
+        }
+        if end < start {
+            problems.push(problem(Kind::EndsBeforeStart));
+        } else if end - start < cue::to_millis(min_duration) {
+            problems.push(problem(Kind::TooShort((end - start) as f64
+                / 1000.0)));
+        }
+        if i > 0 && start < cue::to_millis(linted[i - 1].start) {
+            problems.push(problem(Kind::OutOfOrder));
+        } else if let Some((previous, last_end)) = last_end_opt {
+            if start < last_end {
+                problems.push(problem(Kind::Overlaps(previous)));
+            }
+        }
+        if cue.text == 0 {
+            problems.push(problem(Kind::Empty));
+        }
+        if last_end_opt.is_none_or(|(_, last_end)| end > last_end) {
+            last_end_opt = Some((i + 1, end));
+        }
+    }
+    problems
+}
+
+/// Finds the subtitles of the text by their timing lines, leaving out
+/// the WEBVTT header and NOTE, STYLE and REGION blocks.
+fn subtitles(text: &str) -> Vec<Linted> {
+    let timing = Regex::new(cue::TIMING).unwrap();
+    let lines: Vec<&str> = text.lines().collect();
+    let mut linted: Vec<Linted> = Vec::new();
+    // Where the current block of lines started, and whether it has cues:
+    let mut block_opt: Option<(usize, bool)> = None;
+    let mut skipped = false;
+
+    for (i, line) in lines.iter().enumerate() {
+        if line.trim().is_empty() {
+            block_opt = None;
+            skipped = false;
+            continue;
+        }
+        let (first, has_cue) = *block_opt.get_or_insert((i, false));
+        if first == i && (line.starts_with("WEBVTT") || cue::is_note(line)
+            || ["STYLE", "REGION"].contains(&line.trim_end())) {
+            skipped = true;
+        }
+        if skipped {
+            continue;
+        }
+        if let Some(captures) = timing.captures(line) {
+            // A subtitle starts with its index or identifier, which is
+            // taken for the text of the one before without an empty line:
+            let start_line = if !has_cue {
+                first
+            } else if i > 0 && lines[i - 1].chars().all(|c| c.is_ascii_digit())
+                && !timing.is_match(lines[i - 1]) {
+                if let Some(previous) = linted.last_mut() {
+                    previous.text = previous.text.saturating_sub(1);
+                }
+                i - 1
+            } else {
+                i
+            };
+            linted.push(Linted { line: start_line + 1,
+                start: cue::get_secs(&captures[1]),
+                end: cue::get_secs(&captures[2]), text: 0,
+                separated: !has_cue });
+            block_opt = Some((first, true));
+        } else if has_cue {
+            if let Some(cue) = linted.last_mut() {
+                cue.text += 1;
+            }
+        }
+    }
+    linted
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_problems_by_subtitle_and_line() {
+        let srt = "1\n00:00:01,000 --> 00:00:03,000\nA\n\n\
+            2\n00:00:02,000 --> 00:00:04,000\nB\n\
+            3\n00:00:05,000 --> 00:00:04,500\nC\n\n\
+            4\n00:00:03,000 --> 00:00:03,200\n\n\
+            5\n00:00:06,000 --> 00:00:08,000\nD\n";
+        let kinds: Vec<(usize, usize, Kind)> = lint(srt, 0.5).into_iter()
+            .map(|problem| (problem.cue, problem.line, problem.kind))
+            .collect();
+        assert_eq!(kinds, [
+            (2, 5, Kind::Overlaps(1)),
+            (3, 8, Kind::MissingSeparator),
+            (3, 8, Kind::EndsBeforeStart),
+            (4, 12, Kind::TooShort(0.2)),
+            (4, 12, Kind::OutOfOrder),
+            (4, 12, Kind::Empty),
+        ]);
+    }
+
+    #[test]
+    fn skips_vtt_headers_and_blocks() {
+        let vtt = "WEBVTT\n\nNOTE 00:00:01.000 --> 00:00:02.000\n\n\
+            STYLE\n::cue { color: yellow }\n\n\
+            intro\n00:00:01.000 --> 00:00:02.000 align:start\nA\n\n\
+            00:00:02.000 --> 00:00:03.000\nB\n";
+        assert!(lint(vtt, 0.5).is_empty());
+        assert_eq!(lint(vtt, 2.0).iter().map(|problem| problem.line)
+            .collect::<Vec<_>>(), [8, 12]);
+    }
+}
@@ -0,0 +1,108 @@
+use std::fs;
+use std::io::{self, Read};
+
+use failure::Error;
+
+use helpers;
+use subtitles::Error as SubmodError;
+use submod;
+
+
+/// One job of a `--manifest`: an input, the seconds to shift it by,
+/// and optionally the output to write, like with `--out`.
+#[derive(Debug, PartialEq)]
+pub struct Job {
+    pub input: String,
+    pub seconds: f64,
+    pub output_opt: Option<String>,
+}
+
+/// Reads a manifest from a file, or from stdin for `-`.
+pub fn read(path: &str) -> Result<String, Error> {
+    let mut text = String::new();
+    if path == "-" {
+        io::stdin().read_to_string(&mut text)?;
+    } else {
+        text = fs::read_to_string(path).map_err(|error| SubmodError::Io {
+            path: path.into(), message: format!(
+                "Could not read the manifest `{}`: {}", path, error) })?;
+    }
+    Ok(text)
+}
+
+/// Parses the jobs of a manifest, one per line as
+/// `input<TAB>seconds[<TAB>output]`, along with their line number.
+/// Blank lines and `#` comments are skipped. A line that can't be parsed
+/// is an error for that job only, so the others can still run.
+pub fn parse(text: &str) -> Vec<(usize, Result<Job, Error>)> {
+    text.lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim_end_matches('\r')))
+        .filter(|&(_, line)| !line.trim().is_empty()
+            && !line.trim_start().starts_with('#'))
+        .map(|(number, line)| (number, parse_job(line)))
+        .collect()
+}
+
+fn parse_job(line: &str) -> Result<Job, Error> {
+    let fields: Vec<&str> = line.split('\t').collect();
+    if fields.len() < 2 || fields.len() > 3 {
+        return Err(format_err!("Expected `input<TAB>seconds` or \
+            `input<TAB>seconds<TAB>output`, but found {} field{}",
+            fields.len(), if fields.len() == 1 { "" } else { "s" }));
+    }
+    helpers::is_input(fields[0].to_string()).map_err(|error|
+        format_err!("Invalid input `{}`: {}", fields[0], error))?;
+    helpers::is_offset(fields[1].to_string()).map_err(|error|
+        format_err!("Invalid seconds `{}`: {}", fields[1], error))?;
+    let output_opt = match fields.get(2) {
+        Some(output) if !output.is_empty() => {
+            helpers::is_subtitle(output.to_string()).map_err(|error|
+                format_err!("Invalid output `{}`: {}", output, error))?;
+            Some(output.to_string())
+        },
+        _ => None,
+    };
+    Ok(Job {
+        input: fields[0].to_string(),
+        seconds: submod::get_secs(fields[1]),
+        output_opt,
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_jobs_with_their_line_numbers() {
+        let jobs = parse("# episode\tseconds\n\
+            ep01.srt\t1.5\n\
+            \n\
+            ep02.vtt\t-01:02\tshifted.vtt\r\n");
+        assert_eq!(jobs.len(), 2);
+        assert_eq!(jobs[0].0, 2);
+        assert_eq!(jobs[0].1.as_ref().unwrap(), &Job {
+            input: String::from("ep01.srt"), seconds: 1.5, output_opt: None });
+        assert_eq!(jobs[1].0, 4);
+        assert_eq!(jobs[1].1.as_ref().unwrap(), &Job {
+            input: String::from("ep02.vtt"), seconds: -62.0,
+            output_opt: Some(String::from("shifted.vtt")) });
+    }
+
+    #[test]
+    fn keeps_going_after_invalid_lines() {
+        let jobs = parse("ep01.srt 1.5\n\
+            ep02.mkv\t1\n\
+            ep03.srt\tsoon\n\
+            ep04.srt\t1\tout.txt\n\
+            ep05.srt\t2\n");
+        let errors: Vec<usize> = jobs.iter()
+            .filter(|&(_, job)| job.is_err())
+            .map(|&(line, _)| line)
+            .collect();
+        assert_eq!(errors, [1, 2, 3, 4]);
+        assert!(jobs[4].1.is_ok());
+    }
+}
@@ -0,0 +1,175 @@
+use regex::Regex;
+
+use cue::Cue;
+use text;
+
+
+/// When `--merge-continuations` joins two cues: the longest gap between
+/// them in seconds, and the line length and reading speed the joined
+/// cue may not exceed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MergeLimits {
+    pub max_gap: f64,
+    /// Longest line, in visible characters.
+    pub max_line: usize,
+    /// Highest number of visible characters per second.
+    pub max_cps: f64,
+    /// Count wide characters like CJK ideographs as 2 in the length of
+    /// lines, for the width they take up on screen.
+    pub wide_chars: bool,
+}
+
+impl Default for MergeLimits {
+    fn default() -> MergeLimits {
+        MergeLimits { max_gap: 0.1, max_line: 42, max_cps: 20.0,
+            wide_chars: false }
+    }
+}
+
+/// Joins a cue with the next one when it continues its sentence, like
+/// auto-captions often split them: the first doesn't end a sentence, the
+/// gap between them is short, the same speaker is speaking, and the joined
+/// cue fits in two lines within the limits. The joined cue spans from
+/// the start of the first to the end of the second, and keeps the
+/// number and identifier of the first.
+pub fn merge(first: &Cue, second: &Cue, limits: &MergeLimits) -> Option<Cue> {
+    let gap = second.start - first.end;
+    if !(0.0..=limits.max_gap).contains(&gap) || ends_sentence(&first.text)
+        || is_speaker_change(&first.text, &second.text) {
+        return None;
+    }
+
+    let lines = join_lines(&first.text, &second.text, limits)?;
+    let chars: usize = lines.iter().map(|line| text::visible_len(line)).sum();
+    let duration = second.end - first.start;
+    if duration <= 0.0 || chars as f64 / duration > limits.max_cps {
+        return None;
+    }
+    Some(Cue { end: second.end, text: lines, ..first.clone() })
+}
+
+/// Combines the lines of both cues into at most two lines of at most
+/// `limits.max_line` characters: as they are if that fits, or else with the
+/// lines of each cue joined into one.
+fn join_lines(first: &[String], second: &[String], limits: &MergeLimits)
+    -> Option<Vec<String>>
+{
+    let fits = |lines: &[String]| lines.len() <= 2
+        && lines.iter().all(|line| text::line_len(line, limits.wide_chars)
+            <= limits.max_line);
+    let lines: Vec<String> = first.iter().chain(second).cloned().collect();
+    if fits(&lines) {
+        return Some(lines);
+    }
+    let lines = vec![first.join(" "), second.join(" ")];
+    if fits(&lines) {
+        return Some(lines);
+    }
+    None
+}
+
+/// Whether the visible text ends with terminal punctuation, possibly
+/// followed by closing quotes or brackets.
+fn ends_sentence(lines: &[String]) -> bool {
+    let last = lines.iter().rev()
+        .map(|line| text::visible_text(line))
+        .find(|line| !line.trim().is_empty())
+        .unwrap_or_default();
+    last.trim_end()
+        .trim_end_matches(|c| "\"'”’)]»".contains(c))
+        .ends_with(|c| ".!?…".contains(c))
+}
+
+/// Whether another speaker starts speaking in the second cue, shown by a
+/// dialogue dash starting it, or by a different or missing voice tag.
+fn is_speaker_change(first: &[String], second: &[String]) -> bool {
+    let dash = second.first().is_some_and(|line| {
+        let line = text::visible_text(line);
+        let line = line.trim_start();
+        line.starts_with('-') || line.starts_with('–') || line.starts_with('—')
+    });
+    dash || voice(first) != voice(second)
+}
+
+/// The speaker of the first vtt voice tag, like `Fred` for `<v.loud Fred>`.
+fn voice(lines: &[String]) -> Option<String> {
+    let tag = Regex::new(r"<v(?:\.[^\s>]*)?\s+([^>]+)>").unwrap();
+    lines.iter()
+        .filter_map(|line| tag.captures(line))
+        .map(|captures| captures[1].trim().to_string())
+        .next()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cue(start: f64, end: f64, text: &[&str]) -> Cue {
+        Cue { number: 1, line: 2, id: vec![String::from("1")], start, end,
+            settings: String::new(),
+            text: text.iter().map(|line| line.to_string()).collect() }
+    }
+
+    #[test]
+    fn merges_sentences_split_across_cues() {
+        let merged = merge(&cue(1.0, 2.5, &["I was going to"]),
+            &cue(2.5, 4.0, &["the store yesterday."]),
+            &MergeLimits::default()).unwrap();
+        assert_eq!((merged.start, merged.end), (1.0, 4.0));
+        assert_eq!(merged.text, ["I was going to", "the store yesterday."]);
+        assert_eq!(merged.id, ["1"]);
+    }
+
+    #[test]
+    fn joins_lines_to_fit_in_two() {
+        let merged = merge(&cue(1.0, 3.0, &["I was", "going to"]),
+            &cue(3.0, 5.0, &["the store"]), &MergeLimits::default()).unwrap();
+        assert_eq!(merged.text, ["I was going to", "the store"]);
+        let long = "a line that is really quite long in itself";
+        assert!(merge(&cue(1.0, 5.0, &[long, "and"]),
+            &cue(5.0, 9.0, &["more"]), &MergeLimits::default()).is_none());
+        // 21 ideographs are as wide as 42 characters, leaving no room
+        // once they count as 2:
+        let wide = "私は昨日お店に行くつもりでしたがやめました";
+        assert!(merge(&cue(1.0, 5.0, &[wide, "と"]),
+            &cue(5.0, 9.0, &["言った"]), &MergeLimits::default()).is_some());
+        assert!(merge(&cue(1.0, 5.0, &[wide, "と"]),
+            &cue(5.0, 9.0, &["言った"]), &MergeLimits { wide_chars: true,
+                ..MergeLimits::default() }).is_none());
+    }
+
+    #[test]
+    fn keeps_finished_sentences_apart() {
+        let limits = MergeLimits::default();
+        for text in &["Done.", "Really?!", "<i>He said \"go.\"</i>", "Wait…"] {
+            assert!(merge(&cue(1.0, 2.0, &[text]), &cue(2.0, 3.0, &["next"]),
+                &limits).is_none());
+        }
+    }
+
+    #[test]
+    fn respects_the_gap_and_reading_speed() {
+        let limits = MergeLimits::default();
+        assert!(merge(&cue(1.0, 2.0, &["and"]), &cue(2.2, 3.0, &["then"]),
+            &limits).is_none());
+        assert!(merge(&cue(1.0, 2.0, &["and"]), &cue(2.05, 3.0, &["then"]),
+            &limits).is_some());
+        // 39 characters in one second:
+        assert!(merge(&cue(1.0, 1.5, &["twenty characters an"]),
+            &cue(1.5, 2.0, &["d twenty more of it"]), &limits).is_none());
+    }
+
+    #[test]
+    fn never_merges_across_speakers() {
+        let limits = MergeLimits::default();
+        assert!(merge(&cue(1.0, 2.0, &["- Are you"]),
+            &cue(2.0, 3.0, &["- No"]), &limits).is_none());
+        assert!(merge(&cue(1.0, 2.0, &["<v Fred>Are you"]),
+            &cue(2.0, 3.0, &["<v Bea>coming"]), &limits).is_none());
+        assert!(merge(&cue(1.0, 2.0, &["<v Fred>Are you"]),
+            &cue(2.0, 3.0, &["coming"]), &limits).is_none());
+        assert!(merge(&cue(1.0, 2.0, &["<v Fred>Are you"]),
+            &cue(2.0, 3.0, &["<v.loud Fred>coming"]), &limits).is_some());
+    }
+}
@@ -0,0 +1,167 @@
+use failure::Error;
+use regex::{Captures, Regex};
+
+use cue;
+use subtitles::Error as SubmodError;
+
+
+/// How long a subtitle without an end frame, like `{150}{}Bye`, lasts when
+/// converted, unless the next one starts sooner.
+const OPEN_DURATION: f64 = 3.0;
+
+/// Shifts the frames of a MicroDVD file, leaving the text as it is. `shift`
+/// gets the start and end in seconds, at `fps` frames per second, and
+/// returns the new ones, which are rounded to the nearest frame, or `None`
+/// to remove the subtitle's line. A missing end frame stays missing, and a
+/// `{1}{1}25.000` line giving the frame rate of the file isn't a subtitle,
+/// so it's never shifted.
+pub fn shift<F>(contents: &str, fps: f64, mut shift: F) -> String
+    where F: FnMut(f64, f64) -> Option<(f64, f64)>
+{
+    let frames = frames_regex();
+    // Splitting on \n keeps the \r of \r\n line endings in the text:
+    contents.split('\n')
+        .filter_map(|line| match frames.captures(line) {
+            Some(ref captures) if !is_frame_rate(captures) => {
+                let start = to_secs(&captures[1], fps);
+                let end_opt = captures.get(2).map(|end| to_secs(end.as_str(),
+                    fps));
+                let (start, end) = shift(start, end_opt.unwrap_or(start))?;
+                Some(format!("{{{}}}{{{}}}{}", to_frame(start, fps),
+                    end_opt.map_or(String::new(), |_|
+                        to_frame(end, fps).to_string()),
+                    &captures[3]))
+            },
+            _ => Some(line.to_string()),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Converts MicroDVD subtitles to srt at `fps` frames per second, for the
+/// cues to be modified and written like those of any srt file: `|` becomes
+/// a line break, italics given by `{y:i}` for a line or `{Y:i}` for all
+/// of them become `<i>` tags, and other control codes are left out.
+pub fn to_srt(contents: &str, fps: f64) -> Result<String, Error> {
+    let frames = frames_regex();
+    let mut subtitles: Vec<(f64, Option<f64>, String)> = Vec::new();
+    for line in contents.lines() {
+        match frames.captures(line) {
+            Some(ref captures) if !is_frame_rate(captures) =>
+                subtitles.push((to_secs(&captures[1], fps), captures.get(2)
+                    .map(|end| to_secs(end.as_str(), fps)),
+                    to_text(captures[3].trim_end()))),
+            _ => {},
+        }
+    }
+    if subtitles.is_empty() {
+        return Err(SubmodError::Parse { line: None,
+            message: "No MicroDVD subtitles found, with frames like \
+                {25}{100}".into() }.into());
+    }
+    let mut srt = String::new();
+    for (i, &(start, end_opt, ref text)) in subtitles.iter().enumerate() {
+        let end = end_opt.unwrap_or_else(|| {
+            let open = start + OPEN_DURATION;
+            subtitles.get(i + 1).map_or(open, |next| next.0.min(open))
+        });
+        srt.push_str(&format!("{}{}\n{} --> {}\n{}\n",
+            if i > 0 { "\n" } else { "" }, i + 1,
+            cue::format_time(start, true), cue::format_time(end, true), text));
+    }
+    Ok(srt)
+}
+
+/// The frame rate a `{1}{1}23.976` line before the subtitles gives,
+/// if the file has one.
+pub fn header_fps(contents: &str) -> Option<f64> {
+    let frames = frames_regex();
+    let captures = contents.lines()
+        .find_map(|line| frames.captures(line.trim_end()))?;
+    if is_frame_rate(&captures) {
+        captures[3].trim().parse().ok().filter(|&fps: &f64| fps > 0.0)
+    } else {
+        None
+    }
+}
+
+fn frames_regex() -> Regex {
+    Regex::new(r"^\{(\d+)\}\{(\d+)?\}(.*)$").unwrap()
+}
+
+/// Whether the line is a `{1}{1}23.976` line, which gives the frame rate
+/// of the file rather than a subtitle.
+fn is_frame_rate(captures: &Captures) -> bool {
+    &captures[1] == "1" && captures.get(2).is_some_and(|end|
+        end.as_str() == "1") && captures[3].trim().parse::<f64>().is_ok()
+}
+
+/// The text of a subtitle, with a line break for every `|`.
+fn to_text(text: &str) -> String {
+    let codes = Regex::new(r"\{[A-Za-z]:[^}]*\}").unwrap();
+    let all_italic = text.starts_with("{Y:i}");
+    text.split('|')
+        .map(|line| {
+            let italic = all_italic || line.contains("{y:i}");
+            let line = codes.replace_all(line, "");
+            if italic { format!("<i>{}</i>", line) } else { line.into_owned() }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn to_secs(frame: &str, fps: f64) -> f64 {
+    // Frames are digits only, as matched:
+    frame.parse::<f64>().unwrap() / fps
+}
+
+/// The frame shown at `seconds`, rounded to the nearest one; times before
+/// the start are moved to it.
+fn to_frame(seconds: f64, fps: f64) -> u64 {
+    (seconds.max(0.0) * fps).round() as u64
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MICRODVD: &str = "{1}{1}25.000\r\n{25}{100}Hello|world!\r\n\
+        {150}{}{Y:i}Bye|for now\r\n";
+
+    #[test]
+    fn shifts_frames_only() {
+        assert_eq!(shift(MICRODVD, 25.0, |start, end| Some((start + 1.0,
+            end + 1.0))), "{1}{1}25.000\r\n{50}{125}Hello|world!\r\n\
+            {175}{}{Y:i}Bye|for now\r\n");
+        // Frames before the start are moved to it:
+        assert_eq!(shift("{10}{50}Hi", 23.976, |start, end| Some((start - 1.0,
+            end - 1.0))), "{0}{26}Hi");
+    }
+
+    #[test]
+    fn removes_the_lines_of_removed_subtitles() {
+        assert_eq!(shift(MICRODVD, 25.0, |start, end| if end < 5.0 { None }
+            else { Some((start, end)) }),
+            "{1}{1}25.000\r\n{150}{}{Y:i}Bye|for now\r\n");
+    }
+
+    #[test]
+    fn reads_the_frame_rate_line() {
+        assert_eq!(header_fps(MICRODVD), Some(25.0));
+        assert_eq!(header_fps("{0}{0}\n{1}{1}23.976 \r\n{25}{50}A\n"), None);
+        assert_eq!(header_fps("{25}{50}A\n{1}{1}25\n"), None);
+        assert_eq!(header_fps("{1}{1}0\n{25}{50}A\n"), None);
+    }
+
+    #[test]
+    fn converts_to_srt_with_line_breaks_and_italics() {
+        assert_eq!(to_srt(MICRODVD, 25.0).unwrap(),
+            "1\n00:00:01,000 --> 00:00:04,000\nHello\nworld!\n\n\
+            2\n00:00:06,000 --> 00:00:09,000\n<i>Bye</i>\n<i>for now</i>\n");
+        assert_eq!(to_srt("{0}{}A\n{25}{50}{y:i}B|{c:$0000FF}C\n", 25.0)
+            .unwrap(), "1\n00:00:00,000 --> 00:00:01,000\nA\n\n\
+            2\n00:00:01,000 --> 00:00:02,000\n<i>B</i>\nC\n");
+        assert!(to_srt("{1}{1}25\n", 25.0).is_err());
+    }
+}
@@ -0,0 +1,112 @@
+use regex::Regex;
+
+
+/// Matches the tags submod adds to the stem of its output files,
+/// like `__[+1.50_Sec+]`, including several stacked by older versions.
+/// Tags written with `--precision 0` have no decimals. Scaled timestamps
+/// are tagged with their factor first, like `__[x0.95904]__[+1.50_Sec+]`.
+pub const SHIFT_TAGS: &str =
+    r"(?:__\[x\d+(?:\.\d+)?\])?(?:__\[[+-]\d+(?:\.\d+)?_Sec[+-]\])+";
+
+/// Splits a stem into the part before its tags, and the factor and the sum
+/// of the seconds of those tags, if it has any. The `__[Original]` marker
+/// of `--overname` is dropped as well.
+pub fn split_tag(mut stem: &str) -> (&str, Option<(f64, f64)>) {
+    if let Some(i) = stem.find("__[Original]") {
+        stem = &stem[..i];
+    }
+    // The tags must end the stem, so lookalikes inside the title are left
+    // alone. Older versions could stack several tags, which are all merged:
+    let tags = Regex::new(&format!("{}$", SHIFT_TAGS)).unwrap();
+    let found = match tags.find(stem) {
+        Some(found) => found,
+        None => return (stem, None),
+    };
+    let increment = Regex::new(r"\[([+-]\d+(?:\.\d+)?)_Sec").unwrap()
+        .captures_iter(found.as_str())
+        .map(|number| number[1].parse::<f64>().unwrap())
+        .sum();
+    let factor = Regex::new(r"\[x(\d+(?:\.\d+)?)\]").unwrap()
+        .captures(found.as_str())
+        .map_or(1.0, |factor| factor[1].parse::<f64>().unwrap());
+    (&stem[..found.start()], Some((factor, increment)))
+}
+
+/// The language suffix of a stem, like the `en` of `movie.en` or of
+/// `movie.en__[+1.50_Sec+]`: two or three lowercase letters after its
+/// last dot.
+pub fn language_suffix(stem: &str) -> Option<&str> {
+    let (stem, _) = split_tag(stem);
+    let suffix = &stem[stem.rfind('.')? + 1..];
+    if (2..=3).contains(&suffix.len())
+        && suffix.chars().all(|c| c.is_ascii_lowercase()) {
+        Some(suffix)
+    } else {
+        None
+    }
+}
+
+/// Formats the seconds of a tag with their sign, rounded to `precision`
+/// decimals, like `+1.50`. Seconds that round to zero are `+`.
+pub fn format_increment(seconds: f64, precision: usize) -> String {
+    let formatted = format!("{:.*}", precision, seconds.abs());
+    if seconds < 0.0 && formatted.contains(|c| ('1'..='9').contains(&c)) {
+        format!("-{}", formatted)
+    } else {
+        format!("+{}", formatted)
+    }
+}
+
+/// Formats the factor of a tag with up to 5 decimals, like `__[x0.95904]`,
+/// or nothing if it doesn't scale at all.
+pub fn format_factor(factor: f64) -> String {
+    let factor = format!("{:.5}", factor);
+    let factor = factor.trim_end_matches('0').trim_end_matches('.');
+    if factor == "1" {
+        String::new()
+    } else {
+        format!("__[x{}]", factor)
+    }
+}
+
+/// The file name of an output, with its tag between the stem and
+/// the extension.
+pub fn build_output_name(stem: &str, tag: &str, extension: &str) -> String {
+    format!("{}{}.{}", stem, tag, extension)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_tags_off_the_stem() {
+        assert_eq!(split_tag("movie"), ("movie", None));
+        assert_eq!(split_tag("movie__[-1.25_Sec-]"),
+            ("movie", Some((1.0, -1.25))));
+        assert_eq!(split_tag("movie__[+1.00_Sec+]__[+2.5_Sec+]__[Original]"),
+            ("movie", Some((1.0, 3.5))));
+        assert_eq!(split_tag("movie__[+3_Sec+]"), ("movie", Some((1.0, 3.0))));
+        assert_eq!(split_tag("movie__[x0.95904]__[+1.00_Sec+]"),
+            ("movie", Some((0.95904, 1.0))));
+        assert_eq!(split_tag("movie__[+1.00_Sec+] 2"),
+            ("movie__[+1.00_Sec+] 2", None));
+    }
+
+    #[test]
+    fn finds_language_suffixes() {
+        assert_eq!(language_suffix("movie.en"), Some("en"));
+        assert_eq!(language_suffix("movie.fr__[+1.50_Sec+]"), Some("fr"));
+        assert_eq!(language_suffix("movie"), None);
+        assert_eq!(language_suffix("Mr. Robot"), None);
+        assert_eq!(language_suffix("movie.1080p"), None);
+    }
+
+    #[test]
+    fn builds_output_names() {
+        assert_eq!(build_output_name("movie", "__[+1.00_Sec+]", "vtt"),
+            "movie__[+1.00_Sec+].vtt");
+        assert_eq!(build_output_name("movie", "", "srt"), "movie.srt");
+    }
+}
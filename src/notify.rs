@@ -0,0 +1,38 @@
+use notify_rust::Notification;
+#[cfg(all(unix, not(target_os = "macos")))]
+use notify_rust::Urgency;
+
+
+/// Shows a desktop notification with the number of files that succeeded
+/// and failed. Notifications are a convenience: when no notification
+/// service is available, this silently does nothing.
+pub fn send(succeeded: usize, failed: usize) {
+    let files = |n| if n == 1 { "file" } else { "files" };
+    let (title, body) = if failed == 0 {
+        ("submod: done",
+            format!("{} {} processed successfully.", succeeded, files(succeeded)))
+    } else {
+        ("submod: failed",
+            format!("{} of {} {} failed.", failed, succeeded + failed,
+                files(succeeded + failed)))
+    };
+    let mut notification = Notification::new();
+    notification.appname("submod").summary(title).body(&body);
+    if failed > 0 {
+        urgent(&mut notification);
+    }
+    let _ = notification.show();
+}
+
+#[cfg(target_os = "macos")]
+fn urgent(notification: &mut Notification) {
+    notification.sound_name("Basso");
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn urgent(notification: &mut Notification) {
+    notification.urgency(Urgency::Critical);
+}
+
+#[cfg(windows)]
+fn urgent(_notification: &mut Notification) {}
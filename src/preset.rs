@@ -0,0 +1,214 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use failure::Error;
+
+use cli;
+use color;
+
+
+/// The built-in presets, and the options they expand to. Both normalize
+/// whitespace, fix overlaps by shortening, and flag subtitles of more than
+/// 2 lines, but each with the limits of its own house style.
+pub const BUILT_IN: &[(&str, &str)] = &[
+    // Netflix timed text style guide: gaps of 2 frames at 25 fps, at least
+    // 0.8 seconds per subtitle, and 2 lines of 42 characters:
+    ("netflix", "--normalize --fix-overlaps --min-gap 0.08 --min-duration 0.8 \
+        --max-line 42 --max-lines 2"),
+    // EBU subtitling guidelines for broadcast delivery, at the 25 fps of
+    // PAL: gaps of 2 frames, subtitles of at least 20 frames, and 2 lines
+    // of 42 characters:
+    ("ebu", "--normalize --fix-overlaps --min-gap 0.08 --min-duration 0.8 \
+        --max-line 42 --max-lines 2"),
+];
+
+/// Expands any `--preset <name>` in the command line arguments into the
+/// options of that preset. Preset options are inserted right after the
+/// program name, and are skipped when the same option was given explicitly,
+/// so explicit options always override the preset.
+pub fn expand(args: Vec<String>) -> Result<Vec<String>, Error> {
+    expand_with(args, config_path())
+}
+
+/// Like `expand`, with the custom presets of the config file at
+/// `config_opt`, if there is one.
+fn expand_with(args: Vec<String>, config_opt: Option<PathBuf>)
+    -> Result<Vec<String>, Error>
+{
+    let name = match preset_name(&args) {
+        Some(name) => name,
+        None => return Ok(args),
+    };
+    let preset_args = match lookup(&name, config_opt.as_ref())? {
+        Some(preset) => split_args(&preset),
+        None => return Err(format_err!("Invalid value for \
+            '{}': unknown preset `{}`\n\n\
+            Built-in presets are {}; \
+            custom presets can be added to `{}`.", color::arg("--preset"),
+            name, color::hint(BUILT_IN.iter().map(|p| p.0)
+                .collect::<Vec<_>>().join(", ")),
+            config_opt.map_or(String::from("the config file"),
+                |path| path.display().to_string()))),
+    };
+    let given = given_options(&args);
+
+    let mut expanded = vec![args[0].clone()];
+    let mut i = 0;
+    while i < preset_args.len() {
+        // Keep option values together with their option:
+        let mut end = i + 1;
+        while end < preset_args.len() && !preset_args[end].starts_with("--") {
+            end += 1;
+        }
+        let option = preset_args[i].split('=').next().unwrap();
+        if !given.contains(&option) {
+            expanded.extend_from_slice(&preset_args[i..end]);
+        }
+        i = end;
+    }
+    expanded.extend_from_slice(&args[1..]);
+    Ok(expanded)
+}
+
+/// The long options given in the arguments, with those given by their
+/// short form, like `-s`, or in a group of short flags, like `-qo`, as
+/// their long form.
+fn given_options(args: &[String]) -> Vec<&str> {
+    let mut given = Vec::new();
+    for arg in args {
+        if arg.starts_with("--") {
+            given.push(arg.split('=').next().unwrap());
+        } else if let Some(shorts) = arg.strip_prefix('-') {
+            // Anything after a short option that isn't one is its value,
+            // like the `10` of `-s10`, or negative seconds:
+            given.extend(shorts.chars()
+                .map_while(|c| cli::SHORT_OPTIONS.iter()
+                    .find(|&&(short, _)| short == c)
+                    .map(|&(_, long)| long)));
+        }
+    }
+    given
+}
+
+fn preset_name(args: &[String]) -> Option<String> {
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == "--preset" {
+            return iter.next().cloned();
+        }
+        if let Some(name) = arg.strip_prefix("--preset=") {
+            return Some(name.to_string());
+        }
+    }
+    None
+}
+
+/// Finds a preset by name; custom presets of the config file at
+/// `config_opt` take precedence over built-in ones.
+fn lookup(name: &str, config_opt: Option<&PathBuf>)
+    -> Result<Option<String>, Error>
+{
+    if let Some(path) = config_opt {
+        if path.exists() {
+            let contents = fs::read_to_string(path)?;
+            for line in contents.lines().map(str::trim) {
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+                let (key, value) = match line.find('=') {
+                    Some(i) => (line[..i].trim(), line[i + 1..].trim()),
+                    None => return Err(format_err!("Invalid preset in `{}`: \
+                        `{}`\n\nDefine presets like so:\n    \
+                        {}", path.display(), line,
+                        color::hint("name = --option value --flag"))),
+                };
+                if key == name {
+                    return Ok(Some(value.to_string()));
+                }
+            }
+        }
+    }
+    Ok(BUILT_IN.iter()
+        .find(|preset| preset.0 == name)
+        .map(|preset| preset.1.to_string()))
+}
+
+/// The config file holding custom presets, one `name = options` per line:
+/// `$SUBMOD_CONFIG`, or `submod/presets` in the user's config directory.
+pub fn config_path() -> Option<PathBuf> {
+    if let Some(path) = env::var_os("SUBMOD_CONFIG") {
+        return Some(PathBuf::from(path));
+    }
+    let config_dir = env::var_os("XDG_CONFIG_HOME").map(PathBuf::from)
+        .or_else(|| env::var_os("APPDATA").map(PathBuf::from))
+        .or_else(|| env::var_os("HOME")
+            .map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_dir.join("submod").join("presets"))
+}
+
+/// Splits the options of a preset on whitespace,
+/// except inside double quotes.
+fn split_args(options: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut arg = String::new();
+    let mut quoted = false;
+    for c in options.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !arg.is_empty() {
+                    args.push(arg.split_off(0));
+                }
+            },
+            c => arg.push(c),
+        }
+    }
+    if !arg.is_empty() {
+        args.push(arg);
+    }
+    args
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use testing;
+
+    fn args(args: &str) -> Vec<String> {
+        args.split(' ').map(str::to_string).collect()
+    }
+
+    #[test]
+    fn expands_built_in_presets_under_explicit_options() {
+        assert_eq!(expand_with(args("submod f.srt 1 --preset netflix"), None)
+            .unwrap(), args("submod --normalize --fix-overlaps --min-gap 0.08 \
+            --min-duration 0.8 --max-line 42 --max-lines 2 \
+            f.srt 1 --preset netflix"));
+        assert_eq!(expand_with(args("submod f.srt 1 --preset=ebu \
+            --min-gap=0.1"), None).unwrap(), args("submod --normalize \
+            --fix-overlaps --min-duration 0.8 --max-line 42 --max-lines 2 \
+            f.srt 1 --preset=ebu --min-gap=0.1"));
+        assert!(expand_with(args("submod f.srt 1 --preset bbc"), None)
+            .is_err());
+    }
+
+    #[test]
+    fn lets_short_options_override_custom_presets() {
+        let dir = testing::temp_dir("presets");
+        let path = dir.join("presets");
+        fs::write(&path, "# Skip the intro:\nintro = --start 90 --fix-overlaps")
+            .unwrap();
+        assert_eq!(expand_with(args("submod f.srt 1 -s 10 --preset intro"),
+            Some(path.clone())).unwrap(),
+            args("submod --fix-overlaps f.srt 1 -s 10 --preset intro"));
+        assert_eq!(expand_with(args("submod f.srt -5 -qs10 --preset intro"),
+            Some(path.clone())).unwrap(),
+            args("submod --fix-overlaps f.srt -5 -qs10 --preset intro"));
+        assert_eq!(expand_with(args("submod f.srt -5 --preset intro"),
+            Some(path.clone())).unwrap(),
+            args("submod --start 90 --fix-overlaps f.srt -5 --preset intro"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,203 @@
+use std::fs;
+
+use failure::Error;
+
+
+/// An inclusive range of 1-based cue numbers; `last == None` means
+/// the range is open-ended and runs until the end of the file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CueRange {
+    pub first: usize,
+    pub last: Option<usize>,
+}
+
+impl CueRange {
+    pub fn contains(&self, index: usize) -> bool {
+        index >= self.first && self.last.is_none_or(|last| index <= last)
+    }
+}
+
+/// The cue numbers selected with `--include-cues` and `--exclude-cues`,
+/// and `--from-cue` and `--to-cue`. A cue is selected when it is included
+/// (or no include list was given), within the range, and not excluded.
+#[derive(Debug, Default)]
+pub struct CueSelection {
+    include: Option<Vec<CueRange>>,
+    exclude: Vec<CueRange>,
+    range: Option<CueRange>,
+}
+
+impl CueSelection {
+    pub fn new(include_opt: Option<&str>, exclude_opt: Option<&str>)
+        -> Result<CueSelection, Error>
+    {
+        let mut selection = CueSelection::default();
+        if let Some(spec) = include_opt {
+            selection.include = Some(read_ranges(spec)?);
+        }
+        if let Some(spec) = exclude_opt {
+            selection.exclude = read_ranges(spec)?;
+        }
+        Ok(selection)
+    }
+
+    /// Restricts the selection to the cues from `first_opt` to `last_opt`,
+    /// which are 1-based and inclusive; either may be left out.
+    pub fn with_range(mut self, first_opt: Option<usize>,
+        last_opt: Option<usize>) -> Result<CueSelection, Error>
+    {
+        if first_opt.is_none() && last_opt.is_none() {
+            return Ok(self);
+        }
+        let first = first_opt.unwrap_or(1);
+        if last_opt.is_some_and(|last| last < first) {
+            return Err(format_err!("--to-cue {} comes before --from-cue {}",
+                last_opt.unwrap(), first));
+        }
+        self.range = Some(CueRange { first, last: last_opt });
+        Ok(self)
+    }
+
+    /// True if the include or exclude lists or the range restrict
+    /// the modification.
+    pub fn is_partial(&self) -> bool {
+        self.has_lists() || self.range.is_some()
+    }
+
+    /// True if `--include-cues` or `--exclude-cues` were given.
+    pub fn has_lists(&self) -> bool {
+        self.include.is_some() || !self.exclude.is_empty()
+    }
+
+    /// The range of `--from-cue` and `--to-cue`, if any.
+    pub fn range(&self) -> Option<CueRange> {
+        self.range
+    }
+
+    pub fn contains(&self, index: usize) -> bool {
+        if self.range.is_some_and(|range| !range.contains(index)) {
+            return false;
+        }
+        if let Some(ref include) = self.include {
+            if !include.iter().any(|range| range.contains(index)) {
+                return false;
+            }
+        }
+        !self.exclude.iter().any(|range| range.contains(index))
+    }
+}
+
+/// Reads the ranges from `spec` directly, or from the file it points to
+/// when it starts with '@'.
+fn read_ranges(spec: &str) -> Result<Vec<CueRange>, Error> {
+    if let Some(path) = spec.strip_prefix('@') {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format_err!("Cannot read cue list `{}`: {}", path, e))?;
+        let mut ranges = Vec::new();
+        for (i, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            // Skip blank lines and comments:
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            ranges.extend(parse_ranges(line).map_err(|e|
+                format_err!("{}, line {}: {}", path, i + 1, e))?);
+        }
+        Ok(ranges)
+    } else {
+        parse_ranges(spec).map_err(|e| format_err!("{}", e))
+    }
+}
+
+/// Parses a comma-separated list of cue numbers and ranges,
+/// like `3,45-120,200-`.
+pub fn parse_ranges(spec: &str) -> Result<Vec<CueRange>, String> {
+    spec.split(',')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .map(parse_range)
+        .collect()
+}
+
+fn parse_range(part: &str) -> Result<CueRange, String> {
+    let parse_index = |s: &str| match s.trim().parse::<usize>() {
+        Ok(0) | Err(_) => Err(format!("invalid cue number `{}`", s.trim())),
+        Ok(n) => Ok(n),
+    };
+    // Accept the en dash too, since that is what people paste from QC notes:
+    let mut bounds = part.splitn(2, ['-', '\u{2013}']);
+    let first = parse_index(bounds.next().unwrap())?;
+    let last = match bounds.next() {
+        None => Some(first),
+        Some(s) if s.trim().is_empty() => None,
+        Some(s) => Some(parse_index(s)?),
+    };
+    if let Some(last) = last {
+        if last < first {
+            return Err(format!("invalid cue range `{}`", part));
+        }
+    }
+    Ok(CueRange { first, last })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use testing;
+
+    fn range(first: usize, last: Option<usize>) -> CueRange {
+        CueRange { first, last }
+    }
+
+    #[test]
+    fn parses_open_closed_and_single_ranges() {
+        assert_eq!(parse_ranges("3, 45-120,200-"), Ok(vec![range(3, Some(3)),
+            range(45, Some(120)), range(200, None)]));
+        // The en dash of QC notes, and empty parts:
+        assert_eq!(parse_ranges("45\u{2013}120,,7 - 7,"),
+            Ok(vec![range(45, Some(120)), range(7, Some(7))]));
+        assert_eq!(parse_ranges(""), Ok(vec![]));
+        assert!(range(200, None).contains(100000));
+        assert!(!range(45, Some(120)).contains(121));
+    }
+
+    #[test]
+    fn rejects_invalid_ranges() {
+        for spec in &["0", "x", "-5", "5-3", "1-0", "1-2-3", "1.5", "3,a-"] {
+            assert!(parse_ranges(spec).is_err(), "{}", spec);
+        }
+        assert_eq!(parse_ranges("120-45"),
+            Err(String::from("invalid cue range `120-45`")));
+    }
+
+    #[test]
+    fn reads_ranges_from_files() {
+        let dir = testing::temp_dir("cues");
+        let path = dir.join("cues.txt");
+        fs::write(&path, "# karaoke\n45-120\n\n200-\n").unwrap();
+        let spec = format!("@{}", path.display());
+        assert_eq!(read_ranges(&spec).unwrap(),
+            vec![range(45, Some(120)), range(200, None)]);
+        fs::write(&path, "45-120\n120-45\n").unwrap();
+        assert!(read_ranges(&spec).unwrap_err().to_string()
+            .ends_with(", line 2: invalid cue range `120-45`"));
+        fs::remove_file(&path).unwrap();
+        assert!(read_ranges(&spec).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn intersects_the_selectors() {
+        let selection = CueSelection::new(Some("1-10"), Some("4-5"))
+            .unwrap()
+            .with_range(Some(3), None)
+            .unwrap();
+        let selected: Vec<usize> = (1..=12)
+            .filter(|&index| selection.contains(index))
+            .collect();
+        assert_eq!(selected, vec![3, 6, 7, 8, 9, 10]);
+        assert!(CueSelection::default().with_range(Some(5), Some(4)).is_err());
+        assert!(!CueSelection::default().is_partial());
+    }
+}
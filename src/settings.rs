@@ -0,0 +1,344 @@
+use std::fmt;
+
+
+/// The settings of a vtt cue, following its timestamps on the timing line,
+/// like `position:10%,line-left align:start`.
+/// Settings that aren't known, or have an invalid value, are kept verbatim.
+/// When a known setting is given more than once, the last one wins.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CueSettings {
+    /// Whether the text is written vertically, and in which direction.
+    pub vertical: Option<Vertical>,
+    /// Where the cue box is placed along the line axis.
+    pub line: Option<Line>,
+    /// Where the cue box is placed along the text.
+    pub position: Option<Position>,
+    /// Width of the cue box, in percent of the video.
+    pub size: Option<f64>,
+    /// How the text is aligned within the cue box.
+    pub align: Option<Align>,
+    /// Identifier of the REGION the cue is shown in.
+    pub region: Option<String>,
+    /// The settings that aren't known, or have an invalid value, verbatim.
+    pub unknown: Vec<String>,
+    /// Known settings that were given more than once, like `align`.
+    pub duplicates: Vec<String>,
+    /// Settings of the unknown that are known but have an invalid value,
+    /// or that aren't `key:value` at all; other unknown settings may be
+    /// from later versions of WebVTT.
+    pub malformed: Vec<String>,
+}
+
+/// The keys of the settings WebVTT defines.
+const KEYS: &[&str] = &["vertical", "line", "position", "size", "align",
+    "region"];
+
+/// The direction of vertical text.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Vertical {
+    /// `vertical:rl`, with lines added right to left, like in Japanese.
+    RightToLeft,
+    /// `vertical:lr`, with lines added left to right, like in Mongolian.
+    LeftToRight,
+}
+
+/// Where the cue box is placed along the line axis: a line number,
+/// or a percentage of the video, and which edge of the box it's placed by.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Line {
+    /// The line number or percentage.
+    pub value: LineValue,
+    /// The edge of the box placed there, if given.
+    pub align: Option<Edge>,
+}
+
+/// The place of the cue box along the line axis.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LineValue {
+    /// A line number, counting from the top, or from the bottom when
+    /// negative.
+    Number(f64),
+    /// A percentage of the video.
+    Percent(f64),
+}
+
+/// Where the cue box is placed along the text, in percent of the video,
+/// and which edge of the box it's placed by.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Position {
+    /// The percentage of the video.
+    pub percent: f64,
+    /// The edge of the box placed there, if given.
+    pub align: Option<Edge>,
+}
+
+/// An edge of the cue box, or its center. `line-left` and `line-right`
+/// are written `start` and `end` for the `line` setting.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Edge {
+    /// `start`, or `line-left` for `position`.
+    Start,
+    /// `center`.
+    Center,
+    /// `end`, or `line-right` for `position`.
+    End,
+}
+
+/// How the text is aligned within the cue box.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Align {
+    /// `align:start`, the start of the line in its writing direction.
+    Start,
+    /// `align:center`, or the older `align:middle`.
+    Center,
+    /// `align:end`, the end of the line in its writing direction.
+    End,
+    /// `align:left`.
+    Left,
+    /// `align:right`.
+    Right,
+}
+
+impl CueSettings {
+    /// Parses the settings following the timestamps of a timing line.
+    pub fn parse(settings: &str) -> CueSettings {
+        let mut parsed = CueSettings::default();
+        let mut seen = Vec::new();
+        for setting in settings.split_whitespace() {
+            if parsed.set(setting) {
+                let key = &setting[..setting.find(':').unwrap()];
+                if seen.contains(&key) && !parsed.duplicates.iter()
+                    .any(|duplicate| duplicate == key) {
+                    parsed.duplicates.push(key.to_string());
+                }
+                seen.push(key);
+                continue;
+            }
+            // `auto` is the default of `line` and `position`:
+            if setting.find(':').is_none_or(|i|
+                KEYS.contains(&&setting[..i]) && &setting[i..] != ":auto") {
+                parsed.malformed.push(setting.to_string());
+            }
+            parsed.unknown.push(setting.to_string());
+        }
+        parsed
+    }
+
+    /// Whether a line only has valid settings, like a line that continues
+    /// the settings of a timing line, which some generators wrap.
+    pub fn is_continuation(line: &str) -> bool {
+        let mut settings = CueSettings::default();
+        line.split_whitespace().next().is_some()
+            && line.split_whitespace().all(|setting| settings.set(setting))
+    }
+
+    /// Sets a `key:value` setting, unless it isn't known or its value
+    /// isn't valid.
+    fn set(&mut self, setting: &str) -> bool {
+        let (key, value) = match setting.find(':') {
+            Some(i) => (&setting[..i], &setting[i + 1..]),
+            None => return false,
+        };
+        match key {
+            "vertical" => self.vertical = match value {
+                "rl" => Some(Vertical::RightToLeft),
+                "lr" => Some(Vertical::LeftToRight),
+                _ => return false,
+            },
+            "line" => self.line = match parse_line(value) {
+                Some(line) => Some(line),
+                None => return false,
+            },
+            "position" => self.position = match parse_position(value) {
+                Some(position) => Some(position),
+                None => return false,
+            },
+            "size" => self.size = match parse_percent(value) {
+                Some(size) => Some(size),
+                None => return false,
+            },
+            "align" => self.align = match value {
+                "start" => Some(Align::Start),
+                "center" | "middle" => Some(Align::Center),
+                "end" => Some(Align::End),
+                "left" => Some(Align::Left),
+                "right" => Some(Align::Right),
+                _ => return false,
+            },
+            "region" if !value.is_empty() && !value.contains("-->") =>
+                self.region = Some(value.to_string()),
+            _ => return false,
+        }
+        true
+    }
+}
+
+fn parse_line(value: &str) -> Option<Line> {
+    let (value, align) = split_align(value, "start", "end")?;
+    let value = if value.ends_with('%') {
+        LineValue::Percent(parse_percent(value)?)
+    } else {
+        LineValue::Number(value.parse().ok().filter(|n: &f64| n.is_finite())?)
+    };
+    Some(Line { value, align })
+}
+
+fn parse_position(value: &str) -> Option<Position> {
+    let (value, align) = split_align(value, "line-left", "line-right")?;
+    Some(Position { percent: parse_percent(value)?, align })
+}
+
+/// Splits the edge off a value like `10%,line-left`, if it has one.
+/// Returns `None` if the edge isn't valid.
+fn split_align<'a>(value: &'a str, start: &str, end: &str)
+    -> Option<(&'a str, Option<Edge>)>
+{
+    let i = match value.find(',') {
+        Some(i) => i,
+        None => return Some((value, None)),
+    };
+    let edge = match &value[i + 1..] {
+        edge if edge == start => Edge::Start,
+        "center" => Edge::Center,
+        edge if edge == end => Edge::End,
+        _ => return None,
+    };
+    Some((&value[..i], Some(edge)))
+}
+
+/// Parses a percentage like `42.5%`, which must be from 0 to 100.
+fn parse_percent(value: &str) -> Option<f64> {
+    let percent: f64 = value.strip_suffix('%')?.parse().ok()?;
+    if (0.0..=100.0).contains(&percent) {
+        Some(percent)
+    } else {
+        None
+    }
+}
+
+/// Writes the settings separated by spaces, in the order of the WebVTT
+/// specification, followed by the unknown settings in their own order.
+impl fmt::Display for CueSettings {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let edge = |edge: Option<Edge>, start: &str, end: &str| match edge {
+            Some(Edge::Start) => format!(",{}", start),
+            Some(Edge::Center) => String::from(",center"),
+            Some(Edge::End) => format!(",{}", end),
+            None => String::new(),
+        };
+        let mut settings = Vec::new();
+        if let Some(vertical) = self.vertical {
+            settings.push(format!("vertical:{}", match vertical {
+                Vertical::RightToLeft => "rl",
+                Vertical::LeftToRight => "lr",
+            }));
+        }
+        if let Some(line) = self.line {
+            let value = match line.value {
+                LineValue::Number(number) => number.to_string(),
+                LineValue::Percent(percent) => format!("{}%", percent),
+            };
+            settings.push(format!("line:{}{}", value,
+                edge(line.align, "start", "end")));
+        }
+        if let Some(position) = self.position {
+            settings.push(format!("position:{}%{}", position.percent,
+                edge(position.align, "line-left", "line-right")));
+        }
+        if let Some(size) = self.size {
+            settings.push(format!("size:{}%", size));
+        }
+        if let Some(align) = self.align {
+            settings.push(format!("align:{}", match align {
+                Align::Start => "start",
+                Align::Center => "center",
+                Align::End => "end",
+                Align::Left => "left",
+                Align::Right => "right",
+            }));
+        }
+        if let Some(ref region) = self.region {
+            settings.push(format!("region:{}", region));
+        }
+        settings.extend(self.unknown.iter().cloned());
+        write!(f, "{}", settings.join(" "))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(settings: &str) -> String {
+        CueSettings::parse(settings).to_string()
+    }
+
+    #[test]
+    fn round_trips_all_documented_settings() {
+        for settings in &["vertical:rl", "vertical:lr", "line:0", "line:-2",
+            "line:12.5%", "line:0,start", "line:100%,center", "line:3,end",
+            "position:10%", "position:0%,line-left", "position:50%,center",
+            "position:95.5%,line-right", "size:80%", "align:start",
+            "align:center", "align:end", "align:left", "align:right",
+            "region:fred",
+            "vertical:lr line:-1,end position:20%,line-left size:50% \
+                align:left region:top"] {
+            assert_eq!(round_trip(settings), *settings);
+        }
+    }
+
+    #[test]
+    fn parses_typed_values() {
+        let settings = CueSettings::parse(" line:-1,end position:20%  size:50%");
+        assert_eq!(settings.line, Some(Line { value: LineValue::Number(-1.0),
+            align: Some(Edge::End) }));
+        assert_eq!(settings.position, Some(Position { percent: 20.0,
+            align: None }));
+        assert_eq!(settings.size, Some(50.0));
+        assert!(settings.unknown.is_empty());
+    }
+
+    #[test]
+    fn writes_the_canonical_order() {
+        assert_eq!(round_trip("region:top align:start vertical:rl"),
+            "vertical:rl align:start region:top");
+        assert_eq!(round_trip("align:middle"), "align:center");
+    }
+
+    #[test]
+    fn keeps_unknown_and_invalid_settings_verbatim() {
+        assert_eq!(round_trip("T:10% align:end A:middle"),
+            "align:end T:10% A:middle");
+        let settings = CueSettings::parse("size:120% line:auto \
+            position:10%,start vertical:up align: bogus");
+        assert_eq!(settings, CueSettings { unknown: vec![
+            String::from("size:120%"), String::from("line:auto"),
+            String::from("position:10%,start"), String::from("vertical:up"),
+            String::from("align:"), String::from("bogus")],
+            malformed: vec![String::from("size:120%"),
+            String::from("position:10%,start"), String::from("vertical:up"),
+            String::from("align:"), String::from("bogus")],
+            ..CueSettings::default() });
+        assert_eq!(round_trip(""), "");
+    }
+
+    #[test]
+    fn lets_the_last_duplicate_win() {
+        let settings = CueSettings::parse("align:start line:0 align:end \
+            align:left X:1 X:2");
+        assert_eq!(settings.align, Some(Align::Left));
+        assert_eq!(settings.duplicates, ["align"]);
+        assert!(settings.malformed.is_empty());
+        assert_eq!(settings.to_string(), "line:0 align:left X:1 X:2");
+    }
+
+    #[test]
+    fn tells_malformed_from_future_settings() {
+        let settings = CueSettings::parse("size:120% bogus x-ttml:r1 align:");
+        assert_eq!(settings.malformed, ["size:120%", "bogus", "align:"]);
+        assert!(CueSettings::is_continuation("size:80%  align:center"));
+        assert!(!CueSettings::is_continuation("Note: size:80%"));
+        assert!(!CueSettings::is_continuation(""));
+    }
+}
@@ -0,0 +1,185 @@
+use std::fmt;
+
+use failure::Error;
+use regex::Regex;
+
+use color;
+use subviewer;
+use subtitles::Error as SubmodError;
+
+
+/// Number of lines read from the start, the middle and the end of a file,
+/// so a damaged part can't decide the format by itself.
+const REGION: usize = 100;
+
+/// The least share of the structural markers the detected format must have.
+const MIN_CONFIDENCE: f64 = 2.0 / 3.0;
+
+/// A format that can be told by the content of a file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Srt,
+    Vtt,
+    MicroDvd,
+    SubViewer,
+    Ass,
+    Ttml,
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Format::Srt => "srt",
+            Format::Vtt => "vtt",
+            Format::MicroDvd => "MicroDVD",
+            Format::SubViewer => "SubViewer",
+            Format::Ass => "SubStation Alpha",
+            Format::Ttml => "TTML",
+        })
+    }
+}
+
+/// The format detected for a file, and the share of its structural
+/// markers that are of that format.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sniffed {
+    pub format: Format,
+    pub confidence: f64,
+}
+
+/// Tells the format of a file by its content. A WEBVTT, `[INFORMATION]`
+/// or `[Script Info]` header decides it outright, as does an XML declaration
+/// or `<tt>` element for TTML; otherwise the lines that make up the
+/// structure of a format are counted, i.e. `-->` timing lines, `{frame}`
+/// braces and SubViewer timing lines, and the format with most of them
+/// wins, if it has a clear majority. Lines that only start like a
+/// timestamp, as in TMPlayer files or text like `10:30:00: Meeting`, never
+/// outrank those, as damaged files have them in their text as well.
+/// Returns `None` when there's nothing to tell the format by at all.
+pub fn sniff(contents: &str) -> Result<Option<Sniffed>, Error> {
+    let timing = Regex::new(
+        r"^\d+:\d{2}:\d{2}[,.]\d{1,3}\s*-->\s*\d+:\d{2}:\d{2}[,.]\d{1,3}")?;
+    let frames = Regex::new(r"^\{\d+\}\{\d*\}")?;
+    let prefix = Regex::new(r"^\d{1,2}:\d{2}:\d{2}[:=]")?;
+
+    let lines: Vec<&str> = contents.lines()
+        .map(|line| line.trim_start_matches('\u{feff}').trim())
+        .collect();
+    let header = lines.iter().find(|line| !line.is_empty());
+    if header.is_some_and(|line| line.starts_with("WEBVTT")) {
+        return Ok(Some(Sniffed { format: Format::Vtt, confidence: 1.0 }));
+    }
+    if header.is_some_and(|line|
+        line.eq_ignore_ascii_case("[INFORMATION]")) {
+        return Ok(Some(Sniffed { format: Format::SubViewer,
+            confidence: 1.0 }));
+    }
+    if header.is_some_and(|line|
+        line.eq_ignore_ascii_case("[Script Info]")) {
+        return Ok(Some(Sniffed { format: Format::Ass, confidence: 1.0 }));
+    }
+    if header.is_some_and(|line| line.starts_with("<?xml")
+        || line.starts_with("<tt")) {
+        return Ok(Some(Sniffed { format: Format::Ttml, confidence: 1.0 }));
+    }
+
+    let mut counts = [(Format::Srt, 0), (Format::MicroDvd, 0),
+        (Format::SubViewer, 0)];
+    let mut prefixed = 0;
+    for &line in sample(&lines) {
+        if timing.is_match(line) {
+            counts[0].1 += 1;
+        } else if frames.is_match(line) {
+            counts[1].1 += 1;
+        } else if subviewer::get_timing(line).is_some() {
+            counts[2].1 += 1;
+        } else if prefix.is_match(line) {
+            prefixed += 1;
+        }
+    }
+    counts.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+    let total: usize = counts.iter().map(|&(_, count)| count).sum();
+    if total == 0 && prefixed > 0 {
+        return Err(SubmodError::Parse { line: None,
+            message: format!("The input has lines starting with a \
+                timestamp, like TMPlayer files, but no timing lines of a \
+                supported format.\n\n\
+                Use {} to say which format it is, or convert it to srt \
+                first.", color::hint("--format")) }.into());
+    }
+    if total == 0 {
+        return Ok(None);
+    }
+    let (format, count) = counts[0];
+    let confidence = count as f64 / total as f64;
+    if confidence < MIN_CONFIDENCE {
+        return Err(SubmodError::Parse { line: None,
+            message: format!("Can't tell whether the input is {} or {}: \
+                it has timing lines of both.\n\n\
+                Use {} to say which.", format, counts[1].0,
+                color::hint("--format")) }.into());
+    }
+    Ok(Some(Sniffed { format, confidence }))
+}
+
+/// The lines at the start, in the middle and at the end of a file,
+/// or all of them if it's short.
+fn sample<'a>(lines: &'a [&'a str])
+    -> Box<dyn Iterator<Item = &'a &'a str> + 'a>
+{
+    if lines.len() <= 3 * REGION {
+        return Box::new(lines.iter());
+    }
+    let middle = lines.len() / 2 - REGION / 2;
+    Box::new(lines[..REGION].iter()
+        .chain(&lines[middle..middle + REGION])
+        .chain(&lines[lines.len() - REGION..]))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(contents: &str) -> Option<Format> {
+        sniff(contents).unwrap().map(|sniffed| sniffed.format)
+    }
+
+    #[test]
+    fn headers_decide_the_format() {
+        assert_eq!(format("\u{feff}WEBVTT\n\n{25}{50}Hi\n"),
+            Some(Format::Vtt));
+        assert_eq!(format("[INFORMATION]\n{25}{50}Hi\n"),
+            Some(Format::SubViewer));
+        assert_eq!(format("[Script Info]\nScriptType: v4.00+\n"),
+            Some(Format::Ass));
+        assert_eq!(format("<?xml version=\"1.0\"?>\n<tt>\n"),
+            Some(Format::Ttml));
+        assert_eq!(format(""), None);
+        assert_eq!(format("Hello\n"), None);
+    }
+
+    #[test]
+    fn structure_outranks_text_that_looks_like_timestamps() {
+        let srt = "1\n00:00:01,000 --> 00:00:02,000\nJOHN: 10:30:00: Meeting\n\n\
+            2\n00:00:03,000 --> 00:00:04,000\n00:01:02:Not a timing\n\n\
+            3\n00:00:05,000 --> 00:00:06,000\n{25}{50} is MicroDVD\n";
+        assert_eq!(sniff(srt).unwrap(), Some(Sniffed { format: Format::Srt,
+            confidence: 0.75 }));
+        let error = sniff("00:00:01:Hello\n00:00:04:World\n").unwrap_err();
+        assert!(error.to_string().contains("TMPlayer"));
+        let error = sniff("{25}{100}Hi\n00:00:01.00,00:00:02.00\n")
+            .unwrap_err();
+        assert!(error.to_string().contains("MicroDVD or SubViewer"));
+    }
+
+    #[test]
+    fn samples_the_start_middle_and_end() {
+        // A damaged start doesn't decide the format by itself:
+        let cue = "00:00:01,000 --> 00:00:02,000\nHi\n";
+        let contents = ["{1}{2}x\n".repeat(40), cue.repeat(200)].concat();
+        let sniffed = sniff(&contents).unwrap().unwrap();
+        assert_eq!(sniffed.format, Format::Srt);
+        assert!(sniffed.confidence < 1.0);
+    }
+}
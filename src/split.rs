@@ -0,0 +1,272 @@
+use regex::Regex;
+
+use cue::Cue;
+use text;
+
+
+/// What the time of a split cue is divided in proportion to.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum SplitWeights {
+    /// The visible characters of the text.
+    #[default]
+    Chars,
+    /// The words of the text, so long words, like German compounds,
+    /// don't take up most of the time.
+    Words,
+}
+
+/// How good a place between two words is to split a cue, from best to worst.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Break {
+    Sentence,
+    Clause,
+    Space,
+}
+
+/// A word of the text, with any tags attached to it.
+struct Word<'a> {
+    text: &'a str,
+    /// Whether the word starts a line, other than the first one.
+    new_line: bool,
+    weight: f64,
+    /// How good a place the gap after this word is to split the cue.
+    after: Break,
+}
+
+/// Splits a cue that lasts longer than `max_duration` seconds into as
+/// many pieces as needed, at the places between words that end a sentence
+/// if possible, else a clause, and else anywhere. Each piece gets a share
+/// of the time in proportion to its characters or words.
+/// Tags that are open where the cue is split are closed, and reopened in
+/// the next piece. Pieces after the first have no identifier.
+pub fn split(cue: Cue, max_duration: f64, weights: SplitWeights) -> Vec<Cue> {
+    let duration = cue.end - cue.start;
+    let words = words(&cue.text, weights);
+    let pieces = ((duration / max_duration - 1e-9).ceil() as usize)
+        .min(words.len());
+    if pieces <= 1 {
+        return vec![cue];
+    }
+
+    // The weight of the words up to and including each word:
+    let cumulative: Vec<f64> = words.iter()
+        .scan(0.0, |sum, word| {
+            *sum += word.weight;
+            Some(*sum)
+        })
+        .collect();
+    let total = cumulative[words.len() - 1];
+    let share = |i: usize| if total > 0.0 {
+        cumulative[i] / total
+    } else {
+        (i + 1) as f64 / words.len() as f64
+    };
+
+    // Indices of the words after which the cue is split:
+    let mut ends = Vec::with_capacity(pieces);
+    let mut first = 0;
+    for k in 1..pieces {
+        let target = k as f64 / pieces as f64;
+        let tolerance = 1.0 / pieces as f64 / 3.0;
+        // Leave at least one word for each of the remaining pieces:
+        let last = words.len() - 1 - (pieces - k);
+        let distance = |&i: &usize| (share(i) - target).abs();
+        let end = (first..=last)
+            .filter(|i| distance(i) <= tolerance)
+            .min_by(|a, b| words[*a].after.cmp(&words[*b].after)
+                .then(distance(a).partial_cmp(&distance(b)).unwrap()))
+            .or_else(|| (first..=last).min_by(|a, b|
+                distance(a).partial_cmp(&distance(b)).unwrap()))
+            .unwrap();
+        ends.push(end);
+        first = end + 1;
+    }
+    ends.push(words.len() - 1);
+
+    let tag = Regex::new(r"<(/?)([A-Za-z]+)[^>]*>").unwrap();
+    let mut open: Vec<String> = Vec::new();
+    let mut result = Vec::with_capacity(pieces);
+    let mut first = 0;
+    for (k, &end) in ends.iter().enumerate() {
+        let mut lines = vec![open.concat()];
+        for (i, word) in words[first..=end].iter().enumerate() {
+            let line = lines.last_mut().unwrap();
+            if word.new_line && i > 0 {
+                lines.push(word.text.to_string());
+                continue;
+            }
+            if i > 0 {
+                line.push(' ');
+            }
+            line.push_str(word.text);
+        }
+        // Track the tags that are still open at the end of the piece:
+        for word in &words[first..=end] {
+            for c in tag.captures_iter(word.text) {
+                if &c[1] == "/" {
+                    let opened = open.iter()
+                        .rposition(|t| tag_name(t) == &c[2]);
+                    if let Some(i) = opened {
+                        open.remove(i);
+                    }
+                } else {
+                    open.push(c[0].to_string());
+                }
+            }
+        }
+        if let Some(line) = lines.last_mut() {
+            for t in open.iter().rev() {
+                line.push_str(&format!("</{}>", tag_name(t)));
+            }
+        }
+
+        let start_share = if first == 0 { 0.0 } else { share(first - 1) };
+        result.push(Cue {
+            number: cue.number,
+            line: cue.line,
+            id: if k == 0 { cue.id.clone() } else { Vec::new() },
+            start: cue.start + duration * start_share,
+            end: cue.start + duration * share(end),
+            settings: cue.settings.clone(),
+            text: lines,
+        });
+        first = end + 1;
+    }
+    result
+}
+
+/// Splits the text into words, weighed by their visible characters or as 1.
+fn words(lines: &[String], weights: SplitWeights) -> Vec<Word<'_>> {
+    let mut words = Vec::new();
+    for (i, line) in lines.iter().enumerate() {
+        for (j, word) in line.split_whitespace().enumerate() {
+            let len = text::visible_len(word);
+            words.push(Word {
+                text: word,
+                new_line: i > 0 && j == 0,
+                weight: match weights {
+                    SplitWeights::Chars => len as f64,
+                    SplitWeights::Words => (len > 0) as u8 as f64,
+                },
+                after: break_after(word),
+            });
+        }
+    }
+    words
+}
+
+/// Classifies the gap after a word by its final punctuation,
+/// ignoring closing tags and quotes.
+fn break_after(word: &str) -> Break {
+    let visible = text::visible_text(word);
+    let end = visible.trim_end_matches(['"', '\'', '”', '’', '»', ')']);
+    match end.chars().last() {
+        Some('.') | Some('!') | Some('?') | Some('…') => Break::Sentence,
+        Some(',') | Some(';') | Some(':') | Some('–') | Some('—') =>
+            Break::Clause,
+        _ => Break::Space,
+    }
+}
+
+/// The name of an opening tag, like `c` for `<c.yellow>`.
+fn tag_name(tag: &str) -> &str {
+    let name = &tag[1..];
+    let end = name.find(|c: char| !c.is_ascii_alphabetic())
+        .unwrap_or(name.len());
+    &name[..end]
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cue(text: &[&str], start: f64, end: f64) -> Cue {
+        Cue {
+            number: 1,
+            line: 2,
+            id: vec![String::from("1")],
+            start,
+            end,
+            settings: String::new(),
+            text: text.iter().map(|l| l.to_string()).collect(),
+        }
+    }
+
+    /// The text, start and end of every piece.
+    fn pieces(cue: Cue, max_duration: f64, weights: SplitWeights)
+        -> Vec<(String, f64, f64)>
+    {
+        split(cue, max_duration, weights).into_iter()
+            .map(|c| (c.text.join("|"), (c.start * 1000.0).round() / 1000.0,
+                (c.end * 1000.0).round() / 1000.0))
+            .collect()
+    }
+
+    #[test]
+    fn keeps_short_cues() {
+        let short = cue(&["Hello there."], 1.0, 3.0);
+        assert_eq!(split(short.clone(), 5.0, SplitWeights::Chars), [short]);
+    }
+
+    #[test]
+    fn prefers_splitting_at_the_end_of_a_sentence() {
+        let long = cue(&["I told you before, didn't I? You never listen",
+            "to a word I say."], 10.0, 20.0);
+        assert_eq!(pieces(long, 6.0, SplitWeights::Chars), [
+            (String::from("I told you before, didn't I?"), 10.0, 14.694),
+            (String::from("You never listen|to a word I say."), 14.694, 20.0),
+        ]);
+    }
+
+    #[test]
+    fn falls_back_to_clauses_and_then_spaces() {
+        let clause = cue(&["Well, if you really want to know the truth"],
+            0.0, 8.0);
+        assert_eq!(pieces(clause, 5.0, SplitWeights::Words)[0].0,
+            "Well, if you really");
+        let plain = cue(&["one two three four five six"], 0.0, 6.0);
+        assert_eq!(pieces(plain, 3.0, SplitWeights::Words), [
+            (String::from("one two three"), 0.0, 3.0),
+            (String::from("four five six"), 3.0, 6.0),
+        ]);
+    }
+
+    #[test]
+    fn gives_long_german_words_less_time_by_words() {
+        let german = cue(&["Die Donaudampfschifffahrtsgesellschaft hat",
+            "heute Verspätung. Wir warten noch."], 0.0, 10.0);
+        // By characters, the compound takes up half of the time, which
+        // puts the end of the sentence too far from the middle:
+        assert_eq!(pieces(german.clone(), 6.0, SplitWeights::Chars), [
+            (String::from("Die Donaudampfschifffahrtsgesellschaft"),
+                0.0, 5.286),
+            (String::from("hat|heute Verspätung. Wir warten noch."),
+                5.286, 10.0),
+        ]);
+        // By words, every word gets the same share of the time:
+        assert_eq!(pieces(german, 6.0, SplitWeights::Words), [
+            (String::from("Die Donaudampfschifffahrtsgesellschaft hat|\
+                heute Verspätung."), 0.0, 6.25),
+            (String::from("Wir warten noch."), 6.25, 10.0),
+        ]);
+    }
+
+    #[test]
+    fn never_splits_words() {
+        let word = cue(&["Rindfleischetikettierungsüberwachungsaufgaben"],
+            0.0, 20.0);
+        assert_eq!(split(word, 5.0, SplitWeights::Chars).len(), 1);
+        let three = cue(&["Ja. Nein. Vielleicht."], 0.0, 20.0);
+        assert_eq!(split(three, 5.0, SplitWeights::Words).len(), 3);
+    }
+
+    #[test]
+    fn closes_and_reopens_tags_across_pieces() {
+        let italic = cue(&["<i>We should go. They're coming</i>"], 0.0, 8.0);
+        assert_eq!(pieces(italic, 5.0, SplitWeights::Words), [
+            (String::from("<i>We should go.</i>"), 0.0, 4.8),
+            (String::from("<i>They're coming</i>"), 4.8, 8.0),
+        ]);
+    }
+}
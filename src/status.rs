@@ -0,0 +1,77 @@
+use std::io;
+
+use failure::Error;
+
+use helpers;
+use subtitles::Error as SubmodError;
+#[cfg(feature = "http")]
+use http;
+
+
+/// Invalid arguments or paths, and any other error; also what `--check`
+/// exits with when the output would differ.
+pub const USAGE: i32 = 1;
+/// The input isn't subtitles of its format.
+pub const PARSE: i32 = 2;
+/// A file couldn't be read or written.
+pub const IO: i32 = 3;
+/// There were warnings, with `--warnings-as-errors`.
+pub const WARNINGS: i32 = 4;
+/// A download failed for reasons of the network or the server.
+#[cfg(feature = "http")]
+pub const NETWORK: i32 = 5;
+
+/// What the exit statuses mean, for `--help`.
+pub const HELP: &str = "EXIT STATUS:
+    0    Success
+    1    Invalid arguments or paths, and other errors, or changes found by --check
+    2    The input isn't subtitles of its format
+    3    A file couldn't be read or written
+    4    Warnings, with --warnings-as-errors
+    5    A download failed, with the http feature";
+
+/// The exit status for an error, by the kind of its cause.
+pub fn of(error: &Error) -> i32 {
+    #[cfg(feature = "http")]
+    {
+        if error.iter_chain().any(|cause|
+            cause.downcast_ref::<http::NetworkError>().is_some()) {
+            return NETWORK;
+        }
+    }
+    for cause in error.iter_chain() {
+        match cause.downcast_ref::<SubmodError>() {
+            Some(&SubmodError::Parse { .. }) => return PARSE,
+            Some(&SubmodError::Io { .. }) => return IO,
+            _ if cause.downcast_ref::<io::Error>().is_some() => return IO,
+            _ => {},
+        }
+    }
+    USAGE
+}
+
+/// Reports an error, and returns its status.
+pub fn report(error: Error) -> i32 {
+    let status = of(&error);
+    helpers::report_error(error);
+    status
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tells_errors_apart_by_their_cause() {
+        assert_eq!(of(&format_err!("Unknown option")), USAGE);
+        assert_eq!(of(&SubmodError::Parse { line: Some(3),
+            message: "no time".into() }.into()), PARSE);
+        assert_eq!(of(&SubmodError::Io { path: "movie.srt".into(),
+            message: "Could not write `movie.srt`".into() }.into()), IO);
+        assert_eq!(of(&SubmodError::InvalidTime("soon".into()).into()),
+            USAGE);
+        let missing = io::Error::new(io::ErrorKind::NotFound, "missing");
+        assert_eq!(of(&missing.into()), IO);
+    }
+}
@@ -0,0 +1,273 @@
+use std::collections::HashSet;
+
+use regex::Regex;
+
+
+/// The result of rewriting the css rules of a vtt STYLE block.
+#[derive(Debug, Default, PartialEq)]
+pub struct StyleChanges {
+    /// Number of `::cue(.class)` selectors that were removed.
+    pub pruned: usize,
+    /// Selectors referring to an affected class that are too complex to
+    /// rewrite, and were kept as they are.
+    pub untouched: Vec<String>,
+}
+
+/// A piece of style sheet: either a rule, or the whitespace and comments
+/// between rules.
+enum Part {
+    Rule {
+        /// Whitespace and comments preceding the selectors.
+        lead: String,
+        /// The selector list as written.
+        prelude: String,
+        selectors: Vec<String>,
+        /// Everything from the opening brace up to the closing brace.
+        body: String,
+    },
+    Text(String),
+}
+
+/// Rewrites the lines of a STYLE block (without the "STYLE" line itself):
+/// simple `::cue(.class)` selectors are renamed according to `renames`,
+/// and removed if `prune` is set and their class is not in `used`.
+/// Rules that are left without selectors are dropped entirely.
+/// Any other css passes through untouched.
+pub fn rewrite(lines: &[String], used: &HashSet<String>,
+    renames: &[(String, String)], prune: bool)
+    -> (Vec<String>, StyleChanges)
+{
+    let simple = Regex::new(r"^::cue\(\.([\w-]+)\)$").unwrap();
+    let class = Regex::new(r"\.([\w-]+)").unwrap();
+    let renamed = |name: &str| renames.iter()
+        .find(|&(old, _)| old == name)
+        .map(|(_, new)| new.clone());
+    let is_dead = |name: &str| prune && !used.contains(name);
+
+    let mut changes = StyleChanges::default();
+    let mut css = String::new();
+
+    for part in split_rules(&lines.join("\n")) {
+        let (lead, prelude, selectors, body) = match part {
+            Part::Text(text) => {
+                css.push_str(&text);
+                continue;
+            },
+            Part::Rule { lead, prelude, selectors, body } =>
+                (lead, prelude, selectors, body),
+        };
+        let mut kept = Vec::new();
+        let mut modified = false;
+        for selector in selectors {
+            if let Some(captures) = simple.captures(&selector) {
+                let name = &captures[1];
+                if let Some(new) = renamed(name) {
+                    kept.push(format!("::cue(.{})", new));
+                    modified = true;
+                } else if is_dead(name) {
+                    changes.pruned += 1;
+                    modified = true;
+                } else {
+                    kept.push(selector.clone());
+                }
+                continue;
+            }
+            // Complex selectors that involve an affected class are kept,
+            // but reported, since they may now be dead or wrong:
+            if class.captures_iter(&selector)
+                .any(|c| renamed(&c[1]).is_some() || is_dead(&c[1])) {
+                changes.untouched.push(selector.clone());
+            }
+            kept.push(selector);
+        }
+
+        if kept.is_empty() {
+            // Keep comments, but not the whitespace preceding the rule:
+            if !lead.trim().is_empty() {
+                css.push_str(&lead);
+            }
+        } else {
+            css.push_str(&lead);
+            css.push_str(&if modified { kept.join(", ") } else { prelude });
+            css.push_str(&body);
+        }
+    }
+
+    // Empty lines would end the STYLE block:
+    let lines = css.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.to_string())
+        .collect();
+    (lines, changes)
+}
+
+/// Splits a style sheet into its top-level rules.
+fn split_rules(css: &str) -> Vec<Part> {
+    let mut parts = Vec::new();
+    let mut rest = css;
+
+    while !rest.is_empty() {
+        let lead_len = lead_len(rest);
+        let (lead, after_lead) = rest.split_at(lead_len);
+        let open = match after_lead.find('{') {
+            // At-rules like @media are passed through as they are:
+            Some(i) if !after_lead.starts_with('@') => i,
+            _ => {
+                let end = block_end(after_lead).unwrap_or(after_lead.len());
+                parts.push(Part::Text(rest[..lead_len + end].to_string()));
+                rest = &rest[lead_len + end..];
+                continue;
+            },
+        };
+        let prelude = &after_lead[..open];
+        let end = match block_end(&after_lead[open..]) {
+            Some(end) => open + end,
+            None => {
+                parts.push(Part::Text(rest.to_string()));
+                break;
+            }
+        };
+        let prelude = prelude.trim_end();
+        // Keep the whitespace between the selectors and the brace:
+        let body = after_lead[prelude.len()..end].to_string();
+        parts.push(Part::Rule {
+            lead: lead.to_string(),
+            prelude: prelude.to_string(),
+            selectors: split_selectors(prelude),
+            body,
+        });
+        rest = &after_lead[end..];
+    }
+    parts
+}
+
+/// Returns the length of the leading whitespace and comments.
+fn lead_len(css: &str) -> usize {
+    let mut len = 0;
+    loop {
+        let rest = &css[len..];
+        let trimmed = rest.trim_start();
+        len += rest.len() - trimmed.len();
+        if trimmed.starts_with("/*") {
+            match trimmed.find("*/") {
+                Some(end) => len += end + 2,
+                None => return css.len(),
+            }
+        } else {
+            return len;
+        }
+    }
+}
+
+/// Returns the position right after the brace closing the first block,
+/// or right after the first semicolon of a statement without a block.
+fn block_end(css: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in css.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i + 1);
+                }
+            },
+            ';' if depth == 0 => return Some(i + 1),
+            _ => {},
+        }
+    }
+    None
+}
+
+/// Splits a selector list on the commas that aren't nested
+/// inside parentheses, brackets or quotes.
+fn split_selectors(prelude: &str) -> Vec<String> {
+    let mut selectors = Vec::new();
+    let (mut depth, mut quote, mut start) = (0, None, 0);
+    for (i, c) in prelude.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {},
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (None, '(') | (None, '[') => depth += 1,
+            (None, ')') | (None, ']') => depth -= 1,
+            (None, ',') if depth == 0 => {
+                selectors.push(prelude[start..i].trim().to_string());
+                start = i + 1;
+            },
+            _ => {},
+        }
+    }
+    selectors.push(prelude[start..].trim().to_string());
+    selectors
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(css: &str) -> Vec<String> {
+        css.lines().map(|l| l.to_string()).collect()
+    }
+
+    fn used(classes: &[&str]) -> HashSet<String> {
+        classes.iter().map(|c| c.to_string()).collect()
+    }
+
+    // As exported by YouTube, which styles every color class:
+    const YOUTUBE: &str = "::cue {\n  background-color: rgba(0, 0, 0, 0.8);\n}\n\
+        ::cue(.white) { color: rgb(255, 255, 255); }\n\
+        ::cue(.lime) { color: rgb(0, 255, 0); }\n\
+        ::cue(.cyan) { color: rgb(0, 255, 255); }\n\
+        ::cue(.bg_black) { background-color: rgb(0, 0, 0); }";
+
+    #[test]
+    fn prunes_unused_classes() {
+        let (css, changes) = rewrite(&lines(YOUTUBE), &used(&["lime"]),
+            &[], true);
+        assert_eq!(css, lines("::cue {\n  background-color: rgba(0, 0, 0, 0.8);\n}\n\
+            ::cue(.lime) { color: rgb(0, 255, 0); }"));
+        assert_eq!(changes.pruned, 3);
+        assert!(changes.untouched.is_empty());
+    }
+
+    #[test]
+    fn keeps_everything_without_pruning_or_renames() {
+        let (css, changes) = rewrite(&lines(YOUTUBE), &used(&[]), &[], false);
+        assert_eq!(css, lines(YOUTUBE));
+        assert_eq!(changes, StyleChanges::default());
+    }
+
+    // As written by JW Player, with grouped selectors and comments:
+    const JW_PLAYER: &str = "/* JW Player captions */\n\
+        ::cue(.yellow), ::cue(.warn) {\n  color: #ff0;\n}\n\
+        ::cue(v[voice=\"Bob, Jr.\"]) { color: blue }\n\
+        ::cue(c.yellow) { font-weight: bold; }\n\
+        @media (max-width: 400px) { ::cue(.yellow) { font-size: 80%; } }";
+
+    #[test]
+    fn renames_classes_in_grouped_selectors() {
+        let renames = [(String::from("yellow"), String::from("highlight"))];
+        let (css, changes) = rewrite(&lines(JW_PLAYER),
+            &used(&["highlight", "warn"]), &renames, true);
+        assert_eq!(css, lines("/* JW Player captions */\n\
+            ::cue(.highlight), ::cue(.warn) {\n  color: #ff0;\n}\n\
+            ::cue(v[voice=\"Bob, Jr.\"]) { color: blue }\n\
+            ::cue(c.yellow) { font-weight: bold; }\n\
+            @media (max-width: 400px) { ::cue(.yellow) { font-size: 80%; } }"));
+        assert_eq!(changes.pruned, 0);
+        assert_eq!(changes.untouched, ["::cue(c.yellow)"]);
+    }
+
+    #[test]
+    fn drops_rules_without_selectors_but_keeps_complex_css() {
+        let (css, changes) = rewrite(&lines(JW_PLAYER), &used(&[]), &[], true);
+        assert_eq!(css, lines("/* JW Player captions */\n\
+            ::cue(v[voice=\"Bob, Jr.\"]) { color: blue }\n\
+            ::cue(c.yellow) { font-weight: bold; }\n\
+            @media (max-width: 400px) { ::cue(.yellow) { font-size: 80%; } }"));
+        assert_eq!(changes.pruned, 2);
+        assert_eq!(changes.untouched, ["::cue(c.yellow)"]);
+    }
+}
@@ -1,111 +1,4146 @@
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::fs;
-use std::io::prelude::*;
-use std::io::BufReader;
-use std::path::Path;
+use std::io::{self, prelude::*};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
+use failure::{Error, Fail};
 use regex::Regex;
-use failure::Error;
 
+use color;
+use cue::{self, Block, Cue, FormatSwitch};
+pub use cue::get_secs;
+use encoding::{self, Decoded, Encoding, Fallback};
+use ass;
+use subviewer::{self, Dialect};
+use microdvd;
+use ttml;
+use sniff::{self, Format, Sniffed};
+use selection::CueSelection;
+use settings::CueSettings;
+use merge::{self, MergeLimits};
+use split::{self, SplitWeights};
+use style;
+#[cfg(feature = "langdetect")]
+use lang;
+use helpers;
+use text::{self, Italic};
+use verify::{self, Residual};
+use subtitles::Error as SubmodError;
 
-pub fn transform(input_path: &Path, output_path: &Path, seconds: f64,
-        start_opt: Option<f64>, stop_opt: Option<f64>)
-    -> Result<i32, Error>
+
+/// Counts reported back to the user after a transform.
+#[derive(Debug, Default)]
+pub struct Summary {
+    /// Number of cues in the input file.
+    pub cues: i32,
+    /// Number of cues chosen for modification by all selectors combined.
+    pub selected: i32,
+    /// Where the input switches to another format, like an srt file
+    /// appended to a vtt file.
+    pub format_switch: Option<FormatSwitch>,
+    /// Whether the parts in different formats were merged with `--lenient`.
+    pub merged: bool,
+    /// ISO 639-1 code of the detected language, or "unknown".
+    pub language: Option<&'static str>,
+    /// Cue numbers and original timing lines of the selected cues.
+    pub selection: Vec<(usize, String)>,
+    /// Original and new timing of every cue, when requested
+    /// with `Options::map_timing`.
+    pub timing_map: Vec<TimingChange>,
+    /// Counts of every range the offsets apply to, when requested
+    /// with `Options::stats_per_range`.
+    pub ranges: Vec<RangeStats>,
+    /// Number of entirely and partially italic cues, only counted
+    /// when selecting cues with `--only-italic` or `--only-non-italic`.
+    pub italic_opt: Option<i32>,
+    pub partly_italic: i32,
+    /// Number of cues deleted for being shifted before the start.
+    pub deleted: i32,
+    /// Number of cues moved to start at 00:00:00 rather than before it,
+    /// and the earliest start any cue was shifted to, if before it.
+    pub clamped: i32,
+    pub earliest_start: f64,
+    /// Regexes of the `--replace` rules, with the number of cues
+    /// each of them changed.
+    pub replacements: Vec<(String, usize)>,
+    /// Number of cues deleted for being emptied by `--replace`.
+    pub emptied: i32,
+    /// Number of cues that received right-to-left marks with `--fix-rtl`.
+    pub rtl_fixed: i32,
+    /// Number of cues whose whitespace was normalized with `--normalize`.
+    pub normalized: i32,
+    /// How the input was decoded, when it isn't valid UTF-8.
+    pub decoded: Decoded,
+    /// The format told by the content of the input, for streams and .sub
+    /// files, which have no extension to tell it by.
+    pub sniffed_opt: Option<Sniffed>,
+    /// The frame rate given by `--fps`, and the one the `{1}{1}` line of
+    /// a MicroDVD file gives, when they differ.
+    pub fps_mismatch_opt: Option<(f64, f64)>,
+    /// Wall-clock time the transform took, from reading the input
+    /// to writing the output.
+    pub elapsed: Duration,
+    /// Whether the output was identical to the input, so that nothing
+    /// was written.
+    pub unchanged: bool,
+    /// Number of characters the output encoding could not represent.
+    pub replaced: usize,
+    /// The `--shift-starts` and `--shift-ends` applied to the selected cues,
+    /// if any, and the number of cues whose end was moved back to not
+    /// overlap the next cue.
+    pub edge_shifts_opt: Option<(f64, f64)>,
+    /// When the first cue started, and the seconds all cues were shifted
+    /// by to start it where `--trim-leading-silence` asked.
+    pub leading_silence_opt: Option<(f64, f64)>,
+    pub capped: i32,
+    /// Number of shifted cues trimmed to not overlap a pinned cue.
+    pub trimmed: i32,
+    /// Number of shifted cues deleted for being entirely covered
+    /// by a pinned cue.
+    pub covered: i32,
+    /// Cue numbers and corrections of the cues that were shifted further
+    /// than `--max-shift-per-cue` allows.
+    pub outliers: Vec<(usize, f64)>,
+    /// Cue numbers of the cues with a line longer than `--max-line`, or
+    /// more lines than `--max-lines`.
+    pub overlong: Vec<usize>,
+    /// Cue numbers of the cues that overlapped or were out of order after
+    /// a partial shift, and how `--boundary-conflict` resolved them.
+    pub boundary_conflicts: Vec<(usize, usize)>,
+    pub boundary_policy: BoundaryConflict,
+    /// Number of cues trimmed or deleted by `--boundary-conflict trim`.
+    pub boundary_trimmed: i32,
+    pub boundary_dropped: i32,
+    /// Number of cues joined with the cue before them by
+    /// `--merge-continuations`.
+    pub continuations: i32,
+    /// Number of cues shortened by `--fix-overlaps` to end before the next.
+    pub overlaps_fixed: i32,
+    /// Cues overlapping the next cue too much to be shortened, which are
+    /// left as they are, and the number merged into the next cue instead.
+    pub collapsed: Vec<usize>,
+    pub collapsed_merged: i32,
+    /// Number of cues lengthened to last `--min-duration`, and the number of
+    /// those the next cue left too little room to last that long.
+    pub extended: i32,
+    pub extended_partly: i32,
+    /// Number of cues cut short by `--max-duration --truncate-long`.
+    pub truncated: i32,
+    /// Number of cues dropped by `--drop-low-confidence`, and the number
+    /// of NOTEs before a cue that had no confidence it could read.
+    pub low_confidence: i32,
+    pub unreadable_confidence: i32,
+    /// Number of cues inserted by `--prepend-cue` and `--append-cue`, and
+    /// whether the one of `--prepend-cue` was left out for lack of room.
+    pub credits: i32,
+    pub prepend_skipped: bool,
+    /// Number of cues split for lasting longer than `--max-duration`,
+    /// and the number of cues they were split into.
+    pub split: i32,
+    pub pieces: i32,
+    /// How much longer the subtitles run than the `--video-duration`.
+    pub duration_mismatch_opt: Option<DurationMismatch>,
+    /// The residual offset against the reference of `--verify-against`.
+    pub residual_opt: Option<Residual>,
+    /// Number of cues moved to start at `--min-start`, and the number of
+    /// cues dropped for ending before it.
+    pub min_start_moved: i32,
+    pub min_start_dropped: i32,
+    /// Number of cues clamped or dropped for ending past the latest time
+    /// the output format can represent.
+    pub overflowed: i32,
+    /// Number of REGION blocks dropped when converting to srt.
+    pub regions_dropped: usize,
+    /// Regions that cues refer to, but that aren't defined.
+    pub dangling_regions: Vec<String>,
+    /// Number of vtt cues whose settings continued on the lines after
+    /// their timing line, and were joined onto it.
+    pub settings_joined: i32,
+    /// Number of vtt cues that gave the same setting more than once.
+    pub duplicate_settings: i32,
+    /// Number of STYLE selectors removed because their class is unused.
+    pub styles_pruned: usize,
+    /// STYLE selectors involving a stripped or renamed class that were
+    /// too complex to rewrite.
+    pub styles_untouched: Vec<String>,
+    /// The cue a modification failed on, if it failed on a single cue.
+    pub failed_cue_opt: Option<usize>,
+}
+
+impl Summary {
+    /// Number of warnings the summary reports, like deleted subtitles or
+    /// a `--replace` rule that didn't match, each marked with a `!`.
+    pub fn warnings(&self) -> usize {
+        let decoded = match self.decoded {
+            Decoded::Utf8 | Decoded::Utf16 | Decoded::Given(_) => false,
+            #[cfg(feature = "encodings")]
+            Decoded::Windows1252 => false,
+            #[cfg(feature = "encodings")]
+            Decoded::BomWindows1252 => true,
+            #[cfg(not(feature = "encodings"))]
+            Decoded::Lossy => true,
+        };
+        let residual = self.residual_opt.as_ref().is_some_and(|residual|
+            residual.offset_opt.is_none() || residual.exceeds());
+        let conflicts = !self.boundary_conflicts.is_empty()
+            && self.boundary_policy != BoundaryConflict::Reorder
+            && self.boundary_policy != BoundaryConflict::Trim;
+        [decoded, self.partly_italic > 0, self.format_switch.is_some(),
+            self.deleted > 0, self.covered > 0, !self.outliers.is_empty(),
+            !self.overlong.is_empty(),
+            conflicts, self.duration_mismatch_opt.is_some_and(|mismatch|
+                mismatch.exceeds()),
+            residual, self.overflowed > 0, self.replaced > 0,
+            self.settings_joined > 0, self.duplicate_settings > 0,
+            !self.collapsed.is_empty(), self.unreadable_confidence > 0,
+            self.prepend_skipped, self.fps_mismatch_opt.is_some()].iter()
+            .filter(|&&warned| warned)
+            .count()
+            + self.replacements.iter().filter(|&&(_, count)| count == 0)
+                .count()
+            + self.dangling_regions.len()
+            + self.styles_untouched.len()
+    }
+}
+
+/// The modifications to apply to every subtitle of a file.
+#[derive(Debug)]
+pub struct Options {
+    /// Seconds by which to add or subtract the time encoding.
+    pub seconds: f64,
+    /// Seconds added to the start and the end of every cue on top of
+    /// `seconds`, like for earlier in-cues and later out-cues.
+    pub shift_starts: f64,
+    pub shift_ends: f64,
+    /// Factor by which all timestamps are multiplied before shifting them.
+    pub factor: f64,
+    /// Only modify subtitles that end at or after this time.
+    pub start_opt: Option<f64>,
+    /// Only modify subtitles that start before this time.
+    pub stop_opt: Option<f64>,
+    /// Only modify italic subtitles if true, or non-italic ones if false.
+    pub italic_opt: Option<bool>,
+    /// Cue numbers to include in or exclude from the modification.
+    pub selection: CueSelection,
+    /// Breakpoints `(time, seconds)` sorted by time: subtitles starting
+    /// at or after each breakpoint are shifted by its seconds as well.
+    pub steps: Vec<(f64, f64)>,
+    /// Keep the timing of the first cue, like a translator credit.
+    pub pin_first: bool,
+    /// Keep the timing of the last cue.
+    pub pin_last: bool,
+    /// Largest correction a single cue may get on top of `seconds`,
+    /// or 0 to allow any correction.
+    pub max_shift: f64,
+    /// Longest line a cue may have in characters, and most lines, before
+    /// it's flagged.
+    pub max_line_opt: Option<usize>,
+    pub max_lines_opt: Option<usize>,
+    /// Count wide characters like CJK ideographs as 2 in the length of
+    /// lines, for the width they take up on screen.
+    pub wide_chars: bool,
+    /// Fail instead of warning when a cue exceeds `max_shift`,
+    /// or when the input switches to another format.
+    pub strict: bool,
+    /// What to do with cues shifted to end before 00:00:00.
+    pub on_negative: OnNegative,
+    /// What to do with cues ending past the latest time
+    /// the output format can represent.
+    pub overflow: Overflow,
+    /// Earliest time in seconds a cue may start, after all other changes.
+    pub min_start: f64,
+    /// What to do with cues that overlap or are out of order
+    /// after a partial shift.
+    pub boundary_conflict: BoundaryConflict,
+    /// Join cues that continue the sentence of the cue before them,
+    /// within these limits.
+    pub merge_opt: Option<MergeLimits>,
+    /// Shorten cues that overlap the next cue to end this many seconds
+    /// before it starts.
+    pub fix_overlaps_opt: Option<f64>,
+    /// Merge the cues that can't be shortened that much into the next cue.
+    pub merge_collapsed: bool,
+    /// Shortest a cue may last in seconds before it's lengthened, as far as
+    /// the next cue allows.
+    pub min_duration_opt: Option<f64>,
+    /// Longest a cue may last in seconds before it's split.
+    pub max_duration: Option<f64>,
+    /// Cut cues lasting longer than `max_duration` short instead.
+    pub truncate_long: bool,
+    /// What the time of split cues is divided in proportion to.
+    pub split_weights: SplitWeights,
+    /// Drop the cues a speech recognizer gave a lower confidence than
+    /// this in the NOTE before them.
+    pub min_confidence_opt: Option<f64>,
+    /// Text of a cue to insert before the first cue, like a credit, and
+    /// of one to insert after the last cue.
+    pub prepend_cue_opt: Option<String>,
+    pub append_cue_opt: Option<String>,
+    /// How long the inserted cues last, and the gap they leave to the
+    /// first or last cue, in seconds.
+    pub credit_duration: f64,
+    pub credit_gap: f64,
+    /// Merge the parts of inputs that switch to another format.
+    pub lenient: bool,
+    /// Detect the language of the subtitle text.
+    pub detect_lang: bool,
+    /// Record the original and new timing of every cue in the summary.
+    pub map_timing: bool,
+    /// Break the counts down per range of `Options::ranges`.
+    pub stats_per_range: bool,
+    /// Whether the input is srt, for inputs whose extension doesn't tell,
+    /// like pipes. Otherwise, the extension decides.
+    pub input_srt_opt: Option<bool>,
+    /// Whether the input is an .ass or .ssa script, as `--format` gives
+    /// for inputs whose extension doesn't tell.
+    pub input_ass: bool,
+    /// Whether the input is a TTML document, as `--format` gives for inputs
+    /// whose extension doesn't tell.
+    pub input_ttml: bool,
+    /// Whether srt and vtt inputs are written as TTML, a paragraph per cue,
+    /// for an output like `--out movie.ttml`.
+    pub output_ttml: bool,
+    /// The dialect of .sub inputs, for files whose content doesn't tell.
+    /// Otherwise, it's detected.
+    pub sub_dialect_opt: Option<Dialect>,
+    /// The frame rate of the video, for the frames of MicroDVD .sub files.
+    pub fps_opt: Option<f64>,
+    /// When the first cue started before `--trim-leading-silence` gave
+    /// `seconds`, to report it.
+    pub first_start_opt: Option<f64>,
+    /// Largest number of bytes read from pipes and other streams, or
+    /// downloaded from URLs.
+    pub max_input_size: u64,
+    /// The input when it was downloaded rather than read from the input
+    /// path, which then only gives it a name and a format.
+    pub downloaded_opt: Option<Vec<u8>>,
+    /// How to generate missing vtt cue identifiers, if at all.
+    pub gen_ids: Option<IdMode>,
+    /// Add right-to-left marks to Arabic and Hebrew subtitles.
+    pub fix_rtl: bool,
+    /// Trim and collapse whitespace in the subtitle text.
+    pub normalize: bool,
+    /// Remove all tags from the subtitle text.
+    pub strip_tags: bool,
+    /// Regexes and their replacements, applied in order to the text.
+    pub replacements: Vec<(Regex, String)>,
+    /// `(old, new)` names of vtt classes to rename.
+    pub class_renames: Vec<(String, String)>,
+    /// `(old, new)` identifiers of vtt regions to rename.
+    pub region_renames: Vec<(String, String)>,
+    /// The line endings of the output.
+    pub line_ending: LineEnding,
+    /// Character encoding of the input, when it's given rather than
+    /// detected.
+    pub input_encoding_opt: Option<&'static Encoding>,
+    /// Start the output with a UTF-8 byte order mark when the input
+    /// starts with a byte order mark. Otherwise, it's left out.
+    pub keep_bom: bool,
+    /// Keep the indices of srt subtitles as they are, rather than numbering
+    /// the remaining subtitles from 1.
+    pub keep_numbering: bool,
+    /// Character encoding of the output file.
+    pub output_encoding: &'static Encoding,
+    /// Replacement for characters the output encoding cannot represent.
+    pub fallback: Fallback,
+    /// Write the output even when it's identical to the input.
+    pub force: bool,
+    /// Duration of the video in seconds, to compare the subtitles with.
+    pub video_duration_opt: Option<f64>,
+    /// Largest difference in seconds between the end of the subtitles
+    /// and the end of the video that isn't reported.
+    pub duration_margin: f64,
+    /// Subtitle file to measure the residual offset of the output against.
+    pub reference_opt: Option<PathBuf>,
+    /// Largest residual offset against the reference, in seconds.
+    pub tolerance: f64,
+}
+
+/// Where the last cue of the input ends, compared with the end of the video.
+/// Subtitles made for another cut of the video, like a director's cut,
+/// often end far from it, which a constant shift doesn't fix.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DurationMismatch {
+    pub subtitles: f64,
+    pub video: f64,
+    pub margin: f64,
+}
+
+impl DurationMismatch {
+    /// Seconds by which the subtitles end after the video,
+    /// or before it if negative.
+    pub fn mismatch(&self) -> f64 {
+        self.subtitles - self.video
+    }
+
+    /// Whether the subtitles end further from the video than the margin.
+    pub fn exceeds(&self) -> bool {
+        self.mismatch().abs() > self.margin
+    }
+}
+
+/// The original timing of a cue and its timing in the output,
+/// which is `None` if the cue was deleted.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimingChange {
+    pub cue: usize,
+    pub old: (f64, f64),
+    pub new_opt: Option<(f64, f64)>,
+    /// Index of the range of `Options::ranges` whose offset was applied
+    /// to the cue, or `None` if the cue wasn't selected.
+    pub rule_opt: Option<usize>,
+}
+
+/// What happened to the selected cues of one range of `Options::ranges`:
+/// moved by its offset, moved elsewhere, like to not start before the
+/// video or overlap another cue, or deleted.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RangeStats {
+    pub start: f64,
+    pub end_opt: Option<f64>,
+    /// Total offset of the range, including `seconds` and earlier steps.
+    pub seconds: f64,
+    pub shifted: i32,
+    pub clamped: i32,
+    pub deleted: i32,
+}
+
+/// What `--boundary-conflict` does with cues that overlap or are out of
+/// order after a partial shift.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum BoundaryConflict {
+    /// Sort the cues by start time again.
+    Reorder,
+    /// Trim the earlier cue to end where the later one starts.
+    Trim,
+    /// Only report the cues.
+    #[default]
+    Warn,
+    /// Fail without writing anything.
+    Error,
+}
+
+/// What `--on-negative` does with cues shifted to end before 00:00:00.
+/// Cues that only start before it are moved to start at it either way,
+/// unless with `Error`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OnNegative {
+    /// Delete the cues.
+    Delete,
+    /// Move them to start at 00:00:00, and last a millisecond.
+    Clamp,
+    /// Fail without writing anything.
+    Error,
+}
+
+/// What `--overflow` does with cues that end past the latest time
+/// the output format can represent.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Overflow {
+    /// Move the timestamps back to the latest representable time.
+    Clamp,
+    /// Delete the cues.
+    Drop,
+    /// Fail without writing anything.
+    Error,
+}
+
+/// The line endings of the output.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LineEnding {
+    /// Those of the input: the endings of the lines an .ass, .ssa or .sub
+    /// file keeps, and otherwise the ending most lines of the input have.
+    Keep,
+    Lf,
+    Crlf,
+}
+
+/// The identifiers `--gen-ids` gives vtt cues that don't have one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IdMode {
+    /// Don't generate identifiers, and drop srt indices.
+    None,
+    /// The position of the cue in the output file.
+    Index,
+    /// The start time of the cue.
+    Timestamp,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            seconds: 0.0,
+            shift_starts: 0.0,
+            shift_ends: 0.0,
+            factor: 1.0,
+            start_opt: None,
+            stop_opt: None,
+            italic_opt: None,
+            selection: CueSelection::default(),
+            steps: Vec::new(),
+            pin_first: false,
+            pin_last: false,
+            max_shift: 600.0,
+            max_line_opt: None,
+            max_lines_opt: None,
+            wide_chars: false,
+            strict: false,
+            on_negative: OnNegative::Delete,
+            overflow: Overflow::Clamp,
+            min_start: 0.0,
+            boundary_conflict: BoundaryConflict::Warn,
+            merge_opt: None,
+            fix_overlaps_opt: None,
+            merge_collapsed: false,
+            min_duration_opt: None,
+            max_duration: None,
+            truncate_long: false,
+            split_weights: SplitWeights::Chars,
+            min_confidence_opt: None,
+            prepend_cue_opt: None,
+            append_cue_opt: None,
+            credit_duration: 3.0,
+            credit_gap: 0.5,
+            lenient: false,
+            detect_lang: false,
+            map_timing: false,
+            stats_per_range: false,
+            input_srt_opt: None,
+            input_ass: false,
+            input_ttml: false,
+            output_ttml: false,
+            sub_dialect_opt: None,
+            fps_opt: None,
+            max_input_size: 64 * 1024 * 1024,
+            downloaded_opt: None,
+            first_start_opt: None,
+            gen_ids: None,
+            fix_rtl: false,
+            normalize: false,
+            strip_tags: false,
+            replacements: Vec::new(),
+            class_renames: Vec::new(),
+            region_renames: Vec::new(),
+            line_ending: LineEnding::Keep,
+            input_encoding_opt: None,
+            keep_bom: false,
+            keep_numbering: false,
+            output_encoding: encoding::UTF_8,
+            fallback: Fallback::Transliterate,
+            force: false,
+            video_duration_opt: None,
+            duration_margin: 120.0,
+            reference_opt: None,
+            tolerance: 0.5,
+        }
+    }
+}
+
+impl Options {
+    /// Returns the total offset for a subtitle starting at `time`,
+    /// i.e. the seconds plus the cumulative offsets of all preceding steps.
+    pub fn offset_at(&self, time: f64) -> f64 {
+        self.seconds + self.steps.iter()
+            .take_while(|&&(step_time, _)| step_time <= time)
+            .map(|&(_, step_seconds)| step_seconds)
+            .sum::<f64>()
+    }
+
+    /// Returns the index of the range of `Options::ranges` that a subtitle
+    /// starting at `time` belongs to.
+    pub fn range_at(&self, time: f64) -> usize {
+        self.steps.iter()
+            .take_while(|&&(step_time, _)| step_time <= time)
+            .count()
+    }
+
+    /// Returns the `(start, end, seconds)` of the ranges with their own
+    /// offset: the window of `--start` and `--stop`, split by every step.
+    pub fn ranges(&self) -> Vec<(f64, Option<f64>, f64)> {
+        let window_start = self.start_opt.unwrap_or(0.0);
+        let mut starts = vec![window_start];
+        starts.extend(self.steps.iter()
+            .map(|&(step_time, _)| step_time.max(window_start)));
+        let mut seconds = self.seconds;
+        (0..starts.len())
+            .map(|i| {
+                if i > 0 {
+                    seconds += self.steps[i - 1].1;
+                }
+                let end_opt = match (starts.get(i + 1), self.stop_opt) {
+                    (Some(&next), Some(stop)) => Some(next.min(stop)),
+                    (Some(&next), None) => Some(next),
+                    (None, stop_opt) => stop_opt,
+                };
+                let start = end_opt.map_or(starts[i], |end| starts[i].min(end));
+                (start, end_opt, seconds)
+            })
+            .collect()
+    }
+
+    /// Whether these options leave the text and timing of every cue as
+    /// they are, and report nothing about them either. Selectors and
+    /// safety policies don't count, as they only matter for other
+    /// operations; converting to another format isn't known here.
+    pub fn is_noop(&self) -> bool {
+        self.seconds == 0.0
+            && self.shift_starts == 0.0
+            && self.shift_ends == 0.0
+            && self.factor == 1.0
+            && self.steps.iter().all(|&(_, seconds)| seconds == 0.0)
+            && self.min_start == 0.0
+            && self.merge_opt.is_none()
+            && self.fix_overlaps_opt.is_none()
+            && self.min_duration_opt.is_none()
+            && self.max_duration.is_none()
+            && self.min_confidence_opt.is_none()
+            && self.prepend_cue_opt.is_none()
+            && self.append_cue_opt.is_none()
+            && !self.lenient
+            && !self.detect_lang
+            && self.max_line_opt.is_none()
+            && self.max_lines_opt.is_none()
+            && !self.map_timing
+            && !self.stats_per_range
+            && self.gen_ids.is_none()
+            && !self.fix_rtl
+            && !self.normalize
+            && !self.strip_tags
+            && self.replacements.is_empty()
+            && self.class_renames.is_empty()
+            && self.region_renames.is_empty()
+            && self.input_encoding_opt.is_none()
+            && !self.keep_bom
+            && self.output_encoding == encoding::UTF_8
+            && self.line_ending == LineEnding::Keep
+            && self.video_duration_opt.is_none()
+            && self.reference_opt.is_none()
+    }
+}
+
+/// An error that stopped the transform of a file partway, which tells how
+/// far it got, to help find what's wrong in the input.
+#[derive(Debug)]
+pub struct PartialFailure {
+    pub cause: Error,
+    pub progress: Progress,
+}
+
+impl fmt::Display for PartialFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.cause)
+    }
+}
+
+impl Fail for PartialFailure {
+    fn cause(&self) -> Option<&dyn Fail> {
+        Some(self.cause.as_fail())
+    }
+}
+
+/// How far a transform got before it failed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Progress {
+    /// Number of subtitles processed before the failure, of `total`.
+    pub cues: usize,
+    pub total: usize,
+    /// Line and byte offset in the input of the subtitle that failed,
+    /// or of the last one processed, when known.
+    pub line_opt: Option<usize>,
+    pub offset_opt: Option<usize>,
+    /// Start of the last subtitle processed, as timed in the input.
+    pub last_start_opt: Option<f64>,
+}
+
+/// The lines that differ between an input file and its transformed output.
+#[derive(Debug)]
+pub struct Changes {
+    /// Number of lines that differ, including added or removed lines.
+    pub lines: usize,
+    /// Line number of the first difference, starting at 1.
+    pub first: usize,
+    /// The first differing line of the input and output, if any.
+    pub old_line: Option<String>,
+    pub new_line: Option<String>,
+}
+
+/// Transforms the input file and writes the output file, unless the output
+/// would be identical to the input and `options.force` isn't set; this
+/// leaves already processed files and their modification times alone.
+/// The output file is only ever replaced by a complete one.
+pub fn transform(input_path: &Path, output_path: &Path, options: &Options)
+    -> Result<Summary, Error>
+{
+    let start = Instant::now();
+    let input = read_input(input_path, options)?;
+    let (output, mut summary) = render_path(&input, input_path,
+        to_srt_opt(output_path), options)?;
+    if input == output && !options.force {
+        summary.unchanged = true;
+    } else {
+        helpers::write_atomically(output_path, &output)?;
+    }
+
+    summary.elapsed = start.elapsed();
+    Ok(summary)
+}
+
+/// Runs the transform and writes the output to stdout, in srt or vtt
+/// as given by `to_srt_opt`, or else in the format of the input.
+pub fn print(input_path: &Path, to_srt_opt: Option<bool>, options: &Options)
+    -> Result<Summary, Error>
+{
+    let start = Instant::now();
+    let input = read_input(input_path, options)?;
+    let (output, mut summary) = render_path(&input, input_path, to_srt_opt,
+        options)?;
+    io::stdout().write_all(&output)?;
+
+    summary.elapsed = start.elapsed();
+    Ok(summary)
+}
+
+/// Reads and modifies the cues of an srt or vtt input like `transform`,
+/// but returns them instead of writing them, for `submod chapters`.
+pub fn modified_blocks(input_path: &Path, options: &Options)
+    -> Result<(Vec<Block>, Summary), Error>
+{
+    if is_ass(input_path) || is_sub(input_path, options)
+        || is_ttml(input_path) || options.input_ttml {
+        return Err(format_err!("Chapters can only be extracted from .srt \
+            and .vtt subtitles, not from .ass, .ssa, .sub and TTML files."));
+    }
+    let start = Instant::now();
+    let bytes = read_input(input_path, options)?;
+    let (input, decoded) = decode_input(&bytes, options);
+    let (_, input) = encoding::split_bom(&input);
+    let (parsed, switch_opt) = cue::parse(input.as_bytes())?;
+    let (blocks, mut summary) = modify(parsed, switch_opt,
+        is_srt_input(input_path, &bytes, options)?.0, false, options)?;
+    summary.decoded = decoded;
+    summary.elapsed = start.elapsed();
+    Ok((blocks, summary))
+}
+
+/// A file given with `--companion`, like a vtt track of metadata, whose
+/// cues pair with those of the input by identifier, or else by position,
+/// and get the same timing.
+#[derive(Debug)]
+pub struct Companion {
+    blocks: Vec<Block>,
+    /// The number of the cue of the input that each cue pairs with.
+    pairs: Vec<usize>,
+    srt: bool,
+    bom: bool,
+    crlf: bool,
+}
+
+/// What `write_companion` wrote.
+#[derive(Debug, PartialEq)]
+pub struct CompanionSummary {
+    pub cues: usize,
+    /// Number of cues deleted along with the cue of the input they pair with.
+    pub deleted: usize,
+}
+
+/// Reads the companion of the input, and pairs each of its cues with a cue
+/// of the input: the one with the same identifier, or else the next one
+/// that no cue has the identifier of. Both must have the same cues, or the
+/// pair would no longer be consistent, so anything else fails before either
+/// is written.
+pub fn pair_companion(input_path: &Path, companion_path: &Path,
+    options: &Options) -> Result<Companion, Error>
+{
+    let formats = [input_path, companion_path].iter().all(|path|
+        !is_ass(path) && !is_ttml(path)
+            && path.extension().is_none_or(|ext| ext != "sub"));
+    if !formats || options.sub_dialect_opt.is_some() || options.input_ttml {
+        return Err(format_err!("--companion only applies to .srt and .vtt \
+            subtitles, with a .srt or .vtt companion."));
+    }
+    if options.merge_opt.is_some() || options.min_confidence_opt.is_some()
+        || (options.max_duration.is_some() && !options.truncate_long) {
+        return Err(format_err!("--companion can't be used with \
+            --merge-continuations, --drop-low-confidence or --max-duration, \
+            which would change the subtitles of the input but not those of \
+            the companion, unless it's --truncate-long."));
+    }
+    let read = |bytes: &[u8]| -> Result<(Vec<Block>, bool, bool), Error> {
+        let (text, _) = decode_input(bytes, options);
+        let (bom, text) = encoding::split_bom(&text);
+        let crlf = match options.line_ending {
+            LineEnding::Keep => is_crlf(text),
+            LineEnding::Lf => false,
+            LineEnding::Crlf => true,
+        };
+        Ok((cue::parse(text.as_bytes())?.0, bom, crlf))
+    };
+    let ids: Vec<(usize, Option<String>)> =
+        read(&read_input(input_path, options)?)?.0.into_iter()
+            .filter_map(|block| match block {
+                Block::Cue(cue) => Some((cue.number, cue.id.first().cloned())),
+                _ => None,
+            })
+            .collect();
+    let bytes = fs::read(companion_path).map_err(|error| SubmodError::Io {
+        path: companion_path.to_owned(), message: format!(
+            "Could not read the companion `{}`: {}", companion_path.display(),
+            error) })?;
+    let (blocks, bom, crlf) = read(&bytes)?;
+
+    let by_id: Vec<Option<usize>> = blocks.iter()
+        .filter_map(|block| match *block {
+            Block::Cue(ref cue) => Some(cue.id.first().and_then(|id|
+                ids.iter().find(|(_, other)| other.as_ref() == Some(id))
+                    .map(|&(number, _)| number))),
+            _ => None,
+        })
+        .collect();
+    let mut unpaired = ids.iter().map(|&(number, _)| number)
+        .filter(|number| !by_id.contains(&Some(*number)));
+    let mut pairs: Vec<usize> = Vec::new();
+    for number_opt in &by_id {
+        let number = match number_opt.or_else(|| unpaired.next()) {
+            Some(number) => number,
+            None => break,
+        };
+        if let Some(i) = pairs.iter().position(|&paired| paired == number) {
+            return Err(format_err!("Subtitles {} and {} of the companion \
+                `{}` both have the identifier of subtitle {} of the input.",
+                i + 1, pairs.len() + 1, companion_path.display(), number));
+        }
+        pairs.push(number);
+    }
+    let cues = by_id.len();
+    if cues != ids.len() {
+        return Err(format_err!("The companion `{}` has {} subtitle{}, but \
+            the input has {}; --companion needs one for each subtitle of \
+            the input.", companion_path.display(), cues,
+            if cues == 1 { "" } else { "s" }, ids.len()));
+    }
+    Ok(Companion { blocks, pairs,
+        srt: companion_path.extension().is_some_and(|ext| ext == "srt"),
+        bom, crlf })
+}
+
+/// Gives the cues of a companion the timing their pair in the input got,
+/// as recorded in the timing map of its transform, and writes them to
+/// `output_path`. Cues whose pair was deleted are deleted as well.
+pub fn write_companion(companion: Companion, timing_map: &[TimingChange],
+    output_path: &Path, options: &Options) -> Result<CompanionSummary, Error>
+{
+    let timings: HashMap<usize, Option<(f64, f64)>> = timing_map.iter()
+        .map(|change| (change.cue, change.new_opt))
+        .collect();
+    let mut pairs = companion.pairs.iter();
+    let mut summary = CompanionSummary { cues: 0, deleted: 0 };
+    let mut blocks = retain_cues(companion.blocks, |cue| {
+        // Every cue has a pair, and every cue of the input a timing:
+        match timings[pairs.next().unwrap()] {
+            Some((start, end)) => {
+                cue.start = start;
+                cue.end = end;
+                summary.cues += 1;
+                Ok(true)
+            },
+            None => {
+                summary.deleted += 1;
+                Ok(false)
+            },
+        }
+    })?;
+    if companion.srt {
+        number_cues(&mut blocks, options.keep_numbering);
+    }
+
+    let mut output = Vec::new();
+    cue::write(&blocks, &mut output, companion.srt)?;
+    let output = end_lines(&String::from_utf8(output)?, companion.crlf);
+    let output = encode_output(output, companion.bom, options,
+        &mut Summary::default());
+    helpers::write_atomically(output_path, &output)?;
+    Ok(summary)
+}
+
+/// Reads the whole input. Pipes and other streams can only be read once
+/// and have no size to check up front, so they're read up to
+/// `options.max_input_size` bytes.
+fn read_input(input_path: &Path, options: &Options) -> Result<Vec<u8>, Error> {
+    if let Some(ref input) = options.downloaded_opt {
+        return Ok(input.clone());
+    }
+    if !helpers::is_stream(input_path) {
+        return fs::read(input_path).map_err(|error| SubmodError::Io {
+            path: input_path.to_owned(), message: format!(
+                "Could not read `{}`: {}", input_path.display(), error) }
+            .into());
+    }
+    let mut input = Vec::new();
+    let stream: Box<dyn Read> = if input_path == Path::new("-") {
+        Box::new(io::stdin())
+    } else {
+        Box::new(fs::File::open(input_path)?)
+    };
+    stream.take(options.max_input_size + 1)
+        .read_to_end(&mut input)?;
+    if input.len() as u64 > options.max_input_size {
+        return Err(format_err!("`{}` is larger than {} MB.\n\n\
+            Use {} to read larger pipes and other streams.",
+            input_path.display(),
+            options.max_input_size / (1024 * 1024),
+            color::hint("--max-input-size")));
+    }
+    Ok(input)
+}
+
+/// Whether the path is an .ass or .ssa script, which is only shifted,
+/// never converted to another format.
+pub fn is_ass(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "ass" || ext == "ssa")
+}
+
+/// Whether the path is a TTML document, like the .dfxp files of broadcast
+/// deliveries, which is shifted in place unless converted to srt or vtt.
+pub fn is_ttml(path: &Path) -> bool {
+    path.extension().is_some_and(|ext|
+        ext == "ttml" || ext == "dfxp" || ext == "xml")
+}
+
+/// Decodes the input in the encoding `--encoding` gives, or else in the
+/// one `encoding::decode` detects.
+fn decode_input<'a>(input: &'a [u8], options: &Options)
+    -> (Cow<'a, str>, Decoded)
+{
+    match options.input_encoding_opt {
+        Some(encoding) => (encoding::decode_as(input, encoding),
+            Decoded::Given(encoding)),
+        None => encoding::decode(input),
+    }
+}
+
+/// Whether the input is an .ass or .ssa script: by its extension, by
+/// `--format`, or by the `[Script Info]` header of streams.
+fn is_ass_input(path: &Path, input: &[u8], options: &Options) -> bool {
+    if is_ass(path) || options.input_ass {
+        return true;
+    }
+    is_sniffed(Format::Ass, path, input, options)
+}
+
+/// Whether the input is a TTML document: by its extension, by `--format`,
+/// or by the XML declaration or `<tt>` element of streams.
+fn is_ttml_input(path: &Path, input: &[u8], options: &Options) -> bool {
+    if is_ttml(path) || options.input_ttml {
+        return true;
+    }
+    is_sniffed(Format::Ttml, path, input, options)
+}
+
+/// Whether the input is a stream of the format, by its content, unless
+/// `--format` gives another one.
+fn is_sniffed(format: Format, path: &Path, input: &[u8], options: &Options)
+    -> bool
+{
+    if options.input_srt_opt.is_some() || options.sub_dialect_opt.is_some()
+        || options.input_ass || options.input_ttml
+        || !helpers::is_stream(path) {
+        return false;
+    }
+    // Any other format, or no clear one, is told apart by is_srt_input:
+    let (contents, _) = decode_input(input, options);
+    sniff::sniff(&contents).ok().flatten()
+        .is_some_and(|sniffed| sniffed.format == format)
+}
+
+/// Whether the input is a .sub file, by its extension or by `--format`
+/// for streams; it's only shifted, unless converted to srt or vtt.
+pub fn is_sub(path: &Path, options: &Options) -> bool {
+    options.sub_dialect_opt.is_some()
+        || path.extension().is_some_and(|ext| ext == "sub")
+}
+
+/// Whether the output is converted to srt or vtt, by the extension of
+/// `output_path`, or `None` for .ass, .ssa, .sub and TTML files that are
+/// written in the format of the input.
+fn to_srt_opt(output_path: &Path) -> Option<bool> {
+    match output_path.extension().and_then(|ext| ext.to_str()) {
+        Some("srt") => Some(true),
+        Some("vtt") => Some(false),
+        _ => None,
+    }
+}
+
+/// Whether the input is srt: as given by `--format`, or else by the
+/// extension of a file. Streams have none, so their format is told by
+/// their content, which is also returned; those without any timing lines
+/// are srt.
+fn is_srt_input(input_path: &Path, input: &[u8], options: &Options)
+    -> Result<(bool, Option<Sniffed>), Error>
+{
+    if let Some(srt) = options.input_srt_opt {
+        return Ok((srt, None));
+    }
+    if !helpers::is_stream(input_path) {
+        return Ok((input_path.extension().is_some_and(|ext| ext == "srt"),
+            None));
+    }
+    let (contents, _) = decode_input(input, options);
+    let sniffed_opt = sniff::sniff(&contents)?;
+    let format_opt = sniffed_opt.map(|sniffed| sniffed.format);
+    if let Some(format) = format_opt.filter(|&format|
+        format == Format::MicroDvd || format == Format::SubViewer) {
+        return Err(SubmodError::Parse { line: None,
+            message: format!("The input looks like a {} .sub file.\n\n\
+                Use {} to read it as one.", format, color::hint(format!(
+                    "--format {}", format.to_string().to_lowercase()))) }
+                .into());
+    }
+    Ok((format_opt != Some(Format::Vtt), sniffed_opt))
+}
+
+/// Runs the transform like `transform`, but without writing anything,
+/// for `--dry-run` to preview it.
+pub fn dry_run(input_path: &Path, output_path: &Path, options: &Options)
+    -> Result<Summary, Error>
+{
+    let start = Instant::now();
+    let input = read_input(input_path, options)?;
+    let (output, mut summary) = render_path(&input, input_path,
+        to_srt_opt(output_path), options)?;
+    summary.unchanged = input == output;
+    summary.elapsed = start.elapsed();
+    Ok(summary)
+}
+
+/// Runs the transform without writing anything, and compares what would
+/// be written to `output_path` with the current contents of the input file.
+/// Returns `None` when both are identical.
+pub fn check(input_path: &Path, output_path: &Path, options: &Options)
+    -> Result<(Summary, Option<Changes>), Error>
+{
+    let input = fs::read(input_path)?;
+    let (output, summary) = render_path(&input, input_path,
+        to_srt_opt(output_path), options)?;
+
+    if input == output {
+        return Ok((summary, None));
+    }
+    let old = String::from_utf8_lossy(&input);
+    let new = String::from_utf8_lossy(&output);
+    let (old_lines, new_lines): (Vec<&str>, Vec<&str>) =
+        (old.split('\n').collect(), new.split('\n').collect());
+
+    let mut changes = Changes {
+        lines: 0, first: 0, old_line: None, new_line: None };
+    for i in 0..old_lines.len().max(new_lines.len()) {
+        let (old_line, new_line) = (old_lines.get(i), new_lines.get(i));
+        if old_line != new_line {
+            if changes.lines == 0 {
+                changes.first = i + 1;
+                changes.old_line = old_line.map(|l| l.to_string());
+                changes.new_line = new_line.map(|l| l.to_string());
+            }
+            changes.lines += 1;
+        }
+    }
+    Ok((summary, Some(changes)))
+}
+
+/// Renders the input read from `input_path`, as a script if it's an .ass
+/// or .ssa file, and as srt or vtt otherwise, or as `to_srt_opt` gives.
+fn render_path(input: &[u8], input_path: &Path, to_srt_opt: Option<bool>,
+    options: &Options) -> Result<(Vec<u8>, Summary), Error>
+{
+    if is_ass_input(input_path, input, options) {
+        if to_srt_opt.is_some() {
+            return Err(format_err!("Converting .ass and .ssa scripts to \
+                another format isn't supported; they can only be shifted."));
+        }
+        render_ass(input, options)
+    } else if is_ttml_input(input_path, input, options) {
+        render_ttml(input, to_srt_opt, options)
+    } else if is_sub(input_path, options) {
+        render_sub(input, to_srt_opt, options)
+    } else {
+        let (from_srt, sniffed_opt) = is_srt_input(input_path, input,
+            options)?;
+        let (output, mut summary) = render(input, from_srt,
+            to_srt_opt.unwrap_or(from_srt), options)?;
+        summary.sniffed_opt = sniffed_opt;
+        Ok((output, summary))
+    }
+}
+
+/// Shifts the events of an .ass or .ssa script, leaving everything else
+/// as it is. Only the operations on the timing apply to scripts;
+/// events shifted before the start go as `--on-negative` says.
+fn render_ass(input: &[u8], options: &Options)
+    -> Result<(Vec<u8>, Summary), Error>
+{
+    if let Some(option) = ass_unsupported(options) {
+        return Err(format_err!("{} can't be used with .ass and .ssa \
+            scripts, which submod only shifts.", option));
+    }
+    let (script, decoded) = decode_input(input, options);
+    shift_in_place(&script, decoded, "event", options,
+        |script, shift| ass::shift(script, shift))
+}
+
+/// Converts a SubViewer or MicroDVD .sub file to srt or vtt as `to_srt_opt`
+/// gives, or else shifts its timing lines like the events of a script.
+/// MicroDVD files are timed in frames rather than seconds, which `--fps`
+/// converts, or else the frame rate their `{1}{1}` line gives.
+fn render_sub(input: &[u8], to_srt_opt: Option<bool>, options: &Options)
+    -> Result<(Vec<u8>, Summary), Error>
+{
+    let (contents, decoded) = decode_input(input, options);
+    let (bom, contents) = encoding::split_bom(&contents);
+    let sniffed_opt = match options.sub_dialect_opt {
+        Some(_) => None,
+        None => Some(subviewer::detect(contents)?),
+    };
+    let dialect = match sniffed_opt.map(|sniffed| sniffed.format) {
+        Some(Format::MicroDvd) => Dialect::MicroDvd,
+        Some(_) => Dialect::SubViewer,
+        // Given by --format:
+        None => options.sub_dialect_opt.unwrap(),
+    };
+    let header_fps_opt = match dialect {
+        Dialect::MicroDvd => microdvd::header_fps(contents),
+        Dialect::SubViewer => None,
+    };
+    let fps_opt = options.fps_opt.or(header_fps_opt);
+    if dialect == Dialect::MicroDvd && fps_opt.is_none() {
+        return Err(format_err!("This .sub file is MicroDVD, which is timed \
+            in frames of the video rather than in seconds, and it doesn't \
+            give its frame rate with a line like {{1}}{{1}}23.976.\n\n\
+            Use {} to give the frame rate of the video, like {}, or {} if \
+            it's a SubViewer file after all.", color::hint("--fps <rate>"),
+            color::hint("--fps 23.976"), color::hint("--format subviewer")));
+    }
+    // --fps wins, as the line may be wrong, but not silently:
+    let fps_mismatch_opt = match (options.fps_opt, header_fps_opt) {
+        (Some(given), Some(header)) if (given - header).abs() >= 0.0005 =>
+            Some((given, header)),
+        _ => None,
+    };
+    if let Some(to_srt) = to_srt_opt {
+        let converted = match (dialect, fps_opt) {
+            (Dialect::MicroDvd, Some(fps)) => microdvd::to_srt(contents, fps)?,
+            _ => subviewer::to_srt(contents)?,
+        };
+        // The byte order mark and the line endings go along:
+        let mut srt = [if bom { "\u{FEFF}" } else { "" }, &converted]
+            .concat();
+        if is_crlf(contents) {
+            srt = srt.replace('\n', "\r\n");
+        }
+        // The lines of the converted srt aren't those of the .sub file:
+        let (output, mut summary) = render(srt.as_bytes(), true, to_srt,
+            options).map_err(|error| match error.downcast::<PartialFailure>() {
+                Ok(mut failure) => {
+                    failure.progress.line_opt = None;
+                    failure.progress.offset_opt = None;
+                    failure.into()
+                },
+                Err(error) => error,
+            })?;
+        summary.decoded = decoded;
+        summary.sniffed_opt = sniffed_opt;
+        summary.fps_mismatch_opt = fps_mismatch_opt;
+        return Ok((output, summary));
+    }
+    if let Some(option) = ass_unsupported(options) {
+        return Err(format_err!("{} can't be used with .sub files, which \
+            submod only shifts, unless they're converted with \
+            {} or {}.", option, color::hint("--srt"), color::hint("--vtt")));
+    }
+    let (output, mut summary) = match (dialect, fps_opt) {
+        (Dialect::MicroDvd, Some(fps)) => shift_in_place(contents, decoded,
+            "subtitle", options, |contents, shift|
+                Ok(microdvd::shift(contents, fps, shift)))?,
+        _ => shift_in_place(contents, decoded, "subtitle", options,
+            |contents, shift| Ok(subviewer::shift(contents, shift)))?,
+    };
+    summary.sniffed_opt = sniffed_opt;
+    summary.fps_mismatch_opt = fps_mismatch_opt;
+    Ok((output, summary))
+}
+
+/// Converts a TTML document to srt or vtt as `to_srt_opt` gives, or else
+/// shifts the `begin`, `end` and `dur` of its timed elements, keeping its
+/// structure, styles and regions as they are.
+fn render_ttml(input: &[u8], to_srt_opt: Option<bool>, options: &Options)
+    -> Result<(Vec<u8>, Summary), Error>
+{
+    let (contents, decoded) = decode_input(input, options);
+    if let Some(to_srt) = to_srt_opt {
+        let (_, contents) = encoding::split_bom(&contents);
+        let srt = ttml::to_srt(contents)?;
+        // The lines of the converted srt aren't those of the document:
+        let (output, mut summary) = render(srt.as_bytes(), true, to_srt,
+            options).map_err(|error| match error.downcast::<PartialFailure>() {
+                Ok(mut failure) => {
+                    failure.progress.line_opt = None;
+                    failure.progress.offset_opt = None;
+                    failure.into()
+                },
+                Err(error) => error,
+            })?;
+        summary.decoded = decoded;
+        return Ok((output, summary));
+    }
+    if let Some(option) = ass_unsupported(options) {
+        return Err(format_err!("{} can't be used with TTML files, which \
+            submod only shifts, unless they're converted with \
+            {} or {}.", option, color::hint("--srt"), color::hint("--vtt")));
+    }
+    shift_in_place(&contents, decoded, "subtitle", options,
+        |contents, shift| Ok(ttml::shift(contents, shift)?))
+}
+
+/// Shifts every timing `shift` finds in the contents, numbered in the
+/// order they're found, like the cues of srt and vtt files. Those that end
+/// before the start with `--on-negative delete` are removed, and counted
+/// as deleted.
+fn shift_in_place<S>(contents: &str, decoded: Decoded, noun: &str,
+    options: &Options, shift: S) -> Result<(Vec<u8>, Summary), Error>
+    where S: FnOnce(&str, &mut dyn FnMut(f64, f64) -> Option<(f64, f64)>)
+        -> Result<String, Error>
+{
+    let mut summary = Summary { decoded, ..Summary::default() };
+    let (bom, contents) = encoding::split_bom(contents);
+    let output = shift(contents, &mut |start, end| {
+        summary.cues += 1;
+        let mut cue = Cue { number: summary.cues as usize, line: 0,
+            id: Vec::new(), start, end, settings: String::new(), text: Vec::new() };
+        if options.selection.contains(cue.number)
+            && cue::in_window(cue.start, cue.end, options.start_opt,
+                options.stop_opt) {
+            summary.selected += 1;
+            if !shift_cue(&mut cue, options, &mut summary) {
+                summary.deleted += 1;
+                return None;
+            }
+        }
+        Some((cue.start, cue.end))
+    })?;
+    check_negative(options, &summary, noun)?;
+    if options.strict && !summary.outliers.is_empty() {
+        return Err(format_err!("{} {}{} would be shifted further \
+            than the {} seconds --max-shift-per-cue allows:\n    {}",
+            summary.outliers.len(), noun, if summary.outliers.len() == 1 {
+                "" } else { "s" },
+            options.max_shift, helpers::format_outliers(&summary.outliers)));
+    }
+    // Every line keeps its own ending, unless they're all made the same:
+    let output = match options.line_ending {
+        LineEnding::Keep => output,
+        LineEnding::Lf => end_lines(&output, false),
+        LineEnding::Crlf => end_lines(&output, true),
+    };
+    let output = encode_output(output, bom, options, &mut summary);
+    Ok((output, summary))
+}
+
+/// Whether most lines of the text end with \r\n rather than \n.
+pub fn is_crlf(text: &str) -> bool {
+    let crlf = text.matches("\r\n").count();
+    crlf > text.matches('\n').count() - crlf
+}
+
+/// Ends every line of the text with \r\n if `crlf`, or else with \n.
+pub fn end_lines(text: &str, crlf: bool) -> String {
+    let text = text.replace("\r\n", "\n");
+    if crlf {
+        text.replace('\n', "\r\n")
+    } else {
+        text
+    }
+}
+
+/// Encodes the output in `options.output_encoding`, after a UTF-8 byte
+/// order mark when the input has one and `options.keep_bom` is set.
+fn encode_output(output: String, bom: bool, options: &Options,
+    summary: &mut Summary) -> Vec<u8>
+{
+    if options.output_encoding != encoding::UTF_8 {
+        let (bytes, replaced) = encoding::encode(&output,
+            options.output_encoding, options.fallback);
+        summary.replaced = replaced;
+        return bytes;
+    }
+    if bom && options.keep_bom {
+        return ["\u{FEFF}", &output].concat().into_bytes();
+    }
+    output.into_bytes()
+}
+
+/// Returns the first option that doesn't apply to .ass and .ssa scripts,
+/// or to .sub files kept as they are, as it works on the text or
+/// the blocks of srt and vtt files.
+fn ass_unsupported(options: &Options) -> Option<&'static str> {
+    let unsupported = [
+        (options.italic_opt.is_some(), "--only-italic"),
+        (options.pin_first || options.pin_last, "--pin-first-cue"),
+        (options.merge_opt.is_some(), "--merge-continuations"),
+        (options.fix_overlaps_opt.is_some(), "--fix-overlaps"),
+        (options.min_duration_opt.is_some(), "--min-duration"),
+        (options.max_duration.is_some(), "--max-duration"),
+        (options.min_confidence_opt.is_some(), "--drop-low-confidence"),
+        (options.prepend_cue_opt.is_some(), "--prepend-cue"),
+        (options.append_cue_opt.is_some(), "--append-cue"),
+        (options.min_start > 0.0, "--min-start"),
+        (options.lenient, "--lenient"),
+        (options.detect_lang, "--detect-lang"),
+        (options.map_timing, "--map-out"),
+        (options.stats_per_range, "--stats-per-range"),
+        (options.gen_ids.is_some(), "--gen-ids"),
+        (options.fix_rtl, "--fix-rtl"),
+        (options.normalize, "--normalize"),
+        (options.strip_tags, "--strip-tags"),
+        (!options.replacements.is_empty(), "--replace"),
+        (!options.class_renames.is_empty(), "--rename-class"),
+        (!options.region_renames.is_empty(), "--rename-region"),
+        (options.video_duration_opt.is_some(), "--video-duration"),
+        (options.reference_opt.is_some(), "--verify-against"),
+    ];
+    unsupported.iter()
+        .find(|&&(used, _)| used)
+        .map(|&(_, option)| option)
+}
+
+/// Transforms the input into the exact bytes to write to `output_path`.
+fn render(input: &[u8], from_srt: bool, to_srt: bool, options: &Options)
+    -> Result<(Vec<u8>, Summary), Error>
+{
+    let mut output = Vec::new();
+    let (input, decoded) = decode_input(input, options);
+    let (bom, input) = encoding::split_bom(&input);
+    let crlf = match options.line_ending {
+        LineEnding::Keep => is_crlf(input),
+        LineEnding::Lf => false,
+        LineEnding::Crlf => true,
+    };
+    let mut summary = process(input.as_bytes(), &mut output, from_srt, to_srt,
+        options).map_err(|error| match error.downcast::<PartialFailure>() {
+            // Offsets in the decoded text are only those of the file in UTF-8:
+            Ok(mut failure) => {
+                if decoded == Decoded::Utf8 {
+                    failure.progress.offset_opt = failure.progress.line_opt
+                        .map(|line| byte_offset(input, line)
+                            + if bom { "\u{FEFF}".len() } else { 0 });
+                }
+                failure.into()
+            },
+            Err(error) => error,
+        })?;
+    summary.decoded = decoded;
+    if options.strict && !summary.outliers.is_empty() {
+        return Err(format_err!("{} subtitle{} would be shifted further \
+            than the {} seconds --max-shift-per-cue allows:\n    {}\n\n\
+            Check the correction data, raise the limit, \
+            or drop --strict to only warn.",
+            summary.outliers.len(), if summary.outliers.len() == 1 {
+                "" } else { "s" },
+            options.max_shift, helpers::format_outliers(&summary.outliers)));
+    }
+    if let Some(ref residual) = summary.residual_opt {
+        if options.strict && residual.exceeds() {
+            return Err(format_err!("The subtitles are still {:+.3} seconds \
+                off from `{}`, more than the {} seconds --verify-tolerance \
+                allows.\n\n\
+                This file may need a different shift, or drop --strict \
+                to only warn.", residual.offset_opt.unwrap(),
+                residual.reference.display(), residual.tolerance));
+        }
+    }
+
+    // The cues are written with \n, like they're read without \r:
+    let output = end_lines(&String::from_utf8(output)?, crlf);
+    let output = encode_output(output, bom, options, &mut summary);
+    Ok((output, summary))
+}
+
+/// The offset of the first byte of a line of the text, starting at 1.
+fn byte_offset(text: &str, line: usize) -> usize {
+    text.split('\n').take(line - 1).map(|line| line.len() + 1).sum()
+}
+
+fn process<R: BufRead, W: Write>(reader: R, out: &mut W, from_srt: bool,
+    to_srt: bool, options: &Options) -> Result<Summary, Error>
+{
+    let (parsed, switch_opt) = cue::parse(reader)?;
+    check_timing_lines(&parsed, from_srt)?;
+    let (blocks, mut summary) =
+        modify(parsed, switch_opt, from_srt, to_srt, options)?;
+    if let Some(ref reference) = options.reference_opt {
+        summary.residual_opt = Some(verify::verify(&blocks, reference,
+            options.tolerance)?);
+    }
+    if options.output_ttml {
+        write_ttml(&blocks, out)?;
+    } else {
+        cue::write(&blocks, out, to_srt)?;
+    }
+    Ok(summary)
+}
+
+/// Writes the cues of the blocks as a TTML document, with the same writer
+/// as `transform` of the library.
+fn write_ttml<W: Write>(blocks: &[Block], out: &mut W) -> Result<(), Error> {
+    let cues: Vec<(f64, f64, String)> = blocks.iter()
+        .filter_map(|block| match *block {
+            Block::Cue(ref cue) => Some((cue.start, cue.end,
+                cue.text.join("\n"))),
+            _ => None,
+        })
+        .collect();
+    out.write_all(ttml::write(cues.iter()
+        .map(|&(start, end, ref text)| (start, end, text.as_str())))
+        .as_bytes())?;
+    Ok(())
+}
+
+/// Fails with an `Error::Parse` when the parsed file has no subtitles at all,
+/// or a block outside of any that looks like one with a malformed timing
+/// line: a line with `-->`, or a line after an srt index.
+pub fn check_timing_lines(blocks: &[Block], srt: bool) -> Result<(), Error> {
+    let example = if srt { "00:00:01,000 --> 00:00:04,000" }
+        else { "00:00:01.000 --> 00:00:04.000" };
+    let mut line = 1;
+    for block in blocks {
+        match *block {
+            Block::Cue(ref cue) => line += cue.id.len() + 1 + cue.text.len(),
+            Block::Other(ref lines) => {
+                let is_index = |text: &str| !text.is_empty()
+                    && text.chars().all(|c| c.is_ascii_digit());
+                let malformed_opt = if cue::is_note(&lines[0]) {
+                    None
+                } else if lines.len() > 1 && is_index(&lines[0]) {
+                    Some(1)
+                } else {
+                    lines.iter().position(|text| text.contains("-->"))
+                };
+                if let Some(i) = malformed_opt {
+                    return Err(SubmodError::Parse { line: Some(line + i),
+                        message: format!("`{}` isn't a timing line like {}.",
+                            lines[i].trim(), example) }.into());
+                }
+                line += lines.len();
+            },
+            Block::Empty => line += 1,
+        }
+    }
+    if !blocks.iter().any(|block| matches!(*block, Block::Cue(_))) {
+        return Err(SubmodError::Parse { line: None,
+            message: format!("No {} subtitles found, with timing lines like \
+                {}.", if srt { "srt" } else { "vtt" }, example) }.into());
+    }
+    Ok(())
+}
+
+/// Applies all modifications to the parsed blocks of a file. When one
+/// fails on a subtitle, the error is a `PartialFailure` telling how far it
+/// got; errors about the file as a whole, or its options, are left as is.
+pub fn modify(parsed: Vec<Block>, switch_opt: Option<FormatSwitch>,
+    from_srt: bool, to_srt: bool, options: &Options)
+    -> Result<(Vec<Block>, Summary), Error>
+{
+    let timings: Vec<(usize, usize, f64)> = parsed.iter()
+        .filter_map(|block| match *block {
+            Block::Cue(ref cue) => Some((cue.number, cue.line, cue.start)),
+            _ => None,
+        })
+        .collect();
+    let mut summary = Summary::default();
+    match modify_blocks(parsed, switch_opt, from_srt, to_srt, options,
+        &mut summary) {
+        Ok(blocks) => Ok((blocks, summary)),
+        Err(cause) => match summary.failed_cue_opt {
+            Some(failed) => Err(PartialFailure { cause,
+                progress: progress(&timings, failed) }.into()),
+            None => Err(cause),
+        },
+    }
+}
+
+/// How far the modifications got when the subtitle numbered `failed`
+/// failed.
+fn progress(timings: &[(usize, usize, f64)], failed: usize) -> Progress {
+    let cues = timings.iter().take_while(|&&(number, _, _)| number != failed)
+        .count();
+    let failed_line_opt = timings.get(cues).map(|&(_, line, _)| line);
+    let last_opt = cues.checked_sub(1).map(|i| timings[i]);
+    Progress {
+        cues,
+        total: timings.len(),
+        line_opt: failed_line_opt.or(last_opt.map(|(_, line, _)| line))
+            .filter(|&line| line > 0),
+        offset_opt: None,
+        last_start_opt: last_opt.map(|(_, _, start)| start),
+    }
+}
+
+fn modify_blocks(mut parsed: Vec<Block>, switch_opt: Option<FormatSwitch>,
+    from_srt: bool, to_srt: bool, options: &Options, summary: &mut Summary)
+    -> Result<Vec<Block>, Error>
+{
+    let mut blocks = Vec::new();
+    // Deleted subtitles also lose their trailing empty line:
+    let mut deleted = false;
+
+    if let Some(switch) = switch_opt {
+        if options.strict {
+            return Err(SubmodError::Parse { line: None,
+                message: format!("The input switches to {} at line \
+                {}, like when several files were concatenated.\n\n\
+                Use {} to merge all subtitles into one sorted list \
+                instead.", switch.description, switch.line,
+                color::hint("--lenient")) }.into());
+        }
+        if options.lenient {
+            parsed = cue::merge_segments(parsed);
+            summary.merged = true;
+        }
+        summary.format_switch = Some(switch);
+    }
+    if let Some(video) = options.video_duration_opt {
+        let subtitles = parsed.iter()
+            .filter_map(|block| match *block {
+                Block::Cue(ref cue) => Some(cue.end),
+                _ => None,
+            })
+            .fold(0.0, f64::max);
+        summary.duration_mismatch_opt = Some(DurationMismatch { subtitles,
+            video, margin: options.duration_margin });
+    }
+    if options.detect_lang {
+        #[cfg(feature = "langdetect")]
+        {
+            summary.language = Some(detect_language(&parsed));
+        }
+    }
+
+    let last = parsed.iter()
+        .filter(|block| matches!(block, Block::Cue(_)))
+        .count();
+    if let Some(threshold) = options.min_confidence_opt {
+        parsed = drop_low_confidence(parsed, threshold, summary)?;
+    }
+    let pinned = |number| (options.pin_first && number == 1)
+        || (options.pin_last && number == last);
+    let mut shifted = HashSet::new();
+    summary.replacements = options.replacements.iter()
+        .map(|(regex, _)| (regex.as_str().to_string(), 0))
+        .collect();
+
+    for block in parsed {
+        match block {
+            Block::Cue(mut cue) => {
+                let (timing, is_pinned) =
+                    ((cue.start, cue.end), pinned(cue.number));
+                let selected = summary.selected;
+                deleted = !modify_cue(&mut cue, options, is_pinned, from_srt,
+                    summary);
+                if options.map_timing || options.stats_per_range {
+                    summary.timing_map.push(TimingChange {
+                        cue: cue.number,
+                        old: timing,
+                        new_opt: None,
+                        rule_opt: if summary.selected > selected {
+                            Some(options.range_at(timing.0))
+                        } else {
+                            None
+                        },
+                    });
+                }
+                if deleted {
+                    drop_cue_note(&mut blocks)?;
+                } else {
+                    if (cue.start, cue.end) != timing {
+                        shifted.insert(cue.number);
+                    }
+                    blocks.push(Block::Cue(cue));
+                }
+            },
+            Block::Empty if deleted => deleted = false,
+            block => {
+                deleted = false;
+                blocks.push(block);
+            },
+        }
+    }
+    // A deleted last subtitle has no empty line to lose but the one before:
+    if deleted && blocks.last() == Some(&Block::Empty) {
+        blocks.pop();
+    }
+    check_negative(options, summary, "subtitle")?;
+    summary.leading_silence_opt = options.first_start_opt
+        .map(|first_start| (first_start, options.seconds));
+
+    if options.shift_starts != 0.0 || options.shift_ends != 0.0 {
+        summary.edge_shifts_opt =
+            Some((options.shift_starts, options.shift_ends));
+        let extension = options.shift_ends - options.shift_starts;
+        if extension > 0.0 {
+            cap_extended_ends(&mut blocks, extension, &shifted, summary);
+        }
+    }
+
+    if options.pin_first || options.pin_last {
+        blocks = trim_around_pinned(blocks, &pinned, &shifted, summary);
+    }
+
+    blocks = resolve_boundary_conflicts(blocks, &pinned, &shifted,
+        options.boundary_conflict, summary)?;
+
+    if let Some(min_gap) = options.fix_overlaps_opt {
+        blocks = fix_overlaps(blocks, min_gap, options.merge_collapsed,
+            summary);
+    }
+
+    if let Some(ref limits) = options.merge_opt {
+        blocks = merge_continuations(blocks, limits, summary);
+    }
+
+    if let Some(min_duration) = options.min_duration_opt {
+        extend_short_cues(&mut blocks, min_duration,
+            options.fix_overlaps_opt.unwrap_or(0.0), summary);
+    }
+
+    if let Some(max_duration) = options.max_duration {
+        if options.truncate_long {
+            truncate_long_cues(&mut blocks, max_duration, summary);
+        } else {
+            blocks = split_long_cues(blocks, max_duration,
+                options.split_weights, summary);
+        }
+    }
+
+    // Inserted before any conversion, so they're converted like the rest:
+    if options.prepend_cue_opt.is_some() || options.append_cue_opt.is_some() {
+        insert_credits(&mut blocks, options, summary);
+    }
+
+    if !from_srt {
+        rewrite_settings(&mut blocks, options.strict, summary)?;
+    }
+    blocks = rewrite_regions(blocks, &options.region_renames, to_srt,
+        summary);
+
+    if !to_srt && (options.strip_tags || !options.class_renames.is_empty()) {
+        blocks = rewrite_styles(blocks, options, summary);
+    }
+
+    if to_srt && !from_srt {
+        blocks = vtt_to_srt(blocks);
+    } else if from_srt && !to_srt {
+        blocks = srt_to_vtt(blocks, options.keep_numbering);
+    }
+
+    if let Some(mode) = options.gen_ids {
+        if !to_srt {
+            generate_ids(&mut blocks, mode);
+        }
+    }
+
+    if options.min_start > 0.0 {
+        blocks = enforce_min_start(blocks, options.min_start, summary);
+    }
+
+    if to_srt {
+        blocks = enforce_time_limit(blocks, cue::SRT_MAX_TIME,
+            options.overflow, summary)?;
+        number_cues(&mut blocks, options.keep_numbering);
+    }
+
+    if options.max_line_opt.is_some() || options.max_lines_opt.is_some() {
+        summary.overlong = find_overlong_cues(&blocks, options);
+    }
+
+    if options.map_timing || options.stats_per_range {
+        map_new_timing(&blocks, &mut summary.timing_map);
+    }
+    if options.stats_per_range {
+        summary.ranges = range_stats(options, &summary.timing_map);
+    }
+
+    Ok(blocks)
+}
+
+/// Finds the cues with a line longer than `Options::max_line_opt`, or more
+/// lines than `Options::max_lines_opt`, by their number.
+fn find_overlong_cues(blocks: &[Block], options: &Options) -> Vec<usize> {
+    blocks.iter()
+        .filter_map(|block| match *block {
+            Block::Cue(ref cue) => Some(cue),
+            _ => None,
+        })
+        .filter(|cue| options.max_lines_opt
+            .is_some_and(|max_lines| cue.text.len() > max_lines)
+            || options.max_line_opt.is_some_and(|max_line| cue.text.iter()
+                .any(|line| text::line_len(line, options.wide_chars)
+                    > max_line)))
+        .map(|cue| cue.number)
+        .collect()
+}
+
+/// Fills in the output timing of the recorded cues,
+/// leaving it empty for the cues that were deleted along the way.
+fn map_new_timing(blocks: &[Block], timing_map: &mut [TimingChange]) {
+    // Cues may have been reordered or split, but keep their number:
+    let mut timings: HashMap<usize, (f64, f64)> = HashMap::new();
+    for block in blocks {
+        if let Block::Cue(ref cue) = *block {
+            let timing = timings.entry(cue.number)
+                .or_insert((cue.start, cue.end));
+            timing.0 = timing.0.min(cue.start);
+            timing.1 = timing.1.max(cue.end);
+        }
+    }
+    for change in timing_map.iter_mut() {
+        change.new_opt = timings.get(&change.cue).cloned();
+    }
+}
+
+/// Counts the selected cues of every range that were shifted by its offset,
+/// that ended up elsewhere, and that were deleted.
+fn range_stats(options: &Options, timing_map: &[TimingChange])
+    -> Vec<RangeStats>
+{
+    let mut ranges: Vec<RangeStats> = options.ranges().into_iter()
+        .map(|(start, end_opt, seconds)| RangeStats { start, end_opt, seconds,
+            shifted: 0, clamped: 0, deleted: 0 })
+        .collect();
+    for change in timing_map {
+        let range = match change.rule_opt {
+            Some(rule) => &mut ranges[rule],
+            None => continue,
+        };
+        let (old_start, old_end) = change.old;
+        let offset = options.offset_at(old_start);
+        let expected = (old_start * options.factor + offset
+            + options.shift_starts,
+            old_end * options.factor + offset + options.shift_ends);
+        match change.new_opt {
+            None => range.deleted += 1,
+            Some(new) if (new.0 - expected.0).abs() < 0.0005
+                && (new.1 - expected.1).abs() < 0.0005 => range.shifted += 1,
+            Some(_) => range.clamped += 1,
+        }
+    }
+    ranges
+}
+
+/// Detects the language of the text of the first cues.
+#[cfg(feature = "langdetect")]
+pub fn detect_language(blocks: &[Block]) -> &'static str {
+    let mut sample: Vec<String> = blocks.iter()
+        .filter_map(|block| match *block {
+            Block::Cue(ref cue) => Some(cue.text.iter().cloned()),
+            _ => None,
+        })
+        .take(lang::SAMPLE_CUES)
+        .flatten()
+        .collect();
+    text::strip_tags(&mut sample);
+    lang::detect(&sample).unwrap_or("unknown")
+}
+
+/// Shifts the timing of a selected cue by the offset at its start,
+/// moving it to the start of the video if it now starts before it.
+/// Returns false if it now ends before the start of the video instead.
+fn shift_cue(cue: &mut Cue, options: &Options, summary: &mut Summary) -> bool {
+    let offset = options.offset_at(cue.start);
+    let old_start = cue.start;
+    cue.start = cue.start * options.factor + offset + options.shift_starts;
+    cue.end = cue.end * options.factor + offset + options.shift_ends;
+    // Starts shifted further than ends may pass them:
+    cue.end = cue.end.max(cue.start);
+
+    // Only guard the per-cue part of the shift, not the shift
+    // applied to the file as a whole:
+    let correction = cue.start - old_start - options.seconds
+        - options.shift_starts;
+    if options.max_shift > 0.0 && correction.abs() > options.max_shift {
+        summary.outliers.push((cue.number, correction));
+    }
+
+    // Decide on the times as they're written, rounded to the millisecond,
+    // as offsets that add up may land a hair before 00:00:00.000:
+    if cue::to_millis(cue.start) < 0 {
+        summary.earliest_start = summary.earliest_start.min(cue.start);
+        if cue::to_millis(cue.end) < 0 {
+            if options.on_negative == OnNegative::Delete {
+                return false;
+            }
+            cue.end = 0.001;
+        }
+        summary.clamped += 1;
+    }
+    cue.start = cue.start.max(0.0);
+    cue.end = cue.end.max(0.0);
+    true
+}
+
+/// Fails with `--on-negative error` when any cue was shifted to start
+/// before 00:00:00, giving the largest shift that would have been safe.
+fn check_negative(options: &Options, summary: &Summary, noun: &str)
+    -> Result<(), Error>
+{
+    if options.on_negative != OnNegative::Error
+        || summary.earliest_start >= 0.0 {
+        return Ok(());
+    }
+    Err(format_err!("{} {}{} would start before 00:00:00, the earliest \
+        at -{}.\n\n\
+        Shifting by {:+.3} seconds instead would keep them all; or use \
+        {} or {}.", summary.clamped, noun,
+        if summary.clamped == 1 { "" } else { "s" },
+        cue::format_time(-summary.earliest_start, false),
+        options.seconds - summary.earliest_start,
+        color::hint("--on-negative delete"),
+        color::hint("--on-negative clamp")))
+}
+
+/// Applies the time modifications and text filters to a single cue.
+/// Pinned cues keep their timing, but their text is still filtered.
+/// Returns false if the cue should be deleted instead.
+fn modify_cue(cue: &mut Cue, options: &Options, pinned: bool,
+    from_srt: bool, summary: &mut Summary) -> bool
+{
+    summary.cues += 1;
+    let mut matches_style = true;
+    if let Some(only_italic) = options.italic_opt {
+        let style = text::italic_style(&cue.text);
+        summary.italic_opt.get_or_insert(0);
+        match style {
+            Italic::Full => *summary.italic_opt.get_or_insert(0) += 1,
+            Italic::Partial => summary.partly_italic += 1,
+            Italic::None => {},
+        }
+        matches_style = (style == Italic::Full) == only_italic;
+    }
+
+    // All selectors must agree for a cue to be modified:
+    if !pinned
+        && options.selection.contains(cue.number)
+        && cue::in_window(cue.start, cue.end, options.start_opt,
+            options.stop_opt)
+        && matches_style {
+        summary.selected += 1;
+        summary.selection.push((cue.number, cue.timing_line(from_srt)));
+        if !shift_cue(cue, options, summary) {
+            // the subtitle is now scheduled before the start
+            // of the movie, so we can delete it:
+            summary.deleted += 1;
+            return false;
+        }
+    }
+
+    for (i, (regex, replacement)) in options.replacements.iter().enumerate() {
+        if text::replace(&mut cue.text, regex, replacement) {
+            summary.replacements[i].1 += 1;
+        }
+    }
+    if !options.replacements.is_empty() && cue.text.is_empty() {
+        summary.emptied += 1;
+        return false;
+    }
+
+    if options.normalize && text::normalize_whitespace(&mut cue.text) {
+        summary.normalized += 1;
+    }
+    if options.fix_rtl && text::fix_rtl(&mut cue.text) {
+        summary.rtl_fixed += 1;
+    }
+    if !options.class_renames.is_empty() {
+        text::rename_classes(&mut cue.text, &options.class_renames);
+    }
+    if options.strip_tags {
+        text::strip_tags(&mut cue.text);
+    }
+    true
+}
+
+/// Moves the ends of shifted cues that were lengthened by `extension`
+/// seconds back to where the next cue starts, if they now overlap it.
+/// Cues are never shortened by more than the extension, so cues that
+/// already overlapped are left overlapping.
+fn cap_extended_ends(blocks: &mut [Block], extension: f64,
+    shifted: &HashSet<usize>, summary: &mut Summary)
+{
+    let starts: Vec<f64> = blocks.iter()
+        .filter_map(|block| match *block {
+            Block::Cue(ref cue) => Some(cue.start),
+            _ => None,
+        })
+        .collect();
+    let cues = blocks.iter_mut().filter_map(|block| match *block {
+        Block::Cue(ref mut cue) => Some(cue),
+        _ => None,
+    });
+    for (cue, &next_start) in cues.zip(starts.iter().skip(1)) {
+        if shifted.contains(&cue.number) && cue.end > next_start
+            && next_start >= cue.start {
+            cue.end = next_start.max(cue.end - extension);
+            summary.capped += 1;
+        }
+    }
+}
+
+/// Shortens the cues that end less than `min_gap` seconds before the next
+/// cue starts, so players don't flicker. Cues that would be left without
+/// any duration are merged into the next cue with `merge`, and otherwise
+/// left as they are. Cues out of order don't overlap the next one as such,
+/// so they are left alone.
+fn fix_overlaps(mut blocks: Vec<Block>, min_gap: f64, merge: bool,
+    summary: &mut Summary) -> Vec<Block>
+{
+    let positions: Vec<usize> = blocks.iter().enumerate()
+        .filter(|(_, block)| matches!(block, Block::Cue(_)))
+        .map(|(i, _)| i)
+        .collect();
+    let mut merged = HashSet::new();
+    for pair in positions.windows(2) {
+        let (before, after) = blocks.split_at_mut(pair[1]);
+        let (cue, next) = match (&mut before[pair[0]], &mut after[0]) {
+            (Block::Cue(cue), Block::Cue(next)) => (cue, next),
+            _ => unreachable!(),
+        };
+        let (start, next_start) =
+            (cue::to_millis(cue.start), cue::to_millis(next.start));
+        let end = cue::to_millis(next.start - min_gap);
+        if next_start < start || cue::to_millis(cue.end) <= end {
+            continue;
+        }
+        if end > start {
+            cue.end = next.start - min_gap;
+            summary.overlaps_fixed += 1;
+        } else if merge {
+            next.start = cue.start;
+            next.end = next.end.max(cue.end);
+            next.text = cue.text.drain(..).chain(next.text.drain(..))
+                .collect();
+            merged.insert(pair[0]);
+            summary.collapsed_merged += 1;
+        } else {
+            summary.collapsed.push(cue.number);
+        }
+    }
+
+    // Merged subtitles also lose their trailing empty line:
+    let mut fixed = Vec::with_capacity(blocks.len());
+    let mut dropped = false;
+    for (i, block) in blocks.into_iter().enumerate() {
+        match block {
+            _ if merged.contains(&i) => dropped = true,
+            Block::Empty if dropped => dropped = false,
+            block => {
+                dropped = false;
+                fixed.push(block);
+            },
+        }
+    }
+    fixed
+}
+
+/// Trims the shifted cues that now overlap a pinned cue, so the pinned cue
+/// stays readable: cues are cut off where the pinned cue starts, or start
+/// where it ends, whichever keeps the most of them. Shifted cues that are
+/// entirely covered by a pinned cue are deleted.
+fn trim_around_pinned<F>(blocks: Vec<Block>, pinned: &F,
+    shifted: &HashSet<usize>, summary: &mut Summary) -> Vec<Block>
+    where F: Fn(usize) -> bool
+{
+    let pins: Vec<(f64, f64)> = blocks.iter()
+        .filter_map(|block| match *block {
+            Block::Cue(ref cue) if pinned(cue.number) =>
+                Some((cue.start, cue.end)),
+            _ => None,
+        })
+        .collect();
+
+    let mut trimmed = Vec::with_capacity(blocks.len());
+    // Deleted subtitles also lose their trailing empty line:
+    let mut deleted = false;
+    for block in blocks {
+        let mut cue = match block {
+            Block::Cue(cue) => cue,
+            Block::Empty if deleted => {
+                deleted = false;
+                continue;
+            },
+            block => {
+                deleted = false;
+                trimmed.push(block);
+                continue;
+            },
+        };
+        deleted = false;
+        if shifted.contains(&cue.number) {
+            let timing = (cue.start, cue.end);
+            for &(start, end) in &pins {
+                if cue.start >= end || cue.end <= start {
+                    continue;
+                }
+                if start - cue.start >= cue.end - end {
+                    cue.end = start;
+                } else {
+                    cue.start = end;
+                }
+            }
+            if cue.end <= cue.start {
+                summary.covered += 1;
+                deleted = true;
+                continue;
+            } else if (cue.start, cue.end) != timing {
+                summary.trimmed += 1;
+            }
+        }
+        trimmed.push(Block::Cue(cue));
+    }
+    trimmed
+}
+
+/// Finds the cues that overlap or come before the cue preceding them, because
+/// only one of both was shifted, like when a negative shift after `--start`
+/// moves the first shifted cue before the last unshifted one.
+/// Returns the pairs of cue numbers, in the order of the file.
+fn find_boundary_conflicts<F>(blocks: &[Block], pinned: &F,
+    shifted: &HashSet<usize>) -> Vec<(usize, usize)>
+    where F: Fn(usize) -> bool
+{
+    let mut conflicts = Vec::new();
+    // The last shifted and unshifted cue, by number and end:
+    let (mut last_shifted, mut last_unshifted) = (None, None);
+    for block in blocks {
+        let cue = match *block {
+            Block::Cue(ref cue) if !pinned(cue.number) => cue,
+            _ => continue,
+        };
+        let is_shifted = shifted.contains(&cue.number);
+        let other = if is_shifted { last_unshifted } else { last_shifted };
+        if let Some((number, end)) = other {
+            if cue.start < end {
+                conflicts.push((number, cue.number));
+            }
+        }
+        if is_shifted {
+            last_shifted = Some((cue.number, cue.end));
+        } else {
+            last_unshifted = Some((cue.number, cue.end));
+        }
+    }
+    conflicts
+}
+
+/// Applies the `--boundary-conflict` policy to the cues that overlap or
+/// are out of order after a partial shift.
+fn resolve_boundary_conflicts<F>(mut blocks: Vec<Block>, pinned: &F,
+    shifted: &HashSet<usize>, policy: BoundaryConflict,
+    summary: &mut Summary) -> Result<Vec<Block>, Error>
+    where F: Fn(usize) -> bool
+{
+    let conflicts = find_boundary_conflicts(&blocks, pinned, shifted);
+    if conflicts.is_empty() {
+        return Ok(blocks);
+    }
+    match policy {
+        BoundaryConflict::Error => {
+            summary.failed_cue_opt = Some(conflicts[0].1);
+            return Err(format_err!("{} subtitle{} \
+            would overlap or be out of order after the partial shift:\n    \
+            {}\n\n\
+            Use {} or {} to fix them, or {} to write them anyway.",
+                conflicts.len(),
+                if conflicts.len() == 1 { " pair" } else { " pairs" },
+                helpers::format_conflicts(&conflicts),
+                color::hint("--boundary-conflict reorder"),
+                color::hint("--boundary-conflict trim"),
+                color::hint("--boundary-conflict warn")));
+        },
+        BoundaryConflict::Warn => {},
+        BoundaryConflict::Reorder => blocks = cue::sort_cues(blocks),
+        BoundaryConflict::Trim => {
+            // Trimming a cue can reveal a conflict with the cue before it:
+            let mut pending = conflicts.clone();
+            while !pending.is_empty() {
+                blocks = trim_conflicts(blocks, &pending, summary);
+                pending = find_boundary_conflicts(&blocks, pinned, shifted);
+            }
+        },
+    }
+    summary.boundary_conflicts = conflicts;
+    summary.boundary_policy = policy;
+    Ok(blocks)
+}
+
+/// Trims the earlier cue of every conflict to end where the later one
+/// starts, and deletes it when nothing remains.
+fn trim_conflicts(blocks: Vec<Block>, conflicts: &[(usize, usize)],
+    summary: &mut Summary) -> Vec<Block>
+{
+    let starts: HashMap<usize, f64> = blocks.iter()
+        .filter_map(|block| match *block {
+            Block::Cue(ref cue) => Some((cue.number, cue.start)),
+            _ => None,
+        })
+        .collect();
+    let mut ends: HashMap<usize, f64> = HashMap::new();
+    for &(earlier, later) in conflicts {
+        let end = ends.entry(earlier).or_insert(f64::INFINITY);
+        *end = end.min(starts[&later]);
+    }
+
+    let mut kept = Vec::with_capacity(blocks.len());
+    // Deleted subtitles also lose their trailing empty line:
+    let mut deleted = false;
+    for block in blocks {
+        let mut cue = match block {
+            Block::Cue(cue) => cue,
+            Block::Empty if deleted => {
+                deleted = false;
+                continue;
+            },
+            block => {
+                deleted = false;
+                kept.push(block);
+                continue;
+            },
+        };
+        deleted = false;
+        if let Some(&end) = ends.get(&cue.number) {
+            cue.end = cue.end.min(end);
+            if cue.end <= cue.start {
+                summary.boundary_dropped += 1;
+                deleted = true;
+                continue;
+            }
+            summary.boundary_trimmed += 1;
+        }
+        kept.push(Block::Cue(cue));
+    }
+    if deleted && kept.last() == Some(&Block::Empty) {
+        kept.pop();
+    }
+    kept
+}
+
+/// Lengthens the cues that last less than `min_duration` seconds, but no
+/// further than `min_gap` seconds before the next cue starts. The last cue,
+/// and cues before one out of order, have no next cue to keep clear of.
+fn extend_short_cues(blocks: &mut [Block], min_duration: f64, min_gap: f64,
+    summary: &mut Summary)
+{
+    let starts: Vec<f64> = blocks.iter()
+        .filter_map(|block| match *block {
+            Block::Cue(ref cue) => Some(cue.start),
+            _ => None,
+        })
+        .collect();
+    let cues = blocks.iter_mut().filter_map(|block| match *block {
+        Block::Cue(ref mut cue) => Some(cue),
+        _ => None,
+    });
+    for (i, cue) in cues.enumerate() {
+        let wanted = cue.start + min_duration;
+        if cue::to_millis(cue.end) >= cue::to_millis(wanted) {
+            continue;
+        }
+        let end = match starts.get(i + 1) {
+            Some(&next_start) if next_start >= cue.start =>
+                wanted.min(next_start - min_gap),
+            _ => wanted,
+        };
+        if cue::to_millis(end) > cue::to_millis(cue.end) {
+            cue.end = end;
+            summary.extended += 1;
+        }
+        if cue::to_millis(end) < cue::to_millis(wanted) {
+            summary.extended_partly += 1;
+        }
+    }
+}
+
+/// Cuts the cues that last longer than `max_duration` seconds short.
+fn truncate_long_cues(blocks: &mut [Block], max_duration: f64,
+    summary: &mut Summary)
+{
+    for block in blocks {
+        if let Block::Cue(ref mut cue) = *block {
+            let end = cue.start + max_duration;
+            if cue::to_millis(cue.end) > cue::to_millis(end) {
+                cue.end = end;
+                summary.truncated += 1;
+            }
+        }
+    }
+}
+
+/// Splits the cues that last longer than `max_duration` seconds,
+/// and renumbers the srt indices if any were.
+fn split_long_cues(blocks: Vec<Block>, max_duration: f64,
+    weights: SplitWeights, summary: &mut Summary) -> Vec<Block>
+{
+    let mut result = Vec::with_capacity(blocks.len());
+    for block in blocks {
+        let cue = match block {
+            Block::Cue(cue) => cue,
+            block => {
+                result.push(block);
+                continue;
+            },
+        };
+        let index = is_index(&cue.id);
+        let id = cue.id.clone();
+        let pieces = split::split(cue, max_duration, weights);
+        if pieces.len() > 1 {
+            summary.split += 1;
+            summary.pieces += pieces.len() as i32;
+        }
+        for (i, mut piece) in pieces.into_iter().enumerate() {
+            if i > 0 {
+                result.push(Block::Empty);
+                // Every srt subtitle needs an index, renumbered below:
+                if index {
+                    piece.id = id.clone();
+                }
+            }
+            result.push(Block::Cue(piece));
+        }
+    }
+    if summary.split > 0 {
+        renumber_indices(&mut result);
+    }
+    result
+}
+
+/// Inserts the cues of `--prepend-cue` and `--append-cue`, lasting
+/// `credit_duration`: the first ends `credit_gap` before the first cue
+/// starts, if there's room for it, and the last starts `credit_gap` after
+/// the last cue ends. `\n` in their text breaks the line. They're numbered
+/// like the other cues, but have no number of the input.
+fn insert_credits(blocks: &mut Vec<Block>, options: &Options,
+    summary: &mut Summary)
+{
+    let credit = |text: &str, start: f64| Block::Cue(Cue {
+        number: 0,
+        line: 0,
+        id: Vec::new(),
+        start,
+        end: start + options.credit_duration,
+        settings: String::new(),
+        text: text.replace("\\n", "\n").lines().map(String::from).collect(),
+    });
+    let first_opt = blocks.iter()
+        .position(|block| matches!(block, Block::Cue(_)));
+    if let Some(ref text) = options.prepend_cue_opt {
+        let start = match first_opt.map(|i| &blocks[i]) {
+            Some(Block::Cue(first)) => first.start - options.credit_gap
+                - options.credit_duration,
+            _ => 0.0,
+        };
+        if cue::to_millis(start) < 0 {
+            summary.prepend_skipped = true;
+        } else {
+            let i = first_opt.unwrap_or(blocks.len());
+            let mut inserted = vec![credit(text, start)];
+            if i > 0 && blocks[i - 1] != Block::Empty {
+                inserted.insert(0, Block::Empty);
+            }
+            if first_opt.is_some() {
+                inserted.push(Block::Empty);
+            }
+            blocks.splice(i..i, inserted);
+            summary.credits += 1;
+        }
+    }
+    if let Some(ref text) = options.append_cue_opt {
+        let last_end = blocks.iter()
+            .filter_map(|block| match *block {
+                Block::Cue(ref cue) => Some(cue.end),
+                _ => None,
+            })
+            .fold(None, |last: Option<f64>, end| Some(last.map_or(end,
+                |last| last.max(end))));
+        let start = last_end.map_or(0.0, |end| end + options.credit_gap);
+        while blocks.last() == Some(&Block::Empty) {
+            blocks.pop();
+        }
+        if !blocks.is_empty() {
+            blocks.push(Block::Empty);
+        }
+        blocks.push(credit(text, start));
+        summary.credits += 1;
+    }
+}
+
+/// Joins the cues that continue the sentence of the cue before them
+/// with that cue, repeatedly, as long as the limits allow.
+fn merge_continuations(blocks: Vec<Block>, limits: &MergeLimits,
+    summary: &mut Summary) -> Vec<Block>
+{
+    let mut result: Vec<Block> = Vec::with_capacity(blocks.len());
+    // Where the last cue is, as long as only empty lines follow it:
+    let mut last_opt: Option<usize> = None;
+    for block in blocks {
+        match block {
+            Block::Cue(cue) => {
+                let merged_opt = last_opt.and_then(|last| match result[last] {
+                    Block::Cue(ref previous) =>
+                        merge::merge(previous, &cue, limits),
+                    _ => None,
+                });
+                match (merged_opt, last_opt) {
+                    (Some(merged), Some(last)) => {
+                        result.truncate(last);
+                        result.push(Block::Cue(merged));
+                        summary.continuations += 1;
+                    },
+                    _ => {
+                        last_opt = Some(result.len());
+                        result.push(Block::Cue(cue));
+                    },
+                }
+            },
+            Block::Empty => result.push(Block::Empty),
+            block => {
+                last_opt = None;
+                result.push(block);
+            },
+        }
+    }
+    if summary.continuations > 0 {
+        renumber_indices(&mut result);
+    }
+    result
+}
+
+/// Numbers the srt indices of the cues in order again,
+/// after cues were split or merged; vtt identifiers are kept.
+fn renumber_indices(blocks: &mut [Block]) {
+    let cues = blocks.iter_mut().filter_map(|block| match *block {
+        Block::Cue(ref mut cue) => Some(cue),
+        _ => None,
+    });
+    for (i, cue) in cues.enumerate() {
+        if is_index(&cue.id) {
+            cue.id[0] = (i + 1).to_string();
+        }
+    }
+}
+
+/// Keeps only the cues of a vtt file for an srt output, without their
+/// settings and the tags srt players don't know. The WEBVTT header and
+/// NOTE, STYLE and REGION blocks have no srt equivalent, so they're left
+/// out along with the empty lines around them.
+fn vtt_to_srt(blocks: Vec<Block>) -> Vec<Block> {
+    let mut converted = Vec::with_capacity(blocks.len());
+    for block in blocks {
+        if let Block::Cue(mut cue) = block {
+            if !converted.is_empty() {
+                converted.push(Block::Empty);
+            }
+            cue.settings.clear();
+            text::srt_tags(&mut cue.text);
+            converted.push(Block::Cue(cue));
+        }
+    }
+    converted
+}
+
+/// Turns the cues of an srt file into a vtt file that browsers accept:
+/// a WEBVTT header, and the cues with their formatting translated.
+/// Srt indices aren't needed in vtt, so they're left out, unless
+/// `keep_numbering` keeps them as cue identifiers. Anything else in
+/// the srt file would break the vtt file, so it's left out as well.
+fn srt_to_vtt(blocks: Vec<Block>, keep_numbering: bool) -> Vec<Block> {
+    let mut converted = vec![Block::Other(vec![String::from("WEBVTT")])];
+    for block in blocks {
+        if let Block::Cue(mut cue) = block {
+            converted.push(Block::Empty);
+            if !(keep_numbering && is_index(&cue.id)) {
+                cue.id.clear();
+            }
+            text::vtt_tags(&mut cue.text);
+            converted.push(Block::Cue(cue));
+        }
+    }
+    converted
+}
+
+/// Converts the blocks of an srt or vtt file to the other format, the way
+/// `modify` does without any options.
+pub fn convert(blocks: Vec<Block>, from_srt: bool, to_srt: bool) -> Vec<Block> {
+    let mut blocks = if to_srt && !from_srt {
+        vtt_to_srt(blocks)
+    } else if from_srt && !to_srt {
+        srt_to_vtt(blocks, false)
+    } else {
+        blocks
+    };
+    if to_srt {
+        number_cues(&mut blocks, false);
+    }
+    blocks
+}
+
+/// Numbers the cues of an srt output from 1, replacing whatever
+/// identifiers vtt cues had, or giving them one. With `keep_numbering`,
+/// cues that already have an index keep it.
+fn number_cues(blocks: &mut [Block], keep_numbering: bool) {
+    let cues = blocks.iter_mut().filter_map(|block| match *block {
+        Block::Cue(ref mut cue) => Some(cue),
+        _ => None,
+    });
+    for (i, cue) in cues.enumerate() {
+        if !(keep_numbering && is_index(&cue.id)) {
+            cue.id = vec![(i + 1).to_string()];
+        }
+    }
+}
+
+/// Whether the identifier of a cue is an srt index.
+fn is_index(id: &[String]) -> bool {
+    id.len() == 1 && id[0].parse::<u64>().is_ok()
+}
+
+/// Makes sure no timestamp exceeds the latest time the output format can
+/// represent, rather than writing timestamps with too many hour digits.
+fn enforce_time_limit(blocks: Vec<Block>, max_time: f64, overflow: Overflow,
+    summary: &mut Summary) -> Result<Vec<Block>, Error>
 {
-    let f = fs::File::open(input_path)?;
-    let reader = BufReader::new(f);
-    let timing = Regex::new(
-        r"(\d{2}:\d{2}:\d{2}[,.]\d{3}) --> (\d{2}:\d{2}:\d{2}[,.]\d{3})$")?;
+    retain_cues(blocks, |cue| {
+        // Rounding to milliseconds could still push the end past the limit:
+        if cue::to_millis(cue.end) <= cue::to_millis(max_time) {
+            return Ok(true);
+        }
+        match overflow {
+            Overflow::Error => {
+                summary.failed_cue_opt = Some(cue.number);
+                Err(format_err!("Subtitle {} \
+                would end at {}, past {}, the latest time \
+                srt timestamps can represent.\n\n\
+                Use {} or {} to write the file anyway.", cue.number,
+                cue::format_time(cue.end, true),
+                cue::format_time(max_time, true),
+                color::hint("--overflow clamp"),
+                color::hint("--overflow drop")))
+            },
+            Overflow::Drop => {
+                summary.overflowed += 1;
+                Ok(false)
+            },
+            Overflow::Clamp => {
+                summary.overflowed += 1;
+                cue.start = cue.start.min(max_time);
+                cue.end = max_time;
+                Ok(true)
+            },
+        }
+    })
+}
+
+/// Makes sure no cue starts before `--min-start`, as some players glitch
+/// on subtitles shown at the very start: earlier cues start at it instead,
+/// and those that would be left without any duration are dropped.
+fn enforce_min_start(blocks: Vec<Block>, min_start: f64,
+    summary: &mut Summary) -> Vec<Block>
+{
+    let min_millis = cue::to_millis(min_start);
+    let kept = retain_cues(blocks, |cue| {
+        if cue::to_millis(cue.start) >= min_millis {
+            Ok(true)
+        } else if cue::to_millis(cue.end) <= min_millis {
+            summary.min_start_dropped += 1;
+            Ok(false)
+        } else {
+            summary.min_start_moved += 1;
+            cue.start = min_start;
+            Ok(true)
+        }
+    });
+    // The closure never fails:
+    kept.unwrap()
+}
+
+/// Checks every cue with `keep`, which may modify it, and drops those it
+/// returns false for, along with their trailing empty line.
+fn retain_cues<F>(blocks: Vec<Block>, mut keep: F) -> Result<Vec<Block>, Error>
+    where F: FnMut(&mut Cue) -> Result<bool, Error>
+{
+    let mut kept = Vec::with_capacity(blocks.len());
+    let mut dropped = false;
+    for block in blocks {
+        let mut cue = match block {
+            Block::Cue(cue) => cue,
+            Block::Empty if dropped => {
+                dropped = false;
+                continue;
+            },
+            block => {
+                dropped = false;
+                kept.push(block);
+                continue;
+            },
+        };
+        dropped = !keep(&mut cue)?;
+        if dropped {
+            drop_cue_note(&mut kept)?;
+        } else {
+            kept.push(Block::Cue(cue));
+        }
+    }
+    // A dropped last subtitle has no empty line to lose but the one before:
+    if dropped && kept.last() == Some(&Block::Empty) {
+        kept.pop();
+    }
+    Ok(kept)
+}
+
+/// Matches the confidence in a NOTE, written as `confidence: 0.87` or as
+/// JSON like `{"confidence": 0.87}`, capturing the number.
+const CONFIDENCE: &str =
+    r#"(?i)(?:^|[^\w])"?confidence"?\s*:\s*"?(-?\d+(?:\.\d+)?(?:e[-+]?\d+)?)"#;
+
+/// The NOTE right before the end of `blocks`, with only empty lines after
+/// it, and where it is. Speech recognizers write the metadata of a cue,
+/// like their confidence in it, in such a NOTE before it.
+fn note_before(blocks: &[Block]) -> Option<(usize, &[String])> {
+    let i = blocks.iter().rposition(|block| *block != Block::Empty)?;
+    match blocks[i] {
+        Block::Other(ref lines) if cue::is_note(&lines[0]) =>
+            Some((i, lines)),
+        _ => None,
+    }
+}
 
-    let mut out = fs::File::create(output_path)?;
-    let mut skip: bool = false;
-    let mut deleted_subs = 0;
+/// The confidence given in the lines of a NOTE, if any.
+fn note_confidence(lines: &[String], pattern: &Regex) -> Option<f64> {
+    pattern.captures(&lines.join("\n"))
+        .and_then(|captures| captures[1].parse().ok())
+}
 
-    for line in reader.lines() {
-        let mut new_line = line?;
-        let is_timeline: bool = timing.is_match(&new_line);
+/// Removes the NOTE with the confidence of a cue that was just deleted
+/// from the end of `blocks`, so it isn't left to describe the next cue.
+/// Other NOTEs, like a comment on the whole file, are kept.
+fn drop_cue_note(blocks: &mut Vec<Block>) -> Result<(), Error> {
+    let pattern = Regex::new(CONFIDENCE)?;
+    if let Some((i, _)) = note_before(blocks)
+        .filter(|&(_, lines)| note_confidence(lines, &pattern).is_some()) {
+        blocks.truncate(i);
+    }
+    Ok(())
+}
 
-        if is_timeline {
-            new_line = new_line.replace(",", ".");
-            new_line = process_line(new_line, seconds, start_opt, stop_opt);
+/// Drops the cues a speech recognizer gave a lower confidence than
+/// `threshold` in the NOTE before them, along with that NOTE. Cues without
+/// a NOTE are kept, and so are those whose NOTE has no confidence that can
+/// be read, which are counted.
+fn drop_low_confidence(blocks: Vec<Block>, threshold: f64,
+    summary: &mut Summary) -> Result<Vec<Block>, Error>
+{
+    let pattern = Regex::new(CONFIDENCE)?;
+    let mut kept = Vec::with_capacity(blocks.len());
+    let mut dropped = false;
+    for block in blocks {
+        let cue = match block {
+            Block::Cue(cue) => cue,
+            Block::Empty if dropped => {
+                dropped = false;
+                continue;
+            },
+            block => {
+                dropped = false;
+                kept.push(block);
+                continue;
+            },
+        };
+        let note_opt = note_before(&kept)
+            .map(|(i, lines)| (i, note_confidence(lines, &pattern)));
+        match note_opt {
+            Some((i, Some(confidence))) if confidence < threshold => {
+                kept.truncate(i);
+                // It's still one of the cues of the input:
+                summary.cues += 1;
+                summary.low_confidence += 1;
+                dropped = true;
+                continue;
+            },
+            Some((_, None)) => summary.unreadable_confidence += 1,
+            _ => {},
+        }
+        kept.push(Block::Cue(cue));
+    }
+    // A dropped last subtitle has no empty line to lose but the one before:
+    if dropped && kept.last() == Some(&Block::Empty) {
+        kept.pop();
+    }
+    Ok(kept)
+}
 
-            if new_line == "(DELETED)\n" {
-                deleted_subs += 1;
-                skip = true; // skip/delete upcoming subtitles
-            } else if output_path.extension().unwrap() == "srt" {
-                // Convert back to '.srt' style:
-                new_line = new_line.replace(".", ",");
+/// Puts the settings of vtt cues back on their timing line when some
+/// generator wrapped them onto the lines after it, and writes settings
+/// given more than once with only their last value, in the canonical
+/// order. Other settings are left as they are, including unknown ones,
+/// but with `strict`, malformed settings are refused.
+fn rewrite_settings(blocks: &mut [Block], strict: bool, summary: &mut Summary)
+    -> Result<(), Error>
+{
+    for block in blocks.iter_mut() {
+        let cue = match *block {
+            Block::Cue(ref mut cue) => cue,
+            _ => continue,
+        };
+        // A cue needs some text, so its last line is never a continuation:
+        let continued = cue.text.iter().take(cue.text.len().saturating_sub(1))
+            .take_while(|line| CueSettings::is_continuation(line))
+            .count();
+        let mut original = cue.settings.clone();
+        for line in cue.text.drain(..continued) {
+            original = format!("{} {}", original, line.trim());
+        }
+        let settings = CueSettings::parse(&original);
+        if strict {
+            if let Some(setting) = settings.malformed.first() {
+                summary.failed_cue_opt = Some(cue.number);
+                return Err(SubmodError::Parse { line: None,
+                    message: format!("Subtitle {} has the malformed \
+                    setting `{}`.\n\n\
+                    Drop --strict to keep it as it is.", cue.number,
+                    setting) }.into());
             }
-        } else if skip {
-            // Subtitles can be 1 or 2 lines;
-            // only reset skip if we have arrived at an empty line:
-            if new_line == "" {
-                skip = false;
+        }
+        if continued > 0 {
+            summary.settings_joined += 1;
+        }
+        if !settings.duplicates.is_empty() {
+            summary.duplicate_settings += 1;
+        }
+        if continued > 0 || !settings.duplicates.is_empty() {
+            cue.settings = format_settings(&original, &settings);
+        }
+    }
+    Ok(())
+}
+
+/// Keeps the vtt REGION blocks and the `region:` settings of the cues
+/// referring to them consistent: renamed regions are renamed in both,
+/// and when converting to srt, which has no regions, both are dropped.
+/// References to regions that aren't defined are reported.
+fn rewrite_regions(blocks: Vec<Block>, renames: &[(String, String)],
+    to_srt: bool, summary: &mut Summary) -> Vec<Block>
+{
+    let renamed = |id: &str| renames.iter()
+        .find(|(old, _)| old == id)
+        .map_or(id.to_string(), |(_, new)| new.clone());
+    let mut defined = HashSet::new();
+    let mut rewritten = Vec::with_capacity(blocks.len());
+    // Dropped REGION blocks also lose their trailing empty line:
+    let mut dropped = false;
+
+    for block in blocks {
+        match block {
+            Block::Other(ref lines) if lines[0].trim_end() == "REGION" => {
+                if to_srt {
+                    summary.regions_dropped += 1;
+                    dropped = true;
+                    continue;
+                }
+                let lines = lines.iter()
+                    .map(|line| map_settings(line, "id:", |id| {
+                        let id = renamed(id);
+                        defined.insert(id.clone());
+                        Some(id)
+                    }))
+                    .collect();
+                rewritten.push(Block::Other(lines));
+            },
+            Block::Empty if dropped => dropped = false,
+            Block::Cue(mut cue) => {
+                dropped = false;
+                let mut settings = CueSettings::parse(&cue.settings);
+                if let Some(id) = settings.region.take() {
+                    settings.region = if to_srt { None } else {
+                        Some(renamed(&id)) };
+                    // Only rewrite the settings that changed:
+                    if settings.region.as_ref() != Some(&id) {
+                        cue.settings = format_settings(&cue.settings,
+                            &settings);
+                    }
+                }
+                rewritten.push(Block::Cue(cue));
+            },
+            block => {
+                dropped = false;
+                rewritten.push(block);
+            },
+        }
+    }
+
+    // Regions must be defined in the header, before any cue refers to them,
+    // so only check references once all of them are known:
+    for block in &rewritten {
+        if let Block::Cue(ref cue) = *block {
+            if let Some(id) = CueSettings::parse(&cue.settings).region {
+                if !defined.contains(&id)
+                    && !summary.dangling_regions.contains(&id) {
+                    summary.dangling_regions.push(id);
+                }
             }
-            continue;
         }
-        // Add \n to the lines before writing them:
-        out.write((new_line + "\n").as_bytes())?;
     }
+    rewritten
+}
 
-    return Ok(deleted_subs);
+/// Writes cue settings after the timestamps, keeping the whitespace
+/// that separated the original settings from them.
+fn format_settings(original: &str, settings: &CueSettings) -> String {
+    let settings = settings.to_string();
+    if settings.is_empty() {
+        return settings;
+    }
+    let leading = &original[..original.len() - original.trim_start().len()];
+    format!("{}{}", if leading.is_empty() { " " } else { leading }, settings)
 }
 
-fn process_line(time_line: String, seconds: f64,
-    start_opt: Option<f64>, stop_opt: Option<f64>) -> String
+/// Maps the values of the `key:value` settings in a line of settings
+/// separated by whitespace; settings mapped to `None` are removed.
+/// Lines without such settings are returned as they are.
+fn map_settings<F>(line: &str, key: &str, mut map: F) -> String
+    where F: FnMut(&str) -> Option<String>
 {
-    let (line_start, line_end): (f64, f64);
-    // Create block so &time_line borrow ends before return:
-    {
-        let start_str = &time_line[0..12];
-        let end_str = &time_line[17..29];
-
-        line_start = get_secs(start_str);
-        line_end = get_secs(end_str);
+    if !line.split_whitespace().any(|setting| setting.starts_with(key)) {
+        return line.to_string();
     }
+    let leading = &line[..line.len() - line.trim_start().len()];
+    let settings: Vec<String> = line.split_whitespace()
+        .filter_map(|setting| match setting.strip_prefix(key) {
+            Some(value) => map(value).map(|value| format!("{}{}", key, value)),
+            None => Some(setting.to_string()),
+        })
+        .collect();
+    if settings.is_empty() {
+        String::new()
+    } else {
+        format!("{}{}", leading, settings.join(" "))
+    }
+}
+
+/// Keeps the vtt STYLE blocks in line with the cue text: selectors of
+/// renamed classes are renamed as well, and after stripping tags,
+/// selectors of classes no cue uses anymore are removed.
+/// STYLE blocks that are left without any rules are dropped.
+fn rewrite_styles(blocks: Vec<Block>, options: &Options,
+    summary: &mut Summary) -> Vec<Block>
+{
+    let used: HashSet<String> = blocks.iter()
+        .flat_map(|block| match *block {
+            Block::Cue(ref cue) => text::classes(&cue.text),
+            _ => Vec::new(),
+        })
+        .collect();
 
-    if let Some(start_transform) = start_opt {
-        if line_end < start_transform {
-            return time_line;
+    let mut rewritten = Vec::with_capacity(blocks.len());
+    // Dropped STYLE blocks also lose their trailing empty line:
+    let mut dropped = false;
+    for block in blocks {
+        match block {
+            Block::Other(ref lines) if lines[0].trim_end() == "STYLE" => {
+                let (css, changes) = style::rewrite(&lines[1..], &used,
+                    &options.class_renames, options.strip_tags);
+                summary.styles_pruned += changes.pruned;
+                summary.styles_untouched.extend(changes.untouched);
+                dropped = css.is_empty();
+                if !dropped {
+                    let mut lines = vec![lines[0].clone()];
+                    lines.extend(css);
+                    rewritten.push(Block::Other(lines));
+                }
+            },
+            Block::Empty if dropped => dropped = false,
+            block => {
+                dropped = false;
+                rewritten.push(block);
+            },
         }
     }
-    if let Some(stop_transform) = stop_opt {
-        if line_start > stop_transform {
-            return time_line;
+    rewritten
+}
+
+/// Gives vtt cues without an identifier a generated one that is unique
+/// within the file. Existing identifiers are kept as they are.
+fn generate_ids(blocks: &mut [Block], mode: IdMode) {
+    let mut used: HashSet<String> = blocks.iter()
+        .filter_map(|block| match *block {
+            Block::Cue(ref cue) => cue.id.first().cloned(),
+            _ => None,
+        })
+        .collect();
+
+    let mut index = 0;
+    for block in blocks.iter_mut() {
+        let cue = match *block {
+            Block::Cue(ref mut cue) => cue,
+            _ => continue,
+        };
+        index += 1;
+        if !cue.id.is_empty() {
+            continue;
+        }
+        let id = match mode {
+            IdMode::None => continue,
+            IdMode::Index => index.to_string(),
+            IdMode::Timestamp => cue::format_time(cue.start, false),
+        };
+        // Derived identifiers get a numbered suffix when already taken:
+        let mut unique = id.clone();
+        let mut suffix = 0;
+        while used.contains(&unique) {
+            suffix += 1;
+            unique = format!("{}-{}", id, suffix);
         }
+        used.insert(unique.clone());
+        cue.id.push(unique);
     }
+}
+
+/// Converts a playback speed in percent, or one of the "pal" and "ntsc"
+/// aliases, to the factor by which timestamps should be multiplied.
+/// Media playing faster shows everything earlier, so timestamps are
+/// *divided* by the speed.
+pub fn get_speed_factor(speed_string: &str) -> f64 {
+    let percent = match speed_string {
+        // 23.976 fps film sped up to 25 fps PAL video:
+        "pal" => 25.0 / (24000.0 / 1001.0) * 100.0,
+        // And the reverse, slowed down from 25 fps to 23.976 fps:
+        "ntsc" => (24000.0 / 1001.0) / 25.0 * 100.0,
+        // can't panic since speed_string is validated by helpers::is_speed:
+        _ => speed_string.trim_end_matches('%').parse::<f64>().unwrap(),
+    };
+    100.0 / percent
+}
 
-    let start_string = build_time_string(line_start + seconds);
-    let end_string = build_time_string(line_end + seconds);
+/// Converts a `--rate` like `23.976:25`, as validated by helpers::is_rate,
+/// to the factor by which timestamps should be multiplied: subtitles made
+/// for 23.976 fps come earlier in a 25 fps video of the same frames.
+pub fn get_rate_factor(rate_string: &str) -> f64 {
+    let (from, to) = rate_string.split_at(rate_string.find(':').unwrap());
+    from.parse::<f64>().unwrap() / to[1..].parse::<f64>().unwrap()
+}
 
-    if end_string == "(DELETED)\n" {
-        end_string
-    } else if start_string == "(DELETED)\n" {
-        format!("00:00:00.000 --> {}", end_string)
+/// Processes a `--start` or `--stop` value, as validated by
+/// helpers::is_timing_or_percentage, into seconds. Percentages are of the
+/// `(start, end)` span of the file, from its first start to its last end.
+pub fn get_time(time_string: &str, span: (f64, f64)) -> f64 {
+    if time_string.ends_with('%') {
+        let percent: f64 = time_string.trim_end_matches('%').parse().unwrap();
+        span.0 + (span.1 - span.0) * percent / 100.0
     } else {
-        format!("{} --> {}", start_string, end_string)
+        get_secs(time_string)
     }
 }
 
-/// Processes a &str of the form 'hh:mm:ss.sss'
-/// into the total number of seconds as f64.
-pub fn get_secs(time_string: &str) -> f64 {
-    time_string.rsplit(":")
-        // can't panic since time_string is validated by regex:
-        .map(|t| t.parse::<f64>().unwrap())
-        .zip(&[1.0, 60.0, 3600.0])
-        .map(|(a, b)| a * b)
-        .sum()
+/// Reads the `(start, end)` span of the subtitles of a file,
+/// from the start of the first subtitle to the end of the last one.
+pub fn span(input_path: &Path) -> Result<(f64, f64), Error> {
+    let input = fs::read(input_path).map_err(|error| SubmodError::Io {
+        path: input_path.to_owned(), message: format!(
+            "Could not read `{}` to resolve percentages: {}",
+            input_path.display(), error) })?;
+    let (blocks, _) = cue::parse(encoding::decode(&input).0.as_bytes())?;
+    blocks.iter()
+        .filter_map(|block| match *block {
+            Block::Cue(ref cue) => Some((cue.start, cue.end)),
+            _ => None,
+        })
+        .fold(None, |span_opt: Option<(f64, f64)>, (start, end)|
+            Some(span_opt.map_or((start, end),
+                |span| (span.0.min(start), span.1.max(end)))))
+        .ok_or_else(|| format_err!("`{}` has no subtitles, \
+            so percentages of its duration can't be resolved.",
+            input_path.display()))
 }
 
-fn build_time_string(seconds: f64) -> String {
-    if seconds >= 0.0 {
-        let hours = seconds as u64 / 3600;
-        let mins = (seconds as u64 % 3600) / 60;
-        let secs = seconds % 60.0;
-        format!("{0:02}:{1:02}:{2:06.3}", hours, mins, secs)
-    } else {
-        // the subtitles are now scheduled before the start
-        // of the movie, so we can delete them:
-        String::from("(DELETED)\n")
+/// Processes a &str of the form 'hh:mm:ss=seconds', as validated by
+/// helpers::is_step, into a (time, seconds) breakpoint.
+pub fn get_step(step_string: &str) -> (f64, f64) {
+    let (time_string, seconds) = step_string.split_at(
+        step_string.find('=').unwrap());
+    (get_secs(time_string), seconds[1..].parse().unwrap())
+}
+
+/// A range of `--range` and `--shift`: its optional start and end,
+/// and the seconds it adds.
+pub type Range = (Option<f64>, Option<f64>, f64);
+
+/// Parses a range like `00:10:00-00:20:00=+3`, which adds seconds to the
+/// subtitles starting from its start up to, but not including, its end.
+/// Either time may be left out for a range from the beginning or to the
+/// end, like `-00:05:00=-2.0` or `01:30:00-=+1.5`. As times are never
+/// negative, the first `-` before the `=` always separates the times, and
+/// only the seconds after the `=` may have a minus sign.
+pub fn get_range(spec: &str) -> Result<Range, String> {
+    let usage = format!("Use {}, leaving out the start or end for an open \
+        range, like so:\n    {}, {} or {}", color::hint("start-end=seconds"),
+        color::hint("10:00-20:00=+3"), color::hint("-5:00=-2.0"),
+        color::hint("1:30:00-=1.5"));
+    let (times, seconds) = match spec.find('=') {
+        Some(i) => (&spec[..i], &spec[i + 1..]),
+        None => return Err(format!("missing '=' between the range and \
+            the seconds\n\n{}", usage)),
+    };
+    let (start, end) = match times.find('-') {
+        Some(i) if !times[i + 1..].contains('-') =>
+            (&times[..i], &times[i + 1..]),
+        _ => return Err(format!("a range needs a single '-' between its \
+            start and end\n\n{}", usage)),
+    };
+    let time = |time_string: &str| -> Result<Option<f64>, String> {
+        if time_string.is_empty() {
+            return Ok(None);
+        }
+        if time_string.split(':').all(|t| t.parse::<f64>().is_ok()) {
+            Ok(Some(get_secs(time_string)))
+        } else {
+            Err(format!("`{}` is not a time like hh:mm:ss\n\n{}",
+                time_string, usage))
+        }
+    };
+    let (start_opt, end_opt) = (time(start)?, time(end)?);
+    match (start_opt, end_opt) {
+        (None, None) => return Err(format!("a range needs a start, an end, \
+            or both; use <seconds> to shift everything\n\n{}", usage)),
+        (Some(start), Some(end)) if start >= end => return Err(format!(
+            "the range starts at or after its end\n\n{}", usage)),
+        _ => {},
+    }
+    let seconds = seconds.parse::<f64>().ok().filter(|s| s.is_finite())
+        .ok_or_else(|| format!("`{}` is not a number of seconds\n\n{}",
+            seconds, usage))?;
+    Ok((start_opt, end_opt, seconds))
+}
+
+/// Parses a comma-separated list of disjoint ranges for `--shift`, like
+/// `12:00-25:00=+30,25:00-=+60`, sorted by their start.
+pub fn get_ranges(spec: &str) -> Result<Vec<Range>, String> {
+    let mut ranges = spec.split(',')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .map(get_range)
+        .collect::<Result<Vec<_>, _>>()?;
+    if ranges.is_empty() {
+        return Err(format!("give at least one range, like {}",
+            color::hint("12:00-25:00=+30,25:00-=+60")));
+    }
+    ranges.sort_by(|a, b| a.0.unwrap_or(0.0).partial_cmp(&b.0.unwrap_or(0.0))
+        .unwrap());
+    for pair in ranges.windows(2) {
+        let next_start = pair[1].0.unwrap_or(0.0);
+        if pair[0].1.is_none_or(|end| end > next_start) {
+            return Err(format!("the ranges overlap at {}; each subtitle \
+                can only be in one of them", format_secs(next_start)));
+        }
+    }
+    Ok(ranges)
+}
+
+/// Turns a range into the steps that add its seconds at its start,
+/// and take them off again at its end.
+pub fn range_steps(range: Range) -> Vec<(f64, f64)> {
+    let (start_opt, end_opt, seconds) = range;
+    let mut steps = vec![(start_opt.unwrap_or(0.0), seconds)];
+    if let Some(end) = end_opt {
+        steps.push((end, -seconds));
+    }
+    steps
+}
+
+/// Formats seconds as 'hh:mm:ss.sss' for display.
+pub fn format_secs(seconds: f64) -> String {
+    cue::format_time(seconds, false)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use testing;
+    #[cfg(feature = "encodings")]
+    use encoding_rs;
+
+    fn run(input: &str, from_srt: bool, to_srt: bool, options: &Options)
+        -> String
+    {
+        let mut output = Vec::new();
+        process(input.as_bytes(), &mut output, from_srt, to_srt, options)
+            .unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    const VTT_WITH_IDS: &str = "WEBVTT\n\n\
+        intro\n00:00:01.000 --> 00:00:02.000 align:start\nHello\n\n\
+        00:00:03.000 --> 00:00:04.000\nNo identifier\n\n\
+        42\n00:00:05.000 --> 00:00:06.000\nBye\n";
+
+    #[cfg(unix)]
+    #[test]
+    fn bounds_reading_streams() {
+        let options = Options { max_input_size: 1024, ..Options::default() };
+        assert!(read_input(Path::new("/dev/null"), &options).unwrap()
+            .is_empty());
+        let error = read_input(Path::new("/dev/zero"), &options).unwrap_err();
+        assert!(error.to_string().contains("--max-input-size"));
+    }
+
+    #[test]
+    fn skips_writing_identical_outputs_unless_forced() {
+        let dir = testing::temp_dir("unchanged");
+        let (input, output) = (dir.join("in.srt"), dir.join("out.srt"));
+        fs::write(&input, "1\n00:00:01,000 --> 00:00:02,000\nHi\n").unwrap();
+
+        let summary = transform(&input, &output, &Options::default()).unwrap();
+        assert!(summary.unchanged);
+        assert!(!output.exists());
+
+        let options = Options { force: true, ..Options::default() };
+        let summary = transform(&input, &output, &options).unwrap();
+        assert!(!summary.unchanged);
+        assert_eq!(fs::read(&output).unwrap(), fs::read(&input).unwrap());
+
+        let options = Options { seconds: 1.0, ..Options::default() };
+        assert!(!transform(&input, &output, &options).unwrap().unchanged);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn retimes_a_companion_along_with_the_input() {
+        let dir = testing::temp_dir("companion");
+        let (input, companion) = (dir.join("in.vtt"), dir.join("meta.vtt"));
+        fs::write(&input, "WEBVTT\n\nintro\n00:00:01.000 --> 00:00:02.000\n\
+            A\n\n00:00:03.000 --> 00:00:04.000\nB\n\n\
+            end\n00:00:05.000 --> 00:00:06.000\nC\n").unwrap();
+        fs::write(&companion, "WEBVTT\n\nend\n00:00:05.000 --> 00:00:06.000\n\
+            {\"n\": 3}\n\nintro\n00:00:01.000 --> 00:00:02.000\n{\"n\": 1}\n\n\
+            00:00:03.000 --> 00:00:04.000\n{\"n\": 2}\n").unwrap();
+
+        let options = Options { seconds: 1.5, map_timing: true,
+            ..Options::default() };
+        let paired = pair_companion(&input, &companion, &options).unwrap();
+        assert_eq!(paired.pairs, vec![3, 1, 2]);
+        let summary = transform(&input, &dir.join("out.vtt"), &options)
+            .unwrap();
+        let output = dir.join("meta.out.vtt");
+        assert_eq!(write_companion(paired, &summary.timing_map, &output,
+            &options).unwrap(), CompanionSummary { cues: 3, deleted: 0 });
+        assert_eq!(fs::read_to_string(&output).unwrap(),
+            "WEBVTT\n\nend\n00:00:06.500 --> 00:00:07.500\n{\"n\": 3}\n\n\
+            intro\n00:00:02.500 --> 00:00:03.500\n{\"n\": 1}\n\n\
+            00:00:04.500 --> 00:00:05.500\n{\"n\": 2}\n");
+
+        fs::write(&companion, "WEBVTT\n\n00:00:01.000 --> 00:00:02.000\n\
+            {}\n").unwrap();
+        let error = pair_companion(&input, &companion, &options).unwrap_err();
+        assert!(error.to_string().contains("has 1 subtitle, but the input \
+            has 3"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn detects_options_that_change_nothing() {
+        assert!(Options::default().is_noop());
+        assert!(Options { start_opt: Some(10.0), pin_first: true,
+            italic_opt: Some(true), ..Options::default() }.is_noop());
+        assert!(Options { steps: vec![(10.0, 0.0)], ..Options::default() }
+            .is_noop());
+        assert!(!Options { seconds: 0.001, ..Options::default() }.is_noop());
+        assert!(!Options { factor: 1.04, ..Options::default() }.is_noop());
+        assert!(!Options { steps: vec![(10.0, -1.0)], ..Options::default() }
+            .is_noop());
+        assert!(!Options { strip_tags: true, ..Options::default() }.is_noop());
+        assert!(!Options { normalize: true, ..Options::default() }.is_noop());
+        assert!(!Options { gen_ids: Some(IdMode::None), ..Options::default() }
+            .is_noop());
+        assert!(!Options { class_renames: vec![(String::from("a"),
+            String::from("b"))], ..Options::default() }.is_noop());
+        #[cfg(feature = "encodings")]
+        assert!(!Options { output_encoding: encoding_rs::WINDOWS_1252,
+            ..Options::default() }.is_noop());
+        // Reading the input in another encoding writes it as UTF-8:
+        #[cfg(feature = "encodings")]
+        assert!(!Options { input_encoding_opt: Some(encoding_rs::WINDOWS_1252),
+            ..Options::default() }.is_noop());
+        assert!(!Options { keep_bom: true, ..Options::default() }.is_noop());
+    }
+
+    #[test]
+    fn compares_the_end_of_the_input_with_the_video() {
+        let srt = "1\n00:00:05,000 --> 00:00:08,000\nFirst\n\n\
+            2\n01:38:10,000 --> 01:38:12,000\nLast\n";
+        let (blocks, _) = cue::parse(srt.as_bytes()).unwrap();
+        let options = Options { seconds: 30.0,
+            video_duration_opt: Some(6700.0), ..Options::default() };
+        let (_, summary) = modify(blocks, None, true, true, &options).unwrap();
+        let mismatch = summary.duration_mismatch_opt.unwrap();
+        assert_eq!(mismatch.mismatch(), 5892.0 - 6700.0);
+        assert!(mismatch.exceeds());
+        assert!(!DurationMismatch { margin: 900.0, ..mismatch }.exceeds());
+    }
+
+    #[test]
+    fn shifts_starts_and_ends_separately() {
+        let srt = "1\n00:00:01,000 --> 00:00:02,000\nOne\n\n\
+            2\n00:00:02,100 --> 00:00:03,000\nTwo\n\n\
+            3\n00:00:05,000 --> 00:00:06,000\nThree\n";
+        let options = Options { seconds: 1.0, shift_starts: -0.1,
+            shift_ends: 0.2, ..Options::default() };
+        // The end of the first cue is capped to the start of the second:
+        assert_eq!(run(srt, true, true, &options),
+            "1\n00:00:01,900 --> 00:00:03,000\nOne\n\n\
+            2\n00:00:03,000 --> 00:00:04,200\nTwo\n\n\
+            3\n00:00:05,900 --> 00:00:07,200\nThree\n");
+
+        let options = Options { shift_ends: -0.2, ..Options::default() };
+        let (blocks, _) = cue::parse(srt.as_bytes()).unwrap();
+        let (_, summary) = modify(blocks, None, true, true, &options).unwrap();
+        assert_eq!(summary.edge_shifts_opt, Some((0.0, -0.2)));
+        assert_eq!(summary.capped, 0);
+
+        // Ends never come before starts:
+        let options = Options { shift_starts: 2.0, ..Options::default() };
+        assert!(run(srt, true, true, &options)
+            .starts_with("1\n00:00:03,000 --> 00:00:03,000\n"));
+    }
+
+    #[test]
+    fn merges_continuations_and_renumbers() {
+        let srt = "1\n00:00:01,000 --> 00:00:02,000\nWe could\n\n\
+            2\n00:00:02,000 --> 00:00:03,000\ngo there\n\n\
+            3\n00:00:03,000 --> 00:00:04,000\nright now.\n\n\
+            4\n00:00:04,000 --> 00:00:05,000\n- Sure\n";
+        let options = Options { merge_opt: Some(MergeLimits::default()),
+            ..Options::default() };
+        let (blocks, _) = cue::parse(srt.as_bytes()).unwrap();
+        let (blocks, summary) =
+            modify(blocks, None, true, true, &options).unwrap();
+        assert_eq!(summary.continuations, 2);
+        let mut output = Vec::new();
+        cue::write(&blocks, &mut output, true).unwrap();
+        // The third line doesn't fit, so the lines are joined:
+        assert_eq!(String::from_utf8(output).unwrap(),
+            "1\n00:00:01,000 --> 00:00:04,000\nWe could go there\n\
+            right now.\n\n\
+            2\n00:00:04,000 --> 00:00:05,000\n- Sure\n");
+    }
+
+    #[test]
+    fn resolves_percentages_against_the_span() {
+        let span = (10.0, 110.0);
+        assert_eq!(get_time("90%", span), 100.0);
+        assert_eq!(get_time("0%", span), 10.0);
+        assert_eq!(get_time("100%", span), 110.0);
+        assert_eq!(get_time("01:30", span), 90.0);
+    }
+
+    #[test]
+    fn replaces_cue_text_only() {
+        let rule = |rule| helpers::parse_replacement(rule).unwrap();
+        let options = Options { replacements: vec![rule("intro=outro"),
+            rule("^No (.*)$=Without $1"), rule("(?i)bye=")],
+            ..Options::default() };
+        let (blocks, switch_opt) = cue::parse(VTT_WITH_IDS.as_bytes()).unwrap();
+        let (blocks, summary) = modify(blocks, switch_opt, false, false,
+            &options).unwrap();
+        let mut output = Vec::new();
+        cue::write(&blocks, &mut output, false).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "WEBVTT\n\n\
+            intro\n00:00:01.000 --> 00:00:02.000 align:start\nHello\n\n\
+            00:00:03.000 --> 00:00:04.000\nWithout identifier\n");
+        let counts: Vec<usize> = summary.replacements.iter()
+            .map(|&(_, count)| count)
+            .collect();
+        assert_eq!(counts, [0, 1, 1]);
+        assert_eq!(summary.emptied, 1);
+    }
+
+    const PARTIAL_SRT: &str = "1\n00:00:10,000 --> 00:00:12,000\nA\n\n\
+        2\n00:00:20,000 --> 00:00:22,000\nB\n\n\
+        3\n00:00:30,000 --> 00:00:32,000\nC\n\n\
+        4\n00:00:40,000 --> 00:00:42,000\nD\n";
+
+    fn shift_after_start(policy: BoundaryConflict)
+        -> Result<(String, Summary), Error>
+    {
+        let options = Options { seconds: -15.0, start_opt: Some(25.0),
+            boundary_conflict: policy, ..Options::default() };
+        let (blocks, switch_opt) = cue::parse(PARTIAL_SRT.as_bytes())?;
+        let (blocks, summary) = modify(blocks, switch_opt, true, true,
+            &options)?;
+        let mut output = Vec::new();
+        cue::write(&blocks, &mut output, true)?;
+        Ok((String::from_utf8(output)?, summary))
+    }
+
+    #[test]
+    fn reports_cues_out_of_order_after_a_partial_shift() {
+        let (output, summary) =
+            shift_after_start(BoundaryConflict::Warn).unwrap();
+        assert_eq!(summary.boundary_conflicts, [(2, 3)]);
+        assert!(output.contains("00:00:15,000 --> 00:00:17,000\nC"));
+        assert!(shift_after_start(BoundaryConflict::Error).is_err());
+    }
+
+    #[test]
+    fn reorders_cues_out_of_order_after_a_partial_shift() {
+        let (output, _) =
+            shift_after_start(BoundaryConflict::Reorder).unwrap();
+        assert_eq!(output, "1\n00:00:10,000 --> 00:00:12,000\nA\n\n\
+            2\n00:00:15,000 --> 00:00:17,000\nC\n\n\
+            3\n00:00:20,000 --> 00:00:22,000\nB\n\n\
+            4\n00:00:25,000 --> 00:00:27,000\nD\n");
+    }
+
+    #[test]
+    fn trims_cues_out_of_order_after_a_partial_shift() {
+        let (output, summary) =
+            shift_after_start(BoundaryConflict::Trim).unwrap();
+        assert_eq!(output, "1\n00:00:10,000 --> 00:00:12,000\nA\n\n\
+            2\n00:00:15,000 --> 00:00:17,000\nC\n\n\
+            3\n00:00:25,000 --> 00:00:27,000\nD\n");
+        assert_eq!((summary.boundary_trimmed, summary.boundary_dropped),
+            (0, 1));
+    }
+
+    #[cfg(feature = "encodings")]
+    #[test]
+    fn repairs_windows_1252_with_a_utf_8_bom() {
+        let input = b"\xEF\xBB\xBF1\n00:00:01,000 --> 00:00:02,000\n\
+            Caf\xe9 cr\xe8me, \x93s'il vous pla\xeet\x94\n";
+        let (output, summary) = render(input, true, true,
+            &Options { seconds: 1.0, ..Options::default() }).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "1\n\
+            00:00:02,000 --> 00:00:03,000\n\
+            Café crème, “s'il vous plaît”\n");
+        assert_eq!(summary.decoded, Decoded::BomWindows1252);
+        let (output, _) = render(input, true, true,
+            &Options { seconds: 1.0, keep_bom: true, ..Options::default() })
+            .unwrap();
+        assert!(String::from_utf8(output).unwrap().starts_with("\u{FEFF}1\n"));
+    }
+
+    #[test]
+    fn keeps_the_line_endings_most_lines_have() {
+        let render_str = |input: &str, line_ending| {
+            let options = Options { seconds: 1.0, line_ending,
+                ..Options::default() };
+            let (output, _) = render(input.as_bytes(), true, true, &options)
+                .unwrap();
+            String::from_utf8(output).unwrap()
+        };
+        // Mixed endings, and no newline after the last cue:
+        let crlf = "1\r\n00:00:01,000 --> 00:00:02,000\r\nA\n\r\n\
+            2\r\n00:00:03,000 --> 00:00:04,000\r\nB";
+        let shifted = "1\n00:00:02,000 --> 00:00:03,000\nA\n\n\
+            2\n00:00:04,000 --> 00:00:05,000\nB\n";
+        assert_eq!(render_str(crlf, LineEnding::Keep),
+            shifted.replace('\n', "\r\n"));
+        assert_eq!(render_str(crlf, LineEnding::Lf), shifted);
+        let lf = end_lines(crlf, false);
+        assert_eq!(render_str(&lf, LineEnding::Keep), shifted);
+        assert_eq!(render_str(&lf, LineEnding::Crlf),
+            shifted.replace('\n', "\r\n"));
+
+        let script = "[Events]\r\nDialogue: 0,0:00:01.00,0:00:02.00,,,0,0,0,,A\n";
+        let options = Options { line_ending: LineEnding::Crlf,
+            ..Options::default() };
+        let (output, _) = render_path(script.as_bytes(), Path::new("a.ass"),
+            None, &options).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(),
+            script.replace("A\n", "A\r\n"));
+    }
+
+    #[test]
+    fn writes_utf_16_as_utf_8() {
+        let srt = "1\n00:00:01,000 --> 00:00:02,000\nÇa, c'est l'été…\n";
+        let mut utf16 = vec![0xFF, 0xFE];
+        utf16.extend(srt.encode_utf16().flat_map(u16::to_le_bytes));
+        let options = Options { seconds: 1.0, ..Options::default() };
+        let (output, summary) = render(&utf16, true, true, &options).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(),
+            srt.replace("01,000 --> 00:00:02", "02,000 --> 00:00:03"));
+        assert_eq!(summary.decoded, Decoded::Utf16);
+    }
+
+    #[cfg(feature = "encodings")]
+    #[test]
+    fn writes_given_encodings_as_utf_8() {
+        // 0xA4 is ¤ in windows-1252, which detection would assume:
+        let latin9 = b"1\n00:00:01,000 --> 00:00:02,000\n5 \xa4\n";
+        let options = Options {
+            input_encoding_opt: Some(encoding_rs::ISO_8859_15),
+            ..Options::default() };
+        let (output, summary) = render(latin9, true, true, &options).unwrap();
+        assert!(String::from_utf8(output).unwrap().ends_with("5 €\n"));
+        assert_eq!(summary.decoded,
+            Decoded::Given(encoding_rs::ISO_8859_15));
+    }
+
+    #[test]
+    fn splits_long_cues_and_renumbers_srt_indices() {
+        let input = "1\n00:00:01,000 --> 00:00:11,000\n\
+            Wait here. I'll be back\n\n\
+            2\n00:00:12,000 --> 00:00:13,000\nShort.\n";
+        let options = Options { max_duration: Some(6.0),
+            split_weights: SplitWeights::Words, ..Options::default() };
+        assert_eq!(run(input, true, true, &options), "1\n\
+            00:00:01,000 --> 00:00:05,000\nWait here.\n\n\
+            2\n00:00:05,000 --> 00:00:11,000\nI'll be back\n\n\
+            3\n00:00:12,000 --> 00:00:13,000\nShort.\n");
+    }
+
+    #[test]
+    fn keeps_vtt_identifiers_with_their_text() {
+        let options = Options { seconds: 1.5, ..Options::default() };
+        assert_eq!(run(VTT_WITH_IDS, false, false, &options), "WEBVTT\n\n\
+            intro\n00:00:02.500 --> 00:00:03.500 align:start\nHello\n\n\
+            00:00:04.500 --> 00:00:05.500\nNo identifier\n\n\
+            42\n00:00:06.500 --> 00:00:07.500\nBye\n");
+    }
+
+    #[test]
+    fn keeps_identifiers_of_remaining_cues_after_deletions() {
+        let options = Options { seconds: -2.5, ..Options::default() };
+        assert_eq!(run(VTT_WITH_IDS, false, false, &options), "WEBVTT\n\n\
+            00:00:00.500 --> 00:00:01.500\nNo identifier\n\n\
+            42\n00:00:02.500 --> 00:00:03.500\nBye\n");
+    }
+
+    #[test]
+    fn generates_only_missing_identifiers() {
+        let options = Options {
+            gen_ids: Some(IdMode::Index), ..Options::default() };
+        assert_eq!(run(VTT_WITH_IDS, false, false, &options), "WEBVTT\n\n\
+            intro\n00:00:01.000 --> 00:00:02.000 align:start\nHello\n\n\
+            2\n00:00:03.000 --> 00:00:04.000\nNo identifier\n\n\
+            42\n00:00:05.000 --> 00:00:06.000\nBye\n");
+    }
+
+    #[test]
+    fn generates_unique_identifiers_from_srt() {
+        let srt = "1\n00:00:01,000 --> 00:00:02,000\nA\n\n\
+            2\n00:00:01,000 --> 00:00:02,000\nB\n\n\
+            3\n00:00:01,000 --> 00:00:02,000\nC\n";
+        let options = Options {
+            gen_ids: Some(IdMode::Timestamp), ..Options::default() };
+        assert_eq!(run(srt, true, false, &options),
+            "WEBVTT\n\n00:00:01.000\n00:00:01.000 --> 00:00:02.000\nA\n\n\
+            00:00:01.000-1\n00:00:01.000 --> 00:00:02.000\nB\n\n\
+            00:00:01.000-2\n00:00:01.000 --> 00:00:02.000\nC\n");
+
+        let options = Options {
+            gen_ids: Some(IdMode::None), ..Options::default() };
+        assert_eq!(run(srt, true, false, &options),
+            "WEBVTT\n\n00:00:01.000 --> 00:00:02.000\nA\n\n\
+            00:00:01.000 --> 00:00:02.000\nB\n\n\
+            00:00:01.000 --> 00:00:02.000\nC\n");
+    }
+
+    #[test]
+    fn flags_cues_with_excessive_corrections() {
+        let srt = "1\n00:00:01,000 --> 00:00:02,000\nA\n\n\
+            2\n00:10:00,000 --> 00:10:02,000\nB\n";
+        let options = Options {
+            seconds: 700.0,
+            steps: vec![(60.0, -650.0)],
+            ..Options::default()
+        };
+        let mut output = Vec::new();
+        let summary = process(srt.as_bytes(), &mut output, true, true,
+            &options).unwrap();
+        assert_eq!(summary.outliers, [(2, -650.0)]);
+
+        let options = Options { max_shift: 0.0, ..options };
+        let summary = process(srt.as_bytes(), &mut output, true, true,
+            &options).unwrap();
+        assert!(summary.outliers.is_empty());
+    }
+
+    #[test]
+    fn flags_cues_with_too_long_or_too_many_lines() {
+        let srt = "1\n00:00:01,000 --> 00:00:02,000\n<i>Short enough</i>\n\n\
+            2\n00:00:03,000 --> 00:00:04,000\nA line that's far too long\n\n\
+            3\n00:00:05,000 --> 00:00:06,000\nOne\nTwo\nThree\n";
+        let options = Options {
+            max_line_opt: Some(12),
+            max_lines_opt: Some(2),
+            ..Options::default()
+        };
+        let mut output = Vec::new();
+        let summary = process(srt.as_bytes(), &mut output, true, true,
+            &options).unwrap();
+        assert_eq!(summary.overlong, [2, 3]);
+        assert_eq!(summary.warnings(), 1);
+
+        // Eight kana and ideographs fit in 12 characters, but not 12 columns:
+        let srt = "1\n00:00:01,000 --> 00:00:02,000\n昨日お店に行った\n";
+        let summary = process(srt.as_bytes(), &mut Vec::new(), true, true,
+            &options).unwrap();
+        assert!(summary.overlong.is_empty());
+        let options = Options { wide_chars: true, ..options };
+        let summary = process(srt.as_bytes(), &mut Vec::new(), true, true,
+            &options).unwrap();
+        assert_eq!(summary.overlong, [1]);
+    }
+
+    #[test]
+    fn starts_at_the_millisecond_of_a_pasted_timestamp() {
+        let srt = "1\n00:00:08,000 --> 00:00:10,437\nA\n\n\
+            2\n00:00:08,000 --> 00:00:10,438\nB\n";
+        let options = Options {
+            seconds: 1.0,
+            start_opt: Some(get_time("00:00:10,438", (0.0, 0.0))),
+            ..Options::default()
+        };
+        assert_eq!(run(srt, true, true, &options),
+            "1\n00:00:08,000 --> 00:00:10,437\nA\n\n\
+            2\n00:00:09,000 --> 00:00:11,438\nB\n");
+    }
+
+    #[test]
+    fn window_boundaries_are_exact_to_the_millisecond() {
+        // Cues 1-3 end around --start, and cues 4-6 start around --stop:
+        let timings = ["00:00:10.000 --> 00:00:59.999",
+            "00:00:10.000 --> 00:01:00.000", "00:00:10.000 --> 00:01:00.001",
+            "00:01:59.999 --> 00:02:30.000", "00:02:00.000 --> 00:02:30.000",
+            "00:02:00.001 --> 00:02:30.000"];
+        let srt: String = timings.iter().enumerate()
+            .map(|(i, timing)| format!("{}\n{}\nA\n\n", i + 1,
+                timing.replace('.', ",")))
+            .collect();
+        let vtt: String = timings.iter()
+            .fold(String::from("WEBVTT\n\n"), |vtt, timing|
+                vtt + timing + "\nA\n\n");
+        // Seconds that add up to slightly off the boundaries as floats:
+        let options = Options {
+            seconds: 1.0,
+            start_opt: Some(0.1 * 3.0 * 200.0),
+            stop_opt: Some(0.29 * 100.0 * 4.0 + 4.0),
+            ..Options::default()
+        };
+        assert_ne!((options.start_opt, options.stop_opt),
+            (Some(60.0), Some(120.0)));
+        for &(input, from_srt) in &[(&srt, true), (&vtt, false)] {
+            let mut output = Vec::new();
+            let summary = process(input.as_bytes(), &mut output, from_srt,
+                from_srt, &options).unwrap();
+            let selected: Vec<usize> = summary.selection.iter()
+                .map(|&(number, _)| number)
+                .collect();
+            assert_eq!(selected, [2, 3, 4]);
+        }
+    }
+
+    #[test]
+    fn scales_by_the_ratio_of_the_frame_rates() {
+        assert!((get_rate_factor("23.976:25") - 0.95904).abs() < 1e-9);
+        assert_eq!(get_rate_factor("25:25"), 1.0);
+        assert_eq!(get_rate_factor("25:23.976"), 25.0 / 23.976);
+        let srt = "1\n00:10:00,000 --> 00:10:02,000\nA\n";
+        let options = Options { seconds: 1.0,
+            factor: get_rate_factor("24:25"), ..Options::default() };
+        assert_eq!(run(srt, true, true, &options),
+            "1\n00:09:37,000 --> 00:09:38,920\nA\n");
+    }
+
+    #[test]
+    fn parses_open_and_closed_ranges() {
+        assert_eq!(get_range("10:00-20:00=+3"),
+            Ok((Some(600.0), Some(1200.0), 3.0)));
+        assert_eq!(get_range("-00:05:00=-2.0"), Ok((None, Some(300.0), -2.0)));
+        assert_eq!(get_range("01:30:00-=+1.5"), Ok((Some(5400.0), None, 1.5)));
+        assert_eq!(get_range("0-1.5=-0.25"), Ok((Some(0.0), Some(1.5), -0.25)));
+    }
+
+    #[test]
+    fn rejects_ambiguous_ranges() {
+        for spec in &["10:00=3", "-=3", "-10:00-20:00=3", "10:00--20:00=3",
+            "20:00-10:00=3", "10:00-10:00=3", "1:x-=3", "-5:00", "-5:00=",
+            "-5:00=soon", "-5:00=inf"] {
+            assert!(get_range(spec).is_err(), "{}", spec);
+        }
+    }
+
+    #[test]
+    fn parses_lists_of_disjoint_ranges() {
+        assert_eq!(get_ranges("25:00-=+60, 12:00-25:00=+30"),
+            Ok(vec![(Some(720.0), Some(1500.0), 30.0),
+                (Some(1500.0), None, 60.0)]));
+        for spec in &["", ",", "12:00-25:00=30,20:00-=60",
+            "-25:00=30,12:00-13:00=60", "12:00-=30,12:00-=60"] {
+            assert!(get_ranges(spec).is_err(), "{}", spec);
+        }
+    }
+
+    #[test]
+    fn shifts_ranges_on_top_of_the_seconds() {
+        let srt = "1\n00:00:01,000 --> 00:00:02,000\nA\n\n\
+            2\n00:05:00,000 --> 00:05:01,000\nB\n\n\
+            3\n01:30:00,000 --> 01:30:01,000\nC\n";
+        let mut steps: Vec<(f64, f64)> = ["-05:00=-0.5", "01:30:00-=1.5"]
+            .iter()
+            .flat_map(|spec| range_steps(get_range(spec).unwrap()))
+            .collect();
+        steps.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+        let options = Options { seconds: 1.0, steps, ..Options::default() };
+        assert_eq!(run(srt, true, true, &options),
+            "1\n00:00:01,500 --> 00:00:02,500\nA\n\n\
+            2\n00:05:01,000 --> 00:05:02,000\nB\n\n\
+            3\n01:30:02,500 --> 01:30:03,500\nC\n");
+    }
+
+    #[test]
+    fn shifts_scripts_within_the_window() {
+        let script = include_str!("../tests/fixtures/modern.ass");
+        let options = Options { seconds: -1.25, start_opt: Some(1.2),
+            ..Options::default() };
+        let (output, summary) = render_path(script.as_bytes(),
+            Path::new("modern.ass"), None, &options).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), script
+            .replace("0,0:00:01.50,0:00:03.25,", "0,0:00:00.25,0:00:02.00,")
+            .replace("1,0:01:59.99,0:02:01.00,", "1,0:01:58.74,0:01:59.75,"));
+        assert_eq!((summary.cues, summary.selected), (3, 2));
+
+        let options = Options { normalize: true, ..Options::default() };
+        assert!(render_path(script.as_bytes(), Path::new("modern.ass"), None,
+            &options).is_err());
+    }
+
+    #[test]
+    fn deletes_what_ends_before_the_start_in_place() {
+        let options = Options { seconds: -5.0, fps_opt: Some(25.0),
+            ..Options::default() };
+        for &(path, input, expected) in &[
+            ("m.sub", "{25}{50}Gone\n{100}{200}Kept\n", "{0}{75}Kept\n"),
+            ("s.sub", "00:00:01.00,00:00:02.00\nGone\n\n\
+                00:00:04.00,00:00:08.00\nKept\n",
+                "00:00:00.00,00:00:03.00\nKept\n"),
+            ("e.ass", "[Events]\n\
+                Dialogue: 0,0:00:01.00,0:00:02.00,Default,,0,0,0,,Gone\n\
+                Dialogue: 0,0:00:04.00,0:00:08.00,Default,,0,0,0,,Kept\n",
+                "[Events]\n\
+                Dialogue: 0,0:00:00.00,0:00:03.00,Default,,0,0,0,,Kept\n"),
+            ("t.ttml", "<tt><body>\n<p begin=\"1s\" end=\"2s\">Gone</p>\n\
+                <p begin=\"4s\" end=\"8s\">Kept</p>\n</body></tt>",
+                "<tt><body>\n<p begin=\"0s\" end=\"3s\">Kept</p>\n\
+                </body></tt>")] {
+            let (output, summary) = render_path(input.as_bytes(),
+                Path::new(path), None, &options).unwrap();
+            assert_eq!(String::from_utf8(output).unwrap(), expected, "{}", path);
+            assert_eq!((summary.cues, summary.deleted, summary.clamped), (2, 1, 1),
+                "{}", path);
+        }
+        let options = Options { on_negative: OnNegative::Clamp, ..options };
+        let (output, summary) = render_path(b"{25}{50}Kept\n",
+            Path::new("m.sub"), None, &options).unwrap();
+        assert!(output.ends_with(b"Kept\n"));
+        assert_eq!((summary.deleted, summary.clamped), (0, 1));
+    }
+
+    #[test]
+    fn reads_microdvd_frames_at_the_rate_of_the_file() {
+        let microdvd = "{1}{1}25.000\n{25}{50}A\n";
+        let shift = |fps_opt| render_path(microdvd.as_bytes(),
+            Path::new("m.sub"), None, &Options { seconds: 1.0, fps_opt,
+                ..Options::default() }).unwrap();
+        let (output, summary) = shift(None);
+        assert_eq!(output, b"{1}{1}25.000\n{50}{75}A\n");
+        assert_eq!((summary.fps_mismatch_opt, summary.warnings()), (None, 0));
+        let (_, summary) = shift(Some(25.0));
+        assert_eq!(summary.fps_mismatch_opt, None);
+        // --fps wins, with a warning:
+        let (output, summary) = shift(Some(50.0));
+        assert_eq!(output, b"{1}{1}25.000\n{75}{100}A\n");
+        assert_eq!((summary.fps_mismatch_opt, summary.warnings()),
+            (Some((50.0, 25.0)), 1));
+    }
+
+    #[test]
+    fn shifts_or_converts_ttml_files() {
+        let ttml = "<tt xmlns=\"http://www.w3.org/ns/ttml\"><body>\
+            <p begin=\"1s\" end=\"2s\">A<br/>B</p>\
+            <p begin=\"5s\" end=\"6s\">C</p></body></tt>";
+        let options = Options { seconds: 1.0, start_opt: Some(3.0),
+            ..Options::default() };
+        let (output, summary) = render_path(ttml.as_bytes(),
+            Path::new("news.dfxp"), None, &options).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(),
+            ttml.replace("\"5s\" end=\"6s\"", "\"6s\" end=\"7s\""));
+        assert_eq!((summary.cues, summary.selected), (2, 1));
+        let (output, _) = render_path(ttml.as_bytes(), Path::new("news.ttml"),
+            Some(true), &options).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(),
+            "1\n00:00:01,000 --> 00:00:02,000\nA\nB\n\n\
+            2\n00:00:06,000 --> 00:00:07,000\nC\n");
+
+        let options = Options { strip_tags: true, ..Options::default() };
+        assert!(render_path(ttml.as_bytes(), Path::new("news.ttml"), None,
+            &options).is_err());
+    }
+
+    #[test]
+    fn shifts_or_converts_subviewer_files() {
+        let sub = "[INFORMATION]\n[TITLE]Pilot\n[END INFORMATION]\n\
+            00:00:01.00,00:00:02.00\nA[br]B\n\n00:00:05.00,00:00:06.00\nC\n";
+        let options = Options { seconds: 1.0, start_opt: Some(3.0),
+            ..Options::default() };
+        let (output, summary) = render_path(sub.as_bytes(),
+            Path::new("pilot.sub"), None, &options).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), sub
+            .replace("00:00:05.00,00:00:06.00", "00:00:06.00,00:00:07.00"));
+        assert_eq!((summary.cues, summary.selected), (2, 1));
+        let (output, _) = render_path(sub.as_bytes(), Path::new("pilot.sub"),
+            Some(false), &options).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "WEBVTT\n\n\
+            00:00:01.000 --> 00:00:02.000\nA\nB\n\n\
+            00:00:06.000 --> 00:00:07.000\nC\n");
+
+        let options = Options { strip_tags: true, ..Options::default() };
+        assert!(render_path(sub.as_bytes(), Path::new("pilot.sub"), None,
+            &options).is_err());
+        assert!(render_path(sub.as_bytes(), Path::new("pilot.sub"), Some(true),
+            &options).is_ok());
+        let microdvd = "{25}{50}A|B\n";
+        assert!(render_path(microdvd.as_bytes(), Path::new("pilot.sub"), None,
+            &Options::default()).is_err());
+        let options = Options { seconds: 1.0, fps_opt: Some(25.0),
+            ..Options::default() };
+        let (output, _) = render_path(microdvd.as_bytes(),
+            Path::new("pilot.sub"), None, &options).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "{50}{75}A|B\n");
+        let (output, _) = render_path(microdvd.as_bytes(),
+            Path::new("pilot.sub"), Some(true), &options).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(),
+            "1\n00:00:02,000 --> 00:00:03,000\nA\nB\n");
+    }
+
+    #[test]
+    fn counts_cues_per_range() {
+        let srt = "1\n00:00:01,000 --> 00:00:02,000\nA\n\n\
+            2\n00:00:05,000 --> 00:00:06,000\nB\n\n\
+            3\n00:01:00,000 --> 00:01:02,000\nC\n\n\
+            4\n00:01:10,000 --> 00:01:12,000\nD\n\n\
+            5\n00:03:00,000 --> 00:03:02,000\nE\n";
+        let options = Options {
+            seconds: -5.5,
+            start_opt: Some(3.0),
+            steps: vec![(60.0, 1.0), (120.0, -200.0)],
+            stats_per_range: true,
+            ..Options::default()
+        };
+        let mut output = Vec::new();
+        let summary = process(srt.as_bytes(), &mut output, true, true,
+            &options).unwrap();
+        let range = |start, end_opt, seconds, shifted, clamped, deleted|
+            RangeStats { start, end_opt, seconds, shifted, clamped, deleted };
+        // Cue 1 ends before --start, and cue 2 is clamped to 00:00:00:
+        assert_eq!(summary.ranges, [range(3.0, Some(60.0), -5.5, 0, 1, 0),
+            range(60.0, Some(120.0), -4.5, 2, 0, 0),
+            range(120.0, None, -204.5, 0, 0, 1)]);
+        assert_eq!(summary.timing_map.iter()
+            .map(|change| change.rule_opt)
+            .collect::<Vec<_>>(), [None, Some(0), Some(1), Some(1), Some(2)]);
+    }
+
+    const CREDITS: &str = "1\n00:00:01,000 --> 00:00:04,000\nSubtitles by Anna\n\n\
+        2\n00:00:10,000 --> 00:00:12,000\nHello.\n\n\
+        3\n00:00:20,000 --> 00:00:22,000\nBye.\n\n\
+        4\n00:00:25,000 --> 00:00:28,000\nThe End\n";
+
+    #[test]
+    fn pinned_cues_keep_their_timing_when_shifting_forward() {
+        let options = Options {
+            seconds: 4.0, pin_first: true, pin_last: true,
+            ..Options::default()
+        };
+        // Cue 3 now overlaps the last cue, and is cut off where it starts:
+        assert_eq!(run(CREDITS, true, true, &options),
+            "1\n00:00:01,000 --> 00:00:04,000\nSubtitles by Anna\n\n\
+            2\n00:00:14,000 --> 00:00:16,000\nHello.\n\n\
+            3\n00:00:24,000 --> 00:00:25,000\nBye.\n\n\
+            4\n00:00:25,000 --> 00:00:28,000\nThe End\n");
+    }
+
+    #[test]
+    fn pinned_cues_keep_their_timing_when_shifting_backward() {
+        let options = Options {
+            seconds: -7.0, pin_first: true, ..Options::default()
+        };
+        // Cue 2 now overlaps the first cue, and starts where it ends:
+        assert_eq!(run(CREDITS, true, true, &options),
+            "1\n00:00:01,000 --> 00:00:04,000\nSubtitles by Anna\n\n\
+            2\n00:00:04,000 --> 00:00:05,000\nHello.\n\n\
+            3\n00:00:13,000 --> 00:00:15,000\nBye.\n\n\
+            4\n00:00:18,000 --> 00:00:21,000\nThe End\n");
+
+        let options = Options { seconds: -8.5, ..options };
+        let mut output = Vec::new();
+        let summary = process(CREDITS.as_bytes(), &mut output, true, true,
+            &options).unwrap();
+        assert_eq!((summary.trimmed, summary.covered), (0, 1));
+        assert!(String::from_utf8(output).unwrap().starts_with(
+            "1\n00:00:01,000 --> 00:00:04,000\nSubtitles by Anna\n\n\
+            2\n00:00:11,500 --> 00:00:13,500\nBye.\n"));
+    }
+
+    // An srt file that was appended to a vtt file, as some tools do:
+    const CONCATENATED: &str = "WEBVTT\n\n\
+        00:00:01.000 --> 00:00:02.000\nFirst\n\n\
+        00:00:05.000 --> 00:00:06.000\nThird\n\n\
+        1\n00:00:03,000 --> 00:00:04,000\nSecond\n\n\
+        2\n00:00:07,000 --> 00:00:08,000\nFourth\n";
+
+    #[test]
+    fn merges_concatenated_files_when_lenient() {
+        let options = Options {
+            seconds: 1.0, lenient: true, ..Options::default() };
+        let mut output = Vec::new();
+        let summary = process(CONCATENATED.as_bytes(), &mut output, false,
+            false, &options).unwrap();
+        assert_eq!(summary.format_switch, Some(FormatSwitch {
+            line: 9, description: "srt timestamps after a WEBVTT header" }));
+        assert_eq!(String::from_utf8(output).unwrap(), "WEBVTT\n\n\
+            00:00:02.000 --> 00:00:03.000\nFirst\n\n\
+            2\n00:00:04.000 --> 00:00:05.000\nSecond\n\n\
+            00:00:06.000 --> 00:00:07.000\nThird\n\n\
+            4\n00:00:08.000 --> 00:00:09.000\nFourth\n");
+    }
+
+    #[test]
+    fn rejects_concatenated_files_when_strict() {
+        let srt = "1\n00:00:01,000 --> 00:00:02,000\nA\n\n\
+            2\n00:00:03,000 --> 00:00:04,000\nB\n\n\
+            1\n00:00:02,000 --> 00:00:03,000\nC\n";
+        let options = Options { strict: true, ..Options::default() };
+        let mut output = Vec::new();
+        let error = process(srt.as_bytes(), &mut output, true, true, &options)
+            .unwrap_err();
+        assert!(error.to_string().starts_with("The input switches to \
+            srt numbering that starts over at line 9"));
+    }
+
+    #[test]
+    fn counts_the_warnings_of_a_summary() {
+        assert_eq!(Summary::default().warnings(), 0);
+        let summary = Summary { deleted: 2, capped: 1,
+            replacements: vec![(String::from("a"), 0), (String::from("b"), 3),
+                (String::from("c"), 0)],
+            boundary_conflicts: vec![(1, 2)],
+            boundary_policy: BoundaryConflict::Reorder,
+            styles_untouched: vec![String::from("::cue(.a .b)")],
+            ..Summary::default() };
+        assert_eq!(summary.warnings(), 4);
+    }
+
+    #[test]
+    fn keeps_timing_lookalikes_in_the_text() {
+        let srt = include_str!("../tests/fixtures/lookalike.srt");
+        let options = Options { seconds: 1.0, ..Options::default() };
+        let mut output = Vec::new();
+        let summary = process(srt.as_bytes(), &mut output, true, true,
+            &options).unwrap();
+        assert_eq!(summary.cues, 3);
+        assert_eq!(summary.format_switch, None);
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(output.lines().filter(|line| line.contains("-->")).count(),
+            srt.lines().filter(|line| line.contains("-->")).count());
+        assert!(output.contains("00:00:02,000 --> 00:00:05,000\n\
+            A timing line looks like this:\n00:00:01,000 --> 00:00:04,000\n"));
+
+        // Without the empty line, an srt index still starts the next cue:
+        let srt = "1\n00:00:01,000 --> 00:00:02,000\nA\n\
+            2\n00:00:03,000 --> 00:00:04,000\nB\n";
+        assert_eq!(run(srt, true, true, &options), "1\n\
+            00:00:02,000 --> 00:00:03,000\nA\n\
+            2\n00:00:04,000 --> 00:00:05,000\nB\n");
+    }
+
+    const VTT_WITH_REGIONS: &str = "WEBVTT\n\n\
+        REGION\nid:fred width:40%\nlines:3\n\n\
+        00:00:01.000 --> 00:00:02.000 region:fred align:left\nHi\n\n\
+        00:00:03.000 --> 00:00:04.000 region:bill\nBye\n";
+
+    #[test]
+    fn renames_regions_and_their_references() {
+        let options = Options {
+            region_renames: vec![(String::from("fred"), String::from("top"))],
+            ..Options::default()
+        };
+        let mut output = Vec::new();
+        let summary = process(VTT_WITH_REGIONS.as_bytes(), &mut output,
+            false, false, &options).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "WEBVTT\n\n\
+            REGION\nid:top width:40%\nlines:3\n\n\
+            00:00:01.000 --> 00:00:02.000 align:left region:top\nHi\n\n\
+            00:00:03.000 --> 00:00:04.000 region:bill\nBye\n");
+        assert_eq!(summary.dangling_regions, ["bill"]);
+    }
+
+    #[test]
+    fn joins_wrapped_and_duplicate_settings_onto_the_timing_line() {
+        let vtt = "WEBVTT\n\n\
+            00:00:01.000 --> 00:00:02.000 line:84% align:start\n\
+            size:80%  align:middle\nposition:10% is where it is\n\n\
+            00:00:03.000 --> 00:00:04.000 x-future:1 line:0 line:-1\nB\n\n\
+            00:00:05.000 --> 00:00:06.000 size:80%\nline:0\n";
+        let (blocks, _) = cue::parse(vtt.as_bytes()).unwrap();
+        let (blocks, summary) = modify(blocks, None, false, false,
+            &Options::default()).unwrap();
+        let mut output = Vec::new();
+        cue::write(&blocks, &mut output, false).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "WEBVTT\n\n\
+            00:00:01.000 --> 00:00:02.000 line:84% size:80% align:center\n\
+            position:10% is where it is\n\n\
+            00:00:03.000 --> 00:00:04.000 line:-1 x-future:1\nB\n\n\
+            00:00:05.000 --> 00:00:06.000 size:80%\nline:0\n");
+        assert_eq!((summary.settings_joined, summary.duplicate_settings),
+            (1, 2));
+
+        let strict = Options { strict: true, ..Options::default() };
+        assert!(run(vtt, false, false, &strict).starts_with("WEBVTT"));
+        let malformed = vtt.replace("size:80%\n", "size:180%\n");
+        let (blocks, _) = cue::parse(malformed.as_bytes()).unwrap();
+        let error = modify(blocks, None, false, false, &strict).unwrap_err();
+        assert_eq!(error.to_string().lines().next(),
+            Some("Subtitle 3 has the malformed setting `size:180%`."));
+    }
+
+    #[test]
+    fn drops_regions_when_converting_to_srt() {
+        let mut output = Vec::new();
+        let summary = process(VTT_WITH_REGIONS.as_bytes(), &mut output,
+            false, true, &Options::default()).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(),
+            "1\n00:00:01,000 --> 00:00:02,000\nHi\n\n\
+            2\n00:00:03,000 --> 00:00:04,000\nBye\n");
+        assert_eq!(summary.regions_dropped, 1);
+        assert!(summary.dangling_regions.is_empty());
+    }
+
+    #[test]
+    fn rounds_before_deleting_cues_shifted_to_the_start() {
+        // 0.1 + 0.2 is a hair more than 0.3 in floating point:
+        let srt = "1\n00:00:00,100 --> 00:00:00,300\nA\n\n\
+            2\n00:00:01,000 --> 00:00:02,000\nB\n";
+        let options = Options { seconds: -0.1, steps: vec![(0.0, -0.2)],
+            ..Options::default() };
+        let mut output = Vec::new();
+        let summary = process(srt.as_bytes(), &mut output, true, true,
+            &options).unwrap();
+        assert_eq!((summary.deleted, summary.clamped), (0, 1));
+        assert_eq!(String::from_utf8(output).unwrap(),
+            "1\n00:00:00,000 --> 00:00:00,000\nA\n\n\
+            2\n00:00:00,700 --> 00:00:01,700\nB\n");
+    }
+
+    #[test]
+    fn deletes_clamps_or_refuses_cues_shifted_before_the_start() {
+        let srt = "1\n00:00:01,000 --> 00:00:02,000\nA\n\n\
+            2\n00:00:03,000 --> 00:00:04,000\nB\n\n\
+            3\n00:00:05,000 --> 00:00:06,000\nC\n";
+        let shift = |on_negative| {
+            let options = Options { seconds: -3.5, on_negative,
+                ..Options::default() };
+            let mut output = Vec::new();
+            process(srt.as_bytes(), &mut output, true, true, &options)
+                .map(|summary| (String::from_utf8(output).unwrap(),
+                    (summary.deleted, summary.clamped)))
+        };
+        assert_eq!(shift(OnNegative::Delete).unwrap(),
+            ("1\n00:00:00,000 --> 00:00:00,500\nB\n\n\
+            2\n00:00:01,500 --> 00:00:02,500\nC\n".to_string(), (1, 1)));
+        assert_eq!(shift(OnNegative::Clamp).unwrap(),
+            ("1\n00:00:00,000 --> 00:00:00,001\nA\n\n\
+            2\n00:00:00,000 --> 00:00:00,500\nB\n\n\
+            3\n00:00:01,500 --> 00:00:02,500\nC\n".to_string(), (0, 2)));
+        let error = shift(OnNegative::Error).unwrap_err().to_string();
+        assert!(error.starts_with("2 subtitles would start before 00:00:00, \
+            the earliest at -00:00:02.500."), "{}", error);
+        assert!(error.contains("Shifting by -1.000 seconds instead"));
+    }
+
+    #[test]
+    fn converts_srt_to_vtt_that_browsers_accept() {
+        let srt = "1\n00:00:01,000 --> 00:00:02,000\n\
+            <font color=\"#00ff00\">Hey</font>\n\n\
+            2\n00:00:03,000 --> 00:00:04,000\n{\\an8}<i>Up here</i>\n";
+        assert_eq!(run(srt, true, false, &Options::default()), "WEBVTT\n\n\
+            00:00:01.000 --> 00:00:02.000\nHey\n\n\
+            00:00:03.000 --> 00:00:04.000\n<i>Up here</i>\n");
+        let keep = Options { keep_numbering: true, ..Options::default() };
+        assert!(run(srt, true, false, &keep).starts_with("WEBVTT\n\n\
+            1\n00:00:01.000 --> 00:00:02.000\nHey\n\n2\n"));
+    }
+
+    #[test]
+    fn lengthens_short_cues_and_truncates_long_ones() {
+        let srt = "1\n00:00:01,000 --> 00:00:01,500\nA\n\n\
+            2\n00:00:02,000 --> 00:00:09,000\nB\n\n\
+            3\n00:00:10,000 --> 00:00:10,500\nC\n";
+        let options = Options { min_duration_opt: Some(2.0),
+            max_duration: Some(5.0), truncate_long: true,
+            ..Options::default() };
+        let (blocks, _) = cue::parse(srt.as_bytes()).unwrap();
+        let (_, summary) = modify(blocks, None, true, true, &options).unwrap();
+        assert_eq!((summary.extended, summary.extended_partly,
+            summary.truncated), (2, 1, 1));
+        assert_eq!(run(srt, true, true, &options),
+            "1\n00:00:01,000 --> 00:00:02,000\nA\n\n\
+            2\n00:00:02,000 --> 00:00:07,000\nB\n\n\
+            3\n00:00:10,000 --> 00:00:12,000\nC\n");
+
+        let gap = Options { fix_overlaps_opt: Some(0.1), ..options };
+        assert!(run(srt, true, true, &gap)
+            .starts_with("1\n00:00:01,000 --> 00:00:01,900\n"));
+    }
+
+    #[test]
+    fn drops_low_confidence_cues_with_their_note() {
+        let vtt = "WEBVTT\n\nNOTE by a recognizer\n\n\
+            NOTE {\"confidence\": 0.9}\n\n00:00:01.000 --> 00:00:02.000\nA\n\n\
+            NOTE confidence: 0.2\n\n00:00:03.000 --> 00:00:04.000\nB\n\n\
+            NOTE {\"avg_confidence\": 0.1}\n\n00:00:05.000 --> 00:00:06.000\nC\n";
+        let options = Options { min_confidence_opt: Some(0.5),
+            ..Options::default() };
+        let (blocks, _) = cue::parse(vtt.as_bytes()).unwrap();
+        let (_, summary) = modify(blocks, None, false, false, &options)
+            .unwrap();
+        assert_eq!((summary.cues, summary.low_confidence,
+            summary.unreadable_confidence), (3, 1, 1));
+        assert_eq!(run(vtt, false, false, &options), "WEBVTT\n\n\
+            NOTE by a recognizer\n\n\
+            NOTE {\"confidence\": 0.9}\n\n00:00:01.000 --> 00:00:02.000\nA\n\n\
+            NOTE {\"avg_confidence\": 0.1}\n\n00:00:05.000 --> 00:00:06.000\nC\n");
+
+        // Cues deleted otherwise take their confidence along as well:
+        let shift = Options { seconds: -2.5, ..Options::default() };
+        assert!(run(vtt, false, false, &shift).starts_with("WEBVTT\n\n\
+            NOTE by a recognizer\n\nNOTE confidence: 0.2\n\n\
+            00:00:00.500 --> 00:00:01.500\nB\n"));
+    }
+
+    #[test]
+    fn inserts_credits_around_the_cues() {
+        let srt = "1\n00:00:05,000 --> 00:00:06,000\nA\n\n\
+            2\n00:00:07,000 --> 00:00:08,000\nB\n";
+        let options = Options { prepend_cue_opt: Some("Subbed by us".into()),
+            append_cue_opt: Some("Subs: Ann\\nQC: Bo".into()),
+            ..Options::default() };
+        assert_eq!(run(srt, true, true, &options),
+            "1\n00:00:01,500 --> 00:00:04,500\nSubbed by us\n\n\
+            2\n00:00:05,000 --> 00:00:06,000\nA\n\n\
+            3\n00:00:07,000 --> 00:00:08,000\nB\n\n\
+            4\n00:00:08,500 --> 00:00:11,500\nSubs: Ann\nQC: Bo\n");
+
+        // Nothing is pushed to make room before the first cue:
+        let early = Options { seconds: -2.0, ..options };
+        let (blocks, _) = cue::parse(srt.as_bytes()).unwrap();
+        let (blocks, summary) = modify(blocks, None, true, false, &early)
+            .unwrap();
+        assert!(summary.prepend_skipped);
+        assert_eq!(summary.credits, 1);
+        let mut output = Vec::new();
+        cue::write(&blocks, &mut output, false).unwrap();
+        assert!(String::from_utf8(output).unwrap().starts_with("WEBVTT\n\n\
+            00:00:03.000 --> 00:00:04.000\nA\n"));
+    }
+
+    #[test]
+    fn shortens_or_merges_overlapping_cues() {
+        let srt = "1\n00:00:01,000 --> 00:00:03,500\nA\n\n\
+            2\n00:00:03,000 --> 00:00:05,000\nB\n\n\
+            3\n00:00:05,000 --> 00:00:05,500\nC\n\n\
+            4\n00:00:05,020 --> 00:00:07,000\nD\n";
+        let options = Options { fix_overlaps_opt: Some(0.08),
+            ..Options::default() };
+        let (blocks, _) = cue::parse(srt.as_bytes()).unwrap();
+        let (_, summary) = modify(blocks, None, true, true, &options).unwrap();
+        assert_eq!((summary.overlaps_fixed, summary.collapsed),
+            (2, vec![3]));
+        assert_eq!(run(srt, true, true, &options),
+            "1\n00:00:01,000 --> 00:00:02,920\nA\n\n\
+            2\n00:00:03,000 --> 00:00:04,920\nB\n\n\
+            3\n00:00:05,000 --> 00:00:05,500\nC\n\n\
+            4\n00:00:05,020 --> 00:00:07,000\nD\n");
+
+        let merge = Options { merge_collapsed: true, ..options };
+        assert_eq!(run(srt, true, true, &merge),
+            "1\n00:00:01,000 --> 00:00:02,920\nA\n\n\
+            2\n00:00:03,000 --> 00:00:04,920\nB\n\n\
+            3\n00:00:05,000 --> 00:00:07,000\nC\nD\n");
+    }
+
+    #[test]
+    fn refuses_files_without_or_with_broken_timing_lines() {
+        let error = |text: &str, srt| render(text.as_bytes(), srt, srt,
+            &Options::default()).unwrap_err().to_string();
+        assert_eq!(error("1\n00:00:01,000 --> 00:00:02,000\nA\n\n\
+            2\n00:00:03,000 - 00:00:04,000\nB\n", true),
+            "Line 6: `00:00:03,000 - 00:00:04,000` isn't a timing line like \
+            00:00:01,000 --> 00:00:04,000.");
+        assert!(error("WEBVTT\n\nNOTE a\n\n00:00:01.000 --> 1\nA\n", false)
+            .starts_with("Line 5: `00:00:01.000 --> 1`"));
+        assert!(error("WEBVTT\n\nNOTE no subtitles --> yet\n", false)
+            .starts_with("No vtt subtitles found"));
+        assert!(error("", true).starts_with("No srt subtitles found"));
+    }
+
+    #[test]
+    fn tells_how_far_a_failed_transform_got() {
+        let vtt = "\u{FEFF}WEBVTT\n\n00:00:01.000 --> 00:00:02.000\nA\n\n\
+            00:00:03.000 --> 00:00:04.000 size:80%\nB\n\n\
+            00:00:05.000 --> 00:00:06.000 size:180%\nC\n";
+        let options = Options { strict: true, ..Options::default() };
+        let error = render(vtt.as_bytes(), false, false, &options)
+            .unwrap_err();
+        let failure = error.downcast_ref::<PartialFailure>().unwrap();
+        assert!(failure.to_string().starts_with("Subtitle 3 has"));
+        assert_eq!(failure.progress, Progress { cues: 2, total: 3,
+            line_opt: Some(9), offset_opt: Some(86),
+            last_start_opt: Some(3.0) });
+
+        // Checks of the whole file don't tell of any progress:
+        let options = Options { seconds: -3.5,
+            on_negative: OnNegative::Error, ..Options::default() };
+        let error = render(vtt.as_bytes(), false, false, &options)
+            .unwrap_err();
+        assert!(error.downcast_ref::<PartialFailure>().is_none());
+        assert!(error.to_string().starts_with("2 subtitles would start"),
+            "{}", error);
+        let concatenated = "WEBVTT\n\n00:00:01.000 --> 00:00:02.000\nA\n\n\
+            WEBVTT\n\n00:00:03.000 --> 00:00:04.000\nB\n";
+        let options = Options { strict: true, ..Options::default() };
+        let error = render(concatenated.as_bytes(), false, false, &options)
+            .unwrap_err();
+        assert!(error.downcast_ref::<PartialFailure>().is_none());
+    }
+
+    #[test]
+    fn numbers_the_remaining_cues_from_1() {
+        let srt = "1\n00:00:01,000 --> 00:00:02,000\nA\n\n\
+            2\n00:00:03,000 --> 00:00:04,000\nB\n\n\
+            7\n00:00:05,000 --> 00:00:06,000\nC\n";
+        let options = Options { seconds: -2.5, ..Options::default() };
+        let keep = Options { seconds: -2.5, keep_numbering: true,
+            ..Options::default() };
+        assert_eq!(run(srt, true, true, &options),
+            "1\n00:00:00,500 --> 00:00:01,500\nB\n\n\
+            2\n00:00:02,500 --> 00:00:03,500\nC\n");
+        assert_eq!(run(srt, true, true, &keep),
+            "2\n00:00:00,500 --> 00:00:01,500\nB\n\n\
+            7\n00:00:02,500 --> 00:00:03,500\nC\n");
+
+        // Vtt identifiers aren't srt indices, and may be missing:
+        let vtt = "intro\n00:00:01.000 --> 00:00:02.000\nA\n\n\
+            00:00:03.000 --> 00:00:04.000\nB\n\n\
+            12\n00:00:05.000 --> 00:00:06.000\nC\n";
+        assert_eq!(run(vtt, false, true, &options),
+            "1\n00:00:00,500 --> 00:00:01,500\nB\n\n\
+            2\n00:00:02,500 --> 00:00:03,500\nC\n");
+        assert_eq!(run(vtt, false, true, &keep),
+            "1\n00:00:00,500 --> 00:00:01,500\nB\n\n\
+            12\n00:00:02,500 --> 00:00:03,500\nC\n");
+    }
+
+    // Three hours of subtitles, played at a quarter of the speed:
+    const LONG: &str = "1\n00:00:01,000 --> 00:00:02,000\nStart\n\n\
+        2\n02:59:58,000 --> 03:00:00,000\nEnd\n";
+
+    #[test]
+    fn moves_early_cues_to_the_min_start() {
+        let srt = "1\n00:00:00,000 --> 00:00:00,040\nA\n\n\
+            2\n00:00:00,010 --> 00:00:02,000\nB\n\n\
+            3\n00:00:00,050 --> 00:00:03,000\nC\n";
+        let options = Options { seconds: -1.0, min_start: 0.05,
+            ..Options::default() };
+        let mut output = Vec::new();
+        let summary = process(srt.as_bytes(), &mut output, true, true,
+            &options).unwrap();
+        // Cue 1 was deleted for ending before the start of the video:
+        assert_eq!((summary.deleted, summary.min_start_moved,
+            summary.min_start_dropped), (1, 2, 0));
+        assert_eq!(String::from_utf8(output).unwrap(),
+            "1\n00:00:00,050 --> 00:00:01,000\nB\n\n\
+            2\n00:00:00,050 --> 00:00:02,000\nC\n");
+
+        let options = Options { min_start: 0.05, ..Options::default() };
+        let mut output = Vec::new();
+        let summary = process(srt.as_bytes(), &mut output, true, true,
+            &options).unwrap();
+        assert_eq!((summary.min_start_moved, summary.min_start_dropped),
+            (1, 1));
+        assert_eq!(String::from_utf8(output).unwrap(),
+            "1\n00:00:00,050 --> 00:00:02,000\nB\n\n\
+            2\n00:00:00,050 --> 00:00:03,000\nC\n");
+    }
+
+    #[test]
+    fn enforces_the_srt_time_limit() {
+        let options = Options {
+            factor: 40.0, overflow: Overflow::Error, ..Options::default() };
+        let mut output = Vec::new();
+        let error = process(LONG.as_bytes(), &mut output, true, true,
+            &options).unwrap_err();
+        assert!(error.to_string().starts_with("Subtitle 2 would end at \
+            120:00:00,000, past 99:59:59,999"));
+
+        let options = Options { overflow: Overflow::Clamp, ..options };
+        assert_eq!(run(LONG, true, true, &options),
+            "1\n00:00:40,000 --> 00:01:20,000\nStart\n\n\
+            2\n99:59:59,999 --> 99:59:59,999\nEnd\n");
+
+        let options = Options { overflow: Overflow::Drop, ..options };
+        assert_eq!(run(LONG, true, true, &options),
+            "1\n00:00:40,000 --> 00:01:20,000\nStart\n");
+    }
+
+    #[test]
+    fn vtt_timestamps_have_no_time_limit() {
+        let options = Options {
+            factor: 40.0, overflow: Overflow::Error, ..Options::default() };
+        let vtt = run(LONG, true, false, &options);
+        assert!(vtt.ends_with("119:58:40.000 --> 120:00:00.000\nEnd\n"));
+        // And they can be read back:
+        let options = Options { factor: 0.025, ..Options::default() };
+        assert!(run(&vtt, false, true, &options)
+            .ends_with("02:59:58,000 --> 03:00:00,000\nEnd\n"));
+    }
+
+    const VTT_WITH_STYLE: &str = "WEBVTT\n\n\
+        STYLE\n::cue(.yellow) { color: yellow; }\n\
+        ::cue(.lime) { color: lime; }\n\n\
+        00:00:01.000 --> 00:00:02.000\n<c.yellow>Hello</c> <i>there</i>\n";
+
+    #[test]
+    fn prunes_styles_of_stripped_tags() {
+        let options = Options { strip_tags: true, ..Options::default() };
+        assert_eq!(run(VTT_WITH_STYLE, false, false, &options), "WEBVTT\n\n\
+            00:00:01.000 --> 00:00:02.000\nHello there\n");
+    }
+
+    #[test]
+    fn renames_classes_in_text_and_styles() {
+        let options = Options {
+            class_renames: vec![(String::from("yellow"), String::from("hl"))],
+            ..Options::default()
+        };
+        assert_eq!(run(VTT_WITH_STYLE, false, false, &options), "WEBVTT\n\n\
+            STYLE\n::cue(.hl) { color: yellow; }\n\
+            ::cue(.lime) { color: lime; }\n\n\
+            00:00:01.000 --> 00:00:02.000\n<c.hl>Hello</c> <i>there</i>\n");
     }
 }
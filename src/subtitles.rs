@@ -0,0 +1,625 @@
+use std::error;
+use std::fmt;
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use failure;
+
+use color;
+use cue::{self, Block};
+use encoding;
+use helpers;
+use name;
+use settings::CueSettings;
+use submod::{self, Options, PartialFailure};
+use ttml;
+
+
+/// Why subtitles couldn't be parsed, shifted or written. New kinds of
+/// errors may be added in minor releases, so matches need a `_` arm.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The text isn't in the format it was parsed as.
+    Parse {
+        /// The line it stops being in it, starting at 1, or `None` when
+        /// it's the text as a whole.
+        line: Option<usize>,
+        /// What's wrong with it.
+        message: String,
+    },
+    /// The range to shift ends before it starts.
+    InvalidRange {
+        /// The start of the range, in seconds.
+        start: f64,
+        /// The end of the range, in seconds.
+        end: f64,
+    },
+    /// A cue ends later than the format can represent, like past
+    /// 99:59:59,999 in srt.
+    TimeOutOfRange {
+        /// The index of the cue.
+        index: usize,
+        /// Its end, in seconds.
+        end: f64,
+    },
+    /// This isn't a number of seconds or a time like `-01:23.5`.
+    InvalidTime(String),
+    /// The file has none of the extensions of the formats `transform`
+    /// reads and writes: .srt, .vtt, .ass, .ssa, .sub, .ttml, .dfxp or .xml.
+    UnknownFormat(PathBuf),
+    /// The command can't transform the file like this, for this reason,
+    /// like an .ass script converted to srt, or a MicroDVD .sub file
+    /// without a frame rate.
+    Unsupported(String),
+    /// The file couldn't be read or written.
+    Io {
+        /// The file.
+        path: PathBuf,
+        /// Why, naming the file.
+        message: String,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Parse { line: Some(line), ref message } =>
+                write!(f, "Line {}: {}", line, message),
+            Error::Parse { line: None, ref message } => f.write_str(message),
+            Error::InvalidRange { start, end } => write!(f, "The range \
+                from {} to {} seconds ends before it starts.", start, end),
+            Error::TimeOutOfRange { index, end } => write!(f, "Subtitle {} \
+                ends at {}, later than srt timestamps go.", index,
+                cue::format_time(end, true)),
+            Error::InvalidTime(ref time) => write!(f, "`{}` isn't a number \
+                of seconds or a time like -01:23.5.", time),
+            Error::UnknownFormat(ref path) => write!(f, "`{}` isn't an srt, \
+                vtt, .ass, .ssa, .sub or TTML file.", path.display()),
+            Error::Unsupported(ref message) => f.write_str(message),
+            Error::Io { ref message, .. } => f.write_str(message),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+/// A single subtitle.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cue {
+    /// Position of the cue among the cues, starting at 1.
+    pub index: usize,
+    /// The vtt cue identifier, if it has one.
+    pub id: Option<String>,
+    /// When the cue appears, in seconds.
+    pub start: f64,
+    /// When it disappears, in seconds.
+    pub end: f64,
+    /// The lines of text, joined by `\n`.
+    pub text: String,
+    /// The vtt cue settings, like `align:start line:0`, as written; see
+    /// [`Cue::cue_settings`] for what they say.
+    pub settings: Option<String>,
+}
+
+impl Cue {
+    /// The vtt cue settings, parsed, or the default settings when the cue
+    /// has none.
+    pub fn cue_settings(&self) -> CueSettings {
+        self.settings.as_ref()
+            .map_or_else(CueSettings::default, |settings|
+                CueSettings::parse(settings))
+    }
+}
+
+/// The cues of an srt or vtt file, in order. Everything else, like the
+/// vtt header, notes and style sheets, is kept where it was among them,
+/// for as long as the file stays in its format; srt has no such blocks,
+/// and in vtt the indices of srt files are left out, as the command does.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Subtitles {
+    /// The cues, in order; changing them changes what's written.
+    pub cues: Vec<Cue>,
+    /// The other blocks of the file, and empty lines, with the number of
+    /// cues before each.
+    others: Vec<(usize, Block)>,
+    /// Whether the file was srt.
+    srt: bool,
+}
+
+impl Subtitles {
+    /// Parses the contents of an srt file.
+    pub fn parse_srt(text: &str) -> Result<Subtitles, Error> {
+        Subtitles::parse(text, true)
+    }
+
+    /// Parses the contents of a vtt file, which must start with a WEBVTT
+    /// header.
+    pub fn parse_vtt(text: &str) -> Result<Subtitles, Error> {
+        if !text.trim_start_matches('\u{feff}').starts_with("WEBVTT") {
+            return Err(Error::Parse { line: Some(1),
+                message: "A vtt file starts with a WEBVTT header.".into() });
+        }
+        Subtitles::parse(text, false)
+    }
+
+    /// Reads the text like `read`, but refuses any block but cues in srt,
+    /// and any block but the header, notes, styles and regions in vtt.
+    fn parse(text: &str, srt: bool) -> Result<Subtitles, Error> {
+        let blocks = Subtitles::read_blocks(text, srt)?;
+        let mut line = 1;
+        for (i, block) in blocks.iter().enumerate() {
+            match *block {
+                Block::Cue(ref cue) =>
+                    line += cue.id.len() + 1 + cue.text.len(),
+                Block::Other(ref lines) if srt || i > 0
+                    && !cue::is_note(&lines[0])
+                    && !lines[0].starts_with("STYLE")
+                    && !lines[0].starts_with("REGION") =>
+                    return Err(Error::Parse { line: Some(line),
+                        message: format!("`{}` isn't part of a subtitle.",
+                            lines[0]) }),
+                Block::Other(ref lines) => line += lines.len(),
+                Block::Empty => line += 1,
+            }
+        }
+        Ok(Subtitles::from_blocks(blocks, srt))
+    }
+
+    /// Reads the text of an srt or vtt file like the command does, keeping
+    /// any other block it has, and failing with the same errors.
+    fn read(text: &str, srt: bool) -> Result<Subtitles, Error> {
+        Ok(Subtitles::from_blocks(Subtitles::read_blocks(text, srt)?, srt))
+    }
+
+    fn read_blocks(text: &str, srt: bool) -> Result<Vec<Block>, Error> {
+        let text = text.trim_start_matches('\u{feff}');
+        let (blocks, _) = cue::parse(text.as_bytes())
+            .map_err(|error| Error::Parse { line: None,
+                message: error.to_string() })?;
+        submod::check_timing_lines(&blocks, srt).map_err(from_command)?;
+        Ok(blocks)
+    }
+
+    fn from_blocks(blocks: Vec<Block>, srt: bool) -> Subtitles {
+        let mut subtitles = Subtitles { cues: Vec::new(), others: Vec::new(),
+            srt };
+        for block in blocks {
+            match block {
+                Block::Cue(cue) => {
+                    let settings = cue.settings.trim();
+                    subtitles.cues.push(Cue {
+                        index: subtitles.cues.len() + 1,
+                        id: if srt { None } else { cue.id.first().cloned() },
+                        start: cue.start,
+                        end: cue.end,
+                        text: cue.text.join("\n"),
+                        settings: if settings.is_empty() { None }
+                            else { Some(settings.to_string()) },
+                    });
+                },
+                block => subtitles.others.push((subtitles.cues.len(), block)),
+            }
+        }
+        subtitles
+    }
+
+    /// The cues and other blocks in the order of the file, for the
+    /// command's code; cues are separated by an empty line where there's
+    /// none, like those that were added.
+    fn blocks(&self) -> Vec<Block> {
+        let mut blocks: Vec<Block> = Vec::new();
+        let mut others = self.others.iter().peekable();
+        for i in 0..=self.cues.len() {
+            let mut next = Vec::new();
+            while let Some((_, block)) = others
+                .peek().filter(|&&&(position, _)| position <= i) {
+                next.push(block.clone());
+                others.next();
+            }
+            if let Some(cue) = self.cues.get(i) {
+                let id = if self.srt { Some(cue.index.to_string()) }
+                    else { cue.id.clone() };
+                next.push(Block::Cue(cue::Cue {
+                    number: i + 1,
+                    line: 0,
+                    id: id.into_iter().collect(),
+                    start: cue.start,
+                    end: cue.end,
+                    settings: cue.settings.as_ref()
+                        .map_or_else(String::new, |settings|
+                            format!(" {}", settings)),
+                    text: cue.text.split('\n').map(String::from).collect(),
+                }));
+            }
+            for block in next {
+                let separated = block == Block::Empty || blocks.last()
+                    .is_none_or(|last| *last == Block::Empty);
+                if !separated {
+                    blocks.push(Block::Empty);
+                }
+                blocks.push(block);
+            }
+        }
+        blocks
+    }
+
+    /// Shifts the cues by `offset` seconds, or only those that overlap
+    /// `range`, as `submod --start --stop` does. Cues shifted to start
+    /// before 00:00:00 start at it instead, or are removed if they also end
+    /// before it, and srt cues shifted past 99:59:59,999 end at it. Returns
+    /// the number of cues removed.
+    pub fn shift(&mut self, offset: f64, range: Option<Range<f64>>)
+        -> Result<usize, Error>
+    {
+        if let Some(ref range) = range {
+            if range.end < range.start {
+                return Err(Error::InvalidRange { start: range.start,
+                    end: range.end });
+            }
+        }
+        let options = Options {
+            seconds: offset,
+            start_opt: range.as_ref().map(|range| range.start),
+            stop_opt: range.as_ref().map(|range| range.end),
+            ..Options::default()
+        };
+        let (blocks, summary) = submod::modify(self.blocks(), None, self.srt,
+            self.srt, &options).map_err(from_command)?;
+        *self = Subtitles::from_blocks(blocks, self.srt);
+        Ok(summary.deleted as usize)
+    }
+
+    /// Writes the cues as an srt file, numbered from 1, converted from vtt
+    /// like the command converts them.
+    pub fn to_srt_string(&self) -> Result<String, Error> {
+        if let Some(cue) = self.cues.iter()
+            .find(|cue| cue::to_millis(cue.end)
+                > cue::to_millis(cue::SRT_MAX_TIME)) {
+            return Err(Error::TimeOutOfRange { index: cue.index,
+                end: cue.end });
+        }
+        Ok(write(&submod::convert(self.blocks(), self.srt, true), true))
+    }
+
+    /// Writes the cues as a vtt file, with a WEBVTT header, converted from
+    /// srt like the command converts them.
+    pub fn to_vtt_string(&self) -> String {
+        let mut blocks = submod::convert(self.blocks(), self.srt, false);
+        let header = match blocks.first() {
+            Some(Block::Other(lines)) => lines[0].starts_with("WEBVTT"),
+            _ => false,
+        };
+        if !header {
+            blocks.splice(0..0, vec![Block::Other(vec!["WEBVTT".into()]),
+                Block::Empty]);
+        }
+        write(&blocks, false)
+    }
+
+    /// Writes the cues as a TTML document, a paragraph per cue.
+    fn to_ttml_string(&self) -> String {
+        ttml::write(self.cues.iter()
+            .map(|cue| (cue.start, cue.end, cue.text.as_str())))
+    }
+}
+
+/// Writes the blocks of an srt or vtt file.
+fn write(blocks: &[Block], srt: bool) -> String {
+    let mut output = Vec::new();
+    // Writing to memory can't fail, nor can it write anything but UTF-8:
+    cue::write(blocks, &mut output, srt).unwrap();
+    String::from_utf8(output).unwrap()
+}
+
+/// Parses seconds like `-83.5`, or a time like `-01:23.5` or
+/// `00:14:07,438`, the way `submod` reads its <seconds>; a sign applies to
+/// the whole time.
+pub fn get_secs(time: &str) -> Result<f64, Error> {
+    let unsigned = time.strip_prefix(['-', '+']).unwrap_or(time);
+    let parts: Vec<&str> = unsigned.split(':').collect();
+    if parts.len() <= 3 && parts.iter().all(|part|
+        !part.starts_with(['-', '+']) && part.replace(',', ".")
+            .parse::<f64>().is_ok_and(f64::is_finite)) {
+        Ok(cue::get_secs(time))
+    } else {
+        Err(Error::InvalidTime(time.to_string()))
+    }
+}
+
+/// The path `submod` writes a shift of `input` by `offset` seconds to by
+/// default: next to it, tagged like `movie__[+1.500_Sec+].srt`. An input
+/// that is tagged already gets a tag with the total instead. `partial`
+/// marks a shift of only some of the cues with a `-`, like `_Sec-]`.
+pub fn output_path(input: &Path, offset: f64, partial: bool) -> PathBuf {
+    let stem = input.file_stem().map_or_else(String::new, |stem|
+        stem.to_string_lossy().into_owned());
+    let (stem, tagged_opt) = name::split_tag(&stem);
+    let (factor, seconds) = tagged_opt.map_or((1.0, offset),
+        |(factor, seconds)| (factor, seconds + offset));
+    let tag = format!("{}__[{}_Sec{}]", name::format_factor(factor),
+        name::format_increment(seconds, 3), if partial { "-" } else { "+" });
+    let file_name = match input.extension() {
+        Some(extension) => name::build_output_name(stem, &tag,
+            &extension.to_string_lossy()),
+        None => format!("{}{}", stem, tag),
+    };
+    input.with_file_name(file_name)
+}
+
+/// The formats `transform` reads and writes, by extension, like the
+/// command tells them apart.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Srt,
+    Vtt,
+    Ass,
+    Sub,
+    Ttml,
+}
+
+impl Format {
+    fn of(path: &Path) -> Result<Format, Error> {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("srt") => Ok(Format::Srt),
+            Some("vtt") => Ok(Format::Vtt),
+            Some("ass") | Some("ssa") => Ok(Format::Ass),
+            Some("sub") => Ok(Format::Sub),
+            Some("ttml") | Some("dfxp") | Some("xml") => Ok(Format::Ttml),
+            _ => Err(Error::UnknownFormat(path.to_owned())),
+        }
+    }
+}
+
+/// Shifts the subtitle file at `input` the way `submod <input> <offset>
+/// --start --stop --out <output>` does, and writes it to `output`: in srt
+/// or vtt as its extension says, converted like the command converts, or
+/// else in the format of the input. srt and vtt files are read into
+/// [`Subtitles`] and shifted with [`Subtitles::shift`]. Inputs are decoded
+/// and their line endings kept like the command does, and .ass, .ssa, .sub
+/// and TTML files are shifted in place, keeping everything but their timing
+/// as it is. On top of what the command does, srt and vtt files can be
+/// written as TTML, a paragraph per cue. `output` is only replaced once the
+/// whole file is written. Returns the number of cues removed for ending
+/// before 00:00:00.
+pub fn transform(input: &Path, output: &Path, offset: f64,
+    range: Option<Range<f64>>) -> Result<usize, Error>
+{
+    let (from, to) = (Format::of(input)?, Format::of(output)?);
+    if let Some(ref range) = range {
+        if range.end < range.start {
+            return Err(Error::InvalidRange { start: range.start,
+                end: range.end });
+        }
+    }
+    let text_formats = [Format::Srt, Format::Vtt];
+    if text_formats.contains(&from)
+        && (text_formats.contains(&to) || to == Format::Ttml) {
+        return transform_text(input, output, from == Format::Srt, to, offset,
+            range);
+    }
+    if from != to && !(text_formats.contains(&to) && from != Format::Ass) {
+        return Err(Error::Unsupported(format!("Converting `{}` to `{}` \
+            isn't supported.", input.display(), output.display())));
+    }
+    let options = Options {
+        seconds: offset,
+        start_opt: range.as_ref().map(|range| range.start),
+        stop_opt: range.as_ref().map(|range| range.end),
+        // Written even when nothing changed, as there may be no output yet:
+        force: true,
+        ..Options::default()
+    };
+    submod::transform(input, output, &options)
+        .map(|summary| summary.deleted as usize)
+        .map_err(from_command)
+}
+
+/// Shifts an srt or vtt file as `Subtitles`, decoded and written with the
+/// line endings of the input like the command does.
+fn transform_text(input: &Path, output: &Path, from_srt: bool, to: Format,
+    offset: f64, range: Option<Range<f64>>) -> Result<usize, Error>
+{
+    let bytes = fs::read(input).map_err(|error| Error::Io {
+        path: input.to_owned(), message: format!("Could not read `{}`: {}",
+            input.display(), error) })?;
+    let (text, _) = encoding::decode(&bytes);
+    let mut subtitles = Subtitles::read(&text, from_srt)?;
+    let removed = subtitles.shift(offset, range)?;
+    let contents = match to {
+        Format::Srt => subtitles.to_srt_string()?,
+        Format::Vtt => subtitles.to_vtt_string(),
+        _ => subtitles.to_ttml_string(),
+    };
+    let contents = submod::end_lines(&contents, submod::is_crlf(&text));
+    helpers::write_atomically(output, contents.as_bytes())
+        .map_err(from_command)?;
+    Ok(removed)
+}
+
+/// The `Error` the command failed with, without the colors of its
+/// terminal. Any other error, like a MicroDVD file without a frame rate,
+/// is `Unsupported`.
+fn from_command(error: failure::Error) -> Error {
+    let error = match error.downcast::<PartialFailure>() {
+        Ok(failure) => failure.cause,
+        Err(error) => error,
+    };
+    match error.downcast::<Error>() {
+        Ok(Error::Parse { line, message }) =>
+            Error::Parse { line, message: color::strip(&message) },
+        Ok(error) => error,
+        Err(error) => Error::Unsupported(color::strip(&error.to_string())),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use settings::Align;
+    use testing;
+
+    #[test]
+    fn parses_and_writes_both_formats() {
+        let vtt = "WEBVTT\n\nNOTE made by hand\n\nintro\n\
+            00:00:01.000 --> 00:00:02.000 align:start\nHey\nthere\n";
+        let subtitles = Subtitles::parse_vtt(vtt).unwrap();
+        assert_eq!(subtitles.cues, vec![Cue { index: 1,
+            id: Some("intro".into()), start: 1.0, end: 2.0,
+            text: "Hey\nthere".into(),
+            settings: Some("align:start".into()) }]);
+        assert_eq!(subtitles.cues[0].cue_settings().align, Some(Align::Start));
+        assert_eq!(subtitles.to_srt_string().unwrap(),
+            "1\n00:00:01,000 --> 00:00:02,000\nHey\nthere\n");
+        assert_eq!(subtitles.to_vtt_string(), "WEBVTT\n\nNOTE made by hand\n\nintro\n\
+            00:00:01.000 --> 00:00:02.000 align:start\nHey\nthere\n");
+
+        assert_eq!(Subtitles::parse_vtt("1\n00:00:01.000 --> 00:00:02.000\n\
+            Hey\n").unwrap_err(), Error::Parse { line: Some(1),
+            message: "A vtt file starts with a WEBVTT header.".into() });
+        assert_eq!(Subtitles::parse_srt("1\n00:00:01,000 --> 00:00:02,000\n\
+            Hey\n\nOops\n").unwrap_err(), Error::Parse { line: Some(5),
+            message: "`Oops` isn't part of a subtitle.".into() });
+    }
+
+    #[test]
+    fn shifts_the_cues_in_range() {
+        let mut subtitles = Subtitles::parse_srt(
+            "1\n00:00:01,000 --> 00:00:02,000\nA\n\n\
+            2\n00:00:05,000 --> 00:00:06,000\nB\n\n\
+            3\n00:00:09,000 --> 00:00:10,000\nC\n").unwrap();
+        assert_eq!(subtitles.shift(-3.0, Some(0.0..6.0)), Ok(1));
+        assert_eq!(subtitles.cues.iter()
+            .map(|cue| (cue.index, cue.start, cue.end))
+            .collect::<Vec<_>>(), vec![(1, 2.0, 3.0), (2, 9.0, 10.0)]);
+
+        assert_eq!(subtitles.shift(1.0, Some(5.0..4.0)),
+            Err(Error::InvalidRange { start: 5.0, end: 4.0 }));
+        subtitles.shift(400_000.0, Some(9.0..10.0)).unwrap();
+        assert_eq!(subtitles.cues[1].end, 359_999.999);
+        subtitles.cues[0].end = 400_003.0;
+        assert_eq!(subtitles.to_srt_string(),
+            Err(Error::TimeOutOfRange { index: 1, end: 400_003.0 }));
+    }
+
+    #[test]
+    fn reads_times_and_names_outputs_like_the_command() {
+        assert_eq!(get_secs("-83.5"), Ok(-83.5));
+        assert_eq!(get_secs("-01:23.5"), Ok(-83.5));
+        assert_eq!(get_secs("00:14:07,438"), Ok(847.438));
+        assert_eq!(get_secs("1:-2"), Err(Error::InvalidTime("1:-2".into())));
+        assert_eq!(get_secs("soon"), Err(Error::InvalidTime("soon".into())));
+
+        assert_eq!(output_path(Path::new("dir/movie.srt"), 1.5, false),
+            Path::new("dir/movie__[+1.500_Sec+].srt"));
+        assert_eq!(output_path(Path::new("movie__[+1.500_Sec+].vtt"), -2.0,
+            true), Path::new("movie__[-0.500_Sec-].vtt"));
+    }
+
+    #[test]
+    fn transforms_files() {
+        let dir = testing::temp_dir("library");
+        let input = dir.join("movie.srt");
+        fs::write(&input, "1\n00:00:01,000 --> 00:00:02,000\nA\n\n\
+            2\n00:00:05,000 --> 00:00:06,000\nB\n").unwrap();
+        let output = output_path(&input, -1.5, false).with_extension("vtt");
+        assert_eq!(transform(&input, &output, -1.5, None), Ok(0));
+        assert_eq!(fs::read_to_string(&output).unwrap(), "WEBVTT\n\n\
+            00:00:00.000 --> 00:00:00.500\nA\n\n\
+            00:00:03.500 --> 00:00:04.500\nB\n");
+        assert_eq!(transform(&input, &dir.join("movie.txt"), 1.0, None),
+            Err(Error::UnknownFormat(dir.join("movie.txt"))));
+        assert_eq!(transform(&input, &output, 1.0, Some(5.0..4.0)),
+            Err(Error::InvalidRange { start: 5.0, end: 4.0 }));
+        let ttml = dir.join("movie.dfxp");
+        fs::write(&ttml, "<tt xmlns=\"http://www.w3.org/ns/ttml\">\
+            <head><region xml:id=\"bottom\"/></head><body region=\"bottom\">\
+            <p begin=\"1s\" dur=\"1s\">A</p><p begin=\"5s\" end=\"6s\">B</p>\
+            </body></tt>").unwrap();
+        assert_eq!(transform(&ttml, &dir.join("shifted.ttml"), 1.5,
+            Some(4.0..10.0)), Ok(0));
+        assert_eq!(fs::read_to_string(dir.join("shifted.ttml")).unwrap(),
+            "<tt xmlns=\"http://www.w3.org/ns/ttml\"><head>\
+            <region xml:id=\"bottom\"/></head><body region=\"bottom\">\
+            <p begin=\"1s\" dur=\"1s\">A</p>\
+            <p begin=\"6.5s\" end=\"7.5s\">B</p></body></tt>");
+        assert_eq!(transform(&ttml, &dir.join("movie.srt"), -1.5, None),
+            Ok(0));
+        assert_eq!(fs::read_to_string(&input).unwrap(),
+            "1\n00:00:00,000 --> 00:00:00,500\nA\n\n\
+            2\n00:00:03,500 --> 00:00:04,500\nB\n");
+        assert_eq!(transform(&input, &dir.join("movie.ttml"), 0.0, None),
+            Ok(0));
+        assert!(fs::read_to_string(dir.join("movie.ttml")).unwrap()
+            .contains("<p begin=\"00:00:03.500\" end=\"00:00:04.500\">B</p>"));
+        assert!(match transform(&dir.join("missing.srt"), &output, 1.0, None) {
+            Err(Error::Io { path, .. }) => path == dir.join("missing.srt"),
+            _ => false,
+        });
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn transforms_files_like_the_command() {
+        let dir = testing::temp_dir("library-cli");
+
+        // Line endings are kept, and srt tags converted for vtt:
+        let input = dir.join("movie.srt");
+        fs::write(&input, "1\r\n00:00:01,000 --> 00:00:02,000\r\n\
+            <font color=\"#ffff00\">Hey</font>\r\n").unwrap();
+        assert_eq!(transform(&input, &dir.join("movie.vtt"), 1.0, None),
+            Ok(0));
+        assert_eq!(fs::read_to_string(dir.join("movie.vtt")).unwrap(),
+            "WEBVTT\r\n\r\n00:00:02.000 --> 00:00:03.000\r\nHey\r\n");
+        assert_eq!(transform(&input, &dir.join("shifted.srt"), 1.0, None),
+            Ok(0));
+        assert_eq!(fs::read_to_string(dir.join("shifted.srt")).unwrap(),
+            "1\r\n00:00:02,000 --> 00:00:03,000\r\n\
+            <font color=\"#ffff00\">Hey</font>\r\n");
+
+        // Inputs that aren't UTF-8 are read as Windows-1252:
+        if cfg!(feature = "encodings") {
+            fs::write(&input, b"1\n00:00:01,000 --> 00:00:02,000\nCaf\xe9\n")
+                .unwrap();
+            transform(&input, &input, 0.5, None).unwrap();
+            assert_eq!(fs::read_to_string(&input).unwrap(),
+                "1\n00:00:01,500 --> 00:00:02,500\nCaf\u{e9}\n");
+        }
+
+        // Scripts and .sub files are shifted in place:
+        let script = dir.join("movie.ass");
+        fs::write(&script, "[Events]\nFormat: Layer, Start, End, Text\n\
+            Dialogue: 0,0:00:01.00,0:00:02.50,{\\i1}Hey\n").unwrap();
+        assert_eq!(transform(&script, &script, 1.0, None), Ok(0));
+        assert_eq!(fs::read_to_string(&script).unwrap(), "[Events]\n\
+            Format: Layer, Start, End, Text\n\
+            Dialogue: 0,0:00:02.00,0:00:03.50,{\\i1}Hey\n");
+        assert_eq!(transform(&script, &input, 1.0, None),
+            Err(Error::Unsupported(format!("Converting `{}` to `{}` isn't \
+                supported.", script.display(), input.display()))));
+        let sub = dir.join("movie.sub");
+        fs::write(&sub, "{1}{1}25\n{25}{50}Hey\n").unwrap();
+        assert_eq!(transform(&sub, &sub, -0.5, None), Ok(0));
+        assert_eq!(fs::read_to_string(&sub).unwrap(),
+            "{1}{1}25\n{13}{38}Hey\n");
+        transform(&sub, &input, 0.0, None).unwrap();
+        assert_eq!(fs::read_to_string(&input).unwrap(),
+            "1\n00:00:00,520 --> 00:00:01,520\nHey\n");
+        fs::write(&sub, "{25}{50}Hey\n").unwrap();
+        assert!(match transform(&sub, &input, 0.0, None) {
+            Err(Error::Unsupported(message)) =>
+                message.contains("--fps <rate>") && !message.contains('\u{1b}'),
+            _ => false,
+        });
+
+        // Errors in the input are those of the command:
+        fs::write(&input, "1\n00:00:01,000 -> 00:00:02,000\nHey\n").unwrap();
+        assert_eq!(transform(&input, &input, 1.0, None), Err(Error::Parse {
+            line: Some(2), message: "`00:00:01,000 -> 00:00:02,000` isn't a \
+                timing line like 00:00:01,000 --> 00:00:04,000.".into() }));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
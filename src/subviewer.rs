@@ -0,0 +1,216 @@
+use failure::Error;
+use regex::Regex;
+
+use color;
+use cue;
+use sniff::{self, Format, Sniffed};
+use subtitles::Error as SubmodError;
+
+
+/// The two unrelated formats that share the .sub extension.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Dialect {
+    /// `{start}{end}text` lines, timed in frames of the video.
+    MicroDvd,
+    /// `00:00:01.00,00:00:04.00` lines followed by the text, after an
+    /// optional `[INFORMATION]` header.
+    SubViewer,
+}
+
+/// Tells the dialect of a .sub file by its content: SubViewer files have
+/// an `[INFORMATION]` header or timing lines, and MicroDVD files lines
+/// starting with frames like `{25}{100}`, as `sniff::sniff` counts them.
+/// Files with neither, or too many of both, are refused rather than guessed.
+pub fn detect(contents: &str) -> Result<Sniffed, Error> {
+    match sniff::sniff(contents)? {
+        Some(sniffed) if sniffed.format == Format::MicroDvd
+            || sniffed.format == Format::SubViewer => Ok(sniffed),
+        _ => Err(SubmodError::Parse { line: None,
+            message: format!("Can't tell whether this .sub file is \
+                MicroDVD or SubViewer.\n\n\
+                Use {} or {} to say which.", color::hint("--format microdvd"),
+                color::hint("--format subviewer")) }.into()),
+    }
+}
+
+/// Shifts the timing lines of a SubViewer file, leaving the header and
+/// the text as they are. `shift` gets the start and end in seconds and
+/// returns the new ones, which are written with centisecond precision,
+/// or `None` to remove the subtitle along with its text.
+pub fn shift<F>(contents: &str, mut shift: F) -> String
+    where F: FnMut(f64, f64) -> Option<(f64, f64)>
+{
+    let mut lines: Vec<String> = Vec::new();
+    let mut removing = false;
+    // Splitting on \n keeps the \r of \r\n line endings, to put it back:
+    let mut split = contents.split('\n').peekable();
+    while let Some(line) = split.next() {
+        if removing {
+            // The empty line after a removed subtitle goes with it, unless
+            // it's the end of the file:
+            if line.trim().is_empty() && split.peek().is_some() {
+                removing = false;
+            }
+            if split.peek().is_some() {
+                continue;
+            }
+            // A removed last subtitle has no empty line to lose but the
+            // one before:
+            if lines.last().is_some_and(|line| line.trim().is_empty()) {
+                lines.pop();
+            }
+            lines.push(String::new());
+            break;
+        }
+        match get_timing(line) {
+            Some((start, end)) => match shift(start, end) {
+                Some((start, end)) => {
+                    let ending = if line.ends_with('\r') { "\r" } else { "" };
+                    lines.push(format!("{},{}{}", format_time(start),
+                        format_time(end), ending));
+                },
+                None => removing = true,
+            },
+            None => lines.push(line.to_string()),
+        }
+    }
+    lines.join("\n")
+}
+
+/// Converts SubViewer subtitles to srt, for the cues to be modified and
+/// written like those of any srt file: the header is left out, and `[br]`
+/// becomes a line break.
+pub fn to_srt(contents: &str) -> Result<String, Error> {
+    let line_break = Regex::new(r"(?i)\[br\]").unwrap();
+    let mut srt = String::new();
+    let (mut cues, mut in_cue) = (0, false);
+    for line in contents.lines() {
+        if let Some((start, end)) = get_timing(line) {
+            cues += 1;
+            in_cue = true;
+            srt.push_str(&format!("{}{}\n{} --> {}\n",
+                if cues > 1 { "\n" } else { "" }, cues,
+                cue::format_time(start, true), cue::format_time(end, true)));
+        } else if line.trim().is_empty() {
+            in_cue = false;
+        } else if in_cue {
+            srt.push_str(&line_break.replace_all(line.trim_end(), "\n"));
+            srt.push('\n');
+        }
+    }
+    if cues == 0 {
+        return Err(SubmodError::Parse { line: None,
+            message: "No SubViewer subtitles found, with timing lines \
+                like 00:00:01.00,00:00:04.00".into() }.into());
+    }
+    Ok(srt)
+}
+
+/// Reads a timing line like `00:00:01.00,00:00:04.00` into the start
+/// and end in seconds, or `None` for other lines.
+pub fn get_timing(line: &str) -> Option<(f64, f64)> {
+    let mut times = line.trim().split(',').map(get_time);
+    match (times.next(), times.next(), times.next()) {
+        (Some(Some(start)), Some(Some(end)), None) => Some((start, end)),
+        _ => None,
+    }
+}
+
+/// Reads a time like `00:01:59.99` into seconds.
+fn get_time(time: &str) -> Option<f64> {
+    let parts: Vec<&str> = time.split(':').collect();
+    if parts.len() != 3 || !parts[2].contains('.') || parts.iter().any(|part|
+        part.is_empty() || !part.chars().all(|c| c.is_ascii_digit() || c == '.')) {
+        return None;
+    }
+    let mut seconds = 0.0;
+    for part in parts {
+        seconds = seconds * 60.0 + part.parse::<f64>().ok()?;
+    }
+    Some(seconds)
+}
+
+/// Formats seconds as a time like `00:01:59.99`, rounded to the
+/// centisecond; times before the start are moved to it.
+fn format_time(seconds: f64) -> String {
+    let centis = (seconds.max(0.0) * 100.0).round() as u64;
+    format!("{:02}:{:02}:{:02}.{:02}", centis / 360000, centis / 6000 % 60,
+        centis / 100 % 60, centis % 100)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SUBVIEWER: &str = "[INFORMATION]\r\n[TITLE]Pilot\r\n\
+        [AUTHOR]\r\n[DELAY]0\r\n[END INFORMATION]\r\n[SUBTITLE]\r\n\
+        [COLF]&HFFFFFF,[STYLE]bd,[SIZE]18,[FONT]Arial\r\n\
+        00:00:01.00,00:00:04.00\r\nHello,[br]world.\r\n\r\n\
+        00:01:59.99,00:02:01.50\r\nBye.\r\n";
+
+    #[test]
+    fn detects_the_dialect_by_content() {
+        assert_eq!(detect(SUBVIEWER).unwrap().format, Format::SubViewer);
+        assert_eq!(detect("00:00:01.00,00:00:02.00\nHi\n").unwrap().format,
+            Format::SubViewer);
+        assert_eq!(detect("{25}{100}Hello|world\n{150}{}Bye\n").unwrap()
+            .format, Format::MicroDvd);
+        assert!(detect("Hello\n").is_err());
+        assert!(detect("{25}{100}Hi\n00:00:01.00,00:00:02.00\n").is_err());
+    }
+
+    #[test]
+    fn shifts_timing_lines_only() {
+        let shifted = shift(SUBVIEWER, |start, end|
+            Some((start + 0.51, end + 0.51)));
+        assert_eq!(shifted, SUBVIEWER
+            .replace("00:00:01.00,00:00:04.00", "00:00:01.51,00:00:04.51")
+            .replace("00:01:59.99,00:02:01.50", "00:02:00.50,00:02:02.01"));
+        assert_eq!(shift("00:00:01.00,00:00:04.00\nHi", |start, end|
+            Some((start - 2.0, end - 2.0))), "00:00:00.00,00:00:02.00\nHi");
+    }
+
+    #[test]
+    fn removes_subtitles_with_their_text() {
+        let before = |limit: f64| move |start: f64, end: f64|
+            if start < limit { None } else { Some((start, end)) };
+        assert_eq!(shift(SUBVIEWER, before(60.0)), SUBVIEWER
+            .replace("00:00:01.00,00:00:04.00\r\nHello,[br]world.\r\n\r\n",
+                ""));
+        assert_eq!(shift(SUBVIEWER, before(200.0)), SUBVIEWER
+            .replace("00:00:01.00,00:00:04.00\r\nHello,[br]world.\r\n\r\n\
+                00:01:59.99,00:02:01.50\r\nBye.\r\n", ""));
+        assert_eq!(shift("00:00:01.00,00:00:02.00\nA\n\n\
+            00:00:03.00,00:00:04.00\nB\n", |start, end| if start > 2.0 {
+                None } else { Some((start, end)) }),
+            "00:00:01.00,00:00:02.00\nA\n");
+    }
+
+    #[test]
+    fn converts_to_srt_with_line_breaks() {
+        assert_eq!(to_srt(SUBVIEWER).unwrap(),
+            "1\n00:00:01,000 --> 00:00:04,000\nHello,\nworld.\n\n\
+            2\n00:01:59,990 --> 00:02:01,500\nBye.\n");
+        assert!(to_srt("[INFORMATION]\n[TITLE]Empty\n").is_err());
+    }
+
+    #[test]
+    fn reads_only_whole_timing_lines() {
+        assert_eq!(get_timing(" 01:02:03.45,01:02:04.5 "),
+            Some((3723.45, 3724.5)));
+        for line in &["Hello, world", "00:00:01.00", "00:00:01,00:00:02",
+            "00:00:01.00,00:00:02.00,00:00:03.00", "[COLF]&HFFFFFF,[SIZE]18"] {
+            assert_eq!(get_timing(line), None, "{}", line);
+        }
+    }
+
+    #[test]
+    fn carries_centiseconds_into_hours() {
+        for &(seconds, expected) in &[(0.995, "00:00:01.00"),
+            (59.995, "00:01:00.00"), (3599.99 + 0.01, "01:00:00.00"),
+            (get_time("00:59:59.99").unwrap() + 0.02, "01:00:00.01")] {
+            assert_eq!(format_time(seconds), expected, "{}", seconds);
+        }
+    }
+}
@@ -0,0 +1,186 @@
+use std::path::PathBuf;
+
+use failure::Error;
+
+
+/// Placeholders `--name-template` can use.
+pub const PLACEHOLDERS: &[&str] = &["{stem}", "{tag}", "{seconds}"];
+
+/// Longest file name most filesystems allow, in bytes.
+const MAX_NAME_LEN: usize = 255;
+
+/// Names Windows reserves for devices, with or without an extension.
+const RESERVED: &[&str] = &["CON", "PRN", "AUX", "NUL",
+    "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9",
+    "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9"];
+
+/// Expands a `--name-template` into an output path relative to the
+/// directory of the input, and appends the `extension`.
+/// The result is made safe for the filesystem of this platform.
+pub fn expand(template: &str, stem: &str, tag: &str, seconds: &str,
+    extension: &str) -> Result<PathBuf, Error>
+{
+    expand_for(template, stem, tag, seconds, extension, cfg!(windows))
+}
+
+/// Expands a template following the file naming rules of Windows if
+/// `windows`, or else those of Unix. Invalid characters are replaced by
+/// `_`, redundant separators are collapsed, and a file name that's too
+/// long has its stem truncated, but never its tag or extension.
+fn expand_for(template: &str, stem: &str, tag: &str, seconds: &str,
+    extension: &str, windows: bool) -> Result<PathBuf, Error>
+{
+    let mut stem = stem.to_string();
+    loop {
+        let expanded = template.replace("{stem}", &stem)
+            .replace("{tag}", tag)
+            .replace("{seconds}", seconds);
+        let mut components = split(&expanded, windows)?;
+        let name = match components.pop() {
+            Some(name) => format!("{}.{}", name, extension),
+            None => return Err(format_err!("The name template `{}` \
+                results in an empty file name.", template)),
+        };
+        let excess = name.len().saturating_sub(MAX_NAME_LEN);
+        if excess == 0 {
+            let mut path: PathBuf = components.into_iter().collect();
+            path.push(name);
+            return Ok(path);
+        }
+        if stem.is_empty() || !template.contains("{stem}") {
+            return Err(format_err!("The name template `{}` results in \
+                a file name longer than {} bytes, even without the stem.",
+                template, MAX_NAME_LEN));
+        }
+        // Truncate the stem on a character boundary:
+        let mut len = stem.len().saturating_sub(excess);
+        while !stem.is_char_boundary(len) {
+            len -= 1;
+        }
+        stem.truncate(len);
+    }
+}
+
+/// Splits an expanded template into sanitized path components, dropping
+/// empty and `.` components, and refusing `..` components, which could
+/// escape the output directory.
+fn split(expanded: &str, windows: bool) -> Result<Vec<String>, Error> {
+    let is_separator = |c: char| c == '/' || (windows && c == '\\');
+    let mut components = Vec::new();
+    for component in expanded.split(is_separator) {
+        match component {
+            "" | "." => continue,
+            ".." => return Err(format_err!("The name template results in \
+                `{}`, which would escape the output directory.", expanded)),
+            _ => {},
+        }
+        let component = sanitize(component, windows);
+        if !component.is_empty() {
+            components.push(component);
+        }
+    }
+    Ok(components)
+}
+
+/// Replaces the characters a file name can't contain by `_`.
+fn sanitize(component: &str, windows: bool) -> String {
+    let invalid = |c: char| c == '\0'
+        || (windows && (c < ' ' || "<>:\"|?*".contains(c)));
+    let mut sanitized: String = component.chars()
+        .map(|c| if invalid(c) { '_' } else { c })
+        .collect();
+    if windows {
+        // Windows drops trailing dots and spaces, and reserves device names:
+        sanitized = sanitized.trim_end_matches(['.', ' ']).to_string();
+        let device = sanitized.split('.').next().unwrap_or("");
+        if RESERVED.iter()
+            .any(|reserved| reserved.eq_ignore_ascii_case(device)) {
+            sanitized.insert(device.len(), '_');
+        }
+    }
+    sanitized
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unix(template: &str, stem: &str) -> Result<PathBuf, Error> {
+        expand_for(template, stem, "__[+2.50_Sec+]", "+2.50", "srt", false)
+    }
+
+    fn windows(template: &str, stem: &str) -> Result<PathBuf, Error> {
+        expand_for(template, stem, "__[+2.50_Sec+]", "+2.50", "srt", true)
+    }
+
+    #[test]
+    fn expands_placeholders() {
+        assert_eq!(unix("{stem}{tag}", "movie").unwrap(),
+            PathBuf::from("movie__[+2.50_Sec+].srt"));
+        assert_eq!(unix("shifted/{stem} ({seconds} s)", "movie").unwrap(),
+            PathBuf::from("shifted/movie (+2.50 s).srt"));
+    }
+
+    #[test]
+    fn replaces_invalid_characters() {
+        assert_eq!(unix("{stem}\0: \"what?\"", "a").unwrap(),
+            PathBuf::from("a_: \"what?\".srt"));
+        assert_eq!(windows("{stem}\0: \"what?\"", "a").unwrap(),
+            PathBuf::from("a__ _what__.srt"));
+        assert_eq!(windows("{stem}\tname|*<>", "a").unwrap(),
+            PathBuf::from("a_name____.srt"));
+        assert_eq!(windows("subs\\{stem}. . ", "a").unwrap(),
+            PathBuf::from("subs").join("a.srt"));
+        assert_eq!(unix("back\\slash", "a").unwrap(),
+            PathBuf::from("back\\slash.srt"));
+    }
+
+    #[test]
+    fn renames_reserved_device_names_on_windows() {
+        assert_eq!(windows("{stem}", "con").unwrap(),
+            PathBuf::from("con_.srt"));
+        assert_eq!(windows("LPT1.en", "").unwrap(),
+            PathBuf::from("LPT1_.en.srt"));
+        assert_eq!(unix("{stem}", "con").unwrap(), PathBuf::from("con.srt"));
+    }
+
+    #[test]
+    fn collapses_redundant_separators() {
+        assert_eq!(unix("//subs///./{stem}/", "movie").unwrap(),
+            PathBuf::from("subs/movie.srt"));
+        assert_eq!(windows("subs\\\\/{stem}", "movie").unwrap(),
+            PathBuf::from("subs").join("movie.srt"));
+    }
+
+    #[test]
+    fn refuses_escaping_the_output_directory() {
+        assert!(unix("../{stem}", "movie").is_err());
+        assert!(unix("subs/../../{stem}", "movie").is_err());
+        assert!(windows("..\\{stem}", "movie").is_err());
+        // Only whole components escape:
+        assert_eq!(unix("{stem}..{tag}", "movie").unwrap(),
+            PathBuf::from("movie..__[+2.50_Sec+].srt"));
+    }
+
+    #[test]
+    fn refuses_empty_names() {
+        assert!(unix("", "movie").is_err());
+        assert!(unix("{stem}", "").is_err());
+        assert!(windows("{stem}", ". .").is_err());
+    }
+
+    #[test]
+    fn truncates_the_stem_but_never_the_tag_or_extension() {
+        let stem = "é".repeat(200);
+        let name = unix("{stem}{tag}", &stem).unwrap();
+        let name = name.to_str().unwrap();
+        assert_eq!(name.len(), 254);
+        assert!(name.starts_with("éé"));
+        assert!(name.ends_with("__[+2.50_Sec+].srt"));
+
+        assert!(unix(&"x".repeat(300), "movie").is_err());
+        assert!(unix(&format!("{}{{stem}}", "x".repeat(300)), "movie")
+            .is_err());
+    }
+}
@@ -0,0 +1,19 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+
+/// The number of directories `temp_dir` made so far in this process.
+static DIRS: AtomicUsize = AtomicUsize::new(0);
+
+/// An empty directory for the test `name`, unique to this process and call,
+/// so tests running at the same time never share one.
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("submod-{}-{}-{}", process::id(),
+        DIRS.fetch_add(1, Ordering::SeqCst), name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
@@ -0,0 +1,484 @@
+use std::sync::OnceLock;
+
+use regex::{Captures, Regex};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+
+/// Matches the name and classes of a vtt start tag, like `<c.yellow.big`.
+const TAG_CLASSES: &str = r"<([A-Za-z]+)((?:\.[^\s.<>]+)+)";
+
+/// Unicode RIGHT-TO-LEFT MARK: a zero-width character with strong
+/// right-to-left direction, which makes players lay out the whole line
+/// right-to-left even when it starts with a number or a Latin name.
+const RLM: char = '\u{200F}';
+
+/// Whether a subtitle's text is displayed in italics.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Italic {
+    Full,
+    Partial,
+    None,
+}
+
+/// Classifies subtitle text by the italic tags it contains: either html-style
+/// `<i>` tags, or ASS-style `{\i1}` override tags.
+pub fn italic_style(lines: &[String]) -> Italic {
+    let (mut italic, mut upright) = (0, 0);
+    let mut in_italic = false;
+    for line in lines {
+        let mut rest = line.as_str();
+        while let Some(c) = rest.chars().next() {
+            let tag_end = match c {
+                '<' => rest.find('>'),
+                '{' => rest.find('}'),
+                _ => None,
+            };
+            if let Some(end) = tag_end {
+                match &rest[..=end] {
+                    "<i>" | "<I>" => in_italic = true,
+                    "</i>" | "</I>" => in_italic = false,
+                    tag if tag.starts_with('{') => {
+                        // Override blocks may combine tags, like {\an8\i1}:
+                        for code in tag[1..end].split('\\') {
+                            match code {
+                                "i1" => in_italic = true,
+                                "i0" | "i" => in_italic = false,
+                                _ => {},
+                            }
+                        }
+                    },
+                    _ => {},
+                }
+                rest = &rest[end + 1..];
+                continue;
+            }
+            if !c.is_whitespace() {
+                if in_italic {
+                    italic += 1;
+                } else {
+                    upright += 1;
+                }
+            }
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    match (italic, upright) {
+        (0, _) => Italic::None,
+        (_, 0) => Italic::Full,
+        _ => Italic::Partial,
+    }
+}
+
+/// Trims the subtitle text lines, collapses runs of whitespace
+/// into single spaces, and removes lines that are left empty.
+/// Returns true if any of the lines were changed.
+pub fn normalize_whitespace(lines: &mut Vec<String>) -> bool {
+    let mut changed = false;
+    for line in lines.iter_mut() {
+        let normalized = line.split_whitespace().collect::<Vec<_>>().join(" ");
+        if *line != normalized {
+            *line = normalized;
+            changed = true;
+        }
+    }
+    lines.retain(|line| !line.is_empty());
+    changed
+}
+
+/// Adds a right-to-left mark at the start of every line of a subtitle
+/// whose text is predominantly written in a right-to-left script.
+/// Directional marks that were already present are stripped first,
+/// so broken mark sequences are replaced and repeated calls don't pile up.
+/// Returns true if any of the lines were changed.
+pub fn fix_rtl(lines: &mut [String]) -> bool {
+    let (mut rtl, mut ltr) = (0, 0);
+    for line in lines.iter() {
+        for c in visible_chars(line).filter(|c| c.is_alphabetic()) {
+            if is_rtl(c) {
+                rtl += 1;
+            } else {
+                ltr += 1;
+            }
+        }
+    }
+    if rtl <= ltr {
+        return false;
+    }
+
+    let mut changed = false;
+    for line in lines.iter_mut() {
+        let mut fixed: String = line.chars()
+            .filter(|&c| !is_bidi_control(c))
+            .collect();
+        if !fixed.trim().is_empty() {
+            // Insert the mark after any leading tags like <i> or {\an8}:
+            let at = markup_len(&fixed);
+            fixed.insert(at, RLM);
+        }
+        if *line != fixed {
+            *line = fixed;
+            changed = true;
+        }
+    }
+    changed
+}
+
+/// Removes all html-style tags like <c.yellow> and override tags like {\an8}.
+/// Returns true if any of the lines were changed.
+pub fn strip_tags(lines: &mut [String]) -> bool {
+    let mut changed = false;
+    for line in lines.iter_mut() {
+        let stripped: String = visible_chars(line).collect();
+        if *line != stripped {
+            *line = stripped;
+            changed = true;
+        }
+    }
+    changed
+}
+
+/// Removes the vtt tags srt players don't know, like <c.yellow> or
+/// <v Bob>, and keeps <i>, <b> and <u> without their classes.
+/// Returns true if any of the lines were changed.
+pub fn srt_tags(lines: &mut [String]) -> bool {
+    // Compiled once, as conversions translate the tags of every cue:
+    static TAG: OnceLock<Regex> = OnceLock::new();
+    let tag = TAG.get_or_init(||
+        Regex::new(r"<(/?)([^\s.<>/]*)[^<>]*>").unwrap());
+    let mut changed = false;
+    for line in lines.iter_mut() {
+        let converted = tag.replace_all(line, |c: &Captures| {
+            match c[2].to_lowercase().as_str() {
+                name @ ("i" | "b" | "u") => format!("<{}{}>", &c[1], name),
+                _ => String::new(),
+            }
+        }).into_owned();
+        if *line != converted {
+            *line = converted;
+            changed = true;
+        }
+    }
+    changed
+}
+
+/// Translates srt formatting for vtt: <i>, <b> and <u> are kept, while
+/// tags vtt doesn't know, like <font color="#00ff00">, and override
+/// blocks like {\an8} are removed. Returns true if any line was changed.
+pub fn vtt_tags(lines: &mut [String]) -> bool {
+    static OVERRIDES: OnceLock<Regex> = OnceLock::new();
+    let overrides = OVERRIDES.get_or_init(||
+        Regex::new(r"\{\\[^{}]*\}").unwrap());
+    let mut changed = srt_tags(lines);
+    for line in lines.iter_mut() {
+        if overrides.is_match(line) {
+            *line = overrides.replace_all(line, "").into_owned();
+            changed = true;
+        }
+    }
+    changed
+}
+
+/// Returns the classes used by the vtt tags of the text,
+/// like `yellow` and `big` for <c.yellow.big>.
+pub fn classes(lines: &[String]) -> Vec<String> {
+    let tag = Regex::new(TAG_CLASSES).unwrap();
+    lines.iter()
+        .flat_map(|line| tag.captures_iter(line)
+            .flat_map(|c| c[2].split('.').skip(1)
+                .map(|class| class.to_string())
+                .collect::<Vec<_>>()))
+        .collect()
+}
+
+/// Renames the classes of vtt tags according to `(old, new)` pairs.
+/// Returns true if any of the lines were changed.
+pub fn rename_classes(lines: &mut [String], renames: &[(String, String)])
+    -> bool
+{
+    let tag = Regex::new(TAG_CLASSES).unwrap();
+    let mut changed = false;
+    for line in lines.iter_mut() {
+        let renamed = tag.replace_all(line, |c: &Captures| {
+            let classes: Vec<&str> = c[2].split('.').skip(1)
+                .map(|class| renames.iter()
+                    .find(|&(old, _)| old == class)
+                    .map_or(class, |(_, new)| new.as_str()))
+                .collect();
+            format!("<{}.{}", &c[1], classes.join("."))
+        }).into_owned();
+        if *line != renamed {
+            *line = renamed;
+            changed = true;
+        }
+    }
+    changed
+}
+
+/// Replaces the matches of `regex` in the subtitle text by `replacement`,
+/// which can refer to capture groups like `$1`. Matches may span lines,
+/// and lines that are left blank are removed.
+/// Returns true if any of the lines were changed.
+pub fn replace(lines: &mut Vec<String>, regex: &Regex, replacement: &str)
+    -> bool
+{
+    let text = lines.join("\n");
+    let replaced = regex.replace_all(&text, replacement);
+    if replaced == text {
+        return false;
+    }
+    *lines = replaced.split('\n')
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.to_string())
+        .collect();
+    true
+}
+
+/// Counts the characters of a line the way a reader sees them:
+/// tags are ignored, entities like `&amp;` count as one character,
+/// and so do letters with combining accents and composed emoji.
+pub fn visible_len(line: &str) -> usize {
+    decoded(line).graphemes(true)
+        .filter(|grapheme| !grapheme.chars().all(is_invisible))
+        .count()
+}
+
+/// Like `visible_len`, but counts wide characters like CJK ideographs,
+/// kana and emoji as 2, for the width a line takes up on screen.
+pub fn visible_width(line: &str) -> usize {
+    decoded(line).width()
+}
+
+/// The length of a line for the limits on it: its `visible_len`, or its
+/// `visible_width` when `wide` counts wide characters as 2.
+pub fn line_len(line: &str, wide: bool) -> usize {
+    if wide {
+        visible_width(line)
+    } else {
+        visible_len(line)
+    }
+}
+
+/// Returns the text of a line without its tags.
+pub fn visible_text(line: &str) -> String {
+    visible_chars(line).collect()
+}
+
+/// The visible text of a line, with its entities decoded.
+fn decoded(line: &str) -> String {
+    visible_text(line).replace("&lt;", "<").replace("&gt;", ">")
+        .replace("&nbsp;", "\u{A0}").replace("&lrm;", "\u{200E}")
+        .replace("&rlm;", "\u{200F}").replace("&amp;", "&")
+}
+
+/// Characters of right-to-left scripts like Hebrew, Arabic and Syriac.
+fn is_rtl(c: char) -> bool {
+    matches!(c as u32, 0x0590..=0x08FF | 0xFB1D..=0xFDFF | 0xFE70..=0xFEFF)
+}
+
+/// Zero-width characters that take up no space at all.
+fn is_invisible(c: char) -> bool {
+    is_bidi_control(c) || matches!(c, '\u{200B}' | '\u{2060}' | '\u{FEFF}')
+}
+
+/// Invisible marks, embeddings and isolates that control text direction.
+fn is_bidi_control(c: char) -> bool {
+    matches!(c, '\u{061C}' | '\u{200E}' | '\u{200F}' |
+        '\u{202A}'..='\u{202E}' | '\u{2066}'..='\u{2069}')
+}
+
+/// Returns the characters of the line that are displayed,
+/// skipping html-style tags like <i> and override tags like {\an8}.
+fn visible_chars(line: &str) -> impl Iterator<Item = char> + '_ {
+    let mut closing = None;
+    line.chars().filter(move |&c| {
+        match (closing, c) {
+            (Some(close), _) => {
+                if c == close {
+                    closing = None;
+                }
+                false
+            },
+            (None, '<') => { closing = Some('>'); false },
+            (None, '{') => { closing = Some('}'); false },
+            _ => true,
+        }
+    })
+}
+
+/// Returns the byte length of the tags at the start of the line.
+fn markup_len(line: &str) -> usize {
+    let mut len = 0;
+    loop {
+        let rest = &line[len..];
+        let close = if rest.starts_with('<') {
+            '>'
+        } else if rest.starts_with('{') {
+            '}'
+        } else {
+            return len;
+        };
+        match rest.find(close) {
+            Some(i) => len += i + 1,
+            None => return len,
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixed(lines: &[&str]) -> (bool, Vec<String>) {
+        let mut lines: Vec<String> =
+            lines.iter().map(|l| l.to_string()).collect();
+        let changed = fix_rtl(&mut lines);
+        (changed, lines)
+    }
+
+    #[test]
+    fn replaces_text_with_capture_groups() {
+        let mut lines = vec![String::from("Mr Smith, Mr Jones"),
+            String::from("(sighs)")];
+        let regex = Regex::new(r"Mr (\w+)").unwrap();
+        assert!(replace(&mut lines, &regex, "Mr. $1"));
+        assert_eq!(lines, ["Mr. Smith, Mr. Jones", "(sighs)"]);
+        assert!(!replace(&mut lines, &regex, "Mr. $1"));
+
+        let sound = Regex::new(r"\(\w+\)").unwrap();
+        assert!(replace(&mut lines, &sound, ""));
+        assert_eq!(lines, ["Mr. Smith, Mr. Jones"]);
+        assert!(replace(&mut lines, &Regex::new(r"(?s).*").unwrap(), ""));
+        assert!(lines.is_empty());
+    }
+
+    #[test]
+    fn marks_arabic_lines() {
+        let (changed, lines) = fixed(&["مرحبا بكم.", "كيف حالك؟"]);
+        assert!(changed);
+        assert_eq!(lines, ["\u{200F}مرحبا بكم.", "\u{200F}كيف حالك؟"]);
+    }
+
+    #[test]
+    fn marks_lines_starting_with_numbers_or_latin_names() {
+        let (changed, lines) = fixed(&["John, חכה 10 דקות!", "10 דקות, John!"]);
+        assert!(changed);
+        assert_eq!(lines,
+            ["\u{200F}John, חכה 10 דקות!", "\u{200F}10 דקות, John!"]);
+    }
+
+    #[test]
+    fn leaves_ltr_text_untouched() {
+        let (changed, lines) = fixed(&["Shalom means שלום in Hebrew."]);
+        assert!(!changed);
+        assert_eq!(lines, ["Shalom means שלום in Hebrew."]);
+    }
+
+    #[test]
+    fn inserts_marks_after_leading_tags() {
+        let (_, lines) = fixed(&["{\\an8}<i>שלום!</i>"]);
+        assert_eq!(lines, ["{\\an8}<i>\u{200F}שלום!</i>"]);
+    }
+
+    #[test]
+    fn ignores_markup_when_detecting_direction() {
+        let (changed, _) = fixed(&["<font color=\"#ffffff\">שלום</font>"]);
+        assert!(changed);
+    }
+
+    #[test]
+    fn replaces_broken_mark_sequences() {
+        let (changed, lines) =
+            fixed(&["\u{202B}\u{200F}\u{200F}שלום.\u{202C}", "\u{200E}?מה"]);
+        assert!(changed);
+        assert_eq!(lines, ["\u{200F}שלום.", "\u{200F}?מה"]);
+    }
+
+    #[test]
+    fn classifies_italic_text() {
+        let style = |lines: &[&str]| italic_style(&lines.iter()
+            .map(|l| l.to_string()).collect::<Vec<_>>());
+        assert_eq!(style(&["<i>Meanwhile,", "in Paris...</i>"]), Italic::Full);
+        assert_eq!(style(&["<i>Meanwhile,</i>", "<i>in Paris...</i>"]),
+            Italic::Full);
+        assert_eq!(style(&["{\\an8\\i1}Meanwhile{\\i0}"]), Italic::Full);
+        assert_eq!(style(&["<i>Meanwhile,</i> in Paris"]), Italic::Partial);
+        assert_eq!(style(&["- <i>Who's there?</i>", "- Me."]),
+            Italic::Partial);
+        assert_eq!(style(&["<b>Meanwhile</b>"]), Italic::None);
+    }
+
+    #[test]
+    fn renames_and_strips_vtt_classes() {
+        let mut lines = vec![String::from(
+            "<c.yellow.big>Look</c> <v.loud Bob>out!</v>")];
+        assert_eq!(classes(&lines), ["yellow", "big", "loud"]);
+        let renames = [(String::from("yellow"), String::from("warn"))];
+        assert!(rename_classes(&mut lines, &renames));
+        assert_eq!(lines, ["<c.warn.big>Look</c> <v.loud Bob>out!</v>"]);
+        assert!(strip_tags(&mut lines));
+        assert_eq!(lines, ["Look out!"]);
+        assert!(classes(&lines).is_empty());
+    }
+
+    #[test]
+    fn keeps_only_the_tags_srt_players_know() {
+        let mut lines = vec![
+            String::from("<v Bob><i.loud>Look</i> <c.yellow>out</c>!</v>"),
+            String::from("<00:00:01.500><B>Now</B> <ruby>漢<rt>kan</rt></ruby>"),
+        ];
+        assert!(srt_tags(&mut lines));
+        assert_eq!(lines, ["<i>Look</i> out!", "<b>Now</b> 漢kan"]);
+        assert!(!srt_tags(&mut lines));
+    }
+
+    #[test]
+    fn translates_srt_formatting_for_vtt() {
+        let mut lines = vec![
+            String::from("{\\an8}<font color=\"#00ff00\">Look</font> <I>out</I>!"),
+            String::from("{Sighs} 3 < 4"),
+        ];
+        assert!(vtt_tags(&mut lines));
+        assert_eq!(lines, ["Look <i>out</i>!", "{Sighs} 3 < 4"]);
+        assert!(!vtt_tags(&mut lines));
+    }
+
+    #[test]
+    fn counts_french_characters_not_bytes() {
+        assert_eq!(visible_len("Ça a été très marrant !"), 23);
+        // Accents may also be written as combining characters:
+        assert_eq!(visible_len("e\u{301}te\u{301}"), 3);
+        assert_eq!(visible_len("<i>Où ça ?</i> {\\an8}&lt;rires&gt;"), 15);
+        assert_eq!(visible_width("Où ça ?"), 7);
+    }
+
+    #[test]
+    fn counts_japanese_characters_as_wide() {
+        assert_eq!(visible_len("こんにちは、世界！"), 9);
+        assert_eq!(visible_width("こんにちは、世界！"), 18);
+        // A kana with a combining voicing mark is a single character:
+        assert_eq!(visible_len("か\u{3099}"), 1);
+        assert_eq!(visible_width("<c.jp>ｶﾀｶﾅ</c> 日本"), 9);
+        assert_eq!(line_len("日本", false), 2);
+        assert_eq!(line_len("日本", true), 4);
+    }
+
+    #[test]
+    fn counts_composed_emoji_as_one_character() {
+        assert_eq!(visible_len("Bravo 👍🏽"), 7);
+        assert_eq!(visible_len("👨\u{200D}👩\u{200D}👧"), 1);
+        assert_eq!(visible_len("🇫🇷🇧🇪"), 2);
+        assert_eq!(visible_width("🇫🇷 ❤\u{FE0F}"), 5);
+        assert_eq!(visible_len("\u{200F}שלום"), 4);
+    }
+
+    #[test]
+    fn is_idempotent() {
+        let (_, lines) = fixed(&["- 3 ימים?", "- Paris, כן."]);
+        let lines: Vec<&str> = lines.iter().map(|l| l.as_str()).collect();
+        let (changed, again) = fixed(&lines);
+        assert!(!changed);
+        assert_eq!(again, lines);
+    }
+}
@@ -0,0 +1,561 @@
+use regex::{Captures, Regex};
+
+use cue;
+use subtitles::Error;
+
+
+/// How a time expression was written, to write the shifted time the same
+/// way: a clock time like `00:00:01.500` or `00:00:01:12` in frames, or an
+/// offset like `1.5s`, `1500ms`, `36f` or `15000000t` in ticks. Offsets in
+/// hours or minutes are written in seconds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Form {
+    Clock,
+    ClockFrames,
+    Seconds,
+    Millis,
+    Frames,
+    Ticks,
+}
+
+/// The frame and tick rates of the `ttp:` parameters of the `tt` element,
+/// which frames and ticks are counted in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Timebase {
+    frame_rate: f64,
+    tick_rate: f64,
+}
+
+impl Timebase {
+    /// Reads the parameters of the `tt` element, or the defaults of TTML:
+    /// 30 frames per second, and a tick per frame, or per second without
+    /// a frame rate.
+    fn read(contents: &str) -> Timebase {
+        let root = tokens().captures_iter(contents)
+            .find(|token| token.get(1).is_some_and(|name|
+                local(name.as_str()) == "tt"));
+        let parameter = |name: &str| root.as_ref().and_then(|root|
+            attributes(&root[2]).into_iter()
+                .find(|&(attribute, _)| local(attribute) == name)
+                .map(|(_, value)| value.to_string()));
+        let number = |value: &str| value.trim().parse::<f64>().ok()
+            .filter(|number| *number > 0.0 && number.is_finite());
+        let frame_rate_opt = parameter("frameRate")
+            .and_then(|rate| number(&rate));
+        let multiplier = parameter("frameRateMultiplier").and_then(|ratio| {
+            let mut parts = ratio.split_whitespace().map(number);
+            match (parts.next(), parts.next()) {
+                (Some(Some(numerator)), Some(Some(denominator))) =>
+                    Some(numerator / denominator),
+                _ => None,
+            }
+        }).unwrap_or(1.0);
+        let frame_rate = frame_rate_opt.unwrap_or(30.0) * multiplier;
+        Timebase {
+            frame_rate,
+            tick_rate: parameter("tickRate").and_then(|rate| number(&rate))
+                .unwrap_or(if frame_rate_opt.is_some() { frame_rate }
+                    else { 1.0 }),
+        }
+    }
+
+    /// Reads a time expression into seconds, and how it was written.
+    fn parse(&self, time: &str) -> Option<(f64, Form)> {
+        let time = time.trim();
+        let clock = Regex::new(r"^(\d+):(\d{2}):(\d{2}(?:\.\d+)?)(?::(\d+)(?:\.\d+)?)?$")
+            .unwrap();
+        if let Some(parts) = clock.captures(time) {
+            let seconds = parts[1].parse::<f64>().ok()? * 3600.0
+                + parts[2].parse::<f64>().ok()? * 60.0
+                + parts[3].parse::<f64>().ok()?;
+            return Some(match parts.get(4) {
+                Some(frames) => (seconds + frames.as_str().parse::<f64>().ok()?
+                    / self.frame_rate, Form::ClockFrames),
+                None => (seconds, Form::Clock),
+            });
+        }
+        let offset = Regex::new(r"^(\d+(?:\.\d+)?)(h|ms|m|s|f|t)$").unwrap();
+        let parts = offset.captures(time)?;
+        let count = parts[1].parse::<f64>().ok()?;
+        Some(match &parts[2] {
+            "h" => (count * 3600.0, Form::Seconds),
+            "m" => (count * 60.0, Form::Seconds),
+            "s" => (count, Form::Seconds),
+            "ms" => (count / 1000.0, Form::Millis),
+            "f" => (count / self.frame_rate, Form::Frames),
+            _ => (count / self.tick_rate, Form::Ticks),
+        })
+    }
+
+    /// Writes seconds the way `form` gives, rounded to the millisecond,
+    /// frame or tick; times before the start are moved to it.
+    fn format(&self, seconds: f64, form: Form) -> String {
+        let seconds = seconds.max(0.0);
+        match form {
+            Form::Clock => cue::format_time(seconds, false),
+            Form::ClockFrames => {
+                let frames = (seconds * self.frame_rate).round();
+                let whole = (frames / self.frame_rate).floor();
+                // Rounding can carry a frame into the next second:
+                let (whole, frame) = match frames - (whole
+                    * self.frame_rate).round() {
+                    frame if frame < 0.0 => (whole - 1.0,
+                        frames - ((whole - 1.0) * self.frame_rate).round()),
+                    frame => (whole, frame),
+                };
+                let whole = whole as u64;
+                format!("{:02}:{:02}:{:02}:{:02}", whole / 3600,
+                    whole / 60 % 60, whole % 60, frame as u64)
+            },
+            Form::Seconds => {
+                let millis = format!("{:.3}", cue::to_millis(seconds) as f64
+                    / 1000.0);
+                format!("{}s", millis.trim_end_matches('0')
+                    .trim_end_matches('.'))
+            },
+            Form::Millis => format!("{}ms", cue::to_millis(seconds)),
+            Form::Frames => format!("{}f", (seconds * self.frame_rate)
+                .round()),
+            Form::Ticks => format!("{}t", (seconds * self.tick_rate).round()),
+        }
+    }
+}
+
+/// The `begin`, `end` and `dur` of an element, in seconds, along with how
+/// they were written.
+#[derive(Debug, Default)]
+struct Timing {
+    begin_opt: Option<(f64, Form)>,
+    end_opt: Option<(f64, Form)>,
+    dur_opt: Option<(f64, Form)>,
+}
+
+impl Timing {
+    fn read(attributes_text: &str, timebase: &Timebase)
+        -> Result<Timing, String>
+    {
+        let mut timing = Timing::default();
+        for (name, value) in attributes(attributes_text) {
+            let slot = match name {
+                "begin" => &mut timing.begin_opt,
+                "end" => &mut timing.end_opt,
+                "dur" => &mut timing.dur_opt,
+                _ => continue,
+            };
+            *slot = Some(timebase.parse(value).ok_or_else(|| format!(
+                "`{}` isn't a TTML time expression submod can read, like \
+                00:00:01.500, 00:00:01:12 or 1.5s.", value))?);
+        }
+        Ok(timing)
+    }
+
+}
+
+/// Shifts the `begin`, `end` and `dur` of the timed elements of a TTML
+/// document, leaving everything else, like the styles and regions, as it
+/// is. Only the outermost elements with a `begin` are shifted, as the times
+/// of the elements within them are relative to it. `shift` gets the start
+/// and end in seconds, and returns the new ones; a `dur` is kept as the
+/// difference of the two. When it returns `None`, the element is removed,
+/// along with its line if it's alone on it.
+pub fn shift<F>(contents: &str, mut shift: F) -> Result<String, Error>
+    where F: FnMut(f64, f64) -> Option<(f64, f64)>
+{
+    let timebase = Timebase::read(contents);
+    let time_attribute = Regex::new(
+        r#"(\s)(begin|end|dur)(\s*=\s*)(?:"[^"]*"|'[^']*')"#).unwrap();
+    let mut output = String::with_capacity(contents.len());
+    let mut copied = 0;
+    // Whether each open element is timed, or within a timed one:
+    let mut timed: Vec<bool> = Vec::new();
+    // The depth and start of the element being removed, until its end tag:
+    let mut removing_opt: Option<(usize, usize)> = None;
+    for token in tokens().captures_iter(contents) {
+        let whole = token.get(0).unwrap();
+        if token.get(1).is_none() {
+            if whole.as_str().starts_with("</") {
+                timed.pop();
+                if let Some((depth, start)) = removing_opt {
+                    if timed.len() == depth {
+                        let (start, end) = removal(contents, start, whole.end());
+                        output.push_str(&contents[copied..start]);
+                        copied = end;
+                        removing_opt = None;
+                    }
+                }
+            }
+            continue;
+        }
+        if removing_opt.is_some() {
+            if &token[3] != "/" {
+                timed.push(true);
+            }
+            continue;
+        }
+        let within = timed.last() == Some(&true);
+        let timing = Timing::read(&token[2], &timebase).map_err(|message|
+            Error::Parse { line: Some(line(contents, whole.start())),
+                message })?;
+        // Only a `begin` makes a time container: a `dur` or `end` alone,
+        // like that of a body as long as the programme, times nothing within.
+        if &token[3] != "/" {
+            timed.push(within || timing.begin_opt.is_some());
+        }
+        let (begin, begin_form) = match timing.begin_opt {
+            Some(begin) if !within => begin,
+            _ => continue,
+        };
+        let end = timing.end_opt.map(|(end, _)| end)
+            .or_else(|| timing.dur_opt.map(|(dur, _)| begin + dur))
+            .unwrap_or(begin);
+        let (begin, end) = match shift(begin, end) {
+            Some(times) => times,
+            None if &token[3] == "/" => {
+                let (start, end) = removal(contents, whole.start(), whole.end());
+                output.push_str(&contents[copied..start]);
+                copied = end;
+                continue;
+            },
+            None => {
+                removing_opt = Some((timed.len() - 1, whole.start()));
+                continue;
+            },
+        };
+        let tag = time_attribute.replace_all(whole.as_str(),
+            |attribute: &Captures| {
+                let value = match &attribute[2] {
+                    "begin" => timebase.format(begin, begin_form),
+                    "end" => timebase.format(end, timing.end_opt.unwrap().1),
+                    _ => timebase.format(end - begin,
+                        timing.dur_opt.unwrap().1),
+                };
+                format!("{}{}{}\"{}\"", &attribute[1], &attribute[2],
+                    &attribute[3], value)
+            });
+        output.push_str(&contents[copied..whole.start()]);
+        output.push_str(&tag);
+        copied = whole.end();
+    }
+    output.push_str(&contents[copied..]);
+    Ok(output)
+}
+
+/// An open element, while converting to srt: when it begins and ends,
+/// in seconds from the start, and whether it's a paragraph, or in italics.
+struct Open {
+    begin: f64,
+    end_opt: Option<f64>,
+    paragraph: bool,
+    italic: bool,
+}
+
+/// Converts the paragraphs of a TTML document to srt, for the cues to be
+/// modified and written like those of any srt file. Their times add up those
+/// of the elements they're in, `<br/>` becomes a line break, spans in the
+/// italic font style become `<i>` tags, and other markup is left out.
+pub fn to_srt(contents: &str) -> Result<String, Error> {
+    let timebase = Timebase::read(contents);
+    let whitespace = Regex::new(r"\s+").unwrap();
+    let mut open: Vec<Open> = Vec::new();
+    let mut text_opt: Option<String> = None;
+    let (mut srt, mut cues, mut copied) = (String::new(), 0, 0);
+    for token in tokens().captures_iter(contents) {
+        let whole = token.get(0).unwrap();
+        if let Some(ref mut text) = text_opt {
+            let between = &contents[copied..whole.start()];
+            text.push_str(&decode(&whitespace.replace_all(between, " ")));
+            if whole.as_str().starts_with("<![CDATA[") {
+                text.push_str(&whole.as_str()[9..whole.as_str().len() - 3]);
+            }
+        }
+        copied = whole.end();
+        let name = match token.get(1) {
+            Some(name) => local(name.as_str()),
+            None => {
+                if whole.as_str().starts_with("</") {
+                    if let Some(element) = open.pop() {
+                        match text_opt {
+                            Some(ref mut text) if element.italic =>
+                                text.push_str("</i>"),
+                            _ => {},
+                        }
+                        if element.paragraph {
+                            let text = text_opt.take().unwrap_or_default();
+                            let end = element.end_opt.ok_or_else(||
+                                Error::Parse { line: Some(line(contents,
+                                    whole.start())), message: "The \
+                                    paragraph has no end, nor a dur."
+                                    .into() })?;
+                            cues += 1;
+                            srt.push_str(&format!("{}{}\n{} --> {}\n{}\n",
+                                if cues > 1 { "\n" } else { "" }, cues,
+                                cue::format_time(element.begin, true),
+                                cue::format_time(end, true), lines(&text)));
+                        }
+                    }
+                }
+                continue;
+            },
+        };
+        match text_opt {
+            Some(ref mut text) if name == "br" => text.push('\n'),
+            _ => {},
+        }
+        if &token[3] == "/" {
+            continue;
+        }
+        let timing = Timing::read(&token[2], &timebase).map_err(|message|
+            Error::Parse { line: Some(line(contents, whole.start())),
+                message })?;
+        let (parent_begin, parent_end_opt) = open.last()
+            .map_or((0.0, None), |parent| (parent.begin, parent.end_opt));
+        let begin = parent_begin + timing.begin_opt.map_or(0.0, |(b, _)| b);
+        let end_opt = timing.end_opt.map(|(end, _)| parent_begin + end)
+            .or_else(|| timing.dur_opt.map(|(dur, _)| begin + dur))
+            .or(parent_end_opt);
+        let italic = text_opt.is_some() && attributes(&token[2]).iter()
+            .any(|&(attribute, value)| local(attribute) == "fontStyle"
+                && value == "italic");
+        match text_opt {
+            Some(ref mut text) if italic => text.push_str("<i>"),
+            _ => {},
+        }
+        let paragraph = name == "p";
+        if paragraph {
+            text_opt = Some(String::new());
+        }
+        open.push(Open { begin, end_opt, paragraph, italic });
+    }
+    if cues == 0 {
+        return Err(Error::Parse { line: None, message: "No TTML subtitles \
+            found, with paragraphs like <p begin=\"00:00:01.000\" \
+            end=\"00:00:04.000\">.".into() });
+    }
+    Ok(srt)
+}
+
+/// Writes cues as a TTML document, each a paragraph timed in clock times,
+/// with `<br/>` for line breaks and italic spans for `<i>` tags. Other tags
+/// of srt and vtt text are left out.
+pub fn write<'a, I>(cues: I) -> String
+    where I: IntoIterator<Item = (f64, f64, &'a str)>
+{
+    let tag = Regex::new(r"</?([A-Za-z][\w.]*)[^>]*>").unwrap();
+    let mut ttml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+        <tt xmlns=\"http://www.w3.org/ns/ttml\" \
+        xmlns:tts=\"http://www.w3.org/ns/ttml#styling\">\n\
+        <body>\n<div>\n");
+    for (start, end, text) in cues {
+        let mut paragraph = String::new();
+        let mut copied = 0;
+        for found in tag.captures_iter(text) {
+            let whole = found.get(0).unwrap();
+            paragraph.push_str(&escape(&text[copied..whole.start()]));
+            copied = whole.end();
+            if &found[1] == "i" {
+                paragraph.push_str(if whole.as_str().starts_with("</") {
+                    "</span>" } else { "<span tts:fontStyle=\"italic\">" });
+            }
+        }
+        paragraph.push_str(&escape(&text[copied..]));
+        ttml.push_str(&format!("<p begin=\"{}\" end=\"{}\">{}</p>\n",
+            cue::format_time(start.max(0.0), false),
+            cue::format_time(end.max(0.0), false),
+            paragraph.replace('\n', "<br/>")));
+    }
+    ttml.push_str("</div>\n</body>\n</tt>\n");
+    ttml
+}
+
+/// Escapes the characters that are markup in XML text.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// Matches the markup of an XML document: comments, CDATA sections,
+/// declarations and processing instructions, end tags, and start tags with
+/// their name, their attributes and a `/` if they're empty elements.
+fn tokens() -> Regex {
+    Regex::new(r#"(?s)<!--.*?-->|<!\[CDATA\[.*?\]\]>|<[?!][^>]*>|</[^>]*>|<([A-Za-z_][\w.:-]*)((?:\s+[^\s=/>]+\s*=\s*(?:"[^"]*"|'[^']*'))*)\s*(/?)>"#)
+        .unwrap()
+}
+
+/// The names and values of the attributes of a start tag.
+fn attributes(text: &str) -> Vec<(&str, &str)> {
+    let attribute = Regex::new(r#"([^\s=]+)\s*=\s*(?:"([^"]*)"|'([^']*)')"#)
+        .unwrap();
+    attribute.captures_iter(text)
+        .map(|captures| (captures.get(1).unwrap().as_str(),
+            captures.get(2).or_else(|| captures.get(3)).unwrap().as_str()))
+        .collect()
+}
+
+/// A name without its namespace prefix, like `p` for `tt:p`.
+fn local(name: &str) -> &str {
+    name.rsplit(':').next().unwrap()
+}
+
+/// The line of the document a byte offset is on, starting at 1.
+fn line(contents: &str, offset: usize) -> usize {
+    contents[..offset].matches('\n').count() + 1
+}
+
+/// The part of the contents to remove along with the markup from `start`
+/// to `end`: its whole line, if there's nothing else on it.
+fn removal(contents: &str, start: usize, end: usize) -> (usize, usize) {
+    let line_start = contents[..start].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = contents[end..].find('\n').map_or(contents.len(), |i|
+        end + i + 1);
+    if contents[line_start..start].trim().is_empty()
+        && contents[end..line_end].trim().is_empty() {
+        (line_start, line_end)
+    } else {
+        (start, end)
+    }
+}
+
+/// Decodes the character references of XML text, like `&amp;`.
+fn decode(text: &str) -> String {
+    let reference = Regex::new(r"&(#x[0-9A-Fa-f]+|#\d+|lt|gt|amp|quot|apos);")
+        .unwrap();
+    reference.replace_all(text, |captures: &Captures| {
+        let name = &captures[1];
+        let code_opt = if let Some(hex) = name.strip_prefix("#x") {
+            u32::from_str_radix(hex, 16).ok()
+        } else if let Some(decimal) = name.strip_prefix('#') {
+            decimal.parse().ok()
+        } else {
+            None
+        };
+        match (name, code_opt.and_then(std::char::from_u32)) {
+            (_, Some(character)) => character.to_string(),
+            ("lt", _) => "<".to_string(),
+            ("gt", _) => ">".to_string(),
+            ("amp", _) => "&".to_string(),
+            ("quot", _) => "\"".to_string(),
+            ("apos", _) => "'".to_string(),
+            _ => captures[0].to_string(),
+        }
+    }).into_owned()
+}
+
+/// The lines of the text of a paragraph, without the whitespace around them.
+fn lines(text: &str) -> String {
+    text.split('\n')
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TTML: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+        <tt xmlns=\"http://www.w3.org/ns/ttml\" \
+        xmlns:tts=\"http://www.w3.org/ns/ttml#styling\" \
+        xmlns:ttp=\"http://www.w3.org/ns/ttml#parameter\" \
+        ttp:frameRate=\"25\" ttp:tickRate=\"10000000\">\n\
+        <head><styling><style xml:id=\"s1\" tts:color=\"white\"/></styling>\
+        </head>\n\
+        <body><div>\n\
+        <p begin=\"00:00:01.000\" end=\"00:00:02.500\" style=\"s1\">Hello,<br/>\
+        <span tts:fontStyle=\"italic\">world</span> &amp; more</p>\n\
+        <!-- <p begin=\"00:00:05.000\" end=\"00:00:06.000\">Draft</p> -->\n\
+        <p begin='00:00:03:12' dur=\"2s\"><span begin=\"1s\">Late</span></p>\n\
+        <p begin=\"70000000t\" end=\"9000ms\">Bye</p>\n\
+        </div></body>\n</tt>\n";
+
+    #[test]
+    fn reads_every_kind_of_time_expression() {
+        let timebase = Timebase { frame_rate: 25.0, tick_rate: 10.0 };
+        for &(time, seconds, form) in &[
+            ("01:02:03.5", 3723.5, Form::Clock),
+            ("00:00:01:12", 1.48, Form::ClockFrames),
+            ("1.5h", 5400.0, Form::Seconds),
+            ("2m", 120.0, Form::Seconds),
+            ("2.25s", 2.25, Form::Seconds),
+            ("1500ms", 1.5, Form::Millis),
+            ("50f", 2.0, Form::Frames),
+            ("25t", 2.5, Form::Ticks)] {
+            assert_eq!(timebase.parse(time), Some((seconds, form)), "{}", time);
+        }
+        for time in &["", "1", "-1s", "00:01", "1:2:3", "wallclock(\"2026\")"] {
+            assert_eq!(timebase.parse(time), None, "{}", time);
+        }
+        assert_eq!(timebase.format(1.48 + 0.96, Form::ClockFrames),
+            "00:00:02:11");
+        assert_eq!(timebase.format(59.999, Form::ClockFrames), "00:01:00:00");
+        assert_eq!(timebase.format(2.5, Form::Seconds), "2.5s");
+        assert_eq!(timebase.format(-1.0, Form::Ticks), "0t");
+        assert_eq!(Timebase::read(TTML), Timebase { frame_rate: 25.0,
+            tick_rate: 10_000_000.0 });
+        assert_eq!(Timebase::read("<tt ttp:frameRate=\"30\" \
+            ttp:frameRateMultiplier=\"1000 1001\">").frame_rate,
+            30.0 * 1000.0 / 1001.0);
+    }
+
+    #[test]
+    fn shifts_the_outermost_timed_elements_only() {
+        let shifted = shift(TTML, |begin, end| Some((begin + 1.5, end + 1.5)))
+            .unwrap();
+        assert_eq!(shifted, TTML
+            .replace("begin=\"00:00:01.000\" end=\"00:00:02.500\"",
+                "begin=\"00:00:02.500\" end=\"00:00:04.000\"")
+            .replace("begin='00:00:03:12' dur=\"2s\"",
+                "begin=\"00:00:05:00\" dur=\"2s\"")
+            .replace("begin=\"70000000t\" end=\"9000ms\"",
+                "begin=\"85000000t\" end=\"10500ms\""));
+        // A body with a duration only still has its paragraphs shifted:
+        let programme = include_str!("../tests/fixtures/programme.dfxp");
+        assert_eq!(shift(programme, |begin, end| Some((begin + 5.0, end + 5.0)))
+            .unwrap(), programme
+            .replace("begin=\"00:00:01.000\" end=\"00:00:03.000\"",
+                "begin=\"00:00:06.000\" end=\"00:00:08.000\"")
+            .replace("begin=\"00:00:04.000\"", "begin=\"00:00:09.000\""));
+        let error = shift("<tt><body><p begin=\"soon\">Hi</p></body></tt>",
+            |begin, end| Some((begin, end))).unwrap_err();
+        assert!(error.to_string().starts_with("Line 1: `soon`"), "{}", error);
+    }
+
+    #[test]
+    fn removes_elements_with_their_lines() {
+        let programme = include_str!("../tests/fixtures/programme.dfxp");
+        assert_eq!(shift(programme, |begin, end| if begin < 2.0 { None }
+            else { Some((begin, end)) }).unwrap(), programme.replace(
+            "      <p begin=\"00:00:01.000\" end=\"00:00:03.000\">\
+            The programme starts.</p>\n", ""));
+        assert_eq!(shift("<tt><body><p begin=\"1s\" end=\"2s\">A<br/>B</p>\
+            <p begin=\"3s\" end=\"4s\"/><p begin=\"5s\" end=\"6s\">C</p>\
+            </body></tt>", |begin, end| if begin < 4.0 { None }
+            else { Some((begin, end)) }).unwrap(),
+            "<tt><body><p begin=\"5s\" end=\"6s\">C</p></body></tt>");
+    }
+
+    #[test]
+    fn converts_paragraphs_to_srt() {
+        assert_eq!(to_srt(TTML).unwrap(),
+            "1\n00:00:01,000 --> 00:00:02,500\nHello,\n<i>world</i> & more\n\n\
+            2\n00:00:03,480 --> 00:00:05,480\nLate\n\n\
+            3\n00:00:07,000 --> 00:00:09,000\nBye\n");
+        // Times add up those of the elements the paragraphs are in:
+        assert_eq!(to_srt("<tt><body><div begin=\"10s\" end=\"20s\">\
+            <p begin=\"1s\">A</p></div></body></tt>").unwrap(),
+            "1\n00:00:11,000 --> 00:00:20,000\nA\n");
+        assert!(to_srt("<tt><body><p begin=\"1s\">A</p></body></tt>")
+            .is_err());
+        assert!(to_srt("<tt><body></body></tt>").is_err());
+    }
+
+    #[test]
+    fn writes_cues_as_paragraphs() {
+        let ttml = write(vec![(1.0, 2.5, "<i>Fish</i> & <b>chips</b>\nNow")]);
+        assert!(ttml.contains("<p begin=\"00:00:01.000\" \
+            end=\"00:00:02.500\"><span tts:fontStyle=\"italic\">Fish</span> \
+            &amp; chips<br/>Now</p>"), "{}", ttml);
+        assert_eq!(to_srt(&ttml).unwrap(),
+            "1\n00:00:01,000 --> 00:00:02,500\n<i>Fish</i> & chips\nNow\n");
+    }
+}
@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use failure::Error;
+
+use cue::{self, Block, Cue};
+use encoding;
+use text;
+use subtitles::Error as SubmodError;
+
+
+/// How far the shifted subtitles still are from those of a reference
+/// subtitle file for the same video, as measured by `--verify-against`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Residual {
+    pub reference: PathBuf,
+    /// Median of how much later the reference shows the paired cues,
+    /// or `None` if no cues could be paired.
+    pub offset_opt: Option<f64>,
+    /// Number of cues paired with a cue of the reference.
+    pub pairs: usize,
+    /// Largest residual offset that passes, in seconds.
+    pub tolerance: f64,
+}
+
+impl Residual {
+    /// Whether the residual offset is larger than the tolerance.
+    pub fn exceeds(&self) -> bool {
+        self.offset_opt.is_some_and(|offset| offset.abs() > self.tolerance)
+    }
+}
+
+/// Finds the reference subtitle with the same stem as the input in `dir`,
+/// like `dir/S01E03.vtt` for `S01E03.srt`, trying srt before vtt.
+pub fn find_reference(dir: &Path, input_path: &Path) -> Option<PathBuf> {
+    let stem = input_path.file_stem()?.to_str()?;
+    ["srt", "vtt"].iter()
+        .map(|extension| dir.join(format!("{}.{}", stem, extension)))
+        .find(|path| path.is_file())
+}
+
+/// Reads the reference file and measures the residual offset of the
+/// shifted blocks against it.
+pub fn verify(blocks: &[Block], reference: &Path, tolerance: f64)
+    -> Result<Residual, Error>
+{
+    let bytes = fs::read(reference).map_err(|error| SubmodError::Io {
+        path: reference.to_owned(), message: format!(
+            "Could not read the reference `{}`: {}", reference.display(),
+            error) })?;
+    let (contents, _) = encoding::decode(&bytes);
+    let (reference_blocks, _) = cue::parse(contents.as_bytes())?;
+    let (offset_opt, pairs) = measure(blocks, &reference_blocks);
+    Ok(Residual { reference: reference.to_owned(), offset_opt, pairs,
+        tolerance })
+}
+
+/// Pairs the cues of both files, and returns the median of the differences
+/// between their start times, along with the number of pairs.
+/// Cues are paired by their text when it occurs only once in each file;
+/// files without any such text, like translations, are paired by position
+/// if they have as many cues.
+fn measure(blocks: &[Block], reference: &[Block]) -> (Option<f64>, usize) {
+    let (cues, reference_cues) = (cues(blocks), cues(reference));
+    let (texts, reference_texts) = (unique_texts(&cues),
+        unique_texts(&reference_cues));
+
+    let mut differences: Vec<f64> = texts.iter()
+        .filter_map(|(text, cue)| reference_texts.get(text)
+            .map(|reference_cue| reference_cue.start - cue.start))
+        .collect();
+    if differences.is_empty() && cues.len() == reference_cues.len() {
+        differences = cues.iter().zip(&reference_cues)
+            .map(|(cue, reference_cue)| reference_cue.start - cue.start)
+            .collect();
+    }
+    if differences.is_empty() {
+        return (None, 0);
+    }
+    differences.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let middle = differences.len() / 2;
+    let median = if differences.len().is_multiple_of(2) {
+        (differences[middle - 1] + differences[middle]) / 2.0
+    } else {
+        differences[middle]
+    };
+    (Some(median), differences.len())
+}
+
+fn cues(blocks: &[Block]) -> Vec<&Cue> {
+    blocks.iter()
+        .filter_map(|block| match *block {
+            Block::Cue(ref cue) => Some(cue),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Maps the visible text of every cue, lowercased and with its whitespace
+/// collapsed, to that cue, leaving out texts that occur more than once.
+fn unique_texts<'a>(cues: &[&'a Cue]) -> HashMap<String, &'a Cue> {
+    let mut texts: HashMap<String, Option<&Cue>> = HashMap::new();
+    for &cue in cues {
+        let text = cue.text.iter()
+            .map(|line| text::visible_text(line).to_lowercase())
+            .collect::<Vec<_>>()
+            .join(" ")
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+        if text.is_empty() {
+            continue;
+        }
+        texts.entry(text)
+            .and_modify(|found| *found = None)
+            .or_insert(Some(cue));
+    }
+    texts.into_iter()
+        .filter_map(|(text, cue_opt)| cue_opt.map(|cue| (text, cue)))
+        .collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blocks(srt: &str) -> Vec<Block> {
+        cue::parse(srt.as_bytes()).unwrap().0
+    }
+
+    const SHIFTED: &str = "1\n00:00:01,000 --> 00:00:02,000\nHello\n\n\
+        2\n00:00:03,000 --> 00:00:04,000\n<i>Where  are you?</i>\n\n\
+        3\n00:00:05,000 --> 00:00:06,000\nHello\n\n\
+        4\n00:00:07,000 --> 00:00:08,000\nHere.\n";
+
+    #[test]
+    fn measures_the_median_offset_of_cues_with_the_same_text() {
+        let reference = blocks("1\n00:00:03,500 --> 00:00:04,500\n\
+            where are you?\n\n\
+            2\n00:00:07,400 --> 00:00:08,400\nHere.\n\n\
+            3\n00:00:09,000 --> 00:00:10,000\nHello\n");
+        // The repeated "Hello" is left out:
+        let (offset_opt, pairs) = measure(&blocks(SHIFTED), &reference);
+        assert!((offset_opt.unwrap() - 0.45).abs() < 1e-9);
+        assert_eq!(pairs, 2);
+    }
+
+    #[test]
+    fn pairs_translations_by_position() {
+        let reference = blocks("1\n00:00:00,000 --> 00:00:01,000\nHallo\n\n\
+            2\n00:00:02,000 --> 00:00:03,000\nWaar ben je?\n\n\
+            3\n00:00:04,000 --> 00:00:05,000\nHallo\n\n\
+            4\n00:00:06,000 --> 00:00:07,000\nHier.\n");
+        assert_eq!(measure(&blocks(SHIFTED), &reference), (Some(-1.0), 4));
+        assert_eq!(measure(&blocks(SHIFTED), &reference[..3]), (None, 0));
+    }
+
+    #[test]
+    fn exceeds_only_beyond_the_tolerance() {
+        let residual = |offset_opt| Residual { reference: PathBuf::new(),
+            offset_opt, pairs: 1, tolerance: 0.5 };
+        assert!(residual(Some(-0.6)).exceeds());
+        assert!(!residual(Some(0.5)).exceeds());
+        assert!(!residual(None).exceeds());
+    }
+}
@@ -0,0 +1,170 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use atty;
+use failure::Error;
+
+use color;
+use cue::{self, Block};
+use submod::{self, Options};
+
+
+/// Number of changed subtitles shown in the preview.
+const PREVIEW_CUES: usize = 3;
+
+/// Walks the user through shifting a subtitle file with a few questions,
+/// and returns the equivalent command line arguments,
+/// or `None` if the user didn't confirm.
+pub fn ask(program: &str) -> Result<Option<Vec<String>>, Error> {
+    if !atty::is(atty::Stream::Stdin) || !atty::is(atty::Stream::Stdout) {
+        return Err(format_err!("`submod wizard` asks questions, \
+            so it needs an interactive terminal.\n\n\
+            Without one, give the file and seconds directly, like so:\n    \
+            {}", color::hint("submod movie.srt 2.5")));
+    }
+
+    outln!("{} \
+        (press Ctrl+C at any time to quit)\n", color::bold("Submod wizard"));
+    let file = ask_file()?;
+
+    let seconds = loop {
+        let answer = prompt("Do the subtitles appear too early or too late? \
+            [early/late]")?;
+        let early = match answer.to_lowercase().as_str() {
+            "early" | "e" => true,
+            "late" | "l" => false,
+            _ => continue,
+        };
+        let answer = prompt("By how many seconds? (like 2.5)")?;
+        match answer.replace(',', ".").parse::<f64>() {
+            Ok(amount) if amount > 0.0 => break signed_seconds(early, amount),
+            _ => outln!("Please enter a positive number of seconds."),
+        }
+    };
+
+    preview(Path::new(&file), seconds)?;
+    let answer = prompt("Write the shifted file? [Y/n]")?;
+    if answer.to_lowercase().starts_with('n') {
+        outln!("Nothing was written.");
+        return Ok(None);
+    }
+    Ok(Some(vec![program.to_string(), file, format!("{}", seconds)]))
+}
+
+/// Prints the command that does the same as the wizard did.
+pub fn print_command(args: &[String]) {
+    let command: Vec<String> = args[1..].iter()
+        .map(|arg| shell_quote(arg))
+        .collect();
+    outln!("\n Next time, you can do the same with:\n    {}",
+        color::hint(format!("submod {}", command.join(" "))));
+}
+
+/// Subtitles appearing too early need to be shown later, and vice versa.
+fn signed_seconds(early: bool, amount: f64) -> f64 {
+    if early { amount } else { -amount }
+}
+
+/// Lets the user pick one of the subtitle files in the current directory,
+/// or type the path to another one.
+fn ask_file() -> Result<String, Error> {
+    let mut files: Vec<String> = fs::read_dir(".")?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| name.ends_with(".srt") || name.ends_with(".vtt"))
+        .collect();
+    files.sort();
+
+    if files.is_empty() {
+        outln!("There are no subtitle files in this directory.");
+    } else {
+        outln!("Subtitle files in this directory:");
+        for (i, file) in files.iter().enumerate() {
+            outln!("  {:>2}) {}", i + 1, file);
+        }
+    }
+    loop {
+        let answer = prompt("Which file? (a number, or a path)")?;
+        if let Ok(number) = answer.parse::<usize>() {
+            if number >= 1 && number <= files.len() {
+                return Ok(files[number - 1].clone());
+            }
+        } else if (answer.ends_with(".srt") || answer.ends_with(".vtt"))
+            && Path::new(&answer).is_file() {
+            return Ok(answer);
+        }
+        outln!("Please pick a number from the list, \
+            or the path to an .srt or .vtt file.");
+    }
+}
+
+/// Shows how the first few subtitles would be shifted.
+fn preview(path: &Path, seconds: f64) -> Result<(), Error> {
+    let srt = path.extension().is_some_and(|ext| ext == "srt");
+    let (blocks, switch_opt) = cue::parse(&fs::read(path)?[..])?;
+    let options = Options { seconds, ..Options::default() };
+    let (shifted, _) = submod::modify(blocks.clone(), switch_opt, srt, srt,
+        &options)?;
+
+    let cues = |blocks: Vec<Block>| blocks.into_iter()
+        .filter_map(|block| match block {
+            Block::Cue(cue) => Some(cue),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let shifted = cues(shifted);
+    outln!("\nPreview:");
+    for old in cues(blocks).iter().take(PREVIEW_CUES) {
+        let new = shifted.iter().find(|cue| cue.number == old.number);
+        outln!("  {}  ->  {}   {}",
+            cue::format_time(old.start, srt),
+            new.map_or(String::from("(deleted)   "),
+                |cue| cue::format_time(cue.start, srt)),
+            old.text.first().map_or("", |line| line.as_str()));
+    }
+    outln!();
+    Ok(())
+}
+
+/// Asks a question and returns the trimmed answer.
+fn prompt(question: &str) -> Result<String, Error> {
+    out!("{} ", question);
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    if io::stdin().read_line(&mut answer)? == 0 {
+        return Err(format_err!("No answer was given."));
+    }
+    Ok(answer.trim().to_string())
+}
+
+/// Quotes an argument for a POSIX shell, if it needs quoting.
+fn shell_quote(arg: &str) -> String {
+    let safe = |c: char| c.is_alphanumeric() || "-_.,/:+=@%".contains(c);
+    if !arg.is_empty() && arg.chars().all(safe) {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn early_subtitles_are_shown_later() {
+        assert_eq!(signed_seconds(true, 2.5), 2.5);
+        assert_eq!(signed_seconds(false, 2.5), -2.5);
+    }
+
+    #[test]
+    fn quotes_arguments_for_the_shell() {
+        assert_eq!(shell_quote("movie.srt"), "movie.srt");
+        assert_eq!(shell_quote("-2.5"), "-2.5");
+        assert_eq!(shell_quote("Humans S03E01.en.srt"),
+            "'Humans S03E01.en.srt'");
+        assert_eq!(shell_quote("Don't.srt"), "'Don'\\''t.srt'");
+    }
+}
@@ -0,0 +1,41 @@
+//! Compares the public API of the library, as rustdoc sees it, with
+//! `tests/golden/public_api.txt`, so every change to it is made on purpose:
+//! re-exports, items made by macros and trait impls included. rustdoc only
+//! writes JSON on nightly, so this needs a nightly toolchain next to the
+//! one the tests are built with. Run with `SUBMOD_BLESS=1` to write the
+//! golden file instead, after checking that the new API is right.
+
+extern crate public_api;
+extern crate rustdoc_json;
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn public_api_is_unchanged() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let json = rustdoc_json::Builder::default()
+        .toolchain("nightly")
+        .manifest_path(root.join("Cargo.toml"))
+        .target_dir(root.join("target/public_api"))
+        .quiet(true)
+        .build()
+        .expect("Building the rustdoc JSON needs a nightly toolchain");
+    // What every type gets from the standard library isn't a decision:
+    let api = public_api::Builder::from_rustdoc_json(json)
+        .omit_blanket_impls(true)
+        .omit_auto_trait_impls(true)
+        .build()
+        .unwrap()
+        .to_string();
+
+    let golden = root.join("tests/golden/public_api.txt");
+    if env::var_os("SUBMOD_BLESS").is_some() {
+        fs::write(&golden, &api).unwrap();
+    } else {
+        assert!(fs::read_to_string(&golden).ok().as_ref() == Some(&api),
+            "The public API changed:\n{}\nRun with SUBMOD_BLESS=1 to update \
+            tests/golden/public_api.txt.", api);
+    }
+}
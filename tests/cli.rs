@@ -0,0 +1,584 @@
+//! Runs the submod binary over the files in `tests/fixtures`, and compares
+//! what it writes byte for byte with the golden files in `tests/golden/cli`.
+//! Run with `SUBMOD_BLESS=1` to write the golden files instead, after
+//! checking that the new output is right.
+
+extern crate assert_cmd;
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use assert_cmd::Command;
+
+/// A run of the binary: its name, which is also the stem of its golden
+/// file, the fixture it's given, its arguments after the input, and the
+/// name of the file it should write.
+struct Case {
+    name: &'static str,
+    fixture: &'static str,
+    args: &'static [&'static str],
+    output: &'static str,
+}
+
+const CASES: &[Case] = &[
+    Case { name: "shift", fixture: "movie.srt", args: &["1.5"],
+        output: "movie__[+1.500_Sec+].srt" },
+    Case { name: "shift_back", fixture: "movie.srt", args: &["-1.25"],
+        output: "movie__[-1.250_Sec+].srt" },
+    Case { name: "to_vtt", fixture: "movie.srt", args: &["0", "--vtt"],
+        output: "movie__[+0.000_Sec+].vtt" },
+    Case { name: "to_srt", fixture: "styled.vtt", args: &["2", "--srt"],
+        output: "styled__[+2.000_Sec+].srt" },
+    Case { name: "vtt", fixture: "styled.vtt", args: &["-0.5"],
+        output: "styled__[-0.500_Sec+].vtt" },
+    Case { name: "partial", fixture: "movie.srt",
+        args: &["-2", "--start", "00:00:10", "--stop", "00:00:30"],
+        output: "movie__[-2.000_Sec-].srt" },
+    Case { name: "cue_range", fixture: "styled.vtt",
+        args: &["3", "--from-cue", "2", "--to-cue", "2"],
+        output: "styled__[+3.000_Sec-].vtt" },
+    Case { name: "steps", fixture: "movie.srt",
+        args: &["1", "--step", "00:00:10=0.5", "--range", "-00:00:05=-1"],
+        output: "movie__[+1.000_Sec-].srt" },
+    Case { name: "step_only", fixture: "movie.srt",
+        args: &["--step", "00:00:10=0.5"],
+        output: "movie__[+0.000_Sec-].srt" },
+    Case { name: "range_only", fixture: "movie.srt",
+        args: &["--range", "00:00:10-00:01:00=-1"],
+        output: "movie__[+0.000_Sec-].srt" },
+    Case { name: "segments", fixture: "movie.srt",
+        args: &["--shift", "00:00:12-00:01:00=+30,00:01:00-=+60"],
+        output: "movie__[+0.000_Sec-].srt" },
+    Case { name: "rate", fixture: "movie.srt", args: &["--rate", "24:25"],
+        output: "movie__[x0.96]__[+0.000_Sec+].srt" },
+    Case { name: "lenient", fixture: "concatenated.vtt",
+        args: &["0", "--lenient", "--allow-noop"],
+        output: "concatenated__[+0.000_Sec+].vtt" },
+    Case { name: "generated", fixture: "generated.vtt", args: &["1"],
+        output: "generated__[+1.000_Sec+].vtt" },
+    Case { name: "lookalike", fixture: "lookalike.srt", args: &["1"],
+        output: "lookalike__[+1.000_Sec+].srt" },
+    Case { name: "colons", fixture: "colons.srt", args: &["1"],
+        output: "colons__[+1.000_Sec+].srt" },
+    Case { name: "retag", fixture: "movie__[+1.00_Sec+].srt", args: &["0.5"],
+        output: "movie__[+1.500_Sec+].srt" },
+    Case { name: "template", fixture: "movie.srt",
+        args: &["0.5", "--name-template", "{stem}.shifted"],
+        output: "movie.shifted.srt" },
+    Case { name: "script", fixture: "modern.ass", args: &["1.005"],
+        output: "modern__[+1.005_Sec+].ass" },
+    Case { name: "subviewer", fixture: "pilot.sub", args: &["1.005"],
+        output: "pilot__[+1.005_Sec+].sub" },
+    Case { name: "trim", fixture: "movie.srt",
+        args: &["--trim-leading-silence=0.25"],
+        output: "movie__[-0.750_Sec+].srt" },
+    Case { name: "verified", fixture: "movie.srt",
+        args: &["1.5", "--verify-source-unchanged"],
+        output: "movie__[+1.500_Sec+].srt" },
+    Case { name: "confidence", fixture: "whisper.vtt",
+        args: &["0", "--drop-low-confidence", "0.5"],
+        output: "whisper__[+0.000_Sec+].vtt" },
+    Case { name: "spaced", fixture: "spaced.vtt", args: &["1"],
+        output: "spaced__[+1.000_Sec+].vtt" },
+    Case { name: "credits", fixture: "movie.srt",
+        args: &["5", "--vtt", "--prepend-cue", "Subbed by the Night Owls",
+            "--append-cue", "Timing: Ann\\nQC: Bo"],
+        output: "movie__[+5.000_Sec+].vtt" },
+    Case { name: "microdvd", fixture: "archive.sub",
+        args: &["1", "--fps", "23.976"],
+        output: "archive__[+1.000_Sec+].sub" },
+    Case { name: "microdvd_to_srt", fixture: "archive.sub",
+        args: &["-0.5", "--fps", "23.976", "--srt"],
+        output: "archive__[-0.500_Sec+].srt" },
+    Case { name: "subviewer_to_srt", fixture: "pilot.sub", args: &["-0.5", "--srt"],
+        output: "pilot__[-0.500_Sec+].srt" },
+    Case { name: "ttml", fixture: "broadcast.ttml", args: &["2.5"],
+        output: "broadcast__[+2.500_Sec+].ttml" },
+    Case { name: "ttml_to_srt", fixture: "broadcast.ttml",
+        args: &["-1", "--srt"], output: "broadcast__[-1.000_Sec+].srt" },
+    Case { name: "ttml_programme", fixture: "programme.dfxp", args: &["5"],
+        output: "programme__[+5.000_Sec+].dfxp" },
+    Case { name: "srt_to_ttml", fixture: "movie.srt",
+        args: &["1", "--out", "movie.ttml"], output: "movie.ttml" },
+];
+
+#[test]
+fn writes_the_golden_files() {
+    let bless = env::var_os("SUBMOD_BLESS").is_some();
+    let mut failures = Vec::new();
+    for case in CASES {
+        let output = run(case);
+        let golden = golden_path(case);
+        if bless {
+            fs::write(&golden, &output).unwrap();
+        } else if fs::read(&golden).ok().as_ref() != Some(&output) {
+            failures.push(case.name);
+        }
+    }
+    assert!(failures.is_empty(), "Output differs from the golden files for: \
+        {}\nRun with SUBMOD_BLESS=1 to update them.", failures.join(", "));
+}
+
+#[test]
+fn runs_are_reproducible() {
+    for case in CASES {
+        assert_eq!(run(case), run(case), "{}", case.name);
+    }
+}
+
+#[test]
+fn shifts_every_file_of_a_batch() {
+    let dir = temp_dir("batch");
+    for name in &["ep01.srt", "ep02.srt"] {
+        fs::copy("tests/fixtures/movie.srt", dir.join(name)).unwrap();
+    }
+    // The missing file fails the batch, but not the other files:
+    submod()
+        .current_dir(&dir)
+        .args(["ep01.srt", "ep03.srt", "ep02.srt", "1.5", "--quiet"])
+        .assert()
+        .code(1);
+    let golden = fs::read("tests/golden/cli/shift.srt").unwrap();
+    for name in &["ep01__[+1.500_Sec+].srt", "ep02__[+1.500_Sec+].srt"] {
+        assert_eq!(fs::read(dir.join(name)).unwrap(), golden, "{}", name);
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn only_shifts_the_files_of_the_language_of_lang() {
+    let dir = temp_dir("lang");
+    for name in &["movie.en.srt", "movie.fr.srt"] {
+        fs::copy("tests/fixtures/movie.srt", dir.join(name)).unwrap();
+    }
+    let assert = submod()
+        .current_dir(&dir)
+        .args(["movie.en.srt", "movie.fr.srt", "1.5", "--lang", "en"])
+        .assert()
+        .success();
+    let report = String::from_utf8_lossy(&assert.get_output().stdout);
+    assert!(report.contains("is in `fr`, not `en`"), "{}", report);
+    assert_eq!(fs::read(dir.join("movie.en__[+1.500_Sec+].srt")).unwrap(),
+        fs::read("tests/golden/cli/shift.srt").unwrap());
+    assert!(!dir.join("movie.fr__[+1.500_Sec+].srt").exists());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn checks_every_file_of_a_batch() {
+    let dir = temp_dir("check");
+    fs::copy("tests/fixtures/spaced.vtt", dir.join("spaced.vtt")).unwrap();
+    fs::copy("tests/golden/cli/vtt.vtt", dir.join("clean.vtt")).unwrap();
+    submod()
+        .current_dir(&dir)
+        .args(["clean.vtt", "0", "--check"])
+        .assert()
+        .success();
+    // Any file that would change fails the batch, but all are checked:
+    let assert = submod()
+        .current_dir(&dir)
+        .args(["spaced.vtt", "clean.vtt", "0", "--check"])
+        .assert()
+        .code(1);
+    let report = String::from_utf8_lossy(&assert.get_output().stdout);
+    assert!(report.contains("Would change.") && report.contains("Unchanged."),
+        "{}", report);
+    assert!(report.contains("1 of 2 files would change.")
+        && !report.contains("succeeded"), "{}", report);
+    // And nothing is written:
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn explains_every_option_of_a_preset() {
+    let dir = temp_dir("preset");
+    fs::copy("tests/fixtures/movie.srt", dir.join("movie.srt")).unwrap();
+    let assert = submod()
+        .current_dir(&dir)
+        .env("SUBMOD_CONFIG", "no-such-presets")
+        .args(["movie.srt", "0", "--preset", "netflix", "--explain",
+            "--check"])
+        .assert();
+    let explanation = String::from_utf8_lossy(&assert.get_output().stdout);
+    for sentence in ["are shortened to end 0.08",
+        "less than 0.8 seconds are lengthened", "longer than 42 characters",
+        "more than 2 lines", "Whitespace in the subtitle text is normalized"] {
+        assert!(explanation.contains(sentence), "{}", explanation);
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn chains_frame_shifts_through_the_names_of_the_outputs() {
+    let dir = temp_dir("frames");
+    fs::copy("tests/fixtures/movie.srt", dir.join("movie.srt")).unwrap();
+    // Each run shifts the output of the one before by a frame at 23.976 fps,
+    // to the millisecond its frame ends on:
+    let frame_end = |frame: u32| (f64::from(frame) * 1001.0 / 24.0).round();
+    let mut input = String::from("movie.srt");
+    for frame in 1..=24 {
+        let seconds = (frame_end(frame) - frame_end(frame - 1)) / 1000.0;
+        submod()
+            .current_dir(&dir)
+            .args([input.as_str(), &seconds.to_string(), "--quiet"])
+            .assert()
+            .success();
+        fs::remove_file(dir.join(&input)).unwrap();
+        input = fs::read_dir(&dir).unwrap().next().unwrap().unwrap()
+            .file_name().into_string().unwrap();
+    }
+    assert_eq!(input, "movie__[+1.001_Sec+].srt");
+    // Which lands every timestamp where a single shift puts it:
+    let once = submod()
+        .args(["-", "1.001", "--stdout", "--format", "srt"])
+        .write_stdin(fs::read("tests/fixtures/movie.srt").unwrap())
+        .output()
+        .unwrap();
+    assert_eq!(fs::read(dir.join(&input)).unwrap(), once.stdout);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn deterministic_runs_write_the_same_files() {
+    let args = ["movie.srt", "1.5", "--overwrite", "--keep-backups", "2",
+        "--deterministic", "--include-cues", "2-3", "--selection-out",
+        "picked.json", "--report", "json", "--quiet"];
+    let runs: Vec<Vec<(PathBuf, Vec<u8>)>> = (0..2).map(|_| {
+        let dir = temp_dir("deterministic");
+        fs::copy("tests/fixtures/movie.srt", dir.join("movie.srt")).unwrap();
+        submod().current_dir(&dir).args(args).assert().success();
+        let mut files: Vec<(PathBuf, Vec<u8>)> = fs::read_dir(&dir).unwrap()
+            .map(|entry| entry.unwrap().path())
+            .map(|path| (path.strip_prefix(&dir).unwrap().to_owned(),
+                fs::read(&path).unwrap()))
+            .collect();
+        files.sort();
+        fs::remove_dir_all(&dir).unwrap();
+        files
+    }).collect();
+    let names: Vec<&Path> = runs[0].iter().map(|file| file.0.as_path())
+        .collect();
+    assert_eq!(names, [Path::new("movie.srt"), Path::new("movie.srt.bak"),
+        Path::new("picked.json")]);
+    assert!(runs[0] == runs[1], "the runs wrote different files");
+}
+
+#[test]
+fn pipes_stdin_to_stdout() {
+    // Without --format, the WEBVTT header tells vtt from srt:
+    for &(fixture, seconds, golden) in &[("movie.srt", "1.5", "shift.srt"),
+        ("styled.vtt", "-0.5", "vtt.vtt")] {
+        submod()
+            .args([seconds, "-", "--stdout"])
+            .write_stdin(fs::read(Path::new("tests/fixtures").join(fixture))
+                .unwrap())
+            .assert()
+            .success()
+            .stdout(fs::read(Path::new("tests/golden/cli").join(golden))
+                .unwrap());
+    }
+}
+
+#[test]
+fn sniffs_piped_inputs_by_their_structure() {
+    // Text that looks like other formats doesn't outrank the timing lines:
+    let output = pipe("colons.srt", &["1", "-", "--stdout"]);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, fs::read("tests/golden/cli/colons.srt")
+        .unwrap());
+    // Nor do lines that only start with a timestamp make a format:
+    let output = pipe("tmplayer.txt", &["1", "-", "--stdout"]);
+    assert_eq!(output.status.code(), Some(2));
+    let error = String::from_utf8(output.stderr).unwrap();
+    assert!(error.contains("TMPlayer") && error.contains("--format"),
+        "{}", error);
+    let output = pipe("ambiguous.sub", &["1", "-", "--stdout"]);
+    assert_eq!(output.status.code(), Some(2));
+    let error = String::from_utf8(output.stderr).unwrap();
+    assert!(error.contains("MicroDVD or SubViewer"), "{}", error);
+    // A script header makes it a script, shifted like a script file:
+    let output = pipe("modern.ass", &["1.005", "-", "--stdout"]);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, fs::read("tests/golden/cli/script.ass")
+        .unwrap());
+    // And an XML declaration makes it a TTML document:
+    let output = pipe("broadcast.ttml", &["2.5", "-", "--stdout"]);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, fs::read("tests/golden/cli/ttml.ttml")
+        .unwrap());
+}
+
+#[test]
+fn reports_results_and_errors_as_json() {
+    let dir = temp_dir("json");
+    fs::copy("tests/fixtures/movie.srt", dir.join("movie.srt")).unwrap();
+    let assert = submod()
+        .current_dir(&dir)
+        .args(["movie.srt", "missing.srt", "-2", "--start", "00:00:10",
+            "--json", "--deterministic", "--stats-per-range",
+            "--selection-out", "selection.json", "--report", "json"])
+        .assert()
+        .code(1);
+    let output = assert.get_output();
+    let fields = "\"offset\": -2.000, \"cues\": 5, \"modified\": 3, \
+        \"deleted\": 0, \"warnings\": 0, \"unchanged\": false, \
+        \"elapsed_seconds\": null, \"cues_per_second\": null, \
+        \"duration_mismatch_seconds\": null, \"ranges\": [{\"start\": 10.000, \
+        \"end\": null, \"seconds\": -2.000, \"shifted\": 3, \"clamped\": 0, \
+        \"deleted\": 0}]";
+    assert_eq!(String::from_utf8_lossy(&output.stdout),
+        format!("{{\"input\": \"movie.srt\", \
+        \"output\": \"movie__[-2.000_Sec-].srt\", \"overwritten\": false, \
+        {}}}\n", fields));
+    // The report of --selection-out has the same fields:
+    let selection = fs::read_to_string(dir.join("selection.json")).unwrap();
+    let flattened = selection.replace("\n    ", "").replace("\n  ]", "]")
+        .replace(",\n  ", ", ");
+    assert!(flattened.starts_with(&format!("{{\n  {}, \"selection\": [",
+        fields)), "{}", selection);
+    // Without the colors of the terminal:
+    assert_eq!(String::from_utf8_lossy(&output.stderr),
+        "{\"job\": \"missing.srt\", \
+        \"error\": \"`missing.srt` does not exist.\"}\n");
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn colors_only_terminals_unless_told_otherwise() {
+    let dir = temp_dir("color");
+    fs::copy("tests/fixtures/movie.srt", dir.join("movie.srt")).unwrap();
+    let run = |args: &[&str]| submod()
+        .current_dir(&dir)
+        .args(["movie.srt", "1", "--force"])
+        .args(args)
+        .output()
+        .unwrap();
+    // Pipes aren't terminals:
+    let output = run(&[]);
+    assert!(output.status.success());
+    assert!(output.stdout.starts_with(b"Success.\n"));
+    let output = run(&["--color", "always"]);
+    assert!(output.stdout.starts_with(b"\x1b[32;1mSuccess.\x1b[0m\n"));
+    let output = run(&["--color", "always", "--no-color", "--out", "/"]);
+    assert!(output.stderr.starts_with(b"error: Invalid value"));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn only_verifies_files_that_can_be_read_again() {
+    let output = pipe("movie.srt", &["-", "1", "--stdout",
+        "--verify-source-unchanged"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("--verify-source-unchanged needs reading the input twice"));
+}
+
+#[test]
+fn exits_with_the_status_of_the_error() {
+    let status = |args: &[&str]| submod()
+        .args(args)
+        .output()
+        .unwrap()
+        .status
+        .code();
+    assert_eq!(status(&["tests/fixtures/missing.srt", "1"]), Some(1));
+    assert_eq!(status(&["tests/fixtures/ambiguous.sub", "1", "--stdout"]),
+        Some(2));
+    assert_eq!(status(&["tests/fixtures/movie.srt", "1", "--from-cue", "3",
+        "--to-cue", "2", "--stdout"]), Some(1));
+    assert_eq!(status(&["tests/fixtures/movie.srt", "1", "--name-template",
+        "/tmp/abs_{stem}"]), Some(1));
+    let dir = temp_dir("status");
+    let out = dir.join("missing/out.srt");
+    assert_eq!(status(&["tests/fixtures/movie.srt", "1", "--out",
+        out.to_str().unwrap()]), Some(3));
+    // Subtitle files without subtitles, or with a broken timing line:
+    for &(name, contents) in &[("empty.srt", "Just some text.\n"),
+        ("header.vtt", "WEBVTT\n"),
+        ("broken.srt", "1\n00:00:01,000 --> 00:00:02,000\nA\n\n\
+            2\n00:00:03,000 -> 00:00:04,000\nB\n"),
+        ("broken.vtt", "WEBVTT\n\n00:00:01.000 --> 00:00:0x.000\nA\n")] {
+        let path = dir.join(name);
+        fs::write(&path, contents).unwrap();
+        assert_eq!(status(&[path.to_str().unwrap(), "1", "--stdout"]),
+            Some(2), "{}", name);
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(feature = "encodings")]
+#[test]
+fn converts_the_encoding_without_shifting() {
+    let dir = temp_dir("encoding");
+    fs::write(dir.join("latin.srt"),
+        b"1\n00:00:01,000 --> 00:00:02,000\nCaf\xe9 cr\xe8me\n".as_ref())
+        .unwrap();
+    submod()
+        .current_dir(&dir)
+        .args(["latin.srt", "0", "--encoding", "windows-1252", "--quiet"])
+        .assert()
+        .success();
+    assert_eq!(fs::read_to_string(dir.join("latin__[+0.000_Sec+].srt"))
+        .unwrap(), "1\n00:00:01,000 --> 00:00:02,000\nCafé crème\n");
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn writes_what_it_writes_again_unchanged() {
+    // However the blocks of the input are spaced, the output of one run
+    // is the output of the next:
+    let once = pipe("spaced.vtt", &["-", "1", "--stdout", "--format", "vtt"]);
+    assert!(once.status.success());
+    let dir = temp_dir("idempotent");
+    fs::write(dir.join("once.vtt"), &once.stdout).unwrap();
+    submod()
+        .args(["once.vtt", "0", "--allow-noop", "--stdout"])
+        .current_dir(&dir)
+        .assert()
+        .success()
+        .stdout(once.stdout);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn refuses_outputs_outside_their_directory() {
+    let dir = temp_dir("outside");
+    fs::create_dir_all(dir.join("in")).unwrap();
+    fs::copy("tests/fixtures/movie.srt", dir.join("in/movie.srt")).unwrap();
+    fs::write(dir.join("jobs.tsv"), "in/movie.srt\t1\t../escaped.srt\n")
+        .unwrap();
+    let run = |args: &[&str]| submod()
+        .args(args)
+        .current_dir(&dir)
+        .output()
+        .unwrap();
+    let output = run(&["--manifest", "jobs.tsv"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("--allow-outside-output-dir"));
+    assert!(!dir.join("../escaped.srt").exists());
+    #[cfg(unix)]
+    {
+        fs::create_dir_all(dir.join("elsewhere")).unwrap();
+        std::os::unix::fs::symlink(dir.join("elsewhere"), dir.join("in/link"))
+            .unwrap();
+        let args = ["in/movie.srt", "1", "--name-template", "link/{stem}"];
+        assert_eq!(run(&args).status.code(), Some(1));
+        assert!(!dir.join("elsewhere/movie.srt").exists());
+        let allowed = run(&[&args[..], &["--allow-outside-output-dir"]]
+            .concat());
+        assert!(allowed.status.success());
+        assert!(dir.join("elsewhere/movie.srt").exists());
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+/// Runs the binary with a fixture piped to stdin.
+fn pipe(fixture: &str, args: &[&str]) -> std::process::Output {
+    submod()
+        .args(args)
+        .write_stdin(fs::read(Path::new("tests/fixtures").join(fixture))
+            .unwrap())
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn dry_runs_write_nothing() {
+    let dir = temp_dir("dry_run");
+    fs::copy("tests/fixtures/movie.srt", dir.join("movie.srt")).unwrap();
+    let assert = submod()
+        .current_dir(&dir)
+        .args(["movie.srt", "-6", "--dry-run"])
+        .assert()
+        .success();
+    let report = String::from_utf8_lossy(&assert.get_output().stdout);
+    assert!(report.contains("00:00:05.250 --> 00:00:07.000  =>  \
+        00:00:00.000 --> 00:00:01.000"), "{}", report);
+    assert!(report.contains("movie__[-6.000_Sec+].srt"), "{}", report);
+    let names: Vec<_> = fs::read_dir(&dir).unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+    assert_eq!(names, ["movie.srt"]);
+    // But the reports of the run are written:
+    submod()
+        .current_dir(&dir)
+        .args(["movie.srt", "-6", "--dry-run", "--selection-out",
+            "selection.txt", "--map-out", "map.csv"])
+        .assert()
+        .success();
+    let map = fs::read_to_string(dir.join("map.csv")).unwrap();
+    assert!(map.contains("2,5.250,7.000,0.000,1.000,changed"), "{}", map);
+    assert!(dir.join("selection.txt").exists());
+    assert!(!dir.join("movie__[-6.000_Sec+].srt").exists());
+    // Errors still fail the run:
+    submod()
+        .current_dir(&dir)
+        .args(["missing.srt", "-6", "--dry-run"])
+        .assert()
+        .code(1);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+/// The submod binary, as built by cargo for the tests.
+fn submod() -> Command {
+    Command::cargo_bin("submod").unwrap()
+}
+
+static RUNS: AtomicUsize = AtomicUsize::new(0);
+
+/// Runs the case in a directory of its own, and returns what was written.
+fn run(case: &Case) -> Vec<u8> {
+    let dir = temp_dir(case.name);
+    // Fixtures that test naming are copies of those named plainly:
+    let source = Path::new("tests/fixtures").join(plain_name(case.fixture));
+    let input = dir.join(case.fixture);
+    fs::copy(&source, &input).unwrap();
+
+    // Outputs given with --out are written there too:
+    let output = submod()
+        .current_dir(&dir)
+        .arg(&input)
+        .args(case.args)
+        .arg("--quiet")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{} failed", case.name);
+
+    let written: Vec<PathBuf> = fs::read_dir(&dir).unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| *path != input)
+        .collect();
+    assert_eq!(written, [dir.join(case.output)], "{}", case.name);
+    let output = fs::read(&written[0]).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    output
+}
+
+/// An empty directory, unique to this run of `name`.
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("submod-cli-{}-{}-{}",
+        std::process::id(), RUNS.fetch_add(1, Ordering::SeqCst), name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// The fixture a tagged name like `movie__[+1.00_Sec+].srt` is a copy of.
+fn plain_name(fixture: &str) -> String {
+    match (fixture.find("__["), fixture.rfind('.')) {
+        (Some(tag), Some(dot)) => format!("{}{}", &fixture[..tag],
+            &fixture[dot..]),
+        _ => fixture.to_string(),
+    }
+}
+
+fn golden_path(case: &Case) -> PathBuf {
+    let extension = Path::new(case.output).extension().unwrap();
+    Path::new("tests/golden/cli")
+        .join(case.name)
+        .with_extension(extension)
+}